@@ -94,13 +94,7 @@ impl Args {
             self.global.keep_temporary_directories,
         )?;
 
-        // setup test environment ..
-        let test_file_path = PathBuf::from(&test_environment.work_directory).join("testfile.tmp");
-        let (test_work_directory, environment) =
-            test_environment.init_test_file(&test_file_path, self.format == ParserType::Cram)?;
-
         // generate configuration
-        let env_vars = BTreeMap::from_iter(environment.iter().map(|(k, v)| (k as &str, v as &str)));
         let (document_config, testcase_config) = if self.format == ParserType::Markdown {
             (
                 DocumentConfig::default_markdown(),
@@ -112,6 +106,16 @@ impl Args {
                 TestCaseConfig::default_cram(),
             )
         };
+        let document_config = document_config.with_overrides_from(&self.to_document_config());
+
+        // setup test environment ..
+        let test_file_path = PathBuf::from(&test_environment.work_directory).join("testfile.tmp");
+        let (test_work_directory, environment) = test_environment.init_test_file(
+            &test_file_path,
+            self.format == ParserType::Cram,
+            &document_config,
+        )?;
+        let env_vars = BTreeMap::from_iter(environment.iter().map(|(k, v)| (k as &str, v as &str)));
 
         // execute the test to get the output
         let testcase_config = testcase_config
@@ -128,7 +132,7 @@ impl Args {
                     .work_directory(PathBuf::from(&test_work_directory))
                     .temp_directory(test_environment.tmp_directory.as_path_buf())
                     .file("testfile.tmp".into())
-                    .config(document_config.with_overrides_from(&self.to_document_config()))
+                    .config(document_config)
                     .build()
                     .context("construct build execution context")?,
             )
@@ -158,6 +162,7 @@ impl Args {
                 testcase,
                 escaping: self.global.output_escaping(Some(self.format)),
                 format: self.format,
+                shell: None,
                 result,
             }])
             .context("generate formatted test document content")?;
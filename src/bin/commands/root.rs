@@ -42,6 +42,9 @@ pub enum ScrutRenderer {
     Diff,
     Json,
     Yaml,
+    Junit,
+    Tap,
+    Github,
 }
 
 #[derive(Parser, Debug)]
@@ -86,6 +89,29 @@ pub(crate) struct GlobalParameters {
     #[clap(long, short, global = true)]
     pub(crate) work_directory: Option<PathBuf>,
 
+    /// Run the tests of each document in a sandbox, in which the filesystem is
+    /// read-only, except for the work and the temporary directory, and only the
+    /// loopback network is available. Only supported on Linux.
+    #[clap(long, global = true)]
+    pub(crate) sandbox: bool,
+
+    /// Run the tests of each document in an empty environment, that does not
+    /// inherit any environment variables from the host, with a private home
+    /// directory in the work directory
+    #[clap(long, global = true)]
+    pub(crate) hermetic: bool,
+
+    /// Run all tests of each document in one long-lived shell process, instead
+    /// of starting a new shell for each test, so that state like traps, open
+    /// file descriptors and background jobs is shared in between tests
+    #[clap(long, global = true)]
+    pub(crate) persistent_shell: bool,
+
+    /// Name of an environment variable that is passed through from the host
+    /// into hermetic tests. Can be given multiple times.
+    #[clap(long, value_name = "NAME", global = true)]
+    pub(crate) env_passthrough: Vec<String>,
+
     /// Whether not to clean up temporary directories after test execution
     #[clap(long, conflicts_with = "work_directory", global = true)]
     pub(crate) keep_temporary_directories: bool,
@@ -133,6 +159,18 @@ pub(crate) struct GlobalSharedParameters {
     #[clap(from_global)]
     pub(crate) work_directory: Option<PathBuf>,
 
+    #[clap(from_global)]
+    pub(crate) sandbox: bool,
+
+    #[clap(from_global)]
+    pub(crate) hermetic: bool,
+
+    #[clap(from_global)]
+    pub(crate) persistent_shell: bool,
+
+    #[clap(from_global)]
+    pub(crate) env_passthrough: Vec<String>,
+
     #[clap(from_global)]
     pub(crate) keep_temporary_directories: bool,
 
@@ -160,6 +198,16 @@ impl GlobalSharedParameters {
         if let Some(value) = self.timeout_seconds {
             config.total_timeout = Some(Duration::from_secs(value))
         }
+        if self.sandbox {
+            config.sandbox = Some(true)
+        }
+        if self.hermetic {
+            config.hermetic = Some(true)
+        }
+        if self.persistent_shell {
+            config.persistent_shell = Some(true)
+        }
+        config.env_passthrough = self.env_passthrough.clone();
 
         config
     }
@@ -274,6 +322,38 @@ mod tests {
                     ..DocumentConfig::empty()
                 },
             ),
+            (
+                GlobalSharedParameters {
+                    sandbox: true,
+                    ..Default::default()
+                },
+                DocumentConfig {
+                    sandbox: Some(true),
+                    ..DocumentConfig::empty()
+                },
+            ),
+            (
+                GlobalSharedParameters {
+                    hermetic: true,
+                    env_passthrough: vec!["PATH".into()],
+                    ..Default::default()
+                },
+                DocumentConfig {
+                    hermetic: Some(true),
+                    env_passthrough: vec!["PATH".into()],
+                    ..DocumentConfig::empty()
+                },
+            ),
+            (
+                GlobalSharedParameters {
+                    persistent_shell: true,
+                    ..Default::default()
+                },
+                DocumentConfig {
+                    persistent_shell: Some(true),
+                    ..DocumentConfig::empty()
+                },
+            ),
         ];
 
         for (params, expected) in tests {
@@ -8,31 +8,44 @@
 use std::collections::BTreeMap;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Instant;
 
 use anyhow::Context;
 use anyhow::Result;
 use anyhow::anyhow;
 use anyhow::bail;
 use clap::Parser as ClapParser;
+use clap::ValueEnum;
 use dialoguer::console::style;
 use humantime::format_duration;
+use regex::Regex;
 use scrut::config::DEFAULT_SKIP_DOCUMENT_CODE;
 use scrut::config::DocumentConfig;
 use scrut::config::TestCaseConfig;
 use scrut::executors::context::ContextBuilder;
 use scrut::executors::error::ExecutionError;
 use scrut::executors::error::ExecutionTimeout;
+use scrut::executors::util::default_parallel_count;
 use scrut::outcome::Outcome;
 use scrut::output::ExitStatus;
+use scrut::output::duration_ms;
 use scrut::parsers::markdown::DEFAULT_MARKDOWN_LANGUAGES;
 use scrut::parsers::parser::ParserType;
 use scrut::renderers::diff::DiffRenderer;
+use scrut::renderers::github::GithubRenderer;
+use scrut::renderers::junit::JunitRenderer;
 use scrut::renderers::pretty::DEFAULT_MULTILINE_MATCHED_LINES;
+use scrut::renderers::pretty::DEFAULT_SLOWEST;
 use scrut::renderers::pretty::DEFAULT_SURROUNDING_LINES;
 use scrut::renderers::pretty::PrettyColorRenderer;
 use scrut::renderers::pretty::PrettyMonochromeRenderer;
 use scrut::renderers::renderer::Renderer;
 use scrut::renderers::structured::JsonRenderer;
+use scrut::renderers::structured::TapRenderer;
 use scrut::renderers::structured::YamlRenderer;
 use scrut::testcase::TestCase;
 use scrut::testcase::TestCaseError;
@@ -43,25 +56,95 @@ use tracing::trace;
 
 use super::root::GlobalSharedParameters;
 use super::root::ScrutRenderer;
+use crate::utils::DocumentObserver;
+use crate::utils::Event;
+use crate::utils::EventFormat;
+use crate::utils::EventWriter;
 use crate::utils::FileParser;
+use crate::utils::ParsedTestFile;
 use crate::utils::ProgressWriter;
+use crate::utils::TestCaseSelector;
 use crate::utils::TestEnvironment;
 use crate::utils::canonical_shell;
 use crate::utils::debug_testcases;
 use crate::utils::get_log_level;
 use crate::utils::kill_detached_process;
 use crate::utils::make_executor;
+use crate::utils::make_sandbox;
 
 #[derive(Debug, thiserror::Error)]
 #[error("validation failed")]
 pub struct ValidationFailedError;
 
+/// Outcomes and counts that result from running a single test document
+#[derive(Default)]
+struct DocumentResult {
+    outcomes: Vec<Outcome>,
+    count_success: usize,
+    count_skipped: usize,
+    count_failed: usize,
+    count_detached: usize,
+}
+
+impl DocumentResult {
+    /// Adds the outcomes and counts of another run of the same document
+    fn extend(&mut self, other: DocumentResult) {
+        self.outcomes.extend(other.outcomes);
+        self.count_success += other.count_success;
+        self.count_skipped += other.count_skipped;
+        self.count_failed += other.count_failed;
+        self.count_detached += other.count_detached;
+    }
+}
+
+/// A report that is written into a file, in addition to the output of the
+/// renderer
+#[derive(Debug, Clone)]
+struct ReportTarget {
+    renderer: ScrutRenderer,
+    path: PathBuf,
+}
+
+/// Parse a report target from `FORMAT=PATH`
+fn parse_report_target(value: &str) -> Result<ReportTarget> {
+    let (format, path) = value
+        .split_once('=')
+        .ok_or_else(|| anyhow!("expected FORMAT=PATH, got `{value}`"))?;
+    let renderer = ScrutRenderer::from_str(format, true)
+        .map_err(|err| anyhow!("invalid report format `{format}`: {err}"))?;
+    if path.is_empty() {
+        bail!("missing path for `{format}` report");
+    }
+    Ok(ReportTarget {
+        renderer,
+        path: PathBuf::from(path),
+    })
+}
+
+/// Parse the regular expression of `--filter`
+fn parse_filter(value: &str) -> Result<Regex> {
+    Regex::new(value).with_context(|| format!("invalid filter `{value}`"))
+}
+
 /// Run tests from files or directories
 #[derive(Debug, ClapParser)]
 pub struct Args {
     /// Path to test files or directories
+    ///
+    /// Single test cases can be selected with `PATH:LINE`, with `LINE` being
+    /// the line number of the test case in the file, e.g. `docs/cli.md:142`.
     test_file_paths: Vec<PathBuf>,
 
+    /// Only run test cases with a title that matches this regular expression
+    #[clap(long, value_name = "REGEX", value_parser = parse_filter)]
+    filter: Option<Regex>,
+
+    /// Only run test cases that are tagged with this tag (see the `tags`
+    /// configuration). Can be given multiple times to run test cases that
+    /// have any of the tags.
+    #[clap(long, value_name = "TAG")]
+    tag: Vec<String>,
+
     /// Optional list of paths to test files which are prepended to each test
     /// file in execution. Think: shared test bootstrap.
     /// This is NOT meant to be used from the command line, aside from
@@ -80,6 +163,12 @@ pub struct Args {
     #[clap(long, short = 'A', num_args=0..)]
     append_test_file_paths: Vec<PathBuf>,
 
+    /// Run all test cases once in each of these shells, e.g.
+    /// `--shell-matrix bash,dash,zsh`. Overrides `--shell` and the `shell` and
+    /// `shells` configuration of documents.
+    #[clap(long, value_name = "SHELL", value_delimiter = ',')]
+    shell_matrix: Vec<PathBuf>,
+
     /// Whether to print out debug output - use only
     #[clap(long)]
     debug: bool,
@@ -97,11 +186,25 @@ pub struct Args {
     match_markdown: String,
 
     /// Which renderer to use for generating the result, with `diff` being the
-    /// best choice for human consumption and `json` or `yaml` for further
-    /// machine processing.
+    /// best choice for human consumption, `json` or `yaml` for further
+    /// machine processing, `junit` or `tap` for CI systems and test harnesses
+    /// and `github` for annotations in GitHub Actions.
     #[clap(long, short, default_value = "auto", value_enum)]
     renderer: ScrutRenderer,
 
+    /// Write an additional report of the results into a file, given as
+    /// `FORMAT=PATH` with `FORMAT` being any of the renderers, e.g.
+    /// `--report junit=report.xml`. Can be given multiple times.
+    #[clap(long, value_name = "FORMAT=PATH", value_parser = parse_report_target)]
+    report: Vec<ReportTarget>,
+
+    /// Stream events about the progress of the test run to STDOUT while the
+    /// tests are running, e.g. for editor integrations or live dashboards.
+    /// The output of the renderer is not printed then, use `--report` to
+    /// write it into a file instead.
+    #[clap(long, value_enum, value_name = "FORMAT")]
+    events: Option<EventFormat>,
+
     /// Per default, renderers that provide line numbers use relative numbers within
     /// the test case / the output of the execution. Setting this flag changes that
     /// to use absolute line numbers from within the test file.
@@ -114,10 +217,21 @@ pub struct Args {
     #[clap(long, default_value_t = DEFAULT_MULTILINE_MATCHED_LINES)]
     max_multiline_matched_lines: usize,
 
+    /// List the given number of slowest test cases, with their duration, in
+    /// the summary of the pretty renderer.
+    #[clap(long, value_name = "N", default_value_t = DEFAULT_SLOWEST)]
+    slowest: usize,
+
     /// Increase output verbosity, print out information that is not warning or errors
     #[clap(long)]
     verbose: bool,
 
+    /// Amount of test documents that are executed in parallel. Testcases
+    /// within a single document are still executed one after another. Use
+    /// `0` to run as many documents in parallel as there are CPUs.
+    #[clap(long, short = 'j', default_value_t = 1)]
+    jobs: usize,
+
     #[clap(flatten)]
     global: GlobalSharedParameters,
 }
@@ -133,20 +247,29 @@ impl Args {
         let parser = FileParser::new(&self.match_markdown, &self.match_cram, markdown_languages)
             .context("create file parser")?;
 
-        let tests = parser.find_and_parse(
+        let started = Instant::now();
+        let mut selector = TestCaseSelector::new(self.filter.clone(), self.tag.clone());
+        let test_file_paths = selector.add_paths(&self.test_file_paths);
+        let mut tests = parser.find_and_parse(
             "test",
-            &self
-                .test_file_paths
+            &test_file_paths
                 .iter()
                 .map(|p| p as &Path)
                 .collect::<Vec<_>>(),
             self.global.cram_compat,
         )?;
 
-        // initiate outputs
-        let mut outcomes = vec![];
-        let (mut count_success, mut count_skipped, mut count_failed, mut count_detached) =
-            (0, 0, 0, 0);
+        // reduce to the selected test cases, if any selection is made, and
+        // drop the documents that have none of them
+        if selector.is_selective() {
+            for test in tests.iter_mut() {
+                selector.select(test);
+            }
+            tests.retain(|test| !test.testcases.is_empty());
+            if tests.is_empty() {
+                bail!("no test cases match the provided selection");
+            }
+        }
 
         // load configuration from command line
         let document_config = self.to_document_config();
@@ -162,297 +285,502 @@ impl Args {
             "🔎 Found {} test document(s)",
             style(tests.len()).bold()
         ));
+        let events = EventWriter::new(self.events.clone());
+        for test in &tests {
+            events.emit(&Event::DocumentDiscovered {
+                location: &test.path.display().to_string(),
+                testcases: test.testcases.len(),
+            });
+        }
 
-        for mut test in tests {
-            pw.inc(1);
-            pw.set_message(format!(
-                "👀 {}",
-                style(test.path.to_string_lossy()).yellow()
-            ));
-
-            // prefix append and prepend in document config with directory where test is
-            let test_directory = &test.path.parent().unwrap_or(&current_directory);
-            test.config.append = prefix_with_directory(test_directory, &test.config.append);
-            test.config.prepend = prefix_with_directory(test_directory, &test.config.prepend);
-
-            // compile configuration from test file and parameters
-            let config: DocumentConfig = test.config.with_overrides_from(&document_config);
-
-            // initialize environment in which test will run
-            let shell_path = canonical_shell(config.shell.as_ref().map(|p| p as &Path))?;
-            let mut test_environment = TestEnvironment::new(
-                &shell_path,
-                self.global.work_directory.as_deref(),
-                self.global.keep_temporary_directories,
+        // run all documents, either one after another or in parallel, while
+        // keeping the results in the order in which the documents were found
+        let jobs = match self.jobs {
+            0 => default_parallel_count(),
+            jobs => jobs,
+        };
+        let run_document = |test: ParsedTestFile| {
+            let location = test.path.display().to_string();
+            let document = self.run_document(
+                test,
+                &parser,
+                &document_config,
+                &testcase_config,
+                &current_directory,
+                &pw,
+                &events,
             )?;
+            events.emit(&Event::DocumentFinished {
+                location: &location,
+                succeeded: document.count_success,
+                failed: document.count_failed,
+                skipped: document.count_skipped,
+            });
+            Ok(document)
+        };
+        let documents = if jobs > 1 && tests.len() > 1 {
+            run_parallel(tests, jobs, run_document)?
+        } else {
+            tests
+                .into_iter()
+                .map(run_document)
+                .collect::<Result<Vec<_>>>()?
+        };
+        pw.println("");
+        pw.finish_and_clear();
+
+        // initiate outputs
+        let mut outcomes = vec![];
+        let count_documents = documents.len();
+        let (mut count_success, mut count_skipped, mut count_failed, mut count_detached) =
+            (0, 0, 0, 0);
+        for document in documents {
+            outcomes.extend(document.outcomes);
+            count_success += document.count_success;
+            count_skipped += document.count_skipped;
+            count_failed += document.count_failed;
+            count_detached += document.count_detached;
+        }
+
+        events.emit(&Event::RunFinished {
+            documents: count_documents,
+            succeeded: count_success,
+            failed: count_failed,
+            skipped: count_skipped,
+            detached: count_detached,
+            duration_ms: duration_ms(started.elapsed()),
+        });
+
+        // finally render all outcomes of testcase validations
+        let renderer = self.make_renderer(
+            &self.renderer,
+            !self.global.no_color && console::colors_enabled(),
+        );
 
-            let span = debug_span!("test", path = %&test.path.display(), env = ?&test_environment);
-            let _s = span.enter();
-
-            // extract test cases from content ..
-            debug!(
-                format = %&test.parser_type,
-                num_cases = &test.testcases.len(),
-                config = %&config,
-                "running tests",
-            );
-
-            // compile prepended and appended tests, based on both command line
-            // parameters and the inline per-document configuration
-            let prepend_tests = if !config.prepend.is_empty() {
-                parser.find_and_parse(
-                    "prepend test",
-                    &config
-                        .prepend
-                        .iter()
-                        .map(|p| p as &Path)
-                        .collect::<Vec<_>>(),
-                    self.global.cram_compat,
-                )?
+        info!(
+            success = count_success,
+            skipped = count_skipped,
+            failed = count_failed,
+            detached = count_detached,
+        );
+        let outcomes = outcomes.iter().collect::<Vec<_>>();
+        if !events.is_enabled() {
+            print!("{}", renderer.render(&outcomes)?);
+        }
+
+        // .. and write the same outcomes in any additional report format
+        for report in &self.report {
+            let rendered = self
+                .make_renderer(&report.renderer, false)
+                .render(&outcomes)?;
+            std::fs::write(&report.path, rendered)
+                .with_context(|| format!("write report to {}", report.path.display()))?;
+        }
+
+        if count_failed > 0 {
+            Err(anyhow!(ValidationFailedError))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Create the renderer of the given kind, with `color` enabling colo(u)r
+    /// output where the renderer supports it
+    fn make_renderer(&self, renderer: &ScrutRenderer, color: bool) -> Box<dyn Renderer> {
+        let pretty_renderer = PrettyColorRenderer {
+            max_surrounding_lines: DEFAULT_SURROUNDING_LINES,
+            absolute_line_numbers: self.absolute_line_numbers,
+            summarize: true,
+            max_multiline_matched_lines: self.max_multiline_matched_lines,
+            slowest: self.slowest,
+        };
+        let embedded_renderer = PrettyColorRenderer {
+            summarize: false,
+            ..pretty_renderer
+        };
+        match renderer {
+            ScrutRenderer::Auto | ScrutRenderer::Pretty => {
+                if color {
+                    Box::new(pretty_renderer)
+                } else {
+                    Box::new(PrettyMonochromeRenderer::new(pretty_renderer))
+                }
+            }
+            ScrutRenderer::Diff => Box::<DiffRenderer>::default(),
+            ScrutRenderer::Json => Box::<JsonRenderer>::default(),
+            ScrutRenderer::Yaml => Box::<YamlRenderer>::default(),
+            ScrutRenderer::Junit => Box::new(JunitRenderer::new(embedded_renderer)),
+            ScrutRenderer::Tap => Box::new(TapRenderer::new(embedded_renderer)),
+            ScrutRenderer::Github => Box::new(GithubRenderer::new(embedded_renderer)),
+        }
+    }
+
+    /// Runs all testcases of a single test document, including prepended and
+    /// appended testcases, and validates their outputs.
+    #[allow(clippy::too_many_arguments)]
+    fn run_document(
+        &self,
+        mut test: ParsedTestFile,
+        parser: &FileParser,
+        document_config: &DocumentConfig,
+        testcase_config: &TestCaseConfig,
+        current_directory: &Path,
+        pw: &ProgressWriter,
+        events: &EventWriter,
+    ) -> Result<DocumentResult> {
+        let mut document = DocumentResult::default();
+        let location = test.path.display().to_string();
+        events.emit(&Event::DocumentStarted {
+            location: &location,
+        });
+
+        pw.inc(1);
+        pw.set_message(format!(
+            "👀 {}",
+            style(test.path.to_string_lossy()).yellow()
+        ));
+
+        // prefix append and prepend in document config with directory where test is
+        let test_directory = &test.path.parent().unwrap_or(current_directory);
+        test.config.append = prefix_with_directory(test_directory, &test.config.append);
+        test.config.prepend = prefix_with_directory(test_directory, &test.config.prepend);
+
+        // compile configuration from test file and parameters
+        let config: DocumentConfig = test.config.with_overrides_from(document_config);
+
+        // run all testcases once in each shell, and name the shell in the
+        // outcomes only when running in a shell matrix
+        let shells = config.get_shells();
+        for shell in &shells {
+            let shell_name = if config.shells.is_empty() {
+                None
             } else {
-                vec![]
+                shell.map(|shell| shell.display().to_string())
             };
-            let append_tests = if !config.append.is_empty() {
-                parser.find_and_parse(
-                    "append test",
-                    &config.append.iter().map(|p| p as &Path).collect::<Vec<_>>(),
-                    self.global.cram_compat,
-                )?
-            } else {
-                vec![]
+            let name = match shell_name {
+                Some(ref shell) => format!("{} [{shell}]", test.path.display()),
+                None => test.path.display().to_string(),
             };
+            let mut result = self.run_document_in_shell(
+                &test,
+                *shell,
+                shell_name.as_deref(),
+                &name,
+                parser,
+                &config,
+                testcase_config,
+                pw,
+                events,
+            )?;
+            for outcome in result.outcomes.iter_mut() {
+                outcome.shell = shell_name.clone();
+            }
+            document.extend(result);
+        }
 
-            // gather executions from prepended, test file and appended
-            let mut testcases = prepend_tests
-                .iter()
-                .flat_map(|parsed| parsed.testcases.clone())
-                .collect::<Vec<_>>();
-            testcases.extend(test.testcases.clone());
-            testcases.extend(append_tests.iter().flat_map(|test| test.testcases.clone()));
-
-            // setup testing environment
-            let cram_compat = test.parser_type == ParserType::Cram || self.global.cram_compat;
-            let (test_work_directory, env_vars) =
-                test_environment.init_test_file(&test.path, cram_compat)?;
-
-            // update testcase configuration from command line parameters
-            let env_vars =
-                BTreeMap::from_iter(env_vars.iter().map(|(k, v)| (k as &str, v as &str)));
-            let testcases = testcases
-                .iter_mut()
-                .map(|testcase| {
-                    testcase.config = testcase
-                        .config
-                        .with_overrides_from(&testcase_config)
-                        .with_environment(&env_vars);
-                    trace!(testcase = %&testcase, "running test case");
-                    testcase as &TestCase
-                })
-                .collect::<Vec<_>>();
-
-            // get the appropriate or requested executor
-            let executor = make_executor(&test_environment.shell, cram_compat)?;
-
-            // determine output escaping
-            let escaping = self.global.output_escaping(Some(test.parser_type));
-
-            // run all testcases from the file and gather output ..
-            let outputs = executor.execute_all(
-                testcases.as_slice(),
-                &ContextBuilder::default()
-                    .work_directory(PathBuf::from(&test_work_directory))
-                    .temp_directory(test_environment.tmp_directory.as_path_buf())
-                    .file(test.path.clone())
-                    .config(config.clone())
-                    .build()
-                    .context("failed to build execution context")?,
-            );
-            match outputs {
-                // test execution failed ...
-                Err(err) => match err {
-                    // ... because test was skipped
-                    ExecutionError::Skipped(idx) => {
-                        count_skipped += 1;
-                        outcomes.extend(testcases.iter().map(|testcase| Outcome {
-                            location: Some(test.path.display().to_string()),
-                            testcase: (*testcase).clone(),
-                            output: ("", "", None).into(),
-                            escaping: escaping.clone(),
-                            format: test.parser_type,
-                            result: Err(TestCaseError::Skipped),
-                        }));
-                        pw.println(format!(
-                            "⏩ {}: skipped, because testcase #{} ended in exit code {}",
-                            style(test.path.to_string_lossy()).blue(),
-                            idx + 1,
-                            testcases.get(idx).map_or(DEFAULT_SKIP_DOCUMENT_CODE, |t| t
-                                .config
-                                .get_skip_document_code())
-                        ));
-                        continue;
-                    }
+        Ok(document)
+    }
 
-                    // ... because test timed out
-                    ExecutionError::Timeout(timeout, outputs) => {
-                        handle_early_termination(
-                            &outputs,
-                            &testcases,
-                            &mut outcomes,
-                            test.path.display().to_string(),
-                            escaping.clone(),
-                            test.parser_type,
-                            &mut count_success,
-                            &mut count_failed,
-                            &mut count_skipped,
-                            |output, testcase| {
-                                if matches!(output.exit_code, ExitStatus::Timeout(_)) {
-                                    Err(TestCaseError::Timeout)
-                                } else {
-                                    testcase.validate(output)
-                                }
-                            },
-                        );
-
-                        let (location, timeout) = match timeout {
-                            ExecutionTimeout::Index(idx) => (
-                                format!("per-testcase timeout in testcase #{}", idx + 1),
-                                testcases[idx].config.timeout,
-                            ),
-                            ExecutionTimeout::Total => {
-                                ("per-document timeout".to_string(), config.total_timeout)
-                            }
-                        };
-                        pw.println(format!(
-                            "⌛️ {}: execution timed out after {} at {}",
-                            style(test.path.to_string_lossy()).red(),
-                            timeout.map_or_else(
-                                || "<undef>".to_string(), // this should never happen
-                                |t| format_duration(t).to_string()
-                            ),
-                            location,
-                        ));
-                        continue;
-                    }
+    /// Runs all testcases of a single test document in the given shell (or
+    /// the default shell), with `name` naming the document in messages and
+    /// `shell_name` naming the shell in events, if it is part of a matrix
+    #[allow(clippy::too_many_arguments)]
+    fn run_document_in_shell(
+        &self,
+        test: &ParsedTestFile,
+        shell: Option<&Path>,
+        shell_name: Option<&str>,
+        name: &str,
+        parser: &FileParser,
+        config: &DocumentConfig,
+        testcase_config: &TestCaseConfig,
+        pw: &ProgressWriter,
+        events: &EventWriter,
+    ) -> Result<DocumentResult> {
+        let mut document = DocumentResult::default();
+        let location = test.path.display().to_string();
+
+        // initialize environment in which test will run
+        let shell_path = canonical_shell(shell)?;
+        let mut test_environment = TestEnvironment::new(
+            &shell_path,
+            self.global.work_directory.as_deref(),
+            self.global.keep_temporary_directories,
+        )?;
 
-                    // ... because test failed with fail_fast enabled
-                    ExecutionError::Failed(idx, outputs) => {
-                        handle_early_termination(
-                            &outputs,
-                            &testcases,
-                            &mut outcomes,
-                            test.path.display().to_string(),
-                            escaping.clone(),
-                            test.parser_type,
-                            &mut count_success,
-                            &mut count_failed,
-                            &mut count_skipped,
-                            |output, testcase| testcase.validate(output),
-                        );
-
-                        pw.println(format!(
-                            "⚡ {}: stopped at testcase #{} due to fail_fast",
-                            style(test.path.to_string_lossy()).red(),
-                            idx + 1,
-                        ));
-                        continue;
-                    }
+        let span = debug_span!("test", path = %&test.path.display(), env = ?&test_environment);
+        let _s = span.enter();
 
-                    // ... because of a final error
-                    _ => bail!("failing in {:?}: {}", test.path, err),
-                },
+        // extract test cases from content ..
+        debug!(
+            format = %&test.parser_type,
+            num_cases = &test.testcases.len(),
+            config = %&config,
+            "running tests",
+        );
 
-                // test execution succeeded
-                Ok(outputs) => {
-                    if self.debug {
-                        debug_testcases(&test.testcases, &test.path, &outputs);
-                    }
+        // compile prepended and appended tests, based on both command line
+        // parameters and the inline per-document configuration
+        let prepend_tests = if !config.prepend.is_empty() {
+            parser.find_and_parse(
+                "prepend test",
+                &config
+                    .prepend
+                    .iter()
+                    .map(|p| p as &Path)
+                    .collect::<Vec<_>>(),
+                self.global.cram_compat,
+            )?
+        } else {
+            vec![]
+        };
+        let append_tests = if !config.append.is_empty() {
+            parser.find_and_parse(
+                "append test",
+                &config.append.iter().map(|p| p as &Path).collect::<Vec<_>>(),
+                self.global.cram_compat,
+            )?
+        } else {
+            vec![]
+        };
 
-                    // .. to compare the outputs with testcases and gather that
-                    //    outcome for later rendering
-                    let (mut failed, mut success) = (0, 0);
-                    for (testcase, output) in testcases.into_iter().zip(outputs) {
-                        if output.exit_code == ExitStatus::Detached {
-                            count_detached += 1;
-                            if let Some(ref detached_process) = output.detached_process {
-                                kill_detached_process(&pw, detached_process)?;
-                            }
-                            continue;
-                        }
+        // gather executions from prepended, test file and appended
+        let mut testcases = prepend_tests
+            .iter()
+            .flat_map(|parsed| parsed.testcases.clone())
+            .collect::<Vec<_>>();
+        testcases.extend(test.testcases.clone());
+        testcases.extend(append_tests.iter().flat_map(|test| test.testcases.clone()));
+
+        // setup testing environment
+        let cram_compat = test.parser_type == ParserType::Cram || self.global.cram_compat;
+        let (test_work_directory, env_vars) =
+            test_environment.init_test_file(&test.path, cram_compat, config)?;
+
+        // update testcase configuration from command line parameters
+        let env_vars = BTreeMap::from_iter(env_vars.iter().map(|(k, v)| (k as &str, v as &str)));
+        let testcases = testcases
+            .iter_mut()
+            .map(|testcase| {
+                testcase.config = testcase
+                    .config
+                    .with_overrides_from(testcase_config)
+                    .with_environment(&env_vars);
+                trace!(testcase = %&testcase, "running test case");
+                testcase as &TestCase
+            })
+            .collect::<Vec<_>>();
 
-                        let result = testcase.validate(&output);
-                        if result.is_err() {
-                            failed += 1;
-                        } else {
-                            success += 1;
-                        }
-                        outcomes.push(Outcome {
+        // get the appropriate or requested executor
+        let sandbox = make_sandbox(
+            config.get_sandbox(),
+            Path::new(&test_work_directory),
+            &test_environment.tmp_directory.as_path_buf(),
+        )?;
+        let executor = make_executor(
+            &test_environment.shell,
+            cram_compat,
+            config.get_persistent_shell(),
+            sandbox,
+        )?;
+
+        // determine output escaping
+        let escaping = self.global.output_escaping(Some(test.parser_type));
+
+        // run all testcases from the file and gather output ..
+        let observer = DocumentObserver {
+            events,
+            location: &location,
+            testcases: &testcases,
+            escaping: &escaping,
+            format: test.parser_type,
+            shell: shell_name,
+            started: Mutex::default(),
+            results: Mutex::default(),
+        };
+        let outputs = executor.execute_all_observed(
+            testcases.as_slice(),
+            &ContextBuilder::default()
+                .work_directory(PathBuf::from(&test_work_directory))
+                .temp_directory(test_environment.tmp_directory.as_path_buf())
+                .file(test.path.clone())
+                .config(config.clone())
+                .build()
+                .context("failed to build execution context")?,
+            &observer,
+        );
+        match outputs {
+            // test execution failed ...
+            Err(err) => match err {
+                // ... because test was skipped
+                ExecutionError::Skipped(idx, outputs) => {
+                    let skip_document_code =
+                        testcases.get(idx).map_or(DEFAULT_SKIP_DOCUMENT_CODE, |t| {
+                            t.config.get_skip_document_code()
+                        });
+                    events.emit(&Event::DocumentSkipped {
+                        location: &location,
+                        index: idx,
+                        exit_code: skip_document_code,
+                    });
+                    document.count_skipped += 1;
+
+                    // only testcases that always run were executed after the skip
+                    for (index, testcase) in testcases.iter().enumerate() {
+                        let (output, result) = match outputs.get(index) {
+                            Some(output) if output.always_run => {
+                                let result = observer.validate(index, testcase, output);
+                                if result.is_err() {
+                                    document.count_failed += 1;
+                                } else {
+                                    document.count_success += 1;
+                                }
+                                (output.clone(), result)
+                            }
+                            _ => (("", "", None).into(), Err(TestCaseError::Skipped)),
+                        };
+                        document.outcomes.push(Outcome {
                             location: Some(test.path.display().to_string()),
-                            testcase: testcase.clone(),
+                            testcase: (*testcase).clone(),
                             output,
                             escaping: escaping.clone(),
                             format: test.parser_type,
+                            shell: None,
                             result,
                         });
                     }
-                    count_failed += failed;
-                    count_success += success;
-                    let total = failed + success;
-
-                    if failed > 0 {
-                        pw.println(format!(
-                            "❌ {}: failed {} out of {} testcase{}",
-                            style(test.path.to_string_lossy()).red(),
-                            style(failed).red().bold(),
-                            style(total).bold(),
-                            if total == 1 { "" } else { "s" },
-                        ));
-                    } else if self.verbose {
-                        pw.println(format!(
-                            "✅ {}: passed {} testcase{}",
-                            style(test.path.to_string_lossy()).green(),
-                            style(success).green().bold(),
-                            if success == 1 { "" } else { "s" },
-                        ));
-                    }
+                    pw.println(format!(
+                        "⏩ {}: skipped, because testcase #{} ended in exit code {}",
+                        style(name).blue(),
+                        idx + 1,
+                        skip_document_code,
+                    ));
+                    return Ok(document);
                 }
-            }
-        }
-        pw.println("");
-        pw.finish_and_clear();
 
-        // finally render all outcomes of testcase validations
-        let renderer: Box<dyn Renderer> = match self.renderer {
-            ScrutRenderer::Auto | ScrutRenderer::Pretty => {
-                let color_renderer = PrettyColorRenderer {
-                    max_surrounding_lines: DEFAULT_SURROUNDING_LINES,
-                    absolute_line_numbers: self.absolute_line_numbers,
-                    summarize: true,
-                    max_multiline_matched_lines: self.max_multiline_matched_lines,
-                };
-                if !self.global.no_color && console::colors_enabled() {
-                    Box::new(color_renderer)
-                } else {
-                    Box::new(PrettyMonochromeRenderer::new(color_renderer))
+                // ... because test timed out
+                ExecutionError::Timeout(timeout, outputs) => {
+                    handle_early_termination(
+                        &outputs,
+                        &testcases,
+                        &mut document,
+                        test.path.display().to_string(),
+                        escaping.clone(),
+                        test.parser_type,
+                        |index, output, testcase| observer.validate(index, testcase, output),
+                    );
+
+                    let (index, timeout_location, timeout) = match timeout {
+                        ExecutionTimeout::Index(idx) => (
+                            Some(idx),
+                            format!("per-testcase timeout in testcase #{}", idx + 1),
+                            testcases[idx].config.timeout,
+                        ),
+                        ExecutionTimeout::Total => (
+                            None,
+                            "per-document timeout".to_string(),
+                            config.total_timeout,
+                        ),
+                    };
+                    events.emit(&Event::DocumentTimedOut {
+                        location: &location,
+                        index,
+                        timeout_ms: timeout.map(duration_ms),
+                    });
+                    pw.println(format!(
+                        "⌛️ {}: execution timed out after {} at {}",
+                        style(name).red(),
+                        timeout.map_or_else(
+                            || "<undef>".to_string(), // this should never happen
+                            |t| format_duration(t).to_string()
+                        ),
+                        timeout_location,
+                    ));
+                    return Ok(document);
                 }
-            }
-            ScrutRenderer::Diff => Box::<DiffRenderer>::default(),
-            ScrutRenderer::Json => Box::<JsonRenderer>::default(),
-            ScrutRenderer::Yaml => Box::<YamlRenderer>::default(),
-        };
 
-        info!(
-            success = count_success,
-            skipped = count_skipped,
-            failed = count_failed,
-            detached = count_detached,
-        );
-        print!("{}", renderer.render(&outcomes.iter().collect::<Vec<_>>())?);
+                // ... because test failed with fail_fast enabled
+                ExecutionError::Failed(idx, outputs) => {
+                    handle_early_termination(
+                        &outputs,
+                        &testcases,
+                        &mut document,
+                        test.path.display().to_string(),
+                        escaping.clone(),
+                        test.parser_type,
+                        |index, output, testcase| observer.validate(index, testcase, output),
+                    );
+
+                    pw.println(format!(
+                        "⚡ {}: stopped at testcase #{} due to fail_fast",
+                        style(name).red(),
+                        idx + 1,
+                    ));
+                    return Ok(document);
+                }
 
-        if count_failed > 0 {
-            Err(anyhow!(ValidationFailedError))
-        } else {
-            Ok(())
+                // ... because of a final error
+                _ => bail!("failing in {:?}: {}", test.path, err),
+            },
+
+            // test execution succeeded
+            Ok(outputs) => {
+                if self.debug {
+                    debug_testcases(&test.testcases, &test.path, &outputs);
+                }
+
+                // .. to compare the outputs with testcases and gather that
+                //    outcome for later rendering
+                let (mut failed, mut success) = (0, 0);
+                for (index, (testcase, output)) in testcases.iter().zip(outputs).enumerate() {
+                    if output.exit_code == ExitStatus::Detached {
+                        document.count_detached += 1;
+                        if let Some(ref detached_process) = output.detached_process {
+                            kill_detached_process(pw, detached_process)?;
+                        }
+                        continue;
+                    }
+
+                    let result = observer.validate(index, testcase, &output);
+                    if result.is_err() {
+                        failed += 1;
+                    } else {
+                        success += 1;
+                    }
+                    document.outcomes.push(Outcome {
+                        location: Some(test.path.display().to_string()),
+                        testcase: (*testcase).clone(),
+                        output,
+                        escaping: escaping.clone(),
+                        format: test.parser_type,
+                        shell: None,
+                        result,
+                    });
+                }
+                document.count_failed += failed;
+                document.count_success += success;
+                let total = failed + success;
+
+                if failed > 0 {
+                    pw.println(format!(
+                        "❌ {}: failed {} out of {} testcase{}",
+                        style(name).red(),
+                        style(failed).red().bold(),
+                        style(total).bold(),
+                        if total == 1 { "" } else { "s" },
+                    ));
+                } else if self.verbose {
+                    pw.println(format!(
+                        "✅ {}: passed {} testcase{}",
+                        style(name).green(),
+                        style(success).green().bold(),
+                        if success == 1 { "" } else { "s" },
+                    ));
+                }
+            }
         }
+
+        Ok(document)
     }
 
     /// Translates command line arguments into a document config, that has only
@@ -465,6 +793,7 @@ impl Args {
         if !self.prepend_test_file_paths.is_empty() {
             config.prepend.extend(self.prepend_test_file_paths.clone());
         }
+        config.shells = self.shell_matrix.clone();
 
         config.with_defaults_from(&self.global.to_document_config())
     }
@@ -481,44 +810,48 @@ impl Args {
 fn handle_early_termination<F>(
     outputs: &[scrut::output::Output],
     testcases: &[&TestCase],
-    outcomes: &mut Vec<Outcome>,
+    document: &mut DocumentResult,
     location: String,
     escaping: scrut::escaping::Escaper,
     format: ParserType,
-    count_success: &mut usize,
-    count_failed: &mut usize,
-    count_skipped: &mut usize,
     mut validate_output: F,
 ) where
-    F: FnMut(&scrut::output::Output, &TestCase) -> Result<(), TestCaseError>,
+    F: FnMut(usize, &scrut::output::Output, &TestCase) -> Result<(), TestCaseError>,
 {
-    // append outcomes for each testcase that was executed
-    outcomes.extend(
-        outputs
-            .iter()
-            .zip(testcases.iter())
-            .map(|(output, testcase)| {
-                let result = validate_output(output, testcase);
-                if result.is_err() {
-                    *count_failed += 1;
+    // append outcomes for each testcase that was executed, or that was not
+    // executed before a testcase that always runs
+    document
+        .outcomes
+        .extend(outputs.iter().zip(testcases.iter()).enumerate().map(
+            |(index, (output, testcase))| {
+                let result = if output.exit_code == ExitStatus::Skipped && !output.always_run {
+                    document.count_skipped += 1;
+                    Err(TestCaseError::Skipped)
                 } else {
-                    *count_success += 1;
-                }
+                    let result = validate_output(index, output, testcase);
+                    if result.is_err() {
+                        document.count_failed += 1;
+                    } else {
+                        document.count_success += 1;
+                    }
+                    result
+                };
                 Outcome {
                     location: Some(location.clone()),
                     testcase: (*testcase).clone(),
                     output: output.clone(),
                     escaping: escaping.clone(),
                     format,
+                    shell: None,
                     result,
                 }
-            }),
-    );
+            },
+        ));
 
     // append outcomes for testcases not executed
     let missing = testcases.len() - outputs.len();
     if missing > 0 {
-        outcomes.extend(
+        document.outcomes.extend(
             testcases
                 .iter()
                 .skip(outputs.len())
@@ -528,16 +861,133 @@ fn handle_early_termination<F>(
                     output: ("", "", None).into(),
                     escaping: escaping.clone(),
                     format,
+                    shell: None,
                     result: Err(TestCaseError::Skipped),
                 }),
         );
-        *count_skipped += missing;
+        document.count_skipped += missing;
     }
 }
 
+/// Runs the provided function for all documents using the given amount of
+/// worker threads and returns the results in the order of the documents.
+/// Once a run fails no further documents are started and the failure of the
+/// first document (in order) is returned.
+fn run_parallel<T, R, F>(documents: Vec<T>, jobs: usize, run: F) -> Result<Vec<R>>
+where
+    T: Send,
+    R: Send,
+    F: Fn(T) -> Result<R> + Sync,
+{
+    let jobs = jobs.min(documents.len());
+    let queue = Mutex::new(documents.into_iter().enumerate());
+    let aborted = AtomicBool::new(false);
+    let mut results = thread::scope(|scope| {
+        let workers = (0..jobs)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut results = vec![];
+                    while !aborted.load(Ordering::Relaxed) {
+                        let Some((index, document)) =
+                            queue.lock().expect("document queue lock").next()
+                        else {
+                            break;
+                        };
+                        let result = run(document);
+                        if result.is_err() {
+                            aborted.store(true, Ordering::Relaxed);
+                        }
+                        results.push((index, result));
+                    }
+                    results
+                })
+            })
+            .collect::<Vec<_>>();
+        workers
+            .into_iter()
+            .flat_map(|worker| {
+                worker
+                    .join()
+                    .unwrap_or_else(|err| std::panic::resume_unwind(err))
+            })
+            .collect::<Vec<_>>()
+    });
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
 fn prefix_with_directory(prefix: &Path, paths: &[PathBuf]) -> Vec<PathBuf> {
     paths
         .iter()
         .map(|path| prefix.join(path))
         .collect::<Vec<_>>()
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+    use std::thread;
+    use std::time::Duration;
+
+    use anyhow::Result;
+    use anyhow::bail;
+
+    use super::run_parallel;
+
+    /// Documents with a lower index take longer, so that they finish last
+    fn run_document(index: usize) -> Result<String> {
+        thread::sleep(Duration::from_millis(20 - index as u64));
+        Ok(format!("document {index}"))
+    }
+
+    #[test]
+    fn test_run_parallel_returns_results_in_order() {
+        let results = run_parallel((0..20).collect(), 4, run_document).expect("all documents run");
+        assert_eq!(
+            (0..20)
+                .map(|index| format!("document {index}"))
+                .collect::<Vec<_>>(),
+            results
+        );
+    }
+
+    #[test]
+    fn test_run_parallel_matches_serial_run() {
+        let serial = (0..20)
+            .map(run_document)
+            .collect::<Result<Vec<_>>>()
+            .expect("all documents run");
+        for jobs in [1, 2, 8, 32] {
+            let parallel =
+                run_parallel((0..20).collect(), jobs, run_document).expect("all documents run");
+            assert_eq!(serial, parallel, "jobs: {jobs}");
+        }
+    }
+
+    #[test]
+    fn test_run_parallel_stops_at_failing_document() {
+        let started = AtomicUsize::new(0);
+        let err = run_parallel((0..20).collect(), 2, |index: usize| {
+            started.fetch_add(1, Ordering::SeqCst);
+            match index {
+                0 => {
+                    thread::sleep(Duration::from_millis(50));
+                    bail!("document 0 failed")
+                }
+                1 => bail!("document 1 failed"),
+                _ => Ok(index),
+            }
+        })
+        .expect_err("failing document fails the run");
+        assert_eq!(
+            "document 0 failed",
+            err.to_string(),
+            "failure of the first document in order"
+        );
+        assert!(
+            started.load(Ordering::SeqCst) <= 2,
+            "no documents are started after a failure"
+        );
+    }
+}
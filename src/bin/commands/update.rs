@@ -34,6 +34,7 @@ use scrut::outcome::Outcome;
 use scrut::parsers::markdown::DEFAULT_MARKDOWN_LANGUAGES;
 use scrut::parsers::parser::ParserType;
 use scrut::renderers::pretty::DEFAULT_MULTILINE_MATCHED_LINES;
+use scrut::renderers::pretty::DEFAULT_SLOWEST;
 use scrut::renderers::pretty::DEFAULT_SURROUNDING_LINES;
 use scrut::renderers::pretty::PrettyColorRenderer;
 use scrut::renderers::pretty::PrettyMonochromeRenderer;
@@ -50,6 +51,7 @@ use crate::utils::confirm;
 use crate::utils::debug_testcases;
 use crate::utils::get_log_level;
 use crate::utils::make_executor;
+use crate::utils::make_sandbox;
 
 /// Re-run all testcases in given file(s) and update the output expectations
 #[derive(Debug, Parser)]
@@ -159,7 +161,9 @@ impl Args {
             ));
 
             let config = test.config.with_overrides_from(&document_config);
-            let shell_path = canonical_shell(config.shell.as_ref().map(|p| p as &Path))?;
+
+            // documents that run in multiple shells are updated in the first
+            let shell_path = canonical_shell(config.get_shells().first().copied().flatten())?;
 
             let mut test_environment = TestEnvironment::new(
                 &shell_path,
@@ -190,7 +194,7 @@ impl Args {
             // setup test file environment ..
             let cram_compat = test.parser_type == ParserType::Cram;
             let (test_work_directory, env_vars) =
-                test_environment.init_test_file(&test.path, cram_compat)?;
+                test_environment.init_test_file(&test.path, cram_compat, &config)?;
 
             // extract testcases and update with config from parameters
             let env_vars =
@@ -208,7 +212,17 @@ impl Args {
                 .collect::<Vec<_>>();
 
             // get the appropriate or requested executor
-            let executor = make_executor(&test_environment.shell, cram_compat)?;
+            let sandbox = make_sandbox(
+                config.get_sandbox(),
+                Path::new(&test_work_directory),
+                &test_environment.tmp_directory.as_path_buf(),
+            )?;
+            let executor = make_executor(
+                &test_environment.shell,
+                cram_compat,
+                config.get_persistent_shell(),
+                sandbox,
+            )?;
 
             // execute the tests to use the updated result to update the test file
             let execution_result = executor.execute_all(
@@ -225,7 +239,7 @@ impl Args {
                 // test execution failed ..
                 Err(err) => match err {
                     // .. intentionally with skip, so skip
-                    ExecutionError::Skipped(idx) => {
+                    ExecutionError::Skipped(idx, _) => {
                         count_skipped += 1;
                         pw.println(format!(
                             "⏩ {}: skipped, because testcase #{} ended in exit code {}",
@@ -257,6 +271,7 @@ impl Args {
                             output: output.to_owned(),
                             escaping: self.global.output_escaping(Some(test.parser_type)),
                             format: test.parser_type,
+                            shell: None,
                             result,
                         });
                     }
@@ -415,6 +430,7 @@ impl Args {
             absolute_line_numbers: self.absolute_line_numbers,
             summarize: false,
             max_multiline_matched_lines: self.max_multiline_matched_lines,
+            slowest: DEFAULT_SLOWEST,
         };
         let diff: Box<dyn Renderer> = if self.global.no_color {
             Box::new(PrettyMonochromeRenderer::new(color_renderer))
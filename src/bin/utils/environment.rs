@@ -13,6 +13,7 @@ use std::path::PathBuf;
 use anyhow::Context;
 use anyhow::Result;
 use anyhow::anyhow;
+use scrut::config::DocumentConfig;
 use scrut::executors::DEFAULT_SHELL;
 use tempfile::TempDir;
 use tracing::debug;
@@ -142,6 +143,7 @@ impl TestEnvironment {
         &mut self,
         test_file_path: &Path,
         cram_compat: bool,
+        config: &DocumentConfig,
     ) -> Result<(PathBuf, Vec<(String, String)>)> {
         let (test_file_directory, test_file_name) =
             split_path_abs(test_file_path).with_context(|| {
@@ -156,9 +158,12 @@ impl TestEnvironment {
             test_file_name: &test_file_name,
             test_file_directory: &test_file_directory,
             cram_compat,
+            config,
         };
 
-        Ok((per_file.build_work_directory()?, per_file.build_env_vars()?))
+        let work_directory = per_file.build_work_directory()?;
+        let env_vars = per_file.build_env_vars(&work_directory)?;
+        Ok((work_directory, env_vars))
     }
 }
 
@@ -193,6 +198,7 @@ struct TestFileEnvironment<'a> {
     test_file_name: &'a Path,
     test_file_directory: &'a Path,
     cram_compat: bool,
+    config: &'a DocumentConfig,
 }
 
 impl TestFileEnvironment<'_> {
@@ -214,7 +220,7 @@ impl TestFileEnvironment<'_> {
         Ok(test_work_directory)
     }
 
-    fn build_env_vars(&self) -> Result<Vec<(String, String)>> {
+    fn build_env_vars(&self, work_directory: &Path) -> Result<Vec<(String, String)>> {
         let tmp = String::from(&self.test_environment.tmp_directory);
         let mut env_vars = vec![
             (
@@ -246,6 +252,28 @@ impl TestFileEnvironment<'_> {
             env_vars.push(("TMP".to_string(), tmp.clone()));
             env_vars.push(("TEMP".to_string(), tmp));
         }
+        if self.config.get_hermetic() {
+            env_vars.extend(self.build_private_home(work_directory)?);
+        }
+        Ok(env_vars)
+    }
+
+    /// Hermetic documents do not use the home directory of the user, but a
+    /// private one in the work directory, unless it is passed through
+    fn build_private_home(&self, work_directory: &Path) -> Result<Vec<(String, String)>> {
+        let home = work_directory.join(".home");
+        let mut env_vars = vec![];
+        for (name, directory) in [
+            ("HOME", home.clone()),
+            ("XDG_CONFIG_HOME", home.join(".config")),
+        ] {
+            if self.config.env_passthrough.iter().any(|pass| pass == name) {
+                continue;
+            }
+            fs::create_dir_all(&directory)
+                .with_context(|| format!("create private {name} directory"))?;
+            env_vars.push((name.to_string(), directory.to_string_lossy().to_string()));
+        }
         Ok(env_vars)
     }
 }
@@ -310,6 +338,7 @@ mod tests {
     use std::path::PathBuf;
 
     use anyhow::Context;
+    use scrut::config::DocumentConfig;
     use tempfile::TempDir;
 
     use super::TestEnvironment;
@@ -492,7 +521,7 @@ mod tests {
             let test_file_name = format!("some-test-file-{}.md", idx + 1);
             let test_file_path = PathBuf::from(&test_env.work_directory).join(&test_file_name);
             let (work_dir, env_vars) = test_env
-                .init_test_file(&test_file_path, *cram_compat)
+                .init_test_file(&test_file_path, *cram_compat, &DocumentConfig::empty())
                 .with_context(|| format!("initialize for test document {:?}", test_env))
                 .unwrap();
             if *has_provided_work_dir {
@@ -539,4 +568,53 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_file_environment_hermetic_private_home() {
+        let tests = [
+            (vec![], vec!["HOME", "XDG_CONFIG_HOME"]),
+            (vec!["HOME".to_string()], vec!["XDG_CONFIG_HOME"]),
+        ];
+        for (env_passthrough, expected_variables) in tests {
+            let mut test_env = TestEnvironment::new(Path::new("bash"), None, false)
+                .expect("setup test environment");
+            let test_file_path = PathBuf::from(&test_env.work_directory).join("test.md");
+            let config = DocumentConfig {
+                hermetic: Some(true),
+                env_passthrough,
+                ..Default::default()
+            };
+            let (work_dir, env_vars) = test_env
+                .init_test_file(&test_file_path, false, &config)
+                .expect("initialize for test document");
+            let env_vars = env_vars.into_iter().collect::<HashMap<_, _>>();
+            for name in ["HOME", "XDG_CONFIG_HOME"] {
+                let Some(directory) = env_vars.get(name) else {
+                    assert!(
+                        !expected_variables.contains(&name),
+                        "{} is defined environment variable",
+                        name
+                    );
+                    continue;
+                };
+                assert!(
+                    expected_variables.contains(&name),
+                    "{} is not defined environment variable",
+                    name
+                );
+                assert!(
+                    Path::new(directory).starts_with(&work_dir),
+                    "{} directory {:?} is in work directory {:?}",
+                    name,
+                    directory,
+                    &work_dir
+                );
+                assert!(
+                    Path::new(directory).is_dir(),
+                    "{} directory is created",
+                    name
+                );
+            }
+        }
+    }
 }
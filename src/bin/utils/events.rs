@@ -0,0 +1,231 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use clap::ValueEnum;
+use scrut::escaping::Escaper;
+use scrut::executors::executor::ExecutionObserver;
+use scrut::outcome::Outcome;
+use scrut::output::ExitStatus;
+use scrut::output::Output;
+use scrut::output::duration_ms;
+use scrut::parsers::parser::ParserType;
+use scrut::testcase::TestCase;
+use scrut::testcase::TestCaseError;
+use serde::Serialize;
+use tracing::warn;
+
+/// Supported formats of the event stream
+#[derive(Debug, Clone, ValueEnum)]
+pub(crate) enum EventFormat {
+    /// One JSON object per line
+    Ndjson,
+}
+
+/// An event that describes the progress of a test run
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub(crate) enum Event<'a> {
+    /// A test document was found and will be run
+    DocumentDiscovered { location: &'a str, testcases: usize },
+
+    /// Execution of the test cases of a test document starts
+    DocumentStarted { location: &'a str },
+
+    /// Execution of a test case starts
+    TestcaseStarted {
+        location: &'a str,
+        index: usize,
+        line: usize,
+        title: &'a str,
+    },
+
+    /// A test case was executed and validated
+    TestcaseFinished {
+        location: &'a str,
+        index: usize,
+        duration_ms: u64,
+        outcome: &'a Outcome,
+    },
+
+    /// A test document was skipped, because a test case ended in the skip
+    /// exit code
+    DocumentSkipped {
+        location: &'a str,
+        index: usize,
+        exit_code: i32,
+    },
+
+    /// Execution of a test document timed out, with `index` being set if the
+    /// timeout of a single test case was exceeded
+    DocumentTimedOut {
+        location: &'a str,
+        index: Option<usize>,
+        timeout_ms: Option<u64>,
+    },
+
+    /// All test cases of a test document are done
+    DocumentFinished {
+        location: &'a str,
+        succeeded: usize,
+        failed: usize,
+        skipped: usize,
+    },
+
+    /// All test documents are done
+    RunFinished {
+        documents: usize,
+        succeeded: usize,
+        failed: usize,
+        skipped: usize,
+        detached: usize,
+        duration_ms: u64,
+    },
+}
+
+/// Writes events to STDOUT as they happen, if a format is set. Events from
+/// multiple threads are written one line at a time.
+pub(crate) struct EventWriter {
+    format: Option<EventFormat>,
+    lock: Mutex<()>,
+}
+
+impl EventWriter {
+    pub(crate) fn new(format: Option<EventFormat>) -> Self {
+        Self {
+            format,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Whether events are written at all
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.format.is_some()
+    }
+
+    pub(crate) fn emit(&self, event: &Event) {
+        let Some(EventFormat::Ndjson) = self.format else {
+            return;
+        };
+        let line = match serde_json::to_string(event) {
+            Ok(line) => line,
+            Err(err) => {
+                warn!("failed to serialize event: {err}");
+                return;
+            }
+        };
+        let _lock = self.lock.lock().expect("event writer lock");
+        let mut stdout = std::io::stdout().lock();
+        if let Err(err) = writeln!(stdout, "{line}").and_then(|_| stdout.flush()) {
+            warn!("failed to write event: {err}");
+        }
+    }
+}
+
+/// Emits the events of test cases of a single test document while they are
+/// executed
+pub(crate) struct DocumentObserver<'a> {
+    pub(crate) events: &'a EventWriter,
+    pub(crate) location: &'a str,
+    pub(crate) testcases: &'a [&'a TestCase],
+    pub(crate) escaping: &'a Escaper,
+    pub(crate) format: ParserType,
+    pub(crate) shell: Option<&'a str>,
+    pub(crate) started: Mutex<Vec<Option<Instant>>>,
+    pub(crate) results: Mutex<Vec<Option<Result<(), TestCaseError>>>>,
+}
+
+impl DocumentObserver<'_> {
+    /// Returns the result of the validation of the output of the test case at
+    /// the index, that was computed for the event when the test case finished,
+    /// so that each output is validated only once
+    pub(crate) fn validate(
+        &self,
+        index: usize,
+        testcase: &TestCase,
+        output: &Output,
+    ) -> Result<(), TestCaseError> {
+        self.results
+            .lock()
+            .expect("results lock")
+            .get_mut(index)
+            .and_then(Option::take)
+            .unwrap_or_else(|| validate(testcase, output))
+    }
+}
+
+impl ExecutionObserver for DocumentObserver<'_> {
+    fn testcase_started(&self, index: usize, testcase: &TestCase) {
+        if !self.events.is_enabled() {
+            return;
+        }
+        let mut started = self.started.lock().expect("started lock");
+        if started.len() <= index {
+            started.resize(index + 1, None);
+        }
+        started[index] = Some(Instant::now());
+        self.events.emit(&Event::TestcaseStarted {
+            location: self.location,
+            index,
+            line: testcase.line_number,
+            title: &testcase.title,
+        });
+    }
+
+    fn testcase_finished(&self, index: usize, _testcase: &TestCase, output: &Output) {
+        if !self.events.is_enabled() || output.exit_code == ExitStatus::Detached {
+            return;
+        }
+        let Some(testcase) = self.testcases.get(index) else {
+            return;
+        };
+        // prefer the duration of the execution itself, if the executor knows it
+        let duration = output.duration.unwrap_or_else(|| {
+            self.started
+                .lock()
+                .expect("started lock")
+                .get(index)
+                .copied()
+                .flatten()
+                .map_or(Duration::ZERO, |started| started.elapsed())
+        });
+        let outcome = Outcome {
+            location: Some(self.location.to_string()),
+            testcase: (*testcase).clone(),
+            output: output.clone(),
+            escaping: self.escaping.clone(),
+            format: self.format,
+            shell: self.shell.map(str::to_string),
+            result: validate(testcase, output),
+        };
+        self.events.emit(&Event::TestcaseFinished {
+            location: self.location,
+            index,
+            duration_ms: duration_ms(duration),
+            outcome: &outcome,
+        });
+
+        let mut results = self.results.lock().expect("results lock");
+        if results.len() <= index {
+            results.resize_with(index + 1, || None);
+        }
+        results[index] = Some(outcome.result);
+    }
+}
+
+/// Validates the output of the test case, which fails if it ran into a timeout
+fn validate(testcase: &TestCase, output: &Output) -> Result<(), TestCaseError> {
+    if matches!(output.exit_code, ExitStatus::Timeout(_)) {
+        Err(TestCaseError::Timeout)
+    } else {
+        testcase.validate(output)
+    }
+}
@@ -6,17 +6,63 @@
  */
 
 use std::path::Path;
+use std::sync::Arc;
 
+use anyhow::Context;
 use anyhow::Result;
 use scrut::executors::bash_runner::BashRunner;
 use scrut::executors::bash_script_executor::BashScriptExecutor;
 use scrut::executors::executor::Executor;
+use scrut::executors::sandbox::Sandbox;
 use scrut::executors::stateful_executor::StatefulExecutor;
+use scrut::executors::stateful_executor::StatefulExecutorRunnerGenerator;
 
-pub(crate) fn make_executor(shell: &Path, cram_compat: bool) -> Result<Box<dyn Executor>> {
+pub(crate) fn make_executor(
+    shell: &Path,
+    cram_compat: bool,
+    persistent_shell: bool,
+    sandbox: Option<Arc<Sandbox>>,
+) -> Result<Box<dyn Executor>> {
     Ok(if cram_compat {
-        Box::new(BashScriptExecutor::new(shell))
+        Box::new(BashScriptExecutor::new(shell).with_sandbox(sandbox))
+    } else if persistent_shell {
+        Box::new(StatefulExecutor::new(session_generator(shell, sandbox)?))
+    } else if let Some(sandbox) = sandbox {
+        Box::new(StatefulExecutor::new(
+            BashRunner::sandboxed_stateful_generator(shell, sandbox),
+        ))
     } else {
         Box::new(StatefulExecutor::new(BashRunner::stateful_generator(shell)))
     })
 }
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn session_generator(
+    shell: &Path,
+    sandbox: Option<Arc<Sandbox>>,
+) -> Result<StatefulExecutorRunnerGenerator> {
+    Ok(scrut::executors::session_runner::SessionRunner::stateful_generator(shell, sandbox))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn session_generator(
+    _shell: &Path,
+    _sandbox: Option<Arc<Sandbox>>,
+) -> Result<StatefulExecutorRunnerGenerator> {
+    anyhow::bail!("persistent shell is only supported on Linux and MacOS")
+}
+
+/// Create a sandbox for the execution of a single document, if enabled, in
+/// which only the work and the temporary directory are writable
+pub(crate) fn make_sandbox(
+    enabled: bool,
+    work_directory: &Path,
+    temp_directory: &Path,
+) -> Result<Option<Arc<Sandbox>>> {
+    if !enabled {
+        return Ok(None);
+    }
+    let sandbox =
+        Sandbox::new(&[work_directory, temp_directory]).context("create sandbox for tests")?;
+    Ok(Some(Arc::new(sandbox)))
+}
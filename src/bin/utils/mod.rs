@@ -7,15 +7,19 @@
 
 mod debug;
 mod environment;
+mod events;
 mod executorutil;
 mod file_parser;
 mod kill;
 mod namer;
+mod selection;
 mod ui;
 
 pub(crate) use debug::*;
 pub(crate) use environment::*;
+pub(crate) use events::*;
 pub(crate) use executorutil::*;
 pub(crate) use file_parser::*;
 pub(crate) use kill::*;
+pub(crate) use selection::*;
 pub(crate) use ui::*;
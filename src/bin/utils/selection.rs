@@ -0,0 +1,234 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::path::PathBuf;
+
+use regex::Regex;
+use scrut::testcase::TestCase;
+
+use super::ParsedTestFile;
+
+/// Selects which test cases of the test documents are run, by their line
+/// number in the document, their title or their tags. Test cases must match
+/// all provided criteria to be selected.
+#[derive(Debug, Default)]
+pub struct TestCaseSelector {
+    /// Regular expression that must match the title of the test case
+    filter: Option<Regex>,
+
+    /// Test cases must have at least one of these tags
+    tags: Vec<String>,
+
+    /// Line numbers per test document of test cases that are selected.
+    /// Documents that are not listed here are not filtered by line.
+    lines: BTreeMap<PathBuf, Vec<usize>>,
+}
+
+impl TestCaseSelector {
+    pub fn new(filter: Option<Regex>, tags: Vec<String>) -> Self {
+        Self {
+            filter,
+            tags,
+            lines: BTreeMap::new(),
+        }
+    }
+
+    /// Splits the provided paths, which can be either paths to files or
+    /// directories or `PATH:LINE` selectors of test cases in a file, into the
+    /// paths that need to be parsed and remembers the line selectors.
+    pub fn add_paths(&mut self, paths: &[PathBuf]) -> Vec<PathBuf> {
+        let mut whole = vec![];
+        let mut result = vec![];
+        for path in paths {
+            let (path, line) = match split_line_selector(path) {
+                Some((path, line)) => (path, Some(line)),
+                None => (path.clone(), None),
+            };
+            match line {
+                Some(line) => self.lines.entry(path.clone()).or_default().push(line),
+                None => whole.push(path.clone()),
+            }
+            if !result.contains(&path) {
+                result.push(path);
+            }
+        }
+
+        // a document that is selected as a whole is not filtered by line
+        for path in whole {
+            self.lines.remove(&path);
+        }
+        result
+    }
+
+    /// Returns true if any criteria are set, so that not all test cases are run
+    pub fn is_selective(&self) -> bool {
+        self.filter.is_some() || !self.tags.is_empty() || !self.lines.is_empty()
+    }
+
+    /// Removes all test cases from the test document that are not selected
+    pub fn select(&self, test: &mut ParsedTestFile) {
+        let lines = self.lines.get(&test.path).map(|lines| {
+            lines
+                .iter()
+                .filter_map(|line| find_testcase_at_line(&test.testcases, *line))
+                .collect::<Vec<_>>()
+        });
+        test.testcases = std::mem::take(&mut test.testcases)
+            .into_iter()
+            .filter(|testcase| {
+                lines
+                    .as_ref()
+                    .is_none_or(|lines| lines.contains(&testcase.line_number))
+                    && self.matches(testcase)
+            })
+            .collect();
+    }
+
+    /// Returns true if the title and the tags of the test case match
+    fn matches(&self, testcase: &TestCase) -> bool {
+        self.filter
+            .as_ref()
+            .is_none_or(|filter| filter.is_match(&testcase.title))
+            && (self.tags.is_empty()
+                || self
+                    .tags
+                    .iter()
+                    .any(|tag| testcase.config.tags.contains(tag)))
+    }
+}
+
+/// Splits a `PATH:LINE` selector into path and line number, unless the
+/// provided path exists as-is
+fn split_line_selector(path: &Path) -> Option<(PathBuf, usize)> {
+    if path.exists() {
+        return None;
+    }
+    let (path, line) = path.to_str()?.rsplit_once(':')?;
+    let line = line.parse().ok()?;
+    Some((PathBuf::from(path), line))
+}
+
+/// Returns the line number of the test case that starts at, or is the closest
+/// to start before, the provided line
+fn find_testcase_at_line(testcases: &[TestCase], line: usize) -> Option<usize> {
+    testcases
+        .iter()
+        .map(|testcase| testcase.line_number)
+        .filter(|line_number| *line_number <= line)
+        .max()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use regex::Regex;
+    use scrut::config::DocumentConfig;
+    use scrut::config::TestCaseConfig;
+    use scrut::parsers::parser::ParserType;
+    use scrut::testcase::TestCase;
+
+    use super::ParsedTestFile;
+    use super::TestCaseSelector;
+
+    fn parsed_test_file() -> ParsedTestFile {
+        let testcase = |title: &str, line_number: usize, tags: &[&str]| TestCase {
+            title: title.into(),
+            shell_expression: "true".into(),
+            line_number,
+            config: TestCaseConfig {
+                tags: tags.iter().map(|tag| tag.to_string()).collect(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        ParsedTestFile {
+            path: PathBuf::from("does-not-exist.md"),
+            content: String::new(),
+            parser_type: ParserType::Markdown,
+            testcases: vec![
+                testcase("Create a file", 5, &[]),
+                testcase("Read the file", 12, &["slow"]),
+                testcase("Delete the file", 20, &["slow", "network"]),
+            ],
+            config: DocumentConfig::empty(),
+        }
+    }
+
+    fn selected_titles(selector: &TestCaseSelector) -> Vec<String> {
+        let mut test = parsed_test_file();
+        selector.select(&mut test);
+        test.testcases
+            .into_iter()
+            .map(|testcase| testcase.title)
+            .collect()
+    }
+
+    #[test]
+    fn test_select_all_without_criteria() {
+        let selector = TestCaseSelector::default();
+        assert!(!selector.is_selective());
+        assert_eq!(
+            vec!["Create a file", "Read the file", "Delete the file"],
+            selected_titles(&selector),
+        );
+    }
+
+    #[test]
+    fn test_select_by_filter_and_tags() {
+        let tests = [
+            (
+                Some("file$"),
+                vec![],
+                vec!["Create a file", "Read the file", "Delete the file"],
+            ),
+            (
+                Some("^(Create|Delete)"),
+                vec![],
+                vec!["Create a file", "Delete the file"],
+            ),
+            (None, vec!["slow"], vec!["Read the file", "Delete the file"]),
+            (None, vec!["network", "other"], vec!["Delete the file"]),
+            (Some("Read"), vec!["slow"], vec!["Read the file"]),
+            (Some("Create"), vec!["slow"], vec![]),
+        ];
+        for (filter, tags, expected) in tests {
+            let selector = TestCaseSelector::new(
+                filter.map(|filter| Regex::new(filter).expect("compile filter")),
+                tags.into_iter().map(String::from).collect(),
+            );
+            assert!(selector.is_selective());
+            assert_eq!(expected, selected_titles(&selector), "filter {filter:?}");
+        }
+    }
+
+    #[test]
+    fn test_select_by_line() {
+        let tests = [
+            (vec!["does-not-exist.md:5"], vec!["Create a file"]),
+            (vec!["does-not-exist.md:14"], vec!["Read the file"]),
+            (
+                vec!["does-not-exist.md:20", "does-not-exist.md:6"],
+                vec!["Create a file", "Delete the file"],
+            ),
+            (vec!["does-not-exist.md:3"], vec![]),
+            (
+                vec!["does-not-exist.md:3", "does-not-exist.md"],
+                vec!["Create a file", "Read the file", "Delete the file"],
+            ),
+        ];
+        for (paths, expected) in tests {
+            let mut selector = TestCaseSelector::default();
+            let parse_paths =
+                selector.add_paths(&paths.iter().map(PathBuf::from).collect::<Vec<_>>());
+            assert_eq!(vec![PathBuf::from("does-not-exist.md")], parse_paths);
+            assert_eq!(expected, selected_titles(&selector), "paths {paths:?}");
+        }
+    }
+}
@@ -9,6 +9,7 @@ use std::collections::BTreeMap;
 use std::fmt;
 use std::fmt::Display;
 use std::marker::PhantomData;
+use std::path::Path;
 use std::path::PathBuf;
 use std::time::Duration;
 
@@ -37,6 +38,10 @@ pub enum TestMode {
     /// validate it against a JSON Schema provided inline as YAML.
     #[serde(rename = "jsonschema")]
     JsonSchema,
+    /// Interactive validation mode: execute command in a pseudo terminal,
+    /// drive it with a script of directives (wait for screen content, send
+    /// keys) and assert on the screen or the full transcript.
+    Interactive,
 }
 
 /// The exit code that any test execution can return to skip all tests in one document
@@ -57,6 +62,23 @@ pub struct DocumentConfig {
     #[serde(skip_serializing_if = "TestCaseConfig::is_empty")]
     pub defaults: TestCaseConfig,
 
+    /// Names of environment variables that are passed through from the host
+    /// into the executions of a hermetic document
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pub env_passthrough: Vec<String>,
+
+    /// Whether to start all executions from an empty environment, that only
+    /// contains the variables set by Scrut, a private `HOME` and the variables
+    /// listed in `env_passthrough`, instead of inheriting the environment of
+    /// the host
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hermetic: Option<bool>,
+
+    /// Whether to run all tests in one long-lived shell process, instead of
+    /// starting a new shell for each test. Only supported on Linux and MacOS.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub persistent_shell: Option<bool>,
+
     /// Include these paths in order, as if they were part of this file. All tests
     /// within the prepend paths are prepended to the tests defined in this file.
     /// Use-case is common/shared test setup. Paths must be relative to the
@@ -64,11 +86,22 @@ pub struct DocumentConfig {
     #[serde(skip_serializing_if = "<[_]>::is_empty")]
     pub prepend: Vec<PathBuf>,
 
+    /// Whether to run all tests in a sandbox, in which the filesystem is
+    /// read-only, except for the work and the temporary directory, and only
+    /// the loopback network is available. Only supported on Linux.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sandbox: Option<bool>,
+
     /// The path to the shell. If a full path is not provided, then the command
     /// must be in $PATH.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub shell: Option<PathBuf>,
 
+    /// Paths to shells in which all tests are executed, one run per shell.
+    /// Takes precedence over `shell`.
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pub shells: Vec<PathBuf>,
+
     /// Timeout for the executions of all tests.
     #[serde(
         skip_serializing_if = "is_none_or_default_timeout",
@@ -109,6 +142,11 @@ impl DocumentConfig {
     /// Returns true if none the configuration parameters are set
     pub fn is_empty(&self) -> bool {
         self.shell.is_none()
+            && self.shells.is_empty()
+            && self.sandbox.is_none()
+            && self.hermetic.is_none()
+            && self.persistent_shell.is_none()
+            && self.env_passthrough.is_empty()
             && self.total_timeout.is_none()
             && self.prepend.is_empty()
             && self.append.is_empty()
@@ -116,7 +154,8 @@ impl DocumentConfig {
     }
 
     /// Returns a new instance that fills in unset values from the provided defaults.
-    /// Values for `append` and `prepend` are extended, not overwritten.
+    /// Values for `append`, `prepend` and `env_passthrough` are extended, not
+    /// overwritten.
     pub fn with_defaults_from(&self, defaults: &Self) -> Self {
         // append is added at the end ..
         let mut append = defaults.append.clone();
@@ -126,20 +165,64 @@ impl DocumentConfig {
         let mut prepend = self.prepend.clone();
         prepend.extend(defaults.prepend.clone());
 
+        // passed through variables are combined
+        let mut env_passthrough = defaults.env_passthrough.clone();
+        for name in &self.env_passthrough {
+            if !env_passthrough.contains(name) {
+                env_passthrough.push(name.clone());
+            }
+        }
+
         Self {
             append,
             prepend,
             defaults: self.defaults.with_defaults_from(&defaults.defaults),
+            env_passthrough,
+            hermetic: self.hermetic.or(defaults.hermetic),
+            persistent_shell: self.persistent_shell.or(defaults.persistent_shell),
+            sandbox: self.sandbox.or(defaults.sandbox),
             shell: self.shell.clone().or_else(|| defaults.shell.clone()),
+            // a shell that is set explicitly replaces the shells of the defaults
+            shells: if !self.shells.is_empty() || self.shell.is_some() {
+                self.shells.clone()
+            } else {
+                defaults.shells.clone()
+            },
             total_timeout: self.total_timeout.or(defaults.total_timeout),
         }
     }
 
     /// Returns a new instance that is overridden with provided (set) values.
-    /// Values for `append` and `prepend` are extended, not overwritten.
+    /// Values for `append`, `prepend` and `env_passthrough` are extended, not
+    /// overwritten.
     pub fn with_overrides_from(&self, overrides: &Self) -> Self {
         overrides.with_defaults_from(self)
     }
+
+    pub fn get_sandbox(&self) -> bool {
+        self.sandbox.unwrap_or(false)
+    }
+
+    pub fn get_hermetic(&self) -> bool {
+        self.hermetic.unwrap_or(false)
+    }
+
+    pub fn get_persistent_shell(&self) -> bool {
+        self.persistent_shell.unwrap_or(false)
+    }
+
+    /// Returns the shells in which the tests are executed: either all `shells`
+    /// or only `shell`, with `None` standing for the default shell
+    pub fn get_shells(&self) -> Vec<Option<&Path>> {
+        if self.shells.is_empty() {
+            vec![self.shell.as_deref()]
+        } else {
+            self.shells
+                .iter()
+                .map(|shell| Some(shell.as_path()))
+                .collect()
+        }
+    }
 }
 
 impl Display for DocumentConfig {
@@ -168,11 +251,11 @@ pub enum OutputStreamControl {
     /// Consider both STDOUT and STDERR when evaluating expectations
     /// Caution: Order of STDOUT and STDERR is not guaranteed.
     Combined,
-    // Leave it to the user to explicitly mark which output expectations are for
-    // STDOUT and which are for STDERR by adding `@STDOUT` and `@STDERR` marks
-    // that denote that all following expectations (until the next mar or the
-    // end) are for the identified stream
-    // TODO(implement) Marked,
+    /// Leave it to the user to explicitly mark which output expectations are for
+    /// STDOUT and which are for STDERR by adding `@STDOUT` and `@STDERR` marks
+    /// that denote that all following expectations (until the next mark or the
+    /// end) are for the identified stream
+    Marked,
 }
 
 impl Display for OutputStreamControl {
@@ -233,6 +316,155 @@ impl TestCaseWait {
     }
 }
 
+/// The pseudo terminal in which test cases in interactive mode are executed
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TestCaseTerminal {
+    /// Width of the terminal in characters
+    pub columns: u16,
+
+    /// Height of the terminal in lines
+    pub rows: u16,
+
+    /// How long a `WAIT` directive waits for the expected content to show up
+    /// on the screen, before the test case fails.
+    #[serde(
+        deserialize_with = "parse_duration",
+        serialize_with = "render_duration"
+    )]
+    pub wait_timeout: Duration,
+}
+
+impl Default for TestCaseTerminal {
+    fn default() -> Self {
+        Self {
+            columns: 80,
+            rows: 24,
+            wait_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+impl Display for TestCaseTerminal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let out = serde_json::to_string(&self).map_err(|_| std::fmt::Error)?;
+        write!(f, "{}", out)
+    }
+}
+
+/// Resource limits of the shell process in which a test case is executed,
+/// that are inherited by all processes it starts. Except for `processes`,
+/// the limits apply to each process individually.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TestCaseLimits {
+    /// Max size of the virtual memory of a process (`RLIMIT_AS`), either in
+    /// bytes or with a unit, like `512MiB` or `2GB`
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "parse_byte_size_opt",
+        serialize_with = "render_byte_size_opt"
+    )]
+    pub memory: Option<u64>,
+
+    /// Max CPU time a process can consume (`RLIMIT_CPU`), rounded up to
+    /// full seconds
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "parse_duration_opt",
+        serialize_with = "render_duration_opt"
+    )]
+    pub cpu_time: Option<Duration>,
+
+    /// Max amount of open file descriptors of a process (`RLIMIT_NOFILE`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub open_files: Option<u64>,
+
+    /// Max amount of processes of the executing user (`RLIMIT_NPROC`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub processes: Option<u64>,
+}
+
+impl TestCaseLimits {
+    /// Returns true if no limit is set
+    pub fn is_empty(&self) -> bool {
+        self.memory.is_none()
+            && self.cpu_time.is_none()
+            && self.open_files.is_none()
+            && self.processes.is_none()
+    }
+
+    /// Returns a new instance that fills in unset limits from the provided defaults
+    pub fn with_defaults_from(&self, defaults: &Self) -> Self {
+        Self {
+            memory: self.memory.or(defaults.memory),
+            cpu_time: self.cpu_time.or(defaults.cpu_time),
+            open_files: self.open_files.or(defaults.open_files),
+            processes: self.processes.or(defaults.processes),
+        }
+    }
+
+    /// Returns all set limits
+    pub fn to_vec(&self) -> Vec<ResourceLimit> {
+        [
+            self.memory.map(ResourceLimit::Memory),
+            self.cpu_time.map(ResourceLimit::CpuTime),
+            self.open_files.map(ResourceLimit::OpenFiles),
+            self.processes.map(ResourceLimit::Processes),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+}
+
+impl Display for TestCaseLimits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let limits = self
+            .to_vec()
+            .iter()
+            .map(|limit| limit.to_string())
+            .collect::<Vec<_>>();
+        write!(f, "{{{}}}", limits.join(", "))
+    }
+}
+
+/// A single resource limit of [`TestCaseLimits`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ResourceLimit {
+    Memory(u64),
+    CpuTime(Duration),
+    OpenFiles(u64),
+    Processes(u64),
+}
+
+impl ResourceLimit {
+    /// The name of the limit in the configuration
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Memory(_) => "memory",
+            Self::CpuTime(_) => "cpu_time",
+            Self::OpenFiles(_) => "open_files",
+            Self::Processes(_) => "processes",
+        }
+    }
+
+    /// The value of the limit, as it is written in the configuration
+    pub fn value(&self) -> String {
+        match self {
+            Self::Memory(bytes) => format_byte_size(*bytes),
+            Self::CpuTime(duration) => humantime::format_duration(*duration).to_string(),
+            Self::OpenFiles(amount) | Self::Processes(amount) => amount.to_string(),
+        }
+    }
+}
+
+impl Display for ResourceLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.name(), self.value())
+    }
+}
+
 impl Display for TestCaseWait {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let out = serde_json::to_string(&self).map_err(|_| std::fmt::Error)?;
@@ -261,6 +493,14 @@ pub struct TestCaseConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub fail_fast: Option<bool>,
 
+    /// If true, this test case is executed even if the execution of the
+    /// document ended early, because a previous test case timed out, failed
+    /// with `fail_fast` or returned the `skip_document_code`. Purpose is to
+    /// guarantee teardown (e.g. stopping servers) that previous test cases
+    /// rely on.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub always_run: Option<bool>,
+
     /// A set of environment variable names and values that will be explicitly set
     /// for the test.
     #[serde(skip_serializing_if = "BTreeMap::is_empty")]
@@ -275,7 +515,8 @@ pub struct TestCaseConfig {
     /// - `stdout`: All expectations apply to what is printed on STDOUT
     /// - `stderr`: All expectations apply to what is printed on STDERR
     /// - `combined`: STDOUT and STDERR will combined into a single stream where all expectations are applied on
-    /// - `marked` (todo): User marks which expectations are intended for which stream explicitly
+    /// - `marked`: User marks which expectations are intended for which stream explicitly,
+    ///   using `@STDOUT` and `@STDERR` lines
     #[serde(skip_serializing_if = "Option::is_none")]
     pub output_stream: Option<OutputStreamControl>,
 
@@ -296,6 +537,45 @@ pub struct TestCaseConfig {
     )]
     pub timeout: Option<Duration>,
 
+    /// A max execution time a test can run before it is considered failed.
+    /// Unlike timeout(), the test is not aborted, but runs to its end and is
+    /// validated as usual.
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "parse_duration_opt",
+        serialize_with = "render_duration_opt"
+    )]
+    pub max_duration: Option<Duration>,
+
+    /// The max peak resident memory of the largest process of the execution,
+    /// either in bytes or with a unit, like `100MiB`. Unlike limits(), the
+    /// test is not constrained, but considered failed if it used more.
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "parse_byte_size_opt",
+        serialize_with = "render_byte_size_opt"
+    )]
+    pub max_rss: Option<u64>,
+
+    /// How often a failing test is run again, before it is considered failed.
+    /// Each retry starts from the same shell state as the first attempt.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retries: Option<usize>,
+
+    /// How long to wait before each retry of a failing test
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "parse_duration_opt",
+        serialize_with = "render_duration_opt"
+    )]
+    pub retry_delay: Option<Duration>,
+
+    /// Resource limits of the processes that execute the test, like
+    /// `{memory: 512MiB, cpu_time: 30s, open_files: 256, processes: 64}`.
+    /// Only supported on Linux.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limits: Option<TestCaseLimits>,
+
     /// Sleep for some time before starting this test (i.e. continuing with testing).
     /// If path is provided, then wait will be aborted (and the testing continues)
     /// as soon as path exists and the test will fail if it does not show up
@@ -314,9 +594,20 @@ pub struct TestCaseConfig {
 
     /// The execution mode for this test case:
     /// - `output` (default): execute command, compare output expectations
+    /// - `jsonschema`: execute command, validate output against a JSON Schema
     /// - `interactive`: drive a PTY session with WAIT/SEND_KEYS/ASSERT directives
     #[serde(skip_serializing_if = "Option::is_none")]
     pub mode: Option<TestMode>,
+
+    /// Size of the pseudo terminal and timeout of `WAIT` directives for test
+    /// cases in interactive mode.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub terminal: Option<TestCaseTerminal>,
+
+    /// Free-form labels of the test case, that can be used to select test
+    /// cases from the command line, e.g. `scrut test --tag slow`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
 }
 
 impl TestCaseConfig {
@@ -361,14 +652,22 @@ impl TestCaseConfig {
         self.output_stream.is_none()
             && self.keep_crlf.is_none()
             && self.timeout.is_none()
+            && self.max_duration.is_none()
+            && self.max_rss.is_none()
+            && self.retries.is_none()
+            && self.retry_delay.is_none()
+            && self.limits.is_none()
             && self.detached.is_none()
             && self.fail_fast.is_none()
+            && self.always_run.is_none()
             && self.wait.is_none()
             && self.skip_document_code.is_none()
             && self.strip_ansi_escaping.is_none()
             && self.environment.is_empty()
             && self.interpolated.is_none()
             && self.mode.is_none()
+            && self.terminal.is_none()
+            && self.tags.is_empty()
     }
 
     /// Returns a new instance that fills in unset values from the provided defaults
@@ -380,6 +679,14 @@ impl TestCaseConfig {
                 .or_else(|| defaults.output_stream.clone()),
             keep_crlf: self.keep_crlf.or(defaults.keep_crlf),
             timeout: self.timeout.or(defaults.timeout),
+            max_duration: self.max_duration.or(defaults.max_duration),
+            max_rss: self.max_rss.or(defaults.max_rss),
+            retries: self.retries.or(defaults.retries),
+            retry_delay: self.retry_delay.or(defaults.retry_delay),
+            limits: match (&self.limits, &defaults.limits) {
+                (Some(limits), Some(defaults)) => Some(limits.with_defaults_from(defaults)),
+                (limits, defaults) => limits.clone().or_else(|| defaults.clone()),
+            },
             environment: self
                 .environment
                 .clone()
@@ -392,11 +699,18 @@ impl TestCaseConfig {
                 .clone()
                 .or_else(|| defaults.detached_kill_signal.clone()),
             fail_fast: self.fail_fast.or(defaults.fail_fast),
+            always_run: self.always_run.or(defaults.always_run),
             wait: self.wait.clone().or_else(|| defaults.wait.clone()),
             skip_document_code: self.skip_document_code.or(defaults.skip_document_code),
             strip_ansi_escaping: self.strip_ansi_escaping.or(defaults.strip_ansi_escaping),
             interpolated: self.interpolated.or(defaults.interpolated),
             mode: self.mode.clone().or_else(|| defaults.mode.clone()),
+            terminal: self.terminal.clone().or_else(|| defaults.terminal.clone()),
+            tags: if self.tags.is_empty() {
+                defaults.tags.clone()
+            } else {
+                self.tags.clone()
+            },
         }
     }
 
@@ -439,12 +753,30 @@ impl TestCaseConfig {
         if self.timeout != other.timeout {
             diff.timeout = self.timeout;
         }
+        if self.max_duration != other.max_duration {
+            diff.max_duration = self.max_duration;
+        }
+        if self.max_rss != other.max_rss {
+            diff.max_rss = self.max_rss;
+        }
+        if self.retries != other.retries {
+            diff.retries = self.retries;
+        }
+        if self.retry_delay != other.retry_delay {
+            diff.retry_delay = self.retry_delay;
+        }
+        if self.limits != other.limits {
+            diff.limits = self.limits.clone();
+        }
         if self.detached != other.detached {
             diff.detached = self.detached;
         }
         if self.fail_fast != other.fail_fast {
             diff.fail_fast = self.fail_fast;
         }
+        if self.always_run != other.always_run {
+            diff.always_run = self.always_run;
+        }
         if self.skip_document_code != other.skip_document_code {
             diff.skip_document_code = self.skip_document_code;
         }
@@ -460,6 +792,12 @@ impl TestCaseConfig {
         if self.mode != other.mode {
             diff.mode = self.mode.clone();
         }
+        if self.terminal != other.terminal {
+            diff.terminal = self.terminal.clone();
+        }
+        if self.tags != other.tags {
+            diff.tags = self.tags.clone();
+        }
 
         // difference here is: all env vars that are set in self, but not in other
         // and all that env vars that have different values in self than in other
@@ -493,12 +831,36 @@ impl TestCaseConfig {
         if let Some(value) = self.timeout {
             output.push(format!("timeout: {}", humantime::format_duration(value)))
         }
+        if let Some(value) = self.max_duration {
+            output.push(format!(
+                "max_duration: {}",
+                humantime::format_duration(value)
+            ))
+        }
+        if let Some(value) = self.max_rss {
+            output.push(format!("max_rss: {}", format_byte_size(value)))
+        }
+        if let Some(value) = self.retries {
+            output.push(format!("retries: {}", value))
+        }
+        if let Some(value) = self.retry_delay {
+            output.push(format!(
+                "retry_delay: {}",
+                humantime::format_duration(value)
+            ))
+        }
+        if let Some(ref limits) = self.limits {
+            output.push(format!("limits: {}", limits))
+        }
         if let Some(value) = self.detached {
             output.push(format!("detached: {}", value))
         }
         if let Some(value) = self.fail_fast {
             output.push(format!("fail_fast: {}", value))
         }
+        if let Some(value) = self.always_run {
+            output.push(format!("always_run: {}", value))
+        }
         if let Some(value) = self.skip_document_code {
             output.push(format!("skip_document_code: {}", value))
         }
@@ -534,9 +896,21 @@ impl TestCaseConfig {
                 match mode {
                     TestMode::Output => "output",
                     TestMode::JsonSchema => "jsonschema",
+                    TestMode::Interactive => "interactive",
                 }
             ));
         }
+        if let Some(ref terminal) = self.terminal {
+            output.push(format!(
+                "terminal: {{columns: {}, rows: {}, wait_timeout: {}}}",
+                terminal.columns,
+                terminal.rows,
+                humantime::format_duration(terminal.wait_timeout),
+            ));
+        }
+        if !self.tags.is_empty() {
+            output.push(format!("tags: [{}]", self.tags.join(", ")));
+        }
         format!("{{{}}}", output.join(", "))
     }
 
@@ -549,10 +923,34 @@ impl TestCaseConfig {
         self.fail_fast.unwrap_or(false)
     }
 
+    pub fn get_always_run(&self) -> bool {
+        self.always_run.unwrap_or(false)
+    }
+
+    pub fn get_retries(&self) -> usize {
+        self.retries.unwrap_or(0)
+    }
+
     /// Returns true if this test case is configured for JSON Schema validation mode
     pub fn is_json_schema(&self) -> bool {
         matches!(self.mode, Some(TestMode::JsonSchema))
     }
+
+    /// Returns true if the output expectations are split into `@STDOUT` and
+    /// `@STDERR` sections
+    pub fn is_marked(&self) -> bool {
+        matches!(self.output_stream, Some(OutputStreamControl::Marked))
+    }
+
+    /// Returns true if this test case is configured for interactive mode
+    pub fn is_interactive(&self) -> bool {
+        matches!(self.mode, Some(TestMode::Interactive))
+    }
+
+    /// Returns the configured terminal for interactive mode, or the default
+    pub fn get_terminal(&self) -> TestCaseTerminal {
+        self.terminal.clone().unwrap_or_default()
+    }
 }
 
 impl Display for TestCaseConfig {
@@ -603,15 +1001,90 @@ where
     serializer.serialize_str(&value)
 }
 
+/// Multipliers of the units of byte sizes, largest first
+const BYTE_SIZE_UNITS: [(&str, u64); 7] = [
+    ("GiB", 1 << 30),
+    ("GB", 1_000_000_000),
+    ("MiB", 1 << 20),
+    ("MB", 1_000_000),
+    ("KiB", 1 << 10),
+    ("KB", 1_000),
+    ("B", 1),
+];
+
+/// Parses a byte size, that is either a plain number of bytes or a number
+/// with a unit like `512MiB` or `2GB`
+fn parse_byte_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let (number, multiplier) = BYTE_SIZE_UNITS
+        .iter()
+        .find_map(|(unit, multiplier)| {
+            value
+                .strip_suffix(unit)
+                .map(|number| (number.trim_end(), *multiplier))
+        })
+        .unwrap_or((value, 1));
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|number| number.checked_mul(multiplier))
+        .ok_or_else(|| format!("invalid byte size {value:?}"))
+}
+
+/// Formats a byte size with the largest binary unit it is a multiple of
+pub fn format_byte_size(bytes: u64) -> String {
+    BYTE_SIZE_UNITS
+        .iter()
+        .filter(|(unit, _)| unit.ends_with("iB"))
+        .find(|(_, multiplier)| bytes > 0 && bytes % multiplier == 0)
+        .map_or_else(
+            || bytes.to_string(),
+            |(unit, multiplier)| format!("{}{}", bytes / multiplier, unit),
+        )
+}
+
+fn parse_byte_size_opt<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum ByteSize {
+        Bytes(u64),
+        WithUnit(String),
+    }
+    match Option::<ByteSize>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(ByteSize::Bytes(bytes)) => Ok(Some(bytes)),
+        Some(ByteSize::WithUnit(value)) => {
+            parse_byte_size(&value).map(Some).map_err(de::Error::custom)
+        }
+    }
+}
+
+fn render_byte_size_opt<S>(value: &Option<u64>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match value {
+        Some(bytes) => serializer.serialize_str(&format_byte_size(*bytes)),
+        None => serializer.serialize_none(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::BTreeMap;
+    use std::path::Path;
     use std::path::PathBuf;
     use std::time::Duration;
 
     use super::DocumentConfig;
     use super::KillSignal;
+    use super::TestCaseLimits;
+    use super::TestCaseTerminal;
     use super::TestCaseWait;
+    use super::TestMode;
     use crate::config::OutputStreamControl;
     use crate::config::TestCaseConfig;
 
@@ -623,6 +1096,7 @@ defaults:
   detached: true
   detached_kill_signal: quit
   fail_fast: true
+  always_run: true
   environment:
     BAZ: zoing
     FOO: bar
@@ -631,14 +1105,32 @@ defaults:
   skip_document_code: 123
   strip_ansi_escaping: true
   timeout: 6m 4s
+  max_duration: 1m 2s
+  max_rss: 100MiB
+  retries: 2
+  retry_delay: 1s
+  limits:
+    memory: 512MiB
+    cpu_time: 30s
   wait:
     timeout: 2m 1s
     path: the-wait-path
   interpolated: true
+  tags:
+  - slow
+  - network
+env_passthrough:
+- SSH_AUTH_SOCK
+hermetic: true
+persistent_shell: true
 prepend:
 - prep1
 - prep2
+sandbox: true
 shell: the-shell
+shells:
+- bash
+- dash
 total_timeout: 5m 3s
 ";
 
@@ -650,6 +1142,11 @@ total_timeout: 5m 3s
             config,
             DocumentConfig {
                 shell: Some("the-shell".into()),
+                shells: vec!["bash".into(), "dash".into()],
+                sandbox: Some(true),
+                hermetic: Some(true),
+                persistent_shell: Some(true),
+                env_passthrough: vec!["SSH_AUTH_SOCK".into()],
                 total_timeout: Some(Duration::from_secs(5 * 60 + 3)),
                 prepend: vec!["prep1".into(), "prep2".into()],
                 append: vec!["app1".into(), "app2".into()],
@@ -657,6 +1154,15 @@ total_timeout: 5m 3s
                     output_stream: Some(OutputStreamControl::Stdout),
                     keep_crlf: Some(true),
                     timeout: Some(Duration::from_secs(6 * 60 + 4)),
+                    max_duration: Some(Duration::from_secs(62)),
+                    max_rss: Some(100 * 1024 * 1024),
+                    retries: Some(2),
+                    retry_delay: Some(Duration::from_secs(1)),
+                    limits: Some(TestCaseLimits {
+                        memory: Some(512 * 1024 * 1024),
+                        cpu_time: Some(Duration::from_secs(30)),
+                        ..Default::default()
+                    }),
                     environment: {
                         let mut m = BTreeMap::new();
                         m.insert("FOO".to_string(), "bar".to_string());
@@ -666,6 +1172,7 @@ total_timeout: 5m 3s
                     detached: Some(true),
                     detached_kill_signal: Some(KillSignal::test_default()),
                     fail_fast: Some(true),
+                    always_run: Some(true),
                     wait: Some(TestCaseWait {
                         timeout: Duration::from_secs(2 * 60 + 1),
                         path: Some(PathBuf::from("the-wait-path")),
@@ -674,6 +1181,8 @@ total_timeout: 5m 3s
                     strip_ansi_escaping: Some(true),
                     interpolated: Some(true),
                     mode: None,
+                    terminal: None,
+                    tags: vec!["slow".into(), "network".into()],
                 }
             }
         )
@@ -683,6 +1192,11 @@ total_timeout: 5m 3s
     fn test_render_full_document_config() {
         let config = DocumentConfig {
             shell: Some("the-shell".into()),
+            shells: vec!["bash".into(), "dash".into()],
+            sandbox: Some(true),
+            hermetic: Some(true),
+            persistent_shell: Some(true),
+            env_passthrough: vec!["SSH_AUTH_SOCK".into()],
             total_timeout: Some(Duration::from_secs(5 * 60 + 3)),
             prepend: vec!["prep1".into(), "prep2".into()],
             append: vec!["app1".into(), "app2".into()],
@@ -690,6 +1204,15 @@ total_timeout: 5m 3s
                 output_stream: Some(OutputStreamControl::Stdout),
                 keep_crlf: Some(true),
                 timeout: Some(Duration::from_secs(6 * 60 + 4)),
+                max_duration: Some(Duration::from_secs(62)),
+                max_rss: Some(100 * 1024 * 1024),
+                retries: Some(2),
+                retry_delay: Some(Duration::from_secs(1)),
+                limits: Some(TestCaseLimits {
+                    memory: Some(512 * 1024 * 1024),
+                    cpu_time: Some(Duration::from_secs(30)),
+                    ..Default::default()
+                }),
                 environment: {
                     let mut m = BTreeMap::new();
                     m.insert("FOO".to_string(), "bar".to_string());
@@ -699,6 +1222,7 @@ total_timeout: 5m 3s
                 detached: Some(true),
                 detached_kill_signal: Some(KillSignal::test_default()),
                 fail_fast: Some(true),
+                always_run: Some(true),
                 wait: Some(TestCaseWait {
                     timeout: Duration::from_secs(2 * 60 + 1),
                     path: Some(PathBuf::from("the-wait-path")),
@@ -707,6 +1231,8 @@ total_timeout: 5m 3s
                 strip_ansi_escaping: Some(true),
                 interpolated: Some(true),
                 mode: None,
+                terminal: None,
+                tags: vec!["slow".into(), "network".into()],
             },
         };
         assert_eq!(
@@ -719,6 +1245,7 @@ total_timeout: 5m 3s
 detached: true
 detached_kill_signal: quit
 fail_fast: true
+always_run: true
 environment:
   BAZ: zoing
   FOO: bar
@@ -727,10 +1254,22 @@ output_stream: stderr
 skip_document_code: 123
 strip_ansi_escaping: true
 timeout: 6m 4s
+max_duration: 1m 2s
+max_rss: 100MiB
+retries: 2
+retry_delay: 1s
+limits:
+  memory: 512MiB
+  cpu_time: 30s
+  open_files: 256
+  processes: 64
 wait:
   timeout: 2m 1s
   path: the-wait-path
 interpolated: true
+tags:
+- slow
+- network
 ";
 
     #[test]
@@ -743,6 +1282,16 @@ interpolated: true
                 output_stream: Some(OutputStreamControl::Stderr),
                 keep_crlf: Some(true),
                 timeout: Some(Duration::from_secs(6 * 60 + 4)),
+                max_duration: Some(Duration::from_secs(62)),
+                max_rss: Some(100 * 1024 * 1024),
+                retries: Some(2),
+                retry_delay: Some(Duration::from_secs(1)),
+                limits: Some(TestCaseLimits {
+                    memory: Some(512 * 1024 * 1024),
+                    cpu_time: Some(Duration::from_secs(30)),
+                    open_files: Some(256),
+                    processes: Some(64),
+                }),
                 environment: {
                     let mut m = BTreeMap::new();
                     m.insert("FOO".to_string(), "bar".to_string());
@@ -752,6 +1301,7 @@ interpolated: true
                 detached: Some(true),
                 detached_kill_signal: Some(KillSignal::test_default()),
                 fail_fast: Some(true),
+                always_run: Some(true),
                 wait: Some(TestCaseWait {
                     timeout: Duration::from_secs(2 * 60 + 1),
                     path: Some(PathBuf::from("the-wait-path")),
@@ -760,6 +1310,8 @@ interpolated: true
                 strip_ansi_escaping: Some(true),
                 interpolated: Some(true),
                 mode: None,
+                terminal: None,
+                tags: vec!["slow".into(), "network".into()],
             }
         )
     }
@@ -770,6 +1322,16 @@ interpolated: true
             output_stream: Some(OutputStreamControl::Stderr),
             keep_crlf: Some(true),
             timeout: Some(Duration::from_secs(6 * 60 + 4)),
+            max_duration: Some(Duration::from_secs(62)),
+            max_rss: Some(100 * 1024 * 1024),
+            retries: Some(2),
+            retry_delay: Some(Duration::from_secs(1)),
+            limits: Some(TestCaseLimits {
+                memory: Some(512 * 1024 * 1024),
+                cpu_time: Some(Duration::from_secs(30)),
+                open_files: Some(256),
+                processes: Some(64),
+            }),
             environment: {
                 let mut m = BTreeMap::new();
                 m.insert("FOO".to_string(), "bar".to_string());
@@ -779,6 +1341,7 @@ interpolated: true
             detached: Some(true),
             detached_kill_signal: Some(KillSignal::test_default()),
             fail_fast: Some(true),
+            always_run: Some(true),
             wait: Some(TestCaseWait {
                 timeout: Duration::from_secs(2 * 60 + 1),
                 path: Some(PathBuf::from("the-wait-path")),
@@ -787,6 +1350,8 @@ interpolated: true
             strip_ansi_escaping: Some(true),
             interpolated: Some(true),
             mode: None,
+            terminal: None,
+            tags: vec!["slow".into(), "network".into()],
         };
         assert_eq!(
             serde_yaml::to_string(&config).expect("render testcase config to YAML"),
@@ -815,6 +1380,25 @@ interpolated: true
                 },
                 "{wait: 2m 3s}",
             ),
+            (
+                TestCaseConfig {
+                    max_duration: Some(Duration::from_millis(1500)),
+                    ..Default::default()
+                },
+                "{max_duration: 1s 500ms}",
+            ),
+            (
+                TestCaseConfig {
+                    mode: Some(TestMode::Interactive),
+                    terminal: Some(TestCaseTerminal {
+                        columns: 120,
+                        rows: 40,
+                        wait_timeout: Duration::from_secs(3),
+                    }),
+                    ..Default::default()
+                },
+                "{mode: interactive, terminal: {columns: 120, rows: 40, wait_timeout: 3s}}",
+            ),
             (
                 TestCaseConfig {
                     output_stream: Some(OutputStreamControl::Stderr),
@@ -822,18 +1406,30 @@ interpolated: true
                     detached: Some(false),
                     detached_kill_signal: None,
                     fail_fast: Some(false),
+                    always_run: Some(true),
                     environment: BTreeMap::from([("foo".to_string(), "bar".to_string())]),
                     skip_document_code: Some(123),
                     strip_ansi_escaping: Some(true),
                     interpolated: Some(true),
                     mode: None,
+                    terminal: None,
+                    tags: vec!["slow".into()],
                     timeout: Some(Duration::from_secs(234)),
+                    max_duration: Some(Duration::from_secs(5)),
+                    max_rss: Some(3 * 1024 * 1024),
+                    retries: Some(3),
+                    retry_delay: Some(Duration::from_millis(500)),
+                    limits: Some(TestCaseLimits {
+                        memory: Some(1_000_000),
+                        open_files: Some(16),
+                        ..Default::default()
+                    }),
                     wait: Some(TestCaseWait {
                         timeout: Duration::from_secs(123),
                         path: Some(PathBuf::from("/tmp/wait")),
                     }),
                 },
-                "{output_stream: stderr, keep_crlf: true, timeout: 3m 54s, detached: false, fail_fast: false, skip_document_code: 123, strip_ansi_escaping: true, interpolated: true, wait: {timeout: 2m 3s, path: /tmp/wait}, environment: {foo: \"bar\"}}",
+                "{output_stream: stderr, keep_crlf: true, timeout: 3m 54s, max_duration: 5s, max_rss: 3MiB, retries: 3, retry_delay: 500ms, limits: {memory: 1000000, open_files: 16}, detached: false, fail_fast: false, always_run: true, skip_document_code: 123, strip_ansi_escaping: true, interpolated: true, wait: {timeout: 2m 3s, path: /tmp/wait}, environment: {foo: \"bar\"}, tags: [slow]}",
             ),
         ];
         for (idx, (config, expected)) in tests.iter().enumerate() {
@@ -870,4 +1466,125 @@ interpolated: true
             assert_eq!(config.wait, expect, "for input {raw:?}");
         }
     }
+
+    #[test]
+    fn test_parse_test_case_terminal() {
+        let tests = vec![
+            (
+                "terminal: {}",
+                Some(TestCaseTerminal {
+                    columns: 80,
+                    rows: 24,
+                    wait_timeout: Duration::from_secs(5),
+                }),
+            ),
+            (
+                "terminal:\n    columns: 100\n    rows: 30\n    wait_timeout: 500ms",
+                Some(TestCaseTerminal {
+                    columns: 100,
+                    rows: 30,
+                    wait_timeout: Duration::from_millis(500),
+                }),
+            ),
+        ];
+        for (raw, expect) in tests {
+            let config: TestCaseConfig =
+                serde_yaml::from_str(raw).unwrap_or_else(|err| panic!("parse {raw:?}: {err}"));
+            assert_eq!(config.terminal, expect, "for input {raw:?}");
+        }
+    }
+
+    #[test]
+    fn test_parse_test_case_limits() {
+        let tests = vec![
+            ("limits: {memory: 1024}", Some(1024)),
+            ("limits: {memory: 2KB}", Some(2_000)),
+            ("limits: {memory: 2 KiB}", Some(2_048)),
+            ("limits: {memory: 512MiB}", Some(512 * 1024 * 1024)),
+            ("limits: {memory: 3GB}", Some(3_000_000_000)),
+        ];
+        for (raw, expect) in tests {
+            let config: TestCaseConfig =
+                serde_yaml::from_str(raw).unwrap_or_else(|err| panic!("parse {raw:?}: {err}"));
+            assert_eq!(
+                config.limits.and_then(|limits| limits.memory),
+                expect,
+                "for input {raw:?}"
+            );
+        }
+        for raw in ["limits: {memory: lots}", "limits: {memory: 1TB}"] {
+            assert!(
+                serde_yaml::from_str::<TestCaseConfig>(raw).is_err(),
+                "invalid input {raw:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_test_case_limits_with_defaults_from() {
+        let config = TestCaseConfig {
+            limits: Some(TestCaseLimits {
+                cpu_time: Some(Duration::from_secs(1)),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let defaults = TestCaseConfig {
+            limits: Some(TestCaseLimits {
+                memory: Some(1024),
+                cpu_time: Some(Duration::from_secs(10)),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            Some(TestCaseLimits {
+                memory: Some(1024),
+                cpu_time: Some(Duration::from_secs(1)),
+                ..Default::default()
+            }),
+            config.with_defaults_from(&defaults).limits,
+        );
+    }
+
+    #[test]
+    fn test_document_config_shells_with_overrides_from() {
+        let document = DocumentConfig {
+            shells: vec!["bash".into(), "dash".into()],
+            ..Default::default()
+        };
+        assert_eq!(
+            vec![Some(Path::new("bash")), Some(Path::new("dash"))],
+            document
+                .with_overrides_from(&DocumentConfig::empty())
+                .get_shells(),
+        );
+
+        let command_line = DocumentConfig {
+            shell: Some("zsh".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            vec![Some(Path::new("zsh"))],
+            document.with_overrides_from(&command_line).get_shells(),
+            "explicit shell replaces the shells of the document"
+        );
+
+        let command_line = DocumentConfig {
+            shells: vec!["zsh".into()],
+            ..Default::default()
+        };
+        assert_eq!(
+            vec![Some(Path::new("zsh"))],
+            DocumentConfig {
+                shell: Some("bash".into()),
+                ..Default::default()
+            }
+            .with_overrides_from(&command_line)
+            .get_shells(),
+            "shells replace the shell of the document"
+        );
+
+        assert_eq!(vec![None], DocumentConfig::empty().get_shells());
+    }
 }
@@ -5,9 +5,14 @@
  * LICENSE file in the root directory of this source tree.
  */
 
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
 use std::fmt::Debug;
+use std::ops::Range;
 
 use anyhow::Result;
+use anyhow::bail;
 use serde::Serialize;
 use serde::ser::SerializeMap;
 
@@ -87,8 +92,129 @@ impl DiffTool {
     /// Compares output with expectations and returns line-wise results that
     /// describe whether and which expectations matched, did not match, were
     /// not used and which lines were unexpected
+    ///
+    /// A fast, greedy matching is attempted first. If that does not match
+    /// all lines and expectations, then an optimal alignment is computed,
+    /// that finds a match if any exists and otherwise returns the diff with
+    /// the least amount of unmatched expectations and unexpected lines. Only
+    /// the region between the leading and trailing expectations, that match
+    /// their lines one by one, is aligned. The alignment is bound to
+    /// [`MAX_ALIGNMENT_CELLS`]; beyond that the greedy result is returned
+    /// as-is.
+    ///
+    /// Adjacent unordered expectations (see [`Expectation::unordered`]) are
+    /// aligned as a group, that matches a block of lines in any order, with
+    /// each line matched by exactly one expectation of the group. This holds
+    /// for the greedy matching, too, which fails if a group would have to be
+    /// compared with more than [`MAX_ALIGNMENT_CELLS`] combinations of
+    /// expectations and lines.
+    ///
+    /// Negated expectations (see [`Expectation::negated`]) are not aligned,
+    /// but reported as [`DiffLine::NegatedExpectation`] with all lines of the
+    /// output that they match.
     pub fn diff(&self, output: &[u8]) -> Result<Diff> {
         let lines = output.split_at_newline();
+        if !self
+            .expectations
+            .iter()
+            .any(|expectation| expectation.negated)
+        {
+            return self.diff_lines(&lines);
+        }
+
+        // negated expectations do not take part in the alignment, but are
+        // checked against all lines of output
+        let (negated, positive): (Vec<_>, Vec<_>) =
+            (0..self.expectations.len()).partition(|index| self.expectations[*index].negated);
+        let mut diffs = Self::new(
+            positive
+                .iter()
+                .map(|index| self.expectations[*index].clone())
+                .collect(),
+        )
+        .diff_lines(&lines)?
+        .lines;
+        for diff in diffs.iter_mut() {
+            if let DiffLine::MatchedExpectation { index, .. }
+            | DiffLine::UnmatchedExpectation { index, .. } = diff
+            {
+                *index = positive[*index];
+            }
+        }
+        for index in negated {
+            let expectation = &self.expectations[index];
+            let position = diffs
+                .iter()
+                .position(|diff| diff.expectation_index().is_some_and(|other| other > index))
+                .unwrap_or(diffs.len());
+            diffs.insert(
+                position,
+                DiffLine::NegatedExpectation {
+                    index,
+                    expectation: expectation.to_owned(),
+                    lines: lines
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, line)| expectation.matches(line))
+                        .map(|(line_index, line)| (line_index, line.to_vec()))
+                        .collect(),
+                },
+            );
+        }
+        Ok(Diff::new(diffs))
+    }
+
+    /// Aligns the (not negated) expectations with the lines of output
+    fn diff_lines(&self, lines: &[&[u8]]) -> Result<Diff> {
+        let diff = self.diff_greedy(lines)?;
+        if !diff.has_differences() {
+            return Ok(diff);
+        }
+
+        // leading and trailing expectations, that each match exactly the one
+        // line at their position, are part of an optimal alignment, so that
+        // only the region in between needs to be aligned
+        let is_anchor = |(expectation, line): &(&Expectation, &&[u8])| {
+            !expectation.multiline
+                && !expectation.optional
+                && !expectation.unordered
+                && expectation.matches(line)
+        };
+        let head = self
+            .expectations
+            .iter()
+            .zip(lines)
+            .take_while(is_anchor)
+            .count();
+        let tail = self.expectations[head..]
+            .iter()
+            .rev()
+            .zip(lines[head..].iter().rev())
+            .take_while(is_anchor)
+            .count();
+        let (region_end, lines_end) = (self.expectations.len() - tail, lines.len() - tail);
+        let Some(aligned) = Self::new(self.expectations[head..region_end].to_vec())
+            .diff_aligned(&lines[head..lines_end])
+        else {
+            return Ok(diff);
+        };
+
+        let anchor = |index: usize, line_index: usize| DiffLine::MatchedExpectation {
+            index,
+            expectation: self.expectations[index].to_owned(),
+            lines: vec![(line_index, lines[line_index].to_owned())],
+        };
+        let mut diffs = (0..head)
+            .map(|index| anchor(index, index))
+            .collect::<Vec<_>>();
+        diffs.extend(aligned.lines.into_iter().map(|line| line.shifted(head)));
+        diffs.extend((0..tail).map(|offset| anchor(region_end + offset, lines_end + offset)));
+        Ok(Diff::new(diffs))
+    }
+
+    /// Walks expectations and lines in a single pass, using a one-step
+    /// lookahead to resolve mismatches
+    fn diff_greedy(&self, lines: &[&[u8]]) -> Result<Diff> {
         let to_output_list = |i| -> (usize, Vec<u8>) { (i, lines[i].to_owned()) };
         let mut expectation_index = 0;
         let mut line_index = 0;
@@ -103,6 +229,23 @@ impl DiffTool {
             let next_expectation = self.expectations.get(expectation_index + 1);
             let line = lines[line_index];
 
+            // .. that starts a group of unordered expectations -> assign the
+            //    lines that follow to the expectations of the group
+            if expectation.unordered {
+                let group = expectation_index
+                    ..self.expectations[expectation_index..]
+                        .iter()
+                        .position(|expectation| !expectation.unordered)
+                        .map_or(self.expectations.len(), |position| {
+                            expectation_index + position
+                        });
+                let assignment = self.assign_group(group.clone(), line_index, lines)?;
+                diffs.extend(self.diff_group(group.clone(), line_index, &assignment, lines));
+                line_index += assignment.owners.len();
+                expectation_index = group.end;
+                continue;
+            }
+
             // .. that matches the line
             if expectation.matches(line) {
                 // .. and is multiline -> keep going to next line(s)
@@ -171,7 +314,7 @@ impl DiffTool {
 
             // .. that does not match the current line, so ..
             //   .. let find whatever is closer (if any):
-            match self.peek_match(line_index, &lines, expectation_index) {
+            match self.peek_match(line_index, lines, expectation_index) {
                 //     .. the next matching expectation for the current line
                 PeekMatch::NextExpectation(next_expectation_index) => {
                     // .. note down not matching of all intermediate expectations
@@ -246,6 +389,333 @@ impl DiffTool {
         Ok(Diff::new(diffs))
     }
 
+    /// Assigns the lines, that start at the given line, to the expectations
+    /// of the unordered group, up to the last line that the group matches
+    fn assign_group(
+        &self,
+        group: Range<usize>,
+        start: usize,
+        lines: &[&[u8]],
+    ) -> Result<UnorderedAssignment> {
+        let expectations = &self.expectations[group];
+        let max_window = max_group_window(expectations, lines.len() - start);
+        if max_window.saturating_mul(expectations.len()) > MAX_ALIGNMENT_CELLS {
+            bail!(
+                "unordered group too large: {} expectations cannot be matched with {max_window} lines, which exceeds {MAX_ALIGNMENT_CELLS} combinations",
+                expectations.len()
+            );
+        }
+        let matches = LineMatches::new(expectations, &lines[start..start + max_window]);
+        let mut assignment = assign_unordered(expectations, &matches, 0..max_window);
+        let window_end = assignment
+            .owners
+            .iter()
+            .rposition(Option::is_some)
+            .map_or(0, |offset| offset + 1);
+        assignment.owners.truncate(window_end);
+        Ok(assignment)
+    }
+
+    /// Renders the assignment of the lines, that start at the given line, to
+    /// the expectations of the unordered group as diff lines. Matched
+    /// expectations are listed in the order of their first line, so that the
+    /// diff follows the output.
+    fn diff_group(
+        &self,
+        group: Range<usize>,
+        start: usize,
+        assignment: &UnorderedAssignment,
+        lines: &[&[u8]],
+    ) -> Vec<DiffLine> {
+        let to_output_list = |i: usize| -> (usize, Vec<u8>) { (i, lines[i].to_owned()) };
+        let expectations = &self.expectations[group.clone()];
+        let mut diffs = vec![];
+        let mut unexpected = vec![];
+        for (offset, owner) in assignment.owners.iter().enumerate() {
+            let Some(owner) = *owner else {
+                unexpected.push(to_output_list(start + offset));
+                continue;
+            };
+            if assignment.owners[..offset].contains(&Some(owner)) {
+                continue;
+            }
+            if !unexpected.is_empty() {
+                diffs.push(DiffLine::UnexpectedLines {
+                    lines: std::mem::take(&mut unexpected),
+                });
+            }
+            diffs.push(DiffLine::MatchedExpectation {
+                index: group.start + owner,
+                expectation: expectations[owner].to_owned(),
+                lines: (0..assignment.owners.len())
+                    .filter(|offset| assignment.owners[*offset] == Some(owner))
+                    .map(|offset| to_output_list(start + offset))
+                    .collect(),
+            });
+        }
+        if !unexpected.is_empty() {
+            diffs.push(DiffLine::UnexpectedLines { lines: unexpected });
+        }
+        for (offset, expectation) in expectations.iter().enumerate() {
+            if !expectation.optional && !assignment.owners.contains(&Some(offset)) {
+                diffs.push(DiffLine::UnmatchedExpectation {
+                    index: group.start + offset,
+                    expectation: expectation.to_owned(),
+                });
+            }
+        }
+        diffs
+    }
+
+    /// Computes the alignment of expectations and lines with the least
+    /// amount of unmatched (non-optional) expectations and unexpected lines.
+    ///
+    /// The alignment is modelled as a grid over units × lines with two
+    /// layers: `closed` cells, in which the unit at the cell has not
+    /// matched any line yet, and `open` cells, in which a multiline
+    /// expectation has matched at least one line and can match more. Each
+    /// cell holds the cost of aligning the remaining units and lines,
+    /// so that the walk from the start can favor early matches on ties.
+    /// A unit is either a single expectation or a group of unordered
+    /// expectations, which matches a window of lines at once. The assignment
+    /// of the cheapest window is kept for the walk.
+    /// Returns `None` if the grid, or the windows of the groups, would exceed
+    /// [`MAX_ALIGNMENT_CELLS`].
+    fn diff_aligned(&self, lines: &[&[u8]]) -> Option<Diff> {
+        let units = self.units();
+        let (count_units, count_lines) = (units.len(), lines.len());
+        let width = count_lines + 1;
+        let cells = width.saturating_mul(count_units + 1);
+        if cells > MAX_ALIGNMENT_CELLS {
+            return None;
+        }
+
+        // the windows of the groups at every line are matched in addition to
+        // the grid, which is checked before the grid is allocated
+        let group_work = units
+            .iter()
+            .filter(|unit| self.expectations[unit.start].unordered)
+            .flat_map(|group| {
+                let expectations = &self.expectations[group.clone()];
+                (0..=count_lines).map(|line_index| {
+                    let max_window = max_group_window(expectations, count_lines - line_index);
+                    (max_window + 1).saturating_mul(unordered_work(expectations.len(), max_window))
+                })
+            })
+            .fold(0, usize::saturating_add);
+        if cells.saturating_add(group_work) > MAX_ALIGNMENT_CELLS {
+            return None;
+        }
+
+        let mut closed_steps = vec![AlignStep::Unreachable; (count_units + 1) * width];
+        let mut open_steps = vec![AlignStep::Unreachable; count_units * width];
+        let mut next_closed_costs = vec![UNREACHABLE; width];
+        let mut group_assignments = HashMap::new();
+
+        for unit_index in (0..=count_units).rev() {
+            let unit = units.get(unit_index);
+            let group = unit.filter(|unit| self.expectations[unit.start].unordered);
+            let group_matches =
+                group.map(|group| LineMatches::new(&self.expectations[group.clone()], lines));
+            let mut closed_costs = vec![UNREACHABLE; width];
+            let mut open_costs = vec![UNREACHABLE; width];
+
+            for line_index in (0..=count_lines).rev() {
+                let cell = unit_index * width + line_index;
+                let Some(unit) = unit else {
+                    // all expectations are used up, remaining lines are unexpected
+                    closed_costs[line_index] = (count_lines - line_index) as u32;
+                    closed_steps[cell] = if line_index == count_lines {
+                        AlignStep::End
+                    } else {
+                        AlignStep::Unexpected
+                    };
+                    continue;
+                };
+
+                // the order of candidates decides between equally good paths
+                if let (Some(group), Some(matches)) = (group, &group_matches) {
+                    let expectations = &self.expectations[group.clone()];
+                    let max_window = max_group_window(expectations, count_lines - line_index);
+                    let mut candidates = vec![];
+                    let mut assignments = vec![];
+                    for (window_end, next_cost) in next_closed_costs
+                        .iter()
+                        .enumerate()
+                        .skip(line_index)
+                        .take(max_window + 1)
+                    {
+                        let assignment =
+                            assign_unordered(expectations, matches, line_index..window_end);
+                        // windows that start or end with an unexpected line are
+                        // never better than the window without that line
+                        if assignment.owners.first() == Some(&None)
+                            || assignment.owners.last() == Some(&None)
+                        {
+                            continue;
+                        }
+                        candidates.push((
+                            next_cost.saturating_add(assignment.cost()),
+                            AlignStep::Grouped,
+                        ));
+                        assignments.push((window_end, assignment));
+                    }
+                    if line_index < count_lines {
+                        candidates.push((
+                            closed_costs[line_index + 1].saturating_add(1),
+                            AlignStep::Unexpected,
+                        ));
+                    }
+                    (closed_costs[line_index], closed_steps[cell]) = cheapest(&candidates);
+                    if matches!(closed_steps[cell], AlignStep::Grouped) {
+                        // the cheapest candidate is the first with the lowest
+                        // cost and windows precede the unexpected line
+                        let position = candidates
+                            .iter()
+                            .position(|(cost, _)| *cost == closed_costs[line_index])
+                            .expect("cheapest window is a candidate");
+                        group_assignments.insert(cell, assignments.swap_remove(position));
+                    }
+                    continue;
+                }
+
+                let expectation = &self.expectations[unit.start];
+                let matches_line =
+                    line_index < count_lines && expectation.matches(lines[line_index]);
+                if expectation.multiline {
+                    let mut candidates = vec![];
+                    if matches_line {
+                        candidates.push((open_costs[line_index + 1], AlignStep::Continued));
+                    }
+                    candidates.push((next_closed_costs[line_index], AlignStep::Closed));
+                    (open_costs[line_index], open_steps[cell]) = cheapest(&candidates);
+                }
+
+                let mut candidates = vec![];
+                if matches_line {
+                    candidates.push(if expectation.multiline {
+                        (open_costs[line_index + 1], AlignStep::Opened)
+                    } else {
+                        (next_closed_costs[line_index + 1], AlignStep::Matched)
+                    });
+                }
+                candidates.push((
+                    next_closed_costs[line_index].saturating_add(u32::from(!expectation.optional)),
+                    AlignStep::Skipped,
+                ));
+                if line_index < count_lines {
+                    candidates.push((
+                        closed_costs[line_index + 1].saturating_add(1),
+                        AlignStep::Unexpected,
+                    ));
+                }
+                (closed_costs[line_index], closed_steps[cell]) = cheapest(&candidates);
+            }
+
+            next_closed_costs = closed_costs;
+        }
+
+        // walk from the start along the cheapest steps and collect diff lines
+        let to_output_list = |i: usize| -> (usize, Vec<u8>) { (i, lines[i].to_owned()) };
+        let mut diffs = vec![];
+        let mut unexpected = vec![];
+        let flush_unexpected = |diffs: &mut Vec<DiffLine>, unexpected: &mut Vec<usize>| {
+            if !unexpected.is_empty() {
+                diffs.push(DiffLine::UnexpectedLines {
+                    lines: unexpected.drain(..).map(to_output_list).collect(),
+                });
+            }
+        };
+        let (mut unit_index, mut line_index) = (0, 0);
+        let mut run_start = None;
+        loop {
+            let expectation_index = units.get(unit_index).map_or(0, |unit| unit.start);
+            if let Some(start) = run_start {
+                match open_steps[unit_index * width + line_index] {
+                    AlignStep::Continued => line_index += 1,
+                    AlignStep::Closed => {
+                        diffs.push(DiffLine::MatchedExpectation {
+                            index: expectation_index,
+                            expectation: self.expectations[expectation_index].to_owned(),
+                            lines: (start..line_index).map(to_output_list).collect(),
+                        });
+                        unit_index += 1;
+                        run_start = None;
+                    }
+                    step => unreachable!("invalid step {step:?} in multiline run"),
+                }
+                continue;
+            }
+
+            match closed_steps[unit_index * width + line_index] {
+                AlignStep::End => break,
+                AlignStep::Matched => {
+                    flush_unexpected(&mut diffs, &mut unexpected);
+                    diffs.push(DiffLine::MatchedExpectation {
+                        index: expectation_index,
+                        expectation: self.expectations[expectation_index].to_owned(),
+                        lines: vec![to_output_list(line_index)],
+                    });
+                    unit_index += 1;
+                    line_index += 1;
+                }
+                AlignStep::Opened => {
+                    flush_unexpected(&mut diffs, &mut unexpected);
+                    run_start = Some(line_index);
+                    line_index += 1;
+                }
+                AlignStep::Skipped => {
+                    if !self.expectations[expectation_index].optional {
+                        flush_unexpected(&mut diffs, &mut unexpected);
+                        diffs.push(DiffLine::UnmatchedExpectation {
+                            index: expectation_index,
+                            expectation: self.expectations[expectation_index].to_owned(),
+                        });
+                    }
+                    unit_index += 1;
+                }
+                AlignStep::Grouped => {
+                    flush_unexpected(&mut diffs, &mut unexpected);
+                    let (window_end, assignment) = group_assignments
+                        .remove(&(unit_index * width + line_index))
+                        .expect("assignment of cheapest window is kept");
+                    diffs.extend(self.diff_group(
+                        units[unit_index].clone(),
+                        line_index,
+                        &assignment,
+                        lines,
+                    ));
+                    unit_index += 1;
+                    line_index = window_end;
+                }
+                AlignStep::Unexpected => {
+                    unexpected.push(line_index);
+                    line_index += 1;
+                }
+                step => unreachable!("invalid step {step:?} in alignment"),
+            }
+        }
+        flush_unexpected(&mut diffs, &mut unexpected);
+
+        Some(Diff::new(diffs))
+    }
+
+    /// Splits the expectations into the units of the alignment: each ordered
+    /// expectation is a unit of its own and adjacent unordered expectations
+    /// are grouped into one unit
+    fn units(&self) -> Vec<Range<usize>> {
+        let mut units: Vec<Range<usize>> = vec![];
+        for (index, expectation) in self.expectations.iter().enumerate() {
+            match units.last_mut() {
+                Some(unit) if expectation.unordered && self.expectations[unit.start].unordered => {
+                    unit.end = index + 1
+                }
+                _ => units.push(index..index + 1),
+            }
+        }
+        units
+    }
+
     /// Returns either the index of the index of the next matching expectation
     /// for the current line or if there is none, then the next index of the
     /// line matching the current expectation - or none, if that doesn't exist
@@ -304,6 +774,213 @@ impl DiffTool {
     }
 }
 
+/// Upper bound for the amount of cells (expectations × lines) that
+/// [`DiffTool::diff`] computes to find an optimal alignment, including the
+/// work of matching the windows of unordered groups
+pub const MAX_ALIGNMENT_CELLS: usize = 8_000_000;
+
+/// The work of assigning a window of lines to the expectations of an unordered
+/// group in [`assign_unordered`]: an augmenting path is searched from every
+/// expectation and every line, each of which visits every combination of
+/// expectation and line at most once
+fn unordered_work(expectations: usize, window: usize) -> usize {
+    expectations
+        .saturating_mul(window)
+        .saturating_mul(expectations.saturating_add(window))
+}
+
+/// The most lines that a group of unordered expectations can match, out of
+/// the available lines
+fn max_group_window(expectations: &[Expectation], available: usize) -> usize {
+    if expectations.iter().any(|expectation| expectation.multiline) {
+        available
+    } else {
+        expectations.len().min(available)
+    }
+}
+
+/// Whether expectations match lines, evaluated when first asked for
+struct LineMatches<'a> {
+    expectations: &'a [Expectation],
+    lines: &'a [&'a [u8]],
+    cache: Vec<Cell<Option<bool>>>,
+}
+
+impl<'a> LineMatches<'a> {
+    fn new(expectations: &'a [Expectation], lines: &'a [&'a [u8]]) -> Self {
+        Self {
+            expectations,
+            lines,
+            cache: vec![Cell::new(None); expectations.len() * lines.len()],
+        }
+    }
+
+    /// Whether the expectation at the index matches the line at the index
+    fn get(&self, expectation: usize, line: usize) -> bool {
+        let cell = &self.cache[expectation * self.lines.len() + line];
+        cell.get().unwrap_or_else(|| {
+            let matches = self.expectations[expectation].matches(self.lines[line]);
+            cell.set(Some(matches));
+            matches
+        })
+    }
+}
+
+/// Cost of a cell in the alignment grid that cannot be reached
+const UNREACHABLE: u32 = u32::MAX;
+
+/// The step that is taken from a cell in the alignment grid, which takes a
+/// single byte per cell
+#[derive(Clone, Copy, Debug)]
+#[repr(u8)]
+enum AlignStep {
+    /// Cell cannot be reached
+    Unreachable,
+
+    /// All expectations and lines are aligned
+    End,
+
+    /// A single line expectation matches the line
+    Matched,
+
+    /// A multiline expectation starts its run of matching lines
+    Opened,
+
+    /// A multiline expectation matches one more line
+    Continued,
+
+    /// A multiline expectation ends its run of matching lines
+    Closed,
+
+    /// An expectation is skipped without matching any line
+    Skipped,
+
+    /// A group of unordered expectations matches a window of lines, which
+    /// is kept with the assignment of the lines
+    Grouped,
+
+    /// A line does not match any expectation
+    Unexpected,
+}
+
+/// Returns the first of the cheapest candidates
+fn cheapest(candidates: &[(u32, AlignStep)]) -> (u32, AlignStep) {
+    candidates
+        .iter()
+        .filter(|(cost, _)| *cost != UNREACHABLE)
+        .fold((UNREACHABLE, AlignStep::Unreachable), |best, candidate| {
+            if candidate.0 < best.0 {
+                *candidate
+            } else {
+                best
+            }
+        })
+}
+
+/// The assignment of a window of lines to the expectations of an unordered
+/// group, as computed by [`assign_unordered`]
+struct UnorderedAssignment {
+    /// The (group relative) index of the expectation that matches each line
+    /// of the window, if any
+    owners: Vec<Option<usize>>,
+
+    /// The amount of non-optional expectations that match no line
+    unmatched: usize,
+}
+
+impl UnorderedAssignment {
+    /// The cost of the assignment in the alignment grid
+    fn cost(&self) -> u32 {
+        (self.unmatched + self.owners.iter().filter(|owner| owner.is_none()).count()) as u32
+    }
+}
+
+/// Assigns each line of the window to at most one expectation of an unordered
+/// group, so that as many non-optional expectations and then as many lines as
+/// possible are matched. Single line expectations match at most one line,
+/// multiline expectations any amount. Matching is done with augmenting paths:
+/// first from every non-optional expectation, then from every line. Augmenting
+/// never unmatches a line or an expectation, so the second pass keeps what the
+/// first pass achieved.
+fn assign_unordered(
+    expectations: &[Expectation],
+    matches: &LineMatches,
+    window: Range<usize>,
+) -> UnorderedAssignment {
+    let mut owners = vec![None; window.len()];
+    let matches_at =
+        |expectation: usize, offset: usize| matches.get(expectation, window.start + offset);
+
+    // find a line for expectation, possibly moving other expectations to
+    // different lines (which all expectations need only one of here)
+    fn augment_expectation(
+        expectation: usize,
+        owners: &mut [Option<usize>],
+        visited: &mut [bool],
+        matches_at: &dyn Fn(usize, usize) -> bool,
+    ) -> bool {
+        for offset in 0..owners.len() {
+            if visited[offset] || !matches_at(expectation, offset) {
+                continue;
+            }
+            visited[offset] = true;
+            if owners[offset]
+                .is_none_or(|owner| augment_expectation(owner, owners, visited, matches_at))
+            {
+                owners[offset] = Some(expectation);
+                return true;
+            }
+        }
+        false
+    }
+
+    // find an expectation for the line, possibly moving lines of single line
+    // expectations to other expectations
+    fn augment_line(
+        offset: usize,
+        expectations: &[Expectation],
+        owners: &mut [Option<usize>],
+        visited: &mut [bool],
+        matches_at: &dyn Fn(usize, usize) -> bool,
+    ) -> bool {
+        for (index, expectation) in expectations.iter().enumerate() {
+            if visited[index] || !matches_at(index, offset) {
+                continue;
+            }
+            visited[index] = true;
+            let taken = owners.iter().position(|owner| *owner == Some(index));
+            let available = match taken {
+                Some(_) if expectation.multiline => true,
+                Some(other) => augment_line(other, expectations, owners, visited, matches_at),
+                None => true,
+            };
+            if available {
+                owners[offset] = Some(index);
+                return true;
+            }
+        }
+        false
+    }
+
+    let mut unmatched = 0;
+    for (index, expectation) in expectations.iter().enumerate() {
+        if !expectation.optional {
+            let mut visited = vec![false; owners.len()];
+            if !augment_expectation(index, &mut owners, &mut visited, &matches_at) {
+                unmatched += 1;
+            }
+        }
+    }
+    for offset in 0..owners.len() {
+        if owners[offset].is_none() {
+            let mut visited = vec![false; expectations.len()];
+            augment_line(offset, expectations, &mut owners, &mut visited, &matches_at);
+        }
+    }
+
+    UnorderedAssignment { owners, unmatched }
+}
+
 /// Enumerate the kind of peeked (future) match that was found
 enum PeekMatch {
     /// A future expectation matchers the current line
@@ -352,6 +1029,11 @@ impl Diff {
                 count_unmatched += 1;
             }
             DiffLine::UnexpectedLines { lines } => count_output_lines += lines.len(),
+            DiffLine::NegatedExpectation { lines, .. } => {
+                if !lines.is_empty() {
+                    count_unmatched += 1;
+                }
+            }
         });
         Self {
             lines,
@@ -364,16 +1046,104 @@ impl Diff {
     /// Whether there are any differences in the result, i.e. not all lines
     /// are [`DiffLine::MatchedExpectation`]s
     pub fn has_differences(&self) -> bool {
-        self.lines.iter().any(|line| {
-            !matches!(
+        self.lines.iter().any(DiffLine::is_difference)
+    }
+}
+
+impl Diff {
+    /// Whether the [`DiffLine`] at the given position lies within the lines
+    /// that an unordered group matched, i.e. it is enclosed by matches of
+    /// unordered expectations
+    pub fn is_within_unordered_group(&self, position: usize) -> bool {
+        let is_unordered_match = |line: Option<&DiffLine>| {
+            matches!(
                 line,
+                Some(DiffLine::MatchedExpectation { expectation, .. }) if expectation.unordered
+            )
+        };
+        position > 0
+            && is_unordered_match(self.lines.get(position - 1))
+            && is_unordered_match(self.lines.get(position + 1))
+    }
+
+    /// The values that the matched expectations captured from their lines,
+    /// by name. Later captures of the same name take precedence.
+    pub fn captures(&self) -> BTreeMap<String, String> {
+        let mut captures = BTreeMap::new();
+        for line in &self.lines {
+            if let DiffLine::MatchedExpectation {
+                expectation, lines, ..
+            } = line
+            {
+                for (_, line) in lines {
+                    captures.extend(expectation.captures(line));
+                }
+            }
+        }
+        captures
+    }
+
+    /// Explains why the unmatched expectation at the given position of the
+    /// lines did not match, based on the closest unexpected line whose
+    /// mismatch the expectation can explain
+    pub fn explain_unmatched(&self, position: usize) -> Option<String> {
+        let Some(DiffLine::UnmatchedExpectation { expectation, .. }) = self.lines.get(position)
+        else {
+            return None;
+        };
+        let mut unexpected = self
+            .lines
+            .iter()
+            .enumerate()
+            .filter_map(|(index, line)| match line {
+                DiffLine::UnexpectedLines { lines } => Some((index.abs_diff(position), lines)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        unexpected.sort_by_key(|(distance, _)| *distance);
+        unexpected
+            .into_iter()
+            .flat_map(|(_, lines)| lines)
+            .find_map(|(_, line)| expectation.explain_mismatch(line))
+    }
+
+    /// The lines of the diff in the order of the expectations, together with
+    /// whether they lie within the lines of an unordered group. The lines of
+    /// unordered groups are listed in the order of the output they matched,
+    /// which is re-sorted here by expectation index, followed by the lines
+    /// within the group that were unexpected.
+    pub fn lines_in_expectation_order(&self) -> Vec<(&DiffLine, bool)> {
+        let mut ordered = Vec::with_capacity(self.lines.len());
+        let mut group = vec![];
+        fn flush_group<'a>(
+            ordered: &mut Vec<(&'a DiffLine, bool)>,
+            group: &mut Vec<(usize, &'a DiffLine)>,
+        ) {
+            group.sort_by_key(|(index, _)| *index);
+            ordered.extend(group.drain(..).map(|(_, line)| (line, true)));
+        }
+        for (position, line) in self.lines.iter().enumerate() {
+            let grouped = match line {
                 DiffLine::MatchedExpectation {
-                    index: _,
-                    expectation: _,
-                    lines: _,
+                    index, expectation, ..
                 }
-            )
-        })
+                | DiffLine::UnmatchedExpectation { index, expectation } => {
+                    expectation.unordered.then_some(*index)
+                }
+                DiffLine::UnexpectedLines { .. } => self
+                    .is_within_unordered_group(position)
+                    .then_some(usize::MAX),
+                DiffLine::NegatedExpectation { .. } => None,
+            };
+            if let Some(index) = grouped {
+                group.push((index, line));
+            } else {
+                flush_group(&mut ordered, &mut group);
+                ordered.push((line, false));
+            }
+        }
+        flush_group(&mut ordered, &mut group);
+        ordered
     }
 }
 
@@ -393,6 +1163,11 @@ impl Debug for Diff {
                     expectation: _,
                 } => count_unmatched += 1,
                 DiffLine::UnexpectedLines { lines } => count_unexpected += lines.len(),
+                DiffLine::NegatedExpectation { lines, .. } => {
+                    if !lines.is_empty() {
+                        count_unmatched += 1
+                    }
+                }
             }
             rendered.push_str(&format!("{line:?}"));
         }
@@ -435,6 +1210,78 @@ pub enum DiffLine {
         /// The line(s) of output without expectation match
         lines: Vec<(usize, Vec<u8>)>,
     },
+
+    /// A negated expectation, that is violated by any line of output it matches
+    NegatedExpectation {
+        /// The index within the list of expectations
+        index: usize,
+
+        /// The negated expectation
+        expectation: Expectation,
+
+        /// The line(s) of output that violate the expectation, if any
+        lines: Vec<(usize, Vec<u8>)>,
+    },
+}
+
+impl DiffLine {
+    /// Moves the line, that was diffed from a region of expectations and
+    /// lines which both start at the given offset, to the whole of them
+    fn shifted(self, offset: usize) -> Self {
+        let shift = |lines: Vec<(usize, Vec<u8>)>| {
+            lines
+                .into_iter()
+                .map(|(index, line)| (index + offset, line))
+                .collect()
+        };
+        match self {
+            Self::MatchedExpectation {
+                index,
+                expectation,
+                lines,
+            } => Self::MatchedExpectation {
+                index: index + offset,
+                expectation,
+                lines: shift(lines),
+            },
+            Self::UnmatchedExpectation { index, expectation } => Self::UnmatchedExpectation {
+                index: index + offset,
+                expectation,
+            },
+            Self::NegatedExpectation {
+                index,
+                expectation,
+                lines,
+            } => Self::NegatedExpectation {
+                index: index + offset,
+                expectation,
+                lines: shift(lines),
+            },
+            Self::UnexpectedLines { lines } => Self::UnexpectedLines {
+                lines: shift(lines),
+            },
+        }
+    }
+
+    /// The index of the expectation the line refers to, if any
+    pub fn expectation_index(&self) -> Option<usize> {
+        match self {
+            Self::MatchedExpectation { index, .. }
+            | Self::UnmatchedExpectation { index, .. }
+            | Self::NegatedExpectation { index, .. } => Some(*index),
+            Self::UnexpectedLines { .. } => None,
+        }
+    }
+
+    /// Whether the line describes a difference between expectations and
+    /// output, i.e. anything but a matched or a not violated expectation
+    pub fn is_difference(&self) -> bool {
+        match self {
+            Self::MatchedExpectation { .. } => false,
+            Self::NegatedExpectation { lines, .. } => !lines.is_empty(),
+            _ => true,
+        }
+    }
 }
 
 impl Debug for DiffLine {
@@ -454,6 +1301,17 @@ impl Debug for DiffLine {
             Self::UnmatchedExpectation { index, expectation } => {
                 writeln!(f, "{:04}      | - {}", index + 1, expectation)
             }
+            Self::NegatedExpectation {
+                index,
+                expectation,
+                lines,
+            } => {
+                writeln!(f, "{:04}      | ! {}", index + 1, expectation)?;
+                for (index, line) in lines {
+                    write!(f, "     {:04} | ! {}", index + 1, lossy_string!(line))?;
+                }
+                Ok(())
+            }
             Self::UnexpectedLines { lines } => {
                 for (index, line) in lines {
                     let eol = (line.as_ref() as &[u8]).ends_in_newline();
@@ -496,6 +1354,18 @@ impl Serialize for DiffLine {
                 variant.serialize_entry("expectation", &expectation)?;
                 variant.end()
             }
+            DiffLine::NegatedExpectation {
+                index,
+                expectation,
+                lines,
+            } => {
+                let mut variant = serializer.serialize_map(Some(4))?;
+                variant.serialize_entry("kind", "negated_expectation")?;
+                variant.serialize_entry("index", index)?;
+                variant.serialize_entry("expectation", &expectation)?;
+                variant.serialize_entry("lines", &lines_to_strings(lines))?;
+                variant.end()
+            }
             DiffLine::UnexpectedLines { lines } => {
                 let mut variant = serializer.serialize_map(Some(2))?;
                 variant.serialize_entry("kind", "unexpected_lines")?;
@@ -515,11 +1385,15 @@ fn lines_to_strings(lines: &[(usize, Vec<u8>)]) -> Vec<(usize, String)> {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::BTreeMap;
+
+    use super::AlignStep;
     use super::DiffLine;
     use super::DiffTool;
     use crate::bformatln;
     use crate::blines;
     use crate::diff::Diff;
+    use crate::expectation::tests::expectation_maker;
     use crate::test_expectation;
 
     #[test]
@@ -854,6 +1728,343 @@ mod tests {
         insta::assert_debug_snapshot!(diffs);
     }
 
+    // The greedy matching ends the multiline expectation as soon as the next
+    // expectation matches, which leaves the second `foo` without expectation.
+    // The following MUST WORK, because there is an alignment that matches:
+    // ```scrut
+    // $ echo -e "start\nfoo\nfoo\nbar"
+    // * (glob+)
+    // foo
+    // bar
+    // ```
+    #[test]
+    fn test_alignment_backtracks_multiline_expectation() {
+        let differ = DiffTool {
+            expectations: vec![
+                test_expectation!("glob", "*", false, true),
+                test_expectation!("equal", "foo"),
+                test_expectation!("equal", "bar"),
+            ],
+        };
+
+        let diffs = differ
+            .diff(&blines!("start", "foo", "foo", "bar"))
+            .expect("no error");
+        assert!(!diffs.has_differences(), "{diffs:?}");
+        insta::assert_debug_snapshot!(diffs);
+    }
+
+    #[test]
+    fn test_alignment_returns_minimal_diff() {
+        let differ = DiffTool {
+            expectations: vec![
+                test_expectation!("glob", "*", false, true),
+                test_expectation!("equal", "foo"),
+                test_expectation!("equal", "bar"),
+                test_expectation!("equal", "baz"),
+            ],
+        };
+
+        let diffs = differ
+            .diff(&blines!("start", "foo", "foo", "bar", "zoing"))
+            .expect("no error");
+        assert_eq!(1, diffs.count_unmatched, "{diffs:?}");
+        insta::assert_debug_snapshot!(diffs);
+    }
+
+    #[test]
+    fn test_alignment_falls_back_to_greedy_beyond_bound() {
+        let lines = (0..20_000)
+            .map(|index| format!("line {index}\n"))
+            .collect::<String>();
+        let differ = DiffTool {
+            expectations: (0..20_000)
+                .map(|index| {
+                    if index == 0 || index == 19_999 {
+                        test_expectation!("equal", "other")
+                    } else {
+                        test_expectation!("equal", &format!("line {index}"))
+                    }
+                })
+                .collect(),
+        };
+
+        let diffs = differ.diff(lines.as_bytes()).expect("no error");
+        assert_eq!(2, diffs.count_unmatched, "unmatched expectations");
+        assert_eq!(19_998, diffs.count_matched, "matched expectations");
+    }
+
+    #[test]
+    fn test_alignment_of_region_with_differences_in_large_output() {
+        let lines = (0..20_000)
+            .map(|index| format!("line {index}\n"))
+            .chain(["start\n", "foo\n", "foo\n", "bar\n"].map(String::from))
+            .chain((0..20_000).map(|index| format!("line {index}\n")))
+            .collect::<String>();
+        let line_expectations =
+            || (0..20_000).map(|index| test_expectation!("equal", &format!("line {index}")));
+        let differ = DiffTool {
+            expectations: line_expectations()
+                .chain([
+                    test_expectation!("glob", "*", false, true),
+                    test_expectation!("equal", "foo"),
+                    test_expectation!("equal", "bar"),
+                ])
+                .chain(line_expectations())
+                .collect(),
+        };
+
+        let diffs = differ.diff(lines.as_bytes()).expect("no error");
+        assert!(!diffs.has_differences(), "aligned without differences");
+        assert_eq!(40_003, diffs.count_matched, "matched expectations");
+        assert_eq!(
+            Some(&DiffLine::MatchedExpectation {
+                index: 20_001,
+                expectation: test_expectation!("equal", "foo"),
+                lines: vec![(20_002, b"foo\n".to_vec())],
+            }),
+            diffs.lines.get(20_001)
+        );
+    }
+
+    #[test]
+    fn test_unordered_group_beyond_alignment_bound() {
+        let differ = DiffTool {
+            expectations: (0..500)
+                .map(|index| {
+                    let mut expectation = test_expectation!("equal", &format!("line {index}"));
+                    expectation.unordered = true;
+                    expectation
+                })
+                .collect(),
+        };
+        let lines = (0..500)
+            .rev()
+            .map(|index| {
+                if index == 250 {
+                    "other\n".to_string()
+                } else {
+                    format!("line {index}\n")
+                }
+            })
+            .collect::<String>();
+
+        let diffs = differ.diff(lines.as_bytes()).expect("no error");
+        assert_eq!(1, diffs.count_unmatched, "unmatched expectations");
+        assert_eq!(499, diffs.count_matched, "matched expectations");
+        assert_eq!(
+            Some(&DiffLine::UnexpectedLines {
+                lines: vec![(249, b"other\n".to_vec())]
+            }),
+            diffs.lines.get(249)
+        );
+    }
+
+    #[test]
+    fn test_alignment_bounds_work_of_unordered_groups() {
+        let differ = DiffTool {
+            expectations: (0..100)
+                .map(|index| {
+                    let mut expectation =
+                        test_expectation!("regex", &format!("line {index}"), false, true);
+                    expectation.unordered = true;
+                    expectation
+                })
+                .collect(),
+        };
+        let lines = (0..2_000)
+            .map(|index| format!("line {}\n", index % 100))
+            .collect::<Vec<_>>();
+        let lines = lines.iter().map(|line| line.as_bytes()).collect::<Vec<_>>();
+
+        assert!(
+            differ.diff_aligned(&lines).is_none(),
+            "grid is small, but the windows of the group are not"
+        );
+        assert_eq!(1, std::mem::size_of::<AlignStep>(), "step per cell");
+    }
+
+    #[test]
+    fn test_unordered_group_too_large() {
+        let differ = DiffTool {
+            expectations: (0..3_000)
+                .map(|index| {
+                    let mut expectation = test_expectation!("equal", &format!("line {index}"));
+                    expectation.unordered = true;
+                    expectation
+                })
+                .collect(),
+        };
+        let lines = (0..3_000)
+            .map(|index| format!("line {index}\n"))
+            .collect::<String>();
+
+        let err = differ
+            .diff(lines.as_bytes())
+            .expect_err("group is too large");
+        assert!(
+            err.to_string().starts_with("unordered group too large"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_unordered_group_matches_lines_in_any_order() {
+        let differ = make_unordered(&[
+            "start",
+            "foo (unordered)",
+            "bar (unordered)",
+            "baz (unordered)",
+            "end",
+        ]);
+
+        for lines in [
+            blines!("start", "foo", "bar", "baz", "end"),
+            blines!("start", "baz", "foo", "bar", "end"),
+            blines!("start", "bar", "baz", "foo", "end"),
+        ] {
+            let diffs = differ.diff(&lines).expect("no error");
+            assert!(!diffs.has_differences(), "{diffs:?}");
+            assert_eq!(5, diffs.count_matched, "{diffs:?}");
+        }
+    }
+
+    #[test]
+    fn test_unordered_group_matches_each_line_exactly_once() {
+        let differ = make_unordered(&["foo (unordered)", "foo (unordered)", "bar (unordered)"]);
+
+        let diffs = differ.diff(&blines!("foo", "bar")).expect("no error");
+        assert_eq!(1, diffs.count_unmatched, "{diffs:?}");
+
+        let diffs = differ
+            .diff(&blines!("foo", "bar", "foo", "foo"))
+            .expect("no error");
+        assert_eq!(0, diffs.count_unmatched, "{diffs:?}");
+        assert_eq!(4, diffs.count_output_lines, "{diffs:?}");
+        assert!(diffs.has_differences(), "{diffs:?}");
+    }
+
+    #[test]
+    fn test_unordered_group_assigns_lines_to_most_specific_expectation() {
+        let differ = make_unordered(&["* (glob) (unordered)", "foo (unordered)"]);
+
+        let diffs = differ.diff(&blines!("foo", "bar")).expect("no error");
+        assert!(!diffs.has_differences(), "{diffs:?}");
+    }
+
+    #[test]
+    fn test_unordered_group_with_quantifiers() {
+        let differ = make_unordered(&[
+            "item * (glob+) (unordered)",
+            "total (unordered)",
+            "warning (?) (unordered)",
+            "done",
+        ]);
+
+        for lines in [
+            blines!("item 1", "total", "item 2", "done"),
+            blines!("total", "item 1", "warning", "done"),
+            blines!("item 1", "item 2", "total", "done"),
+        ] {
+            let diffs = differ.diff(&lines).expect("no error");
+            assert!(!diffs.has_differences(), "{diffs:?}");
+        }
+
+        let diffs = differ.diff(&blines!("total", "done")).expect("no error");
+        assert_eq!(1, diffs.count_unmatched, "{diffs:?}");
+    }
+
+    #[test]
+    fn test_unordered_group_diff() {
+        let differ = make_unordered(&[
+            "start",
+            "foo (unordered)",
+            "bar (unordered)",
+            "baz (unordered)",
+            "end",
+        ]);
+
+        let diffs = differ
+            .diff(&blines!("start", "baz", "zoing", "foo", "end"))
+            .expect("no error");
+        insta::assert_debug_snapshot!(diffs);
+
+        let ordered = diffs
+            .lines_in_expectation_order()
+            .into_iter()
+            .map(|(line, unordered)| match line {
+                DiffLine::MatchedExpectation { index, .. } => format!("= {index}"),
+                DiffLine::UnmatchedExpectation { index, .. } => format!("- {index}"),
+                DiffLine::UnexpectedLines { lines } => {
+                    format!("+ {} {unordered}", lines[0].0)
+                }
+                DiffLine::NegatedExpectation { index, .. } => format!("! {index}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["= 0", "= 1", "- 2", "= 3", "+ 2 true", "= 4"], ordered);
+    }
+
+    #[test]
+    fn test_negated_expectation_checks_all_lines() {
+        let differ = make_unordered(&["start", "*panicked at* (!glob)", "end"]);
+
+        let diffs = differ.diff(&blines!("start", "end")).expect("no error");
+        assert!(!diffs.has_differences(), "{diffs:?}");
+        assert_eq!(
+            vec![Some(0), Some(1), Some(2)],
+            diffs
+                .lines
+                .iter()
+                .map(DiffLine::expectation_index)
+                .collect::<Vec<_>>()
+        );
+
+        let diffs = differ
+            .diff(&blines!(
+                "thread panicked at foo",
+                "start",
+                "end",
+                "thread panicked at bar"
+            ))
+            .expect("no error");
+        assert!(diffs.has_differences(), "{diffs:?}");
+        insta::assert_debug_snapshot!(diffs);
+    }
+
+    #[test]
+    fn test_negated_expectation_does_not_consume_lines() {
+        let differ = make_unordered(&["WARNING* (!glob)", "* (glob+)"]);
+
+        let diffs = differ.diff(&blines!("foo", "bar")).expect("no error");
+        assert!(!diffs.has_differences(), "{diffs:?}");
+
+        let diffs = differ
+            .diff(&blines!("foo", "WARNING bar"))
+            .expect("no error");
+        assert_eq!(1, diffs.count_unmatched, "{diffs:?}");
+        assert_eq!(2, diffs.count_output_lines, "{diffs:?}");
+    }
+
+    #[test]
+    fn test_captures_of_matched_expectations() {
+        let differ = make_unordered(&[
+            "id: (?P<FIRST>\\d+) (regex)",
+            "id: (?P<SECOND>\\d+) (regex+)",
+            "name: (?P<NAME>.+) (regex?)",
+        ]);
+
+        let diffs = differ
+            .diff(&blines!("id: 1", "id: 2", "id: 3"))
+            .expect("no error");
+        assert_eq!(
+            BTreeMap::from([
+                ("FIRST".to_string(), "1".to_string()),
+                ("SECOND".to_string(), "3".to_string()),
+            ]),
+            diffs.captures()
+        );
+    }
+
     #[test]
     fn test_serialize() {
         let diff = Diff::new(vec![
@@ -874,6 +2085,16 @@ mod tests {
         insta::assert_snapshot!(&rendered);
     }
 
+    fn make_unordered(expectations: &[&str]) -> DiffTool {
+        let maker = expectation_maker();
+        DiffTool {
+            expectations: expectations
+                .iter()
+                .map(|line| maker.parse(line).expect("parse expectation"))
+                .collect(),
+        }
+    }
+
     fn make() -> DiffTool {
         DiffTool {
             expectations: vec![
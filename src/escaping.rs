@@ -10,6 +10,7 @@ use anyhow::Result;
 use clap::ValueEnum;
 use unicode_categories::UnicodeCategories;
 
+use crate::expectation::ends_in_modifier;
 use crate::newline::BytesNewline;
 
 /// Provide ASCII and unicode compatible strings with all non-printable
@@ -102,10 +103,12 @@ fn byte_to_ascii(byte: &u8) -> String {
 fn escaped_expectation_ascii(line: &[u8]) -> String {
     let escaped = escaped_printable_ascii(line.trim_newlines());
     let encoded = lossy_string!(line.trim_newlines());
-    if encoded == escaped {
-        encoded
-    } else {
+    if encoded != escaped {
         format!("{escaped} (escaped)")
+    } else if ends_in_modifier(&encoded) {
+        format!("{encoded} (equal)")
+    } else {
+        encoded
     }
 }
 
@@ -137,10 +140,12 @@ fn escaped_printable_unicode(bytes: &[u8]) -> String {
 fn escaped_expectation_unicode(line: &[u8]) -> String {
     let escaped = escaped_printable_unicode(line.trim_newlines());
     let encoded = lossy_string!(line.trim_newlines());
-    if encoded == escaped {
-        encoded
-    } else {
+    if encoded != escaped {
         format!("{escaped} (escaped)")
+    } else if ends_in_modifier(&encoded) {
+        format!("{encoded} (equal)")
+    } else {
+        encoded
     }
 }
 
@@ -164,6 +169,7 @@ pub fn strip_colors_bytes(input: &[u8]) -> Result<Vec<u8>> {
 #[cfg(test)]
 mod tests {
 
+    use super::Escaper;
     use super::escaped_printable_ascii;
     use super::escaped_printable_unicode;
 
@@ -211,4 +217,23 @@ mod tests {
             assert_eq!(expect, &escaped, "from `{from}`");
         }
     }
+
+    #[test]
+    fn test_escaped_expectation() {
+        let tests = vec![
+            ("foo\n", "foo"),
+            ("foo\tbar\n", "foo\\tbar (escaped)"),
+            ("foo (unordered)\n", "foo (unordered) (equal)"),
+            ("foo(unordered)\n", "foo(unordered)"),
+            ("foo (!)\n", "foo (!) (equal)"),
+            ("foo (!glob)\n", "foo (!glob) (equal)"),
+        ];
+
+        for (from, expect) in tests {
+            for escaper in [Escaper::Ascii, Escaper::Unicode] {
+                let escaped = escaper.escaped_expectation(from.as_bytes());
+                assert_eq!(expect, &escaped, "from `{from}`");
+            }
+        }
+    }
 }
@@ -9,51 +9,20 @@ use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::Arc;
 
 use anyhow::Result;
 use tracing::trace;
-use tracing::warn;
 
 use super::context::Context as ExecutionContext;
 use super::runner::Runner;
+use super::sandbox::Sandbox;
+use super::shell_dialect::ShellDialect;
 use super::stateful_executor::StatefulExecutorRunnerGenerator;
 use super::subprocess_runner::SubprocessRunner;
 use crate::output::Output;
 use crate::testcase::TestCase;
 
-#[doc = include_str!("./bash_runner.excluded_variables.md")]
-pub const BASH_EXCLUDED_VARIABLES: &[&str] = &[
-    // variables from Scrut internals
-    "__SCRUT_DECLARE_VARS_CMD",
-    "__SCRUT_TEMP_STATE_PATH",
-    // variables set by scrut in every execution
-    "SCRUT_TEST",
-    // variables from `man bash`
-    "BASHOPTS",
-    "BASH_ALIASES",
-    "BASH_ARGC",
-    "BASH_ARGV",
-    "BASH_ARGV0",
-    "BASH_CMDS",
-    "BASH_COMMAND",
-    "BASH_EXECUTION_STRING",
-    "BASH_LINENO",
-    "BASH_REMATCH",
-    "BASH_SOURCE",
-    "BASH_SUBSHELL",
-    "BASH_VERSINFO",
-    "COPROC",
-    "DIRSTACK",
-    "EUID",
-    "FUNCNAME",
-    "LINENO",
-    "PPID",
-    "SHELLOPTS",
-    "UID",
-];
-
-const BASH_TEMPLATE: &str = include_str!("bash_runner.template");
-
 /// A [`Runner`], that is intended to run a series of contextual related
 /// [`crate::executors::execution::Execution`]s, which
 /// that ought to share the same environmental context (environment variables, shell
@@ -68,14 +37,20 @@ const BASH_TEMPLATE: &str = include_str!("bash_runner.template");
 /// they would have been executed from within the same parent bash process (or as close as that is
 /// possible without actually running in the same process).
 ///
+/// Despite its name, the runner supports `zsh` and POSIX shells as well. How the environmental
+/// context is dumped and restored is determined by the [`ShellDialect`] of the shell.
+///
 /// This Runner is not concurrency-safe (the shared state directory with the `state` file mandates
 /// sequential, isolated execution).
 ///
 /// Underneath the [`SubprocessRunner`] is used, so timeout constraints are fully supported.
+/// If a [`Sandbox`] is provided, then the executions run within it, using the
+/// [`super::sandbox_runner::SandboxRunner`].
 #[derive(Clone)]
 pub struct BashRunner {
     pub shell: PathBuf,
     pub state_directory: PathBuf,
+    pub sandbox: Option<Arc<Sandbox>>,
 }
 
 impl BashRunner {
@@ -83,15 +58,33 @@ impl BashRunner {
         Self {
             shell: shell.to_owned(),
             state_directory: state_directory.to_owned(),
+            sandbox: None,
         }
     }
 
     pub fn stateful_generator(shell: &Path) -> StatefulExecutorRunnerGenerator {
+        Self::make_stateful_generator(shell, None)
+    }
+
+    /// Like [`Self::stateful_generator`], but all executions run within the
+    /// provided sandbox
+    pub fn sandboxed_stateful_generator(
+        shell: &Path,
+        sandbox: Arc<Sandbox>,
+    ) -> StatefulExecutorRunnerGenerator {
+        Self::make_stateful_generator(shell, Some(sandbox))
+    }
+
+    fn make_stateful_generator(
+        shell: &Path,
+        sandbox: Option<Arc<Sandbox>>,
+    ) -> StatefulExecutorRunnerGenerator {
         let shell = shell.to_owned();
         Box::new(move |state_directory: &Path| -> Box<dyn Runner> {
             let shell_instance = Self {
                 shell: shell.to_owned(),
                 state_directory: state_directory.to_owned(),
+                sandbox: sandbox.clone(),
             };
             Box::new(shell_instance) as Box<dyn Runner>
         })
@@ -102,27 +95,25 @@ impl Runner for BashRunner {
     fn run(&self, name: &str, testcase: &TestCase, context: &ExecutionContext) -> Result<Output> {
         let shell = self.shell.to_owned();
 
-        // render the bash script
-        let state_directory_str = self.state_directory.to_string_lossy();
-        let expression = BASH_TEMPLATE
-            .replace("{state_directory}", &state_directory_str)
-            .replace("{name}", name)
-            .replace("{shell_expression}", &testcase.shell_expression)
-            .replace("{excluded_variables}", &BASH_EXCLUDED_VARIABLES.join("|"))
-            .replace(
-                "{persist_state}",
-                if testcase.config.detached.unwrap_or(false) {
-                    "0"
-                } else {
-                    "1"
-                },
-            );
+        // render the script in the dialect of the shell
+        let expression = ShellDialect::from_shell(&shell).render_script(
+            &self.state_directory,
+            &testcase.shell_expression,
+            !testcase.config.detached.unwrap_or(false),
+        );
         trace!("compiled expression {}", &expression);
 
         let mut testcase = testcase.clone();
         testcase.shell_expression = expression;
 
-        let mut output = SubprocessRunner(shell).run(name, &testcase, context)?;
+        let sandbox = self.sandbox.clone();
+        let mut output = if testcase.config.is_interactive() {
+            run_interactive(shell, sandbox, name, &testcase, context)?
+        } else if let Some(sandbox) = sandbox {
+            run_sandboxed(shell, sandbox, name, &testcase, context)?
+        } else {
+            SubprocessRunner(shell).run(name, &testcase, context)?
+        };
 
         // read captured environment variables for interpolation support
         let env_path = self.state_directory.join("env");
@@ -134,8 +125,56 @@ impl Runner for BashRunner {
     }
 }
 
+/// Interactive test cases are executed in a pseudo terminal
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn run_interactive(
+    shell: PathBuf,
+    sandbox: Option<Arc<Sandbox>>,
+    name: &str,
+    testcase: &TestCase,
+    context: &ExecutionContext,
+) -> Result<Output> {
+    super::pty_runner::PtyRunner::new(shell)
+        .with_sandbox(sandbox)
+        .run(name, testcase, context)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn run_interactive(
+    _shell: PathBuf,
+    _sandbox: Option<Arc<Sandbox>>,
+    _name: &str,
+    _testcase: &TestCase,
+    _context: &ExecutionContext,
+) -> Result<Output> {
+    anyhow::bail!("interactive mode is not supported on this platform")
+}
+
+/// Sandboxed test cases are executed within the namespaces of the sandbox
+#[cfg(target_os = "linux")]
+pub(super) fn run_sandboxed(
+    shell: PathBuf,
+    sandbox: Arc<Sandbox>,
+    name: &str,
+    testcase: &TestCase,
+    context: &ExecutionContext,
+) -> Result<Output> {
+    super::sandbox_runner::SandboxRunner::new(shell, sandbox).run(name, testcase, context)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(super) fn run_sandboxed(
+    _shell: PathBuf,
+    _sandbox: Arc<Sandbox>,
+    _name: &str,
+    _testcase: &TestCase,
+    _context: &ExecutionContext,
+) -> Result<Output> {
+    anyhow::bail!("sandbox is only supported on Linux")
+}
+
 /// Parse an env file (null-delimited KEY=VALUE entries) into a BTreeMap
-fn parse_env_file(path: &Path) -> Result<BTreeMap<String, String>> {
+pub(super) fn parse_env_file(path: &Path) -> Result<BTreeMap<String, String>> {
     let content = fs::read(path)?;
     let is_valid_key =
         |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
@@ -158,6 +197,7 @@ mod tests {
 
     use super::BashRunner;
     use super::Runner;
+    use crate::config::DocumentConfig;
     use crate::executors::DEFAULT_SHELL;
     use crate::executors::context::Context as ExecutionContext;
     use crate::output::Output;
@@ -170,6 +210,7 @@ mod tests {
         let output = BashRunner {
             shell: DEFAULT_SHELL.to_owned(),
             state_directory: temp_dir.path().into(),
+            sandbox: None,
         }
         .run(
             "name",
@@ -188,6 +229,7 @@ mod tests {
         let output = BashRunner {
             shell: DEFAULT_SHELL.to_owned(),
             state_directory: temp_dir.path().into(),
+            sandbox: None,
         }
         .run(
             "name",
@@ -207,6 +249,7 @@ mod tests {
         let output = BashRunner {
             shell: DEFAULT_SHELL.to_owned(),
             state_directory: temp_dir.path().into(),
+            sandbox: None,
         }
         .run(
             "name",
@@ -225,6 +268,7 @@ mod tests {
         let _ = BashRunner {
             shell: DEFAULT_SHELL.to_owned(),
             state_directory: temp_dir.path().into(),
+            sandbox: None,
         }
         .run(
             "name",
@@ -249,6 +293,7 @@ mod tests {
         let _ = BashRunner {
             shell: DEFAULT_SHELL.to_owned(),
             state_directory: temp_dir.path().into(),
+            sandbox: None,
         }
         .run("name", &testcase, &context)
         .expect("execute without error");
@@ -270,6 +315,7 @@ mod tests {
         let output = BashRunner {
             shell: DEFAULT_SHELL.to_owned(),
             state_directory: temp_dir.path().into(),
+            sandbox: None,
         }
         .run(
             "name",
@@ -292,6 +338,7 @@ mod tests {
         let output = BashRunner {
             shell: DEFAULT_SHELL.to_owned(),
             state_directory: temp_dir.path().into(),
+            sandbox: None,
         }
         .run("name", &testcase, &ExecutionContext::new_for_test())
         .expect("execute without error");
@@ -300,4 +347,80 @@ mod tests {
             "detached processes do not capture env"
         );
     }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn test_hermetic_state_contains_only_passed_through_variables() {
+        let temp_dir = TempDir::with_prefix("runner.").expect("create temporary directory");
+        let runner = BashRunner {
+            shell: DEFAULT_SHELL.to_owned(),
+            state_directory: temp_dir.path().into(),
+            sandbox: None,
+        };
+        let context = ExecutionContext::new_for_test_with_config(DocumentConfig {
+            hermetic: Some(true),
+            env_passthrough: vec!["PATH".into()],
+            ..Default::default()
+        });
+        runner
+            .run("name", &TestCase::from_expression("export OWN=1"), &context)
+            .expect("execute without error");
+        let output = runner
+            .run(
+                "name",
+                &TestCase::from_expression("echo \"${HOME:-none} $OWN ${PATH:+path}\""),
+                &context,
+            )
+            .expect("execute without error");
+
+        let expect: Output = ("none 1 path\n", "").into();
+        assert_eq!(expect, output);
+        assert!(
+            !output.captured_env.contains_key("HOME"),
+            "host variable is not captured in env"
+        );
+        assert!(
+            output.captured_env.contains_key("PATH"),
+            "passed through variable is captured in env"
+        );
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn test_execute_persists_state_in_dialect_of_shell() {
+        for (shell, persists_functions) in [("bash", true), ("zsh", true), ("sh", false)] {
+            let Ok(shell) = which::which(shell) else {
+                continue;
+            };
+            let temp_dir = TempDir::with_prefix("runner.").expect("create temporary directory");
+            let runner = BashRunner::new(&shell, temp_dir.path());
+            let context = ExecutionContext::new_for_test();
+            runner
+                .run(
+                    "name",
+                    &TestCase::from_expression(
+                        "mkdir sub && cd sub && touch file && set -o noglob && alias say=echo \
+                            && OWN=\"it's own\" && export EXPORTED=1 && fn() { echo fn; }",
+                    ),
+                    &context,
+                )
+                .expect("execute without error");
+            let output = runner
+                .run(
+                    "name",
+                    &TestCase::from_expression(
+                        "say \"$OWN\" $(sh -c 'echo $EXPORTED') *; fn 2>/dev/null || true",
+                    ),
+                    &context,
+                )
+                .expect("execute without error");
+
+            let expect: Output = if persists_functions {
+                ("it's own 1 *\nfn\n", "").into()
+            } else {
+                ("it's own 1 *\n", "").into()
+            };
+            assert_eq!(expect, output, "state of {}", shell.display());
+        }
+    }
 }
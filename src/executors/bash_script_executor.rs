@@ -8,6 +8,10 @@
 use std::collections::BTreeMap;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
 
 use anyhow::Context;
 use anyhow::anyhow;
@@ -18,6 +22,7 @@ use rand::rng;
 use tracing::debug;
 
 use super::DEFAULT_SHELL;
+use super::bash_runner::run_sandboxed;
 use super::context::Context as ExecutionContext;
 use super::error::ExecutionError;
 use super::error::ExecutionTimeout;
@@ -25,6 +30,7 @@ use super::executor::DEFAULT_TOTAL_TIMEOUT;
 use super::executor::Executor;
 use super::executor::Result;
 use super::runner::Runner;
+use super::sandbox::Sandbox;
 use super::subprocess_runner::SubprocessRunner;
 use crate::config::OutputStreamControl;
 use crate::config::TestCaseConfig;
@@ -58,13 +64,21 @@ const DIVIDER_PREFIX_BYTES: &[u8] = b"~~~~~~~~EXECDIVIDER::";
 /// otherwise ends the execution pre-maturely) then the whole script execution
 /// is ended and no results for individual executions are assigned.
 ///
+/// The duration of each execution is estimated from the time between the
+/// dividers, which requires a shell that provides `$EPOCHREALTIME` (Bash 5+).
+///
 /// !! Caution: Executions that detach (e.g. `nohup expression &`) are likely
 /// to mess with the output assignment !!
-pub struct BashScriptExecutor(PathBuf);
+pub struct BashScriptExecutor(PathBuf, Option<Arc<Sandbox>>);
 
 impl BashScriptExecutor {
     pub fn new(bash_path: &Path) -> Self {
-        Self(bash_path.to_owned())
+        Self(bash_path.to_owned(), None)
+    }
+
+    /// Run the script within the provided sandbox, if any
+    pub fn with_sandbox(self, sandbox: Option<Arc<Sandbox>>) -> Self {
+        Self(self.0, sandbox)
     }
 }
 
@@ -81,14 +95,22 @@ impl Executor for BashScriptExecutor {
         context: &ExecutionContext,
     ) -> Result<Vec<Output>> {
         let testcase = compile_testcase(testcases, context)?;
-        let runner = SubprocessRunner(self.0.to_owned());
-        let output = runner
-            .run("script", &testcase, context)
-            .map_err(|err| ExecutionError::from_execute(err, None, None))?;
+        let started = SystemTime::now().duration_since(UNIX_EPOCH).ok();
+        let output = match self.1 {
+            Some(ref sandbox) => run_sandboxed(
+                self.0.to_owned(),
+                sandbox.clone(),
+                "script",
+                &testcase,
+                context,
+            ),
+            None => SubprocessRunner(self.0.to_owned()).run("script", &testcase, context),
+        }
+        .map_err(|err| ExecutionError::from_execute(err, None, None))?;
         let skip_document_code = testcase.config.get_skip_document_code();
         match output.exit_code {
             ExitStatus::Code(code) if code == skip_document_code => {
-                return Err(ExecutionError::Skipped(0));
+                return Err(ExecutionError::Skipped(0, vec![]));
             }
             ExitStatus::Timeout(_) => {
                 return Err(ExecutionError::Timeout(
@@ -99,6 +121,12 @@ impl Executor for BashScriptExecutor {
                         stdout: remove_dividers_from_output(&output.stdout),
                         detached_process: None,
                         captured_env: BTreeMap::new(),
+                        interactive: None,
+                        signal: None,
+                        duration: None,
+                        resource_usage: None,
+                        previous_attempts: vec![],
+                        always_run: false,
                     }],
                 ));
             }
@@ -111,18 +139,30 @@ impl Executor for BashScriptExecutor {
             _ => {}
         }
 
-        // iterate STDOUT and split by divider string
+        // iterate STDOUT and split by divider string, with each execution
+        // lasting from the previous divider (or the start) to its own
         let mut outputs = vec![];
+        let mut previous = started;
         iterate_divided_output(
             "STDOUT",
             (&output.stdout).into(),
-            |_index: usize, out: &[u8], exit_code: i32| {
+            |_index: usize, out: &[u8], exit_code: i32, timestamp: Option<Duration>| {
+                let duration = previous
+                    .zip(timestamp)
+                    .map(|(previous, timestamp)| timestamp.saturating_sub(previous));
+                previous = timestamp;
                 outputs.push(Output {
                     stderr: vec![].into(),
                     stdout: out.to_vec().into(),
                     exit_code: ExitStatus::Code(exit_code),
                     detached_process: None,
                     captured_env: BTreeMap::new(),
+                    interactive: None,
+                    signal: None,
+                    duration,
+                    resource_usage: None,
+                    previous_attempts: vec![],
+                    always_run: false,
                 });
                 Ok(())
             },
@@ -131,7 +171,7 @@ impl Executor for BashScriptExecutor {
         // skip this?
         for (index, output) in outputs.iter().enumerate() {
             if output.exit_code == ExitStatus::Code(skip_document_code) {
-                return Err(ExecutionError::Skipped(index));
+                return Err(ExecutionError::Skipped(index, vec![]));
             }
         }
 
@@ -152,7 +192,7 @@ impl Executor for BashScriptExecutor {
             iterate_divided_output(
                 "STDERR",
                 (&output.stderr).into(),
-                |index: usize, out: &[u8], _exit_code: i32| {
+                |index: usize, out: &[u8], _exit_code: i32, _timestamp: Option<Duration>| {
                     if index >= outputs.len() {
                         return Err(ExecutionError::aborted(
                             anyhow!(
@@ -182,6 +222,19 @@ fn compile_testcase(testcases: &[&TestCase], context: &ExecutionContext) -> Resu
     // iterate all test cases and make sure that they have a consistent configuration
     // as there is no support for a divergent, per-testcase config.
     for (index, testcase) in testcases.iter().enumerate() {
+        let with_defaults = testcase.config.with_defaults_from(&context.config.defaults);
+        if with_defaults.get_always_run() {
+            return Err(ExecutionError::failed(
+                index,
+                anyhow!("always_run not supported in bash-script execution"),
+            ));
+        }
+        if with_defaults.max_rss.is_some() {
+            return Err(ExecutionError::failed(
+                index,
+                anyhow!("max_rss not supported in bash-script execution"),
+            ));
+        }
         macro_rules! set_consistent {
             ($attrib:ident) => {
                 if config.$attrib.is_none() {
@@ -202,6 +255,7 @@ fn compile_testcase(testcases: &[&TestCase], context: &ExecutionContext) -> Resu
         set_consistent!(output_stream);
         set_consistent!(skip_document_code);
         set_consistent!(wait);
+        set_consistent!(limits);
         if !config.environment.is_empty() && config.environment != testcase.config.environment {
             return Err(ExecutionError::failed(
                 index,
@@ -259,6 +313,12 @@ fn compile_script(testcases: &[&TestCase], config: &TestCaseConfig) -> Result<St
                 anyhow!("timeout per execution not supported in bash-script execution",),
             ));
         }
+        if testcase.config.is_interactive() {
+            return Err(ExecutionError::failed(
+                index,
+                anyhow!("interactive mode not supported in bash-script execution"),
+            ));
+        }
 
         // add exported environment variables before expression
         // note: this executor is only used for Cram `.t` execution, which does
@@ -299,7 +359,7 @@ fn compile_script(testcases: &[&TestCase], config: &TestCaseConfig) -> Result<St
 
 fn iterate_divided_output<C>(name: &str, output: &[u8], mut callback: C) -> Result<()>
 where
-    C: FnMut(usize, &[u8], i32) -> Result<()>,
+    C: FnMut(usize, &[u8], i32, Option<Duration>) -> Result<()>,
 {
     let mut buffer = vec![];
     let mut expected_index = 0;
@@ -312,6 +372,7 @@ where
                 prefix,
                 output_index,
                 exit_code,
+                timestamp,
             } => {
                 if output_index != expected_index {
                     debug!("---- {}\n{}\n----", name, lossy_string!(output));
@@ -333,7 +394,7 @@ where
                 if let Some(mut prefix) = prefix {
                     output.append(&mut prefix);
                 }
-                callback(output_index, &output, exit_code)?;
+                callback(output_index, &output, exit_code, timestamp)?;
                 expected_index += 1;
                 buffer.clear();
             }
@@ -342,9 +403,13 @@ where
     Ok(())
 }
 
-/// Create a new divider that separated outputs of multiple executions
+/// Create a new divider that separated outputs of multiple executions. The
+/// divider ends in the time it was printed, if the shell provides it.
 fn generate_divider(salt: &str, index: usize) -> String {
-    format!("{}{}::{}::$?", DIVIDER_PREFIX, salt, index)
+    format!(
+        "{}{}::{}::$?::${{EPOCHREALTIME:-}}",
+        DIVIDER_PREFIX, salt, index
+    )
 }
 
 #[derive(Debug, PartialEq)]
@@ -353,6 +418,7 @@ enum DividerSearch {
         prefix: Option<Vec<u8>>,
         output_index: usize,
         exit_code: i32,
+        timestamp: Option<Duration>,
     },
     NotFound,
 }
@@ -396,8 +462,15 @@ fn parse_divider_bytes(line: &[u8]) -> anyhow::Result<DividerSearch> {
     let index = index.unwrap();
     let output_index =
         String::from_utf8(line[0..index].to_vec()).context("output index must be utf8")?;
+    let line = &line[index + 2..];
+
+    // get exit code and optional timestamp
+    let (exit_code, timestamp) = match line.windows(2).position(|window| window == b"::") {
+        Some(index) => (&line[0..index], parse_timestamp(&line[index + 2..])),
+        None => (line, None),
+    };
     let exit_code =
-        String::from_utf8(line[index + 2..].to_vec()).context("return code index must be utf8")?;
+        String::from_utf8(exit_code.to_vec()).context("return code index must be utf8")?;
 
     Ok(DividerSearch::Found {
         prefix,
@@ -407,9 +480,30 @@ fn parse_divider_bytes(line: &[u8]) -> anyhow::Result<DividerSearch> {
         exit_code: exit_code
             .parse::<i32>()
             .with_context(|| format!("parse divider exit code {}", exit_code))?,
+        timestamp,
     })
 }
 
+/// Parse the value of `$EPOCHREALTIME` (seconds since epoch with microsecond
+/// fraction, separated by a locale dependent decimal point) into the duration
+/// since epoch. Returns `None` if the value is empty or invalid.
+fn parse_timestamp(value: &[u8]) -> Option<Duration> {
+    let value = std::str::from_utf8(value).ok()?;
+    let (seconds, fraction) = value.split_once(['.', ',']).unwrap_or((value, ""));
+    let seconds = seconds.parse::<u64>().ok()?;
+    if !fraction.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    let micros = if fraction.is_empty() {
+        0
+    } else {
+        format!("{:0<6}", &fraction[..fraction.len().min(6)])
+            .parse::<u32>()
+            .ok()?
+    };
+    Some(Duration::from_secs(seconds) + Duration::from_micros(micros.into()))
+}
+
 /// Generate a random alphanumeric string of given size
 fn random_string(size: usize) -> String {
     rng()
@@ -430,9 +524,13 @@ mod tests {
     use super::DIVIDER_PREFIX;
     use super::DividerSearch;
     use super::parse_divider_bytes;
+    use super::parse_timestamp;
+    use crate::config::DocumentConfig;
     use crate::config::TestCaseConfig;
+    use crate::executors::context::Context;
     use crate::executors::error::ExecutionError;
     use crate::executors::error::ExecutionTimeout;
+    use crate::executors::executor::Executor;
     use crate::executors::executor::tests::combined_output_test_suite;
     use crate::executors::executor::tests::run_executor_tests;
     use crate::executors::executor::tests::standard_output_test_suite;
@@ -532,6 +630,74 @@ mod tests {
         run_executor_tests(BashScriptExecutor::default(), tests);
     }
 
+    #[test]
+    fn test_does_not_support_max_rss() {
+        let tests = vec![(
+            "Testcase with max_rss is rejected",
+            vec![TestCase {
+                title: "Test".into(),
+                shell_expression: "echo OK1".into(),
+                config: TestCaseConfig {
+                    max_rss: Some(1024),
+                    ..Default::default()
+                },
+                ..Default::default()
+            }],
+            None,
+            Err(ExecutionError::failed(
+                0,
+                anyhow!("max_rss not supported in bash-script execution"),
+            )),
+        )];
+
+        run_executor_tests(BashScriptExecutor::default(), tests);
+
+        let result = BashScriptExecutor::default().execute_all(
+            &[&TestCase::from_expression("echo OK1")],
+            &Context::new_for_test_with_config(DocumentConfig {
+                defaults: TestCaseConfig {
+                    max_rss: Some(1024),
+                    ..Default::default()
+                },
+                ..Default::default()
+            }),
+        );
+        assert_eq!(
+            Err(ExecutionError::failed(
+                0,
+                anyhow!("max_rss not supported in bash-script execution"),
+            )),
+            result,
+            "max_rss in the document defaults is rejected"
+        );
+    }
+
+    #[test]
+    fn test_does_not_support_always_run() {
+        let tests = vec![(
+            "Testcase that always runs is rejected",
+            vec![
+                TestCase::from_expression("echo OK1"),
+                TestCase {
+                    title: "Test".into(),
+                    shell_expression: "echo OK2".into(),
+                    config: TestCaseConfig {
+                        always_run: Some(true),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+            ],
+            None,
+            Err(ExecutionError::failed(
+                1,
+                anyhow!("always_run not supported in bash-script execution"),
+            )),
+        )];
+
+        run_executor_tests(BashScriptExecutor::default(), tests);
+    }
+
     #[test]
     fn test_skipped_test_returns_skipped_error() {
         let tests = vec![(
@@ -543,7 +709,7 @@ mod tests {
             ],
             None,
             // sequential cannot identify which of the tests returned an error
-            Err(ExecutionError::Skipped(0)),
+            Err(ExecutionError::Skipped(0, vec![])),
         )];
 
         run_executor_tests(BashScriptExecutor::default(), tests);
@@ -627,6 +793,7 @@ mod tests {
                     prefix: None,
                     output_index: 5,
                     exit_code: 12,
+                    timestamp: None,
                 },
             ),
             (
@@ -635,6 +802,7 @@ mod tests {
                     prefix: None,
                     output_index: 981,
                     exit_code: 128,
+                    timestamp: None,
                 },
             ),
             (
@@ -643,6 +811,25 @@ mod tests {
                     prefix: Some(b"something".to_vec()),
                     output_index: 123,
                     exit_code: 234,
+                    timestamp: None,
+                },
+            ),
+            (
+                formatln!("{}abcd::7::0::1700000000.250000", DIVIDER_PREFIX),
+                DividerSearch::Found {
+                    prefix: None,
+                    output_index: 7,
+                    exit_code: 0,
+                    timestamp: Some(Duration::from_millis(1_700_000_000_250)),
+                },
+            ),
+            (
+                formatln!("{}abcd::8::1::", DIVIDER_PREFIX),
+                DividerSearch::Found {
+                    prefix: None,
+                    output_index: 8,
+                    exit_code: 1,
+                    timestamp: None,
                 },
             ),
         ];
@@ -652,6 +839,27 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_timestamp() {
+        let tests = vec![
+            ("", None),
+            ("foo", None),
+            ("1700000000", Some(Duration::from_secs(1_700_000_000))),
+            (
+                "1700000000.123456",
+                Some(Duration::from_micros(1_700_000_000_123_456)),
+            ),
+            (
+                "1700000000,5",
+                Some(Duration::from_millis(1_700_000_000_500)),
+            ),
+            ("1700000000.12a", None),
+        ];
+        for (value, expect) in tests {
+            assert_eq!(expect, parse_timestamp(value.as_bytes()), "from `{value}`");
+        }
+    }
+
     #[test]
     fn test_non_printable_ascii_in_output() {
         let tests = vec![(
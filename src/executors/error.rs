@@ -50,22 +50,31 @@ pub enum ExecutionError {
         error: anyhow::Error,
 
         /// Potentially the last output leading to the abort of execution
-        output: Option<Output>,
+        output: Option<Box<Output>>,
     },
 
     /// Returned if either a single [`crate::testcase::TestCase`] execution timed
-    /// out or if all are (see [`ExecutionTimeout`])
+    /// out or if all are (see [`ExecutionTimeout`]).
+    /// Contains all outputs collected so far, followed by the outputs of the
+    /// tests that are configured to `always_run`, with tests that were not
+    /// executed in between having the [`crate::output::ExitStatus::Skipped`]
+    /// exit status.
     Timeout(ExecutionTimeout, Vec<Output>),
 
     /// Returned if a [`crate::testcase::TestCase`] failed validation and had
     /// fail_fast set to true, causing immediate termination of the test document.
-    /// Contains the index of the failed test and all outputs collected so far.
+    /// Contains the index of the failed test and all outputs collected so far,
+    /// followed by the outputs of the tests that are configured to
+    /// `always_run` (see [`ExecutionError::Timeout`]).
     Failed(usize, Vec<Output>),
 
     /// Returned if a specific [`crate::testcase::TestCase`] execution is
     /// intentionally skipped by the user.
     /// This is not a final error.
-    Skipped(usize),
+    /// Contains the index of the skipping test and the outputs of the tests
+    /// that are configured to `always_run`. All other outputs have the
+    /// [`crate::output::ExitStatus::Skipped`] exit status.
+    Skipped(usize, Vec<Output>),
 }
 
 fn stringable_cmp<T: ToString>(a: T, b: T) -> bool {
@@ -76,7 +85,10 @@ impl ExecutionError {
     /// Construct a new error without an index (e.g. when failure in execute_all)
     /// happens before or after executions take place
     pub fn aborted(error: anyhow::Error, output: Option<Output>) -> Self {
-        Self::AbortedExecutions { error, output }
+        Self::AbortedExecutions {
+            error,
+            output: output.map(Box::new),
+        }
     }
 
     /// Construct a new error with an index, that denotes a specific execution
@@ -92,7 +104,7 @@ impl ExecutionError {
     ) -> Self {
         match index {
             Some(index) => Self::FailedExecution { index, error },
-            None => Self::AbortedExecutions { error, output },
+            None => Self::aborted(error, output),
         }
     }
 }
@@ -136,7 +148,7 @@ impl Display for ExecutionError {
             ExecutionError::Failed(idx, _output) => {
                 write!(f, "test {} failed with fail_fast enabled", idx + 1)
             }
-            ExecutionError::Skipped(idx) => write!(f, "skipped test {}", idx + 1),
+            ExecutionError::Skipped(idx, _output) => write!(f, "skipped test {}", idx + 1),
         }
     }
 }
@@ -24,7 +24,7 @@ use crate::output::Output;
 use crate::testcase::TestCase;
 
 /// Default timeout for all executions within a single test document
-pub static DEFAULT_TOTAL_TIMEOUT: Duration = Duration::from_mins(15);
+pub static DEFAULT_TOTAL_TIMEOUT: Duration = Duration::from_secs(15 * 60);
 
 pub type Result<T> = anyhow::Result<T, ExecutionError>;
 
@@ -36,8 +36,42 @@ pub trait Executor {
     /// Run multiple Executions and get their Output. May or may not support
     /// timeout per Execution or in total (or neither)
     fn execute_all(&self, testcases: &[&TestCase], context: &Context) -> Result<Vec<Output>>;
+
+    /// Run multiple Executions like [`Executor::execute_all`], while notifying
+    /// the observer about the progress. Executors that run all test cases at
+    /// once notify about all of them before and after the execution.
+    fn execute_all_observed(
+        &self,
+        testcases: &[&TestCase],
+        context: &Context,
+        observer: &dyn ExecutionObserver,
+    ) -> Result<Vec<Output>> {
+        for (index, testcase) in testcases.iter().enumerate() {
+            observer.testcase_started(index, testcase);
+        }
+        let outputs = self.execute_all(testcases, context)?;
+        for (index, (testcase, output)) in testcases.iter().zip(&outputs).enumerate() {
+            observer.testcase_finished(index, testcase, output);
+        }
+        Ok(outputs)
+    }
 }
 
+/// Receives notifications about the progress of an [`Executor`], with `index`
+/// being the position of the test case in the executed list
+pub trait ExecutionObserver {
+    /// Called before the test case is executed
+    fn testcase_started(&self, _index: usize, _testcase: &TestCase) {}
+
+    /// Called after the test case was executed
+    fn testcase_finished(&self, _index: usize, _testcase: &TestCase, _output: &Output) {}
+}
+
+/// An [`ExecutionObserver`] that ignores all notifications
+pub struct NoopObserver;
+
+impl ExecutionObserver for NoopObserver {}
+
 #[cfg(test)]
 pub(super) mod tests {
     use std::collections::BTreeMap;
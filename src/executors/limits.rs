@@ -0,0 +1,104 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::process::Command;
+
+use anyhow::Result;
+
+use crate::config::TestCaseLimits;
+
+/// Prepares the command so that the started process runs with the resource
+/// limits.
+///
+/// Resource limits are only supported on Linux.
+pub fn limit_command(command: &mut Command, limits: &TestCaseLimits) -> Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::process::CommandExt;
+
+        // the limits are collected before, because allocating in between fork
+        // and exec is not safe
+        let rlimits = linux::rlimits(limits);
+
+        // SAFETY: only async-signal-safe functions are called in between fork
+        // and exec
+        unsafe {
+            command.pre_exec(move || linux::apply(&rlimits));
+        }
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = (command, limits);
+        anyhow::bail!("resource limits are only supported on Linux")
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::io;
+
+    use nix::libc;
+
+    use crate::config::ResourceLimit;
+    use crate::config::TestCaseLimits;
+
+    #[cfg(target_env = "gnu")]
+    type Resource = libc::__rlimit_resource_t;
+    #[cfg(not(target_env = "gnu"))]
+    type Resource = libc::c_int;
+
+    /// A resource of `setrlimit(2)` and its soft and hard limit
+    pub(super) type Rlimit = (Resource, libc::rlim_t, libc::rlim_t);
+
+    /// Returns the `setrlimit(2)` resources and values of the limits
+    pub(super) fn rlimits(limits: &TestCaseLimits) -> Vec<Rlimit> {
+        limits
+            .to_vec()
+            .into_iter()
+            .map(|limit| match limit {
+                ResourceLimit::Memory(bytes) => (libc::RLIMIT_AS, bytes, bytes),
+                ResourceLimit::CpuTime(duration) => {
+                    // processes receive SIGXCPU at the soft limit, but are
+                    // killed right away if it equals the hard limit
+                    let seconds = duration.as_secs() + u64::from(duration.subsec_nanos() > 0);
+                    (libc::RLIMIT_CPU, seconds, seconds + 1)
+                }
+                ResourceLimit::OpenFiles(amount) => (libc::RLIMIT_NOFILE, amount, amount),
+                ResourceLimit::Processes(amount) => (libc::RLIMIT_NPROC, amount, amount),
+            })
+            .collect()
+    }
+
+    /// Sets the soft and the hard resource limits of the current process. Hard
+    /// limits are never raised, so that this works without privileges.
+    pub(super) fn apply(rlimits: &[Rlimit]) -> io::Result<()> {
+        for &(resource, soft, hard) in rlimits {
+            let mut current = libc::rlimit {
+                rlim_cur: 0,
+                rlim_max: 0,
+            };
+
+            // SAFETY: the pointers reference valid, initialized structures
+            unsafe {
+                if libc::getrlimit(resource, &mut current) == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                let hard = hard.min(current.rlim_max);
+                let limit = libc::rlimit {
+                    rlim_cur: soft.min(hard),
+                    rlim_max: hard,
+                };
+                if libc::setrlimit(resource, &limit) == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+        }
+        Ok(())
+    }
+}
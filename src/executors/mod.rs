@@ -16,6 +16,11 @@
 //! Currently there are two implementations available:
 //! - [`crate::executors::bash_script_executor::BashScriptExecutor`]
 //! - [`crate::executors::stateful_executor::StatefulExecutor`]
+//!
+//! Both can run their executions within a [`crate::executors::sandbox::Sandbox`].
+//! The [`crate::executors::stateful_executor::StatefulExecutor`] can also run
+//! all executions in one long-lived shell, using the
+//! [`crate::executors::session_runner::SessionRunner`].
 
 use std::path::Path;
 use std::sync::LazyLock;
@@ -26,9 +31,20 @@ pub mod context;
 pub mod error;
 pub mod execution;
 pub mod executor;
+pub mod limits;
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+pub mod pty_runner;
 pub mod runner;
+pub mod sandbox;
+#[cfg(target_os = "linux")]
+pub mod sandbox_runner;
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+pub mod session_runner;
+pub mod shell_dialect;
 pub mod stateful_executor;
 pub mod subprocess_runner;
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+pub mod usage;
 pub mod util;
 
 static SHELL_PATH: LazyLock<String> = LazyLock::new(|| {
@@ -0,0 +1,469 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::io::Write;
+use std::os::fd::AsRawFd;
+use std::os::unix::process::CommandExt;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::mpsc::channel;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::Context;
+use anyhow::Result;
+use anyhow::bail;
+use nix::libc;
+use nix::pty::Winsize;
+use nix::pty::openpty;
+use nix::sys::signal::Signal;
+use nix::sys::signal::killpg;
+use nix::unistd::Pid;
+use tempfile::NamedTempFile;
+use tracing::debug;
+use tracing::debug_span;
+use tracing::trace;
+
+use super::context::Context as ExecutionContext;
+use super::limits::limit_command;
+use super::runner::Runner;
+use super::sandbox::Sandbox;
+use super::usage::wait_with_usage;
+use super::util::hermetic_environment;
+use crate::interactive::InteractiveDirective;
+use crate::interactive::InteractiveSession;
+use crate::interactive::InteractiveStep;
+use crate::interactive::encode_keys;
+use crate::interactive::matches_any_line;
+use crate::output::ExitStatus;
+use crate::output::Output;
+use crate::testcase::TestCase;
+use crate::validation::ValidationBody;
+
+/// How long the terminal must not receive any output to be considered settled,
+/// before the screen is captured for an `ASSERT` directive
+const SETTLE_DURATION: Duration = Duration::from_millis(50);
+
+/// How long to wait for remaining output after the process ended
+const DRAIN_DURATION: Duration = Duration::from_millis(100);
+
+/// A runner that starts an interpreter (usually `bash`) within a pseudo
+/// terminal and drives the execution of the shell expression of a given
+/// [`crate::testcase::TestCase`] with its [`InteractiveDirective`]s.
+///
+/// The output of the terminal (the transcript) is captured as STDOUT. The
+/// screen after each directive is recorded in an [`InteractiveSession`] for
+/// later validation.
+///
+/// Constraining the max execution time is supported.
+#[derive(Clone)]
+pub struct PtyRunner(pub(super) PathBuf, pub(super) Option<Arc<Sandbox>>);
+
+impl PtyRunner {
+    pub fn new(p: PathBuf) -> Self {
+        Self(p, None)
+    }
+
+    /// Run the shell expression within the provided sandbox, if any
+    pub fn with_sandbox(self, sandbox: Option<Arc<Sandbox>>) -> Self {
+        Self(self.0, sandbox)
+    }
+}
+
+impl Runner for PtyRunner {
+    fn run(&self, _name: &str, testcase: &TestCase, context: &ExecutionContext) -> Result<Output> {
+        let ValidationBody::Interactive(ref body) = testcase.body else {
+            bail!("pseudo terminal execution requires an interactive test case");
+        };
+        let shell = &self.0;
+        let terminal = testcase.config.get_terminal();
+
+        // the shell expression is provided as a script file, because STDIN is
+        // the terminal that is driven by the directives
+        let mut script =
+            NamedTempFile::new_in(&context.temp_directory).context("create script file")?;
+        script
+            .write_all(testcase.shell_expression.as_bytes())
+            .context("write script file")?;
+
+        let pty = openpty(
+            Some(&Winsize {
+                ws_row: terminal.rows,
+                ws_col: terminal.columns,
+                ws_xpixel: 0,
+                ws_ypixel: 0,
+            }),
+            None,
+        )
+        .context("open pseudo terminal")?;
+        set_close_on_exec(&pty.master)?;
+        set_close_on_exec(&pty.slave)?;
+
+        // apply environment variables (ensure SHELL and terminal are set)
+        let mut envs = testcase.config.environment.clone();
+        envs.insert("SHELL".into(), shell.to_string_lossy().to_string());
+        envs.entry("TERM".into()).or_insert_with(|| "xterm".into());
+        envs.insert("COLUMNS".into(), terminal.columns.to_string());
+        envs.insert("LINES".into(), terminal.rows.to_string());
+
+        // hermetic executions start from an empty environment
+        let mut command = Command::new(shell);
+        if let Some(passthrough) = hermetic_environment(&context.config) {
+            command.env_clear().envs(&passthrough);
+        }
+        command
+            .arg(script.path())
+            .envs(&envs)
+            .current_dir(&context.work_directory)
+            .stdin(Stdio::from(pty.slave.try_clone()?))
+            .stdout(Stdio::from(pty.slave.try_clone()?))
+            .stderr(Stdio::from(pty.slave));
+        if let Some(ref sandbox) = self.1 {
+            sandbox
+                .join(&mut command, &context.work_directory)
+                .context("join sandbox")?;
+        }
+        if let Some(ref limits) = testcase.config.limits {
+            limit_command(&mut command, limits)?;
+        }
+
+        // SAFETY: only async-signal-safe functions are called in between fork
+        // and exec, to make the terminal the controlling terminal of the new
+        // session of the child
+        unsafe {
+            command.pre_exec(|| {
+                if libc::setsid() == -1 || libc::ioctl(0, libc::TIOCSCTTY as _, 0) == -1 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        let child = command.spawn().context("start process")?;
+
+        // release the terminal handles of the child, so that reading from the
+        // terminal ends when the child (and all its children) end
+        drop(command);
+
+        let span = debug_span!("process", pid = child.id());
+        let _s = span.enter();
+        trace!(testcase = %&testcase, "running testcase in pseudo terminal");
+
+        let master = File::from(pty.master);
+        let mut writer = master.try_clone().context("clone pseudo terminal")?;
+        let mut session = TerminalSession::new(master, &terminal)?;
+        let timeout_at = testcase.config.timeout.map(|timeout| {
+            debug!(
+                "running interactive session (max {})",
+                humantime::format_duration(timeout)
+            );
+            Instant::now() + timeout
+        });
+        let deadline = |duration: Duration| {
+            let at = Instant::now() + duration;
+            timeout_at.map_or(at, |timeout_at| at.min(timeout_at))
+        };
+
+        // process all directives in order, stop at the first failing wait
+        let mut steps = vec![];
+        for directive in &body.directives {
+            session.drain();
+            let mut timed_out = false;
+            match directive {
+                InteractiveDirective::Wait(expectation) => {
+                    let until = deadline(terminal.wait_timeout);
+                    while !matches_any_line(expectation, &session.screen()) {
+                        if !session.receive(until) {
+                            timed_out = !matches_any_line(expectation, &session.screen());
+                            break;
+                        }
+                    }
+                }
+                InteractiveDirective::SendKeys(keys) => {
+                    // the process may already be gone, which will be visible
+                    // in the validation of subsequent directives
+                    if let Err(err) = writer
+                        .write_all(&encode_keys(keys))
+                        .and_then(|_| writer.flush())
+                    {
+                        debug!("failed to send keys {keys:?}: {err}");
+                    }
+                }
+                InteractiveDirective::Assert(_) => {
+                    session.settle(deadline(terminal.wait_timeout));
+                }
+                InteractiveDirective::AssertTranscript(_) => {}
+            }
+            steps.push(InteractiveStep {
+                screen: session.screen(),
+                timed_out,
+            });
+            if timed_out {
+                debug!("timed out waiting for `{directive}`, aborting");
+                kill_process_group(child.id());
+                break;
+            }
+        }
+
+        // wait for the process to end
+        let mut resource_usage = None;
+        let mut signal = None;
+        let exit_code = loop {
+            if let Some((exit, usage)) =
+                wait_with_usage(child.id(), false).context("check process exit")?
+            {
+                resource_usage = Some(usage);
+                signal = exit.signal();
+                break exit.code().map_or(ExitStatus::Unknown, ExitStatus::Code);
+            }
+            if timeout_at.is_some_and(|at| at <= Instant::now()) {
+                kill_process_group(child.id());
+                wait_with_usage(child.id(), true).context("wait for killed process")?;
+                break ExitStatus::Timeout(testcase.config.timeout.unwrap_or_default());
+            }
+            if !session.receive(deadline(SETTLE_DURATION)) {
+                thread::sleep(Duration::from_millis(10));
+            }
+        };
+        session.settle(Instant::now() + DRAIN_DURATION);
+
+        Ok(Output {
+            stdout: testcase.render_output(&session.transcript)?.to_vec().into(),
+            stderr: vec![].into(),
+            exit_code,
+            detached_process: None,
+            captured_env: BTreeMap::new(),
+            interactive: Some(Box::new(InteractiveSession {
+                screen: session.screen(),
+                steps,
+            })),
+            signal,
+            duration: None,
+            resource_usage,
+            previous_attempts: vec![],
+            always_run: false,
+        })
+    }
+}
+
+/// The reading end of the pseudo terminal, that keeps track of the transcript
+/// and emulates the screen
+struct TerminalSession {
+    receiver: Receiver<Vec<u8>>,
+    parser: vt100::Parser,
+    transcript: Vec<u8>,
+    columns: u16,
+    closed: bool,
+}
+
+impl TerminalSession {
+    fn new(mut master: File, terminal: &crate::config::TestCaseTerminal) -> Result<Self> {
+        let (sender, receiver) = channel();
+        thread::Builder::new()
+            .name("pty-reader".into())
+            .spawn(move || {
+                let mut buffer = [0; 4096];
+                // reading fails (EIO) once all handles of the terminal are closed
+                while let Ok(size) = master.read(&mut buffer) {
+                    if size == 0 || sender.send(buffer[..size].to_vec()).is_err() {
+                        break;
+                    }
+                }
+            })
+            .context("start pseudo terminal reader")?;
+        Ok(Self {
+            receiver,
+            parser: vt100::Parser::new(terminal.rows, terminal.columns, 0),
+            transcript: vec![],
+            columns: terminal.columns,
+            closed: false,
+        })
+    }
+
+    /// Process output that was received up until now
+    fn drain(&mut self) {
+        while let Ok(data) = self.receiver.try_recv() {
+            self.process(&data);
+        }
+    }
+
+    /// Wait for and process output until the deadline. Returns false if no
+    /// output was received before the deadline or the terminal is closed.
+    fn receive(&mut self, until: Instant) -> bool {
+        if self.closed {
+            return false;
+        }
+        match self
+            .receiver
+            .recv_timeout(until.saturating_duration_since(Instant::now()))
+        {
+            Ok(data) => {
+                self.process(&data);
+                true
+            }
+            Err(RecvTimeoutError::Timeout) => false,
+            Err(RecvTimeoutError::Disconnected) => {
+                self.closed = true;
+                false
+            }
+        }
+    }
+
+    /// Process output until there is no more output for a short while or the
+    /// deadline is reached
+    fn settle(&mut self, until: Instant) {
+        while Instant::now() < until && self.receive(until.min(Instant::now() + SETTLE_DURATION)) {}
+    }
+
+    fn process(&mut self, data: &[u8]) {
+        self.parser.process(data);
+        self.transcript.extend(data);
+    }
+
+    /// The current lines of the screen, without trailing whitespace
+    fn screen(&self) -> Vec<String> {
+        self.parser
+            .screen()
+            .rows(0, self.columns)
+            .map(|row| row.trim_end().to_string())
+            .collect()
+    }
+}
+
+/// Keep the terminal handles from leaking into processes that are started
+/// concurrently, e.g. when test documents are executed in parallel
+fn set_close_on_exec(fd: &impl AsRawFd) -> Result<()> {
+    // SAFETY: the file descriptor is owned and open for the duration of the call
+    if unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC) } == -1 {
+        return Err(std::io::Error::last_os_error()).context("set close-on-exec");
+    }
+    Ok(())
+}
+
+fn kill_process_group(pid: u32) {
+    if let Err(err) = killpg(Pid::from_raw(pid as i32), Signal::SIGKILL) {
+        debug!("failed to kill process group {pid}: {err}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PtyRunner;
+    use super::Runner;
+    use crate::config::TestCaseConfig;
+    use crate::config::TestMode;
+    use crate::executors::DEFAULT_SHELL;
+    use crate::executors::context::Context as ExecutionContext;
+    use crate::expectation::tests::expectation_maker;
+    use crate::interactive::InteractiveDirective;
+    use crate::output::ExitStatus;
+    use crate::testcase::TestCase;
+    use crate::validation::InteractiveBody;
+    use crate::validation::ValidationBody;
+
+    fn interactive_testcase(expression: &str, directives: &[&str]) -> TestCase {
+        let maker = expectation_maker();
+        TestCase {
+            title: "Test".into(),
+            shell_expression: expression.into(),
+            body: ValidationBody::Interactive(InteractiveBody {
+                directives: directives
+                    .iter()
+                    .map(|line| InteractiveDirective::parse(line, &maker).expect("parse directive"))
+                    .collect(),
+            }),
+            config: TestCaseConfig {
+                mode: Some(TestMode::Interactive),
+                timeout: Some(std::time::Duration::from_secs(10)),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_run_prompt_in_terminal() {
+        let testcase = interactive_testcase(
+            "read -p 'Continue? ' answer && test -t 0 && echo \"got $answer\"",
+            &[
+                "WAIT Continue? (glob)",
+                "SEND_KEYS yes<Enter>",
+                "WAIT got yes",
+            ],
+        );
+        let output = PtyRunner::new(DEFAULT_SHELL.to_path_buf())
+            .run("name", &testcase, &ExecutionContext::new_for_test())
+            .expect("execute without error");
+        assert_eq!(ExitStatus::Code(0), output.exit_code);
+        let session = output.interactive.clone().expect("session is recorded");
+        assert_eq!(3, session.steps.len());
+        assert!(session.steps.iter().all(|step| !step.timed_out));
+        assert_eq!(
+            "Continue? yes\ngot yes\n",
+            String::from_utf8_lossy((&output.stdout).into())
+        );
+        testcase.validate(&output).expect("validates");
+    }
+
+    #[test]
+    fn test_run_measures_resource_usage() {
+        let testcase = interactive_testcase(
+            "head -c 20000000 /dev/zero | tail -c 1 | wc -c",
+            &["WAIT 1"],
+        );
+        let output = PtyRunner::new(DEFAULT_SHELL.to_path_buf())
+            .run("name", &testcase, &ExecutionContext::new_for_test())
+            .expect("execute without error");
+        let usage = output.resource_usage.expect("resource usage is measured");
+        assert!(
+            usage.max_rss >= 1024 * 1024,
+            "peak memory is measured: {usage:?}"
+        );
+    }
+
+    #[test]
+    fn test_run_aborts_on_wait_timeout() {
+        let mut testcase = interactive_testcase(
+            "echo something && sleep 10",
+            &["WAIT nothing", "SEND_KEYS never"],
+        );
+        testcase.config.terminal = Some(crate::config::TestCaseTerminal {
+            wait_timeout: std::time::Duration::from_millis(200),
+            ..Default::default()
+        });
+        let output = PtyRunner::new(DEFAULT_SHELL.to_path_buf())
+            .run("name", &testcase, &ExecutionContext::new_for_test())
+            .expect("execute without error");
+        let session = output.interactive.clone().expect("session is recorded");
+        assert_eq!(1, session.steps.len());
+        assert!(session.steps[0].timed_out);
+        assert_eq!("something", session.steps[0].screen[0]);
+        assert!(testcase.validate(&output).is_err());
+    }
+
+    #[test]
+    fn test_run_uses_terminal_size() {
+        let mut testcase = interactive_testcase("stty size", &["WAIT 7 33"]);
+        testcase.config.terminal = Some(crate::config::TestCaseTerminal {
+            columns: 33,
+            rows: 7,
+            ..Default::default()
+        });
+        let output = PtyRunner::new(DEFAULT_SHELL.to_path_buf())
+            .run("name", &testcase, &ExecutionContext::new_for_test())
+            .expect("execute without error");
+        assert_eq!(7, output.interactive.expect("session").screen.len());
+        assert_eq!("7 33\n", String::from_utf8_lossy((&output.stdout).into()));
+    }
+}
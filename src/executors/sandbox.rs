@@ -0,0 +1,456 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::path::Path;
+use std::process::Command;
+
+use anyhow::Result;
+
+/// A sandbox in which all executions of a single test document run, so that
+/// tests cannot accidentally modify the host or reach the network.
+///
+/// On Linux the sandbox consists of new user, mount, network and PID
+/// namespaces:
+/// - the whole filesystem is mounted read-only, except for the provided
+///   writable directories (i.e. the work and the temporary directory)
+/// - only the loopback network device is available
+/// - processes of the host are not visible, and all processes in the sandbox
+///   are terminated when the sandbox is dropped
+///
+/// The namespaces are created once and held by a holder process, so that
+/// state like detached processes or listening sockets is shared between the
+/// executions, as it is without sandbox. Executions join the namespaces when
+/// they are started with a [`Command`] that was prepared with [`Sandbox::join`].
+///
+/// Sandboxing requires unprivileged user namespaces and is not supported on
+/// other platforms.
+pub struct Sandbox {
+    #[cfg(target_os = "linux")]
+    namespaces: linux::Namespaces,
+}
+
+impl Sandbox {
+    /// Create a new sandbox in which only the provided directories are writable
+    pub fn new(writable_directories: &[&Path]) -> Result<Self> {
+        #[cfg(target_os = "linux")]
+        {
+            Ok(Self {
+                namespaces: linux::Namespaces::new(writable_directories)?,
+            })
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = writable_directories;
+            anyhow::bail!("sandbox is only supported on Linux")
+        }
+    }
+
+    /// Prepare the command so that the started process runs within the
+    /// sandbox, in the given directory
+    pub fn join(&self, command: &mut Command, directory: &Path) -> Result<()> {
+        #[cfg(target_os = "linux")]
+        {
+            self.namespaces.join(command, directory)
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = (command, directory);
+            anyhow::bail!("sandbox is only supported on Linux")
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::ffi::CStr;
+    use std::ffi::CString;
+    use std::fs;
+    use std::fs::File;
+    use std::io;
+    use std::os::fd::AsRawFd;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::process::CommandExt;
+    use std::path::Path;
+    use std::process::Child;
+    use std::process::Command;
+    use std::process::Stdio;
+
+    use anyhow::Context;
+    use anyhow::Result;
+    use nix::libc;
+    use tracing::debug;
+
+    /// The namespaces that make up the sandbox, by their name in `/proc/PID/ns`
+    const NAMESPACES: [(&str, libc::c_int); 4] = [
+        ("user", libc::CLONE_NEWUSER),
+        ("mnt", libc::CLONE_NEWNS),
+        ("net", libc::CLONE_NEWNET),
+        ("pid_for_children", libc::CLONE_NEWPID),
+    ];
+
+    /// Mount flags that must be kept when remounting. On Linux the `ST_*`
+    /// flags of `statvfs` share their values with the `MS_*` flags of `mount`.
+    const KEEP_MOUNT_FLAGS: libc::c_ulong = libc::MS_NOSUID
+        | libc::MS_NODEV
+        | libc::MS_NOEXEC
+        | libc::MS_NOATIME
+        | libc::MS_NODIRATIME
+        | libc::MS_RELATIME;
+
+    pub(super) struct Namespaces {
+        /// The process that holds the namespaces
+        holder: Child,
+
+        /// Handles of the namespaces of the holder process
+        files: Vec<(File, libc::c_int)>,
+    }
+
+    impl Namespaces {
+        pub(super) fn new(writable_directories: &[&Path]) -> Result<Self> {
+            // everything that the holder process needs is prepared up front,
+            // because allocations are not safe in between fork and exec
+            // SAFETY: getuid and getgid always succeed
+            let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+            let setup = Setup {
+                uid_map: CString::new(format!("{uid} {uid} 1"))?,
+                gid_map: CString::new(format!("{gid} {gid} 1"))?,
+                mount_points: read_mount_points()?,
+                writable_directories: writable_directories
+                    .iter()
+                    .map(|path| {
+                        let path = fs::canonicalize(path).with_context(|| {
+                            format!("resolve writable directory {}", path.display())
+                        })?;
+                        Ok(CString::new(path.as_os_str().as_bytes())?)
+                    })
+                    .collect::<Result<Vec<_>>>()?,
+            };
+
+            // the holder process never executes the program, it stays within
+            // the callback until it is killed
+            let mut command = Command::new("scrut-sandbox");
+            command
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null());
+
+            // SAFETY: only async-signal-safe functions are called in between
+            // fork and exec
+            unsafe {
+                command.pre_exec(move || {
+                    setup.apply()?;
+                    match libc::fork() {
+                        -1 => Err(io::Error::last_os_error()),
+                        0 => {
+                            // the first process in the new PID namespace
+                            // becomes its init process, which must live as
+                            // long as the sandbox does
+                            libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGKILL);
+                            libc::mount(
+                                c"proc".as_ptr(),
+                                c"/proc".as_ptr(),
+                                c"proc".as_ptr(),
+                                libc::MS_NOSUID | libc::MS_NODEV | libc::MS_NOEXEC,
+                                std::ptr::null(),
+                            );
+                            close_inherited_files();
+                            reap_children()
+                        }
+                        pid => {
+                            close_inherited_files();
+                            exit_with_child(pid)
+                        }
+                    }
+                });
+            }
+            let holder = command.spawn().context("create sandbox namespaces")?;
+            debug!(pid = holder.id(), "created sandbox");
+
+            // the holder is terminated on drop, if opening the namespaces fails
+            let mut namespaces = Self {
+                holder,
+                files: vec![],
+            };
+            for (name, kind) in NAMESPACES {
+                let path = format!("/proc/{}/ns/{name}", namespaces.holder.id());
+                let file = File::open(&path).with_context(|| format!("open {path}"))?;
+                namespaces.files.push((file, kind));
+            }
+
+            Ok(namespaces)
+        }
+
+        pub(super) fn join(&self, command: &mut Command, directory: &Path) -> Result<()> {
+            let namespaces = self
+                .files
+                .iter()
+                .map(|(file, kind)| (file.as_raw_fd(), *kind))
+                .collect::<Vec<_>>();
+            let directory = CString::new(directory.as_os_str().as_bytes())?;
+
+            // SAFETY: only async-signal-safe functions are called in between
+            // fork and exec
+            unsafe {
+                command.pre_exec(move || {
+                    for (fd, kind) in &namespaces {
+                        check(libc::setns(*fd, *kind))?;
+                    }
+
+                    // joining the mount namespace resets the directory
+                    check(libc::chdir(directory.as_ptr()))?;
+
+                    // a process group of its own allows to end the process
+                    // with all its children, as without sandbox
+                    check(libc::setpgid(0, 0))?;
+
+                    // only children are started in the joined PID namespace
+                    match libc::fork() {
+                        -1 => Err(io::Error::last_os_error()),
+                        0 => {
+                            libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGKILL);
+                            Ok(())
+                        }
+                        pid => {
+                            close_inherited_files();
+                            exit_with_child(pid)
+                        }
+                    }
+                });
+            }
+            Ok(())
+        }
+    }
+
+    impl Drop for Namespaces {
+        fn drop(&mut self) {
+            // ending the holder ends the init process of the PID namespace,
+            // which terminates all remaining processes in the sandbox
+            let _ = self.holder.kill();
+            let _ = self.holder.wait();
+        }
+    }
+
+    /// All that is needed to set up the namespaces in the holder process
+    struct Setup {
+        uid_map: CString,
+        gid_map: CString,
+        mount_points: Vec<CString>,
+        writable_directories: Vec<CString>,
+    }
+
+    impl Setup {
+        /// Create the namespaces and set up the filesystem and the network
+        ///
+        /// SAFETY: must only be called in between fork and exec
+        unsafe fn apply(&self) -> io::Result<()> {
+            unsafe {
+                check(libc::unshare(
+                    libc::CLONE_NEWUSER
+                        | libc::CLONE_NEWNS
+                        | libc::CLONE_NEWNET
+                        | libc::CLONE_NEWPID,
+                ))?;
+
+                // map the current user and group into the user namespace
+                write_file(c"/proc/self/setgroups", c"deny")?;
+                write_file(c"/proc/self/uid_map", &self.uid_map)?;
+                write_file(c"/proc/self/gid_map", &self.gid_map)?;
+
+                // do not propagate any of the following changes to the host
+                check(libc::mount(
+                    std::ptr::null(),
+                    c"/".as_ptr(),
+                    std::ptr::null(),
+                    libc::MS_REC | libc::MS_PRIVATE,
+                    std::ptr::null(),
+                ))?;
+
+                // make all mounts read-only; mounts that cannot be changed
+                // (e.g. because they are hidden by other mounts) are skipped,
+                // except for the root
+                for mount_point in &self.mount_points {
+                    let result = remount(mount_point, true);
+                    if mount_point.as_bytes() == b"/" {
+                        result?;
+                    }
+                }
+
+                // .. except for the writable directories
+                for directory in &self.writable_directories {
+                    check(libc::mount(
+                        directory.as_ptr(),
+                        directory.as_ptr(),
+                        std::ptr::null(),
+                        libc::MS_BIND | libc::MS_REC,
+                        std::ptr::null(),
+                    ))?;
+                    remount(directory, false)?;
+                }
+
+                enable_loopback()
+            }
+        }
+    }
+
+    /// Change a mount to be read-only or writable, keeping all other flags
+    unsafe fn remount(path: &CStr, read_only: bool) -> io::Result<()> {
+        unsafe {
+            let mut stat: libc::statvfs = std::mem::zeroed();
+            check(libc::statvfs(path.as_ptr(), &mut stat))?;
+            let mut flags = libc::MS_REMOUNT | libc::MS_BIND | (stat.f_flag & KEEP_MOUNT_FLAGS);
+            if read_only {
+                flags |= libc::MS_RDONLY;
+            }
+            check(libc::mount(
+                std::ptr::null(),
+                path.as_ptr(),
+                std::ptr::null(),
+                flags,
+                std::ptr::null(),
+            ))
+        }
+    }
+
+    /// Bring up the loopback device, which is down in a new network namespace
+    unsafe fn enable_loopback() -> io::Result<()> {
+        unsafe {
+            let socket = libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0);
+            check(socket)?;
+            let mut request: libc::ifreq = std::mem::zeroed();
+            for (target, source) in request.ifr_name.iter_mut().zip(b"lo") {
+                *target = *source as libc::c_char;
+            }
+            let mut result = check(libc::ioctl(socket, libc::SIOCGIFFLAGS, &mut request));
+            if result.is_ok() {
+                request.ifr_ifru.ifru_flags |= (libc::IFF_UP | libc::IFF_RUNNING) as libc::c_short;
+                result = check(libc::ioctl(socket, libc::SIOCSIFFLAGS, &request));
+            }
+            libc::close(socket);
+            result
+        }
+    }
+
+    unsafe fn write_file(path: &CStr, content: &CStr) -> io::Result<()> {
+        unsafe {
+            let fd = libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+            check(fd)?;
+            let bytes = content.to_bytes();
+            let written = libc::write(fd, bytes.as_ptr().cast(), bytes.len());
+            libc::close(fd);
+            if written != bytes.len() as isize {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        }
+    }
+
+    /// Close all files but STDIN, STDOUT and STDERR, so that the process that
+    /// started the command does not wait for this process to execute
+    unsafe fn close_inherited_files() {
+        unsafe {
+            if libc::syscall(libc::SYS_close_range, 3, libc::c_uint::MAX, 0) != 0 {
+                for fd in 3..1024 {
+                    libc::close(fd);
+                }
+            }
+        }
+    }
+
+    /// Wait for the child to end and end the same way
+    unsafe fn exit_with_child(pid: libc::pid_t) -> ! {
+        unsafe {
+            let mut status = 0;
+            while libc::waitpid(pid, &mut status, 0) == -1
+                && io::Error::last_os_error().kind() == io::ErrorKind::Interrupted
+            {}
+            if libc::WIFSIGNALED(status) {
+                let signal = libc::WTERMSIG(status);
+                libc::signal(signal, libc::SIG_DFL);
+                libc::kill(libc::getpid(), signal);
+                libc::_exit(128 + signal)
+            }
+            libc::_exit(libc::WEXITSTATUS(status))
+        }
+    }
+
+    /// Reap all processes that end in the sandbox, until killed
+    unsafe fn reap_children() -> ! {
+        unsafe {
+            loop {
+                if libc::wait(std::ptr::null_mut()) == -1 {
+                    libc::sleep(1);
+                }
+            }
+        }
+    }
+
+    fn check(result: libc::c_int) -> io::Result<()> {
+        if result == -1 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns the paths of all current mounts
+    fn read_mount_points() -> Result<Vec<CString>> {
+        let content =
+            fs::read_to_string("/proc/self/mountinfo").context("read /proc/self/mountinfo")?;
+        content
+            .lines()
+            .filter_map(|line| line.split(' ').nth(4))
+            .map(|path| Ok(CString::new(unescape_mount_point(path))?))
+            .collect()
+    }
+
+    /// Mount points in `/proc/self/mountinfo` contain octal escapes for
+    /// whitespace and backslashes (e.g. `\040` for a space)
+    fn unescape_mount_point(path: &str) -> Vec<u8> {
+        let bytes = path.as_bytes();
+        let mut result = Vec::with_capacity(bytes.len());
+        let mut index = 0;
+        while index < bytes.len() {
+            let escaped = bytes
+                .get(index + 1..index + 4)
+                .filter(|_| bytes[index] == b'\\')
+                .and_then(|digits| std::str::from_utf8(digits).ok())
+                .and_then(|digits| u8::from_str_radix(digits, 8).ok());
+            if let Some(value) = escaped {
+                result.push(value);
+                index += 4;
+                continue;
+            }
+            result.push(bytes[index]);
+            index += 1;
+        }
+        result
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::unescape_mount_point;
+
+        #[test]
+        fn test_unescape_mount_point() {
+            let tests = [
+                ("/", "/"),
+                ("/mnt/some\\040dir", "/mnt/some dir"),
+                ("/mnt/tab\\011and\\134backslash", "/mnt/tab\tand\\backslash"),
+                ("/mnt/trailing\\04", "/mnt/trailing\\04"),
+            ];
+            for (path, expected) in tests {
+                assert_eq!(
+                    expected.as_bytes(),
+                    unescape_mount_point(path),
+                    "unescape {path}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,347 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::io::Seek;
+use std::io::Write;
+use std::os::fd::FromRawFd;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::channel;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::Context;
+use anyhow::Result;
+use nix::libc;
+use tempfile::tempfile_in;
+use tracing::debug;
+use tracing::debug_span;
+use tracing::trace;
+
+use super::context::Context as ExecutionContext;
+use super::limits::limit_command;
+use super::runner::Runner;
+use super::sandbox::Sandbox;
+use super::usage::wait_with_usage;
+use super::util::hermetic_environment;
+use crate::config::OutputStreamControl;
+use crate::output::DetachedProcess;
+use crate::output::ExitStatus;
+use crate::output::Output;
+use crate::testcase::TestCase;
+
+/// How often to check whether the process ended
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// How long to wait for remaining output after the process was aborted
+const DRAIN_DURATION: Duration = Duration::from_millis(100);
+
+/// A runner that starts an interpreter (usually `bash`) within a [`Sandbox`]
+/// and writes the shell expression of a given [`crate::testcase::TestCase`]
+/// into STDIN. Behaves otherwise like the
+/// [`crate::executors::subprocess_runner::SubprocessRunner`].
+///
+/// Constraining the max execution time is supported.
+#[derive(Clone)]
+pub struct SandboxRunner {
+    shell: PathBuf,
+    sandbox: Arc<Sandbox>,
+}
+
+impl SandboxRunner {
+    pub fn new(shell: PathBuf, sandbox: Arc<Sandbox>) -> Self {
+        Self { shell, sandbox }
+    }
+}
+
+impl Runner for SandboxRunner {
+    fn run(&self, _name: &str, testcase: &TestCase, context: &ExecutionContext) -> Result<Output> {
+        let mut command = shell_command(&self.shell, testcase, context);
+        self.sandbox
+            .join(&mut command, &context.work_directory)
+            .context("join sandbox")?;
+        if let Some(ref limits) = testcase.config.limits {
+            limit_command(&mut command, limits)?;
+        }
+        run_command(command, testcase, context)
+    }
+}
+
+/// Returns the command that starts the shell with the environment of the
+/// test case in the work directory
+pub(super) fn shell_command(
+    shell: &Path,
+    testcase: &TestCase,
+    context: &ExecutionContext,
+) -> Command {
+    // apply environment variables (ensure SHELL is set)
+    let mut envs = testcase.config.environment.clone();
+    envs.insert("SHELL".into(), shell.to_string_lossy().to_string());
+
+    // hermetic executions start from an empty environment
+    let mut command = Command::new(shell);
+    if let Some(passthrough) = hermetic_environment(&context.config) {
+        command.env_clear().envs(&passthrough);
+    }
+    command.envs(&envs).current_dir(&context.work_directory);
+    command
+}
+
+/// Starts the shell command, writes the shell expression of the test case
+/// into STDIN and returns the output of the execution. Everything that must
+/// happen before the shell executes, like joining a sandbox or applying
+/// resource limits, must be prepared in the command.
+pub(super) fn run_command(
+    mut command: Command,
+    testcase: &TestCase,
+    context: &ExecutionContext,
+) -> Result<Output> {
+    let input = testcase.shell_expression.as_bytes().to_vec();
+    let is_detached = testcase.config.detached.unwrap_or(false);
+    if is_detached {
+        // STDIN is provided as a file, so that the detached process can
+        // read it after this runner returned
+        let mut tmp =
+            tempfile_in(&context.temp_directory).context("Create temporary STDIN file")?;
+        tmp.write_all(&input).context("write to STDIN file")?;
+        tmp.seek(std::io::SeekFrom::Start(0))
+            .context("reset STDIN file")?;
+        command
+            .stdin(Stdio::from(tmp))
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        let process = command.spawn().context("start process")?;
+        debug!(pid = process.id(), "detaching, not waiting for output");
+        return Ok(Output {
+            exit_code: ExitStatus::Detached,
+            detached_process: testcase.config.detached_kill_signal.clone().map(|signal| {
+                DetachedProcess {
+                    pid: process.id(),
+                    signal,
+                }
+            }),
+            ..Default::default()
+        });
+    }
+
+    // combined output is read from a single pipe that both STDOUT and
+    // STDERR of the process write into
+    let combined = if testcase.config.output_stream == Some(OutputStreamControl::Combined) {
+        let (reader, writer) = pipe().context("create output pipe")?;
+        command
+            .stdout(Stdio::from(writer.try_clone()?))
+            .stderr(Stdio::from(writer));
+        Some(reader)
+    } else {
+        command.stdout(Stdio::piped()).stderr(Stdio::piped());
+        None
+    };
+    let mut process = command
+        .stdin(Stdio::piped())
+        .spawn()
+        .context("start process")?;
+
+    // release the pipe handles of the process, so that reading ends when
+    // the process (and all its children) end
+    drop(command);
+
+    let span = debug_span!("process", pid = process.id());
+    let _s = span.enter();
+    trace!(testcase = %&testcase, "running testcase in process");
+
+    let stdin = process.stdin.take();
+    thread::spawn(move || stdin.map(|mut stdin| stdin.write_all(&input)));
+    let (stdout, stderr) = match combined {
+        Some(reader) => (read_all(reader), read_all(std::io::empty())),
+        None => (
+            read_all(process.stdout.take().context("take STDOUT")?),
+            read_all(process.stderr.take().context("take STDERR")?),
+        ),
+    };
+
+    // wait for the process to finish, or abort it after the timeout
+    match testcase.config.timeout {
+        Some(timeout) => debug!(
+            "waiting for output (max {})",
+            humantime::format_duration(timeout)
+        ),
+        None => debug!("waiting for output (no timeout)"),
+    }
+    let timeout_at = testcase
+        .config
+        .timeout
+        .map(|timeout| Instant::now() + timeout);
+    let mut resource_usage = None;
+    let mut signal = None;
+    let exit_code = loop {
+        if let Some((exit, usage)) =
+            wait_with_usage(process.id(), false).context("check process exit")?
+        {
+            resource_usage = Some(usage);
+            signal = exit.signal();
+            break exit.code().map_or(ExitStatus::Unknown, ExitStatus::Code);
+        }
+        if timeout_at.is_some_and(|at| at <= Instant::now()) {
+            process.kill().context("kill process")?;
+            wait_with_usage(process.id(), true).context("wait for killed process")?;
+            break ExitStatus::Timeout(testcase.config.timeout.unwrap_or_default());
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+
+    // output of aborted processes may never end, if they started children
+    let (stdout, stderr) = if matches!(exit_code, ExitStatus::Timeout(_)) {
+        (
+            stdout.recv_timeout(DRAIN_DURATION).unwrap_or_default(),
+            stderr.recv_timeout(DRAIN_DURATION).unwrap_or_default(),
+        )
+    } else {
+        (
+            stdout.recv().unwrap_or_default(),
+            stderr.recv().unwrap_or_default(),
+        )
+    };
+
+    Ok(Output {
+        stderr: testcase.render_output(&stderr[..])?.to_vec().into(),
+        stdout: testcase.render_output(&stdout[..])?.to_vec().into(),
+        exit_code,
+        detached_process: None,
+        captured_env: BTreeMap::new(),
+        interactive: None,
+        signal,
+        duration: None,
+        resource_usage,
+        previous_attempts: vec![],
+        always_run: false,
+    })
+}
+
+/// Create a pipe, that is not inherited by other processes
+fn pipe() -> std::io::Result<(File, File)> {
+    let mut fds = [0; 2];
+    // SAFETY: the file descriptors are owned by the returned files
+    unsafe {
+        if libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) == -1 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok((File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])))
+    }
+}
+
+/// Read everything from the reader in the background
+fn read_all<R: Read + Send + 'static>(mut reader: R) -> Receiver<Vec<u8>> {
+    let (sender, receiver) = channel();
+    thread::spawn(move || {
+        let mut buffer = vec![];
+        let _ = reader.read_to_end(&mut buffer);
+        let _ = sender.send(buffer);
+    });
+    receiver
+}
+
+#[cfg(test)]
+#[cfg(feature = "volatile_tests")]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use super::Runner;
+    use super::SandboxRunner;
+    use crate::config::OutputStreamControl;
+    use crate::config::TestCaseConfig;
+    use crate::executors::DEFAULT_SHELL;
+    use crate::executors::context::Context as ExecutionContext;
+    use crate::executors::sandbox::Sandbox;
+    use crate::output::ExitStatus;
+    use crate::output::Output;
+    use crate::testcase::TestCase;
+
+    fn run(testcase: &TestCase) -> Output {
+        let context = ExecutionContext::new_for_test();
+        let sandbox = Sandbox::new(&[&context.work_directory, &context.temp_directory])
+            .expect("create sandbox");
+        SandboxRunner::new(DEFAULT_SHELL.to_path_buf(), Arc::new(sandbox))
+            .run("name", testcase, &context)
+            .expect("execute without error")
+    }
+
+    #[test]
+    fn test_execute_captures_stdout_stderr_and_exit_code() {
+        let output = run(&TestCase::from_expression(
+            "echo OK1 && ( 1>&2 echo OK2 ) && exit 123",
+        ));
+        let expect: Output = ("OK1\n", "OK2\n", Some(123)).into();
+        assert_eq!(expect, output);
+    }
+
+    #[test]
+    fn test_execute_captures_stdout_and_stderr_combined() {
+        let output = run(&TestCase {
+            title: "Test".into(),
+            shell_expression: "echo OK1 && ( 1>&2 echo OK2 )".into(),
+            config: TestCaseConfig {
+                output_stream: Some(OutputStreamControl::Combined),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let expect: Output = ("OK1\nOK2\n", "").into();
+        assert_eq!(expect, output);
+    }
+
+    #[test]
+    fn test_execute_only_work_directory_is_writable() {
+        let output = run(&TestCase::from_expression(
+            "touch file && echo OK1; touch \"$HOME/.scrut-sandbox\" 2>/dev/null || echo OK2",
+        ));
+        let expect: Output = ("OK1\nOK2\n", "").into();
+        assert_eq!(expect, output);
+    }
+
+    #[test]
+    fn test_execute_has_only_loopback_network() {
+        let output = run(&TestCase::from_expression(
+            "tail -n +3 /proc/net/dev | cut -d: -f1 | tr -d ' '",
+        ));
+        let expect: Output = ("lo\n", "").into();
+        assert_eq!(expect, output);
+    }
+
+    #[test]
+    fn test_execute_measures_resource_usage() {
+        let output = run(&TestCase::from_expression(
+            "head -c 20000000 /dev/zero | tail -c 1 | wc -c",
+        ));
+        let usage = output.resource_usage.expect("resource usage is measured");
+        assert!(
+            usage.max_rss >= 1024 * 1024,
+            "peak memory is measured: {usage:?}"
+        );
+    }
+
+    #[test]
+    fn test_execute_respects_timeout() {
+        let output = run(&TestCase::from_expression_timed(
+            "echo ONE && sleep 1 && echo TWO",
+            Some(Duration::from_millis(100)),
+        ));
+        assert_eq!(
+            ExitStatus::Timeout(Duration::from_millis(100)),
+            output.exit_code,
+            "timeout reflected in exit code",
+        );
+    }
+}
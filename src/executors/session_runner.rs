@@ -0,0 +1,616 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::cell::RefCell;
+use std::fs;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Write;
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Child;
+use std::process::ChildStdin;
+use std::process::Command;
+use std::process::Stdio;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::mpsc::channel;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::Context;
+use anyhow::Result;
+use anyhow::bail;
+use nix::libc;
+use tracing::debug;
+use tracing::trace;
+use tracing::warn;
+
+use super::bash_runner::parse_env_file;
+use super::context::Context as ExecutionContext;
+use super::runner::Runner;
+use super::sandbox::Sandbox;
+use super::shell_dialect::ShellDialect;
+use super::stateful_executor::StatefulExecutorRunnerGenerator;
+use super::util::hermetic_environment;
+use super::util::is_valid_name;
+use super::util::quote;
+use crate::config::OutputStreamControl;
+use crate::output::DetachedProcess;
+use crate::output::ExitStatus;
+use crate::output::Output;
+use crate::testcase::TestCase;
+
+/// Prefix of the line the shell writes into the control channel, after it
+/// finished executing a test case
+const DONE_MARKER: &str = "__scrut_done";
+
+/// How long to wait for the shell to end on its own, before it is killed
+const SHUTDOWN_DURATION: Duration = Duration::from_secs(1);
+
+/// How often to check whether the shell ended during shutdown
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// A [`Runner`] that executes all test cases of a document in one long-lived
+/// shell process, instead of starting a new shell for each of them like the
+/// [`super::bash_runner::BashRunner`] does. Hence everything that cannot be
+/// written into a state file is shared in between test cases as well, like
+/// open file descriptors, `trap`s, `coproc`s and background jobs.
+///
+/// The shell reads commands from its STDIN, which serves as the control
+/// channel. Each test case is written into a script in the state directory,
+/// which the shell sources with STDOUT and STDERR redirected into files of
+/// its own, so that the output of each test case is still captured
+/// separately. Once done, the shell writes the exit code into its STDOUT.
+///
+/// A test case that runs into its timeout ends the shell with all processes
+/// it started. A test case that ends the shell (e.g. with `exit`) ends the
+/// session: the next test case starts in a new shell, without the state of
+/// the previous ones.
+///
+/// Messages the shell itself writes into its STDERR, like errors in the
+/// control channel, are written into a file in the state directory, which is
+/// reported when the shell ends while executing a test case.
+///
+/// Interactive test cases, resource limits and retries are not supported. A
+/// retry could not start from the state of the failed attempt, because that
+/// state lives in the shell process.
+pub struct SessionRunner {
+    shell: PathBuf,
+    state_directory: PathBuf,
+    sandbox: Option<Arc<Sandbox>>,
+    session: Rc<RefCell<Option<ShellSession>>>,
+}
+
+impl SessionRunner {
+    /// Returns a generator for the [`super::stateful_executor::StatefulExecutor`],
+    /// whose runners all share the same shell session. If a sandbox is
+    /// provided, then the shell runs within it.
+    pub fn stateful_generator(
+        shell: &Path,
+        sandbox: Option<Arc<Sandbox>>,
+    ) -> StatefulExecutorRunnerGenerator {
+        let shell = shell.to_owned();
+        let session = Rc::new(RefCell::new(None));
+        Box::new(move |state_directory: &Path| -> Box<dyn Runner> {
+            Box::new(Self {
+                shell: shell.to_owned(),
+                state_directory: state_directory.to_owned(),
+                sandbox: sandbox.clone(),
+                session: session.clone(),
+            })
+        })
+    }
+}
+
+impl Runner for SessionRunner {
+    fn run(&self, name: &str, testcase: &TestCase, context: &ExecutionContext) -> Result<Output> {
+        if testcase.config.is_interactive() {
+            bail!("interactive mode is not supported in a persistent shell session")
+        }
+        if testcase.config.limits.is_some() {
+            bail!("limits are not supported in a persistent shell session")
+        }
+        if testcase.config.get_retries() > 0 {
+            bail!("retries are not supported in a persistent shell session")
+        }
+        if testcase.config.max_rss.is_some() {
+            bail!("max_rss is not supported in a persistent shell session")
+        }
+
+        // a new session is started for every run of the executor, and after
+        // the shell of the previous session ended
+        let mut session = self.session.borrow_mut();
+        if session
+            .as_ref()
+            .is_none_or(|session| session.state_directory != self.state_directory)
+        {
+            *session = Some(ShellSession::start(
+                &self.shell,
+                self.sandbox.as_deref(),
+                &self.state_directory,
+                testcase,
+                context,
+            )?);
+        }
+        let shell_session = session.as_mut().expect("session is started");
+        let (exit_code, pid) = shell_session.execute(name, testcase)?;
+        let is_ended = !matches!(exit_code, ExitStatus::Code(_) | ExitStatus::Detached)
+            || shell_session.has_ended();
+        let paths = shell_session.paths(name);
+        if is_ended {
+            *session = None;
+        }
+
+        if exit_code == ExitStatus::Detached {
+            // process IDs within the sandbox are not valid outside of it, but
+            // all processes in the sandbox end with it anyway
+            let pid = pid.filter(|_| self.sandbox.is_none());
+            return Ok(Output {
+                exit_code,
+                detached_process: pid
+                    .zip(testcase.config.detached_kill_signal.clone())
+                    .map(|(pid, signal)| DetachedProcess { pid, signal }),
+                ..Default::default()
+            });
+        }
+
+        let read = |path: &Path| fs::read(path).unwrap_or_default();
+        let captured_env = if paths.env.exists() {
+            parse_env_file(&paths.env)?
+        } else {
+            Default::default()
+        };
+        Ok(Output {
+            stderr: testcase
+                .render_output(&read(&paths.stderr))?
+                .to_vec()
+                .into(),
+            stdout: testcase
+                .render_output(&read(&paths.stdout))?
+                .to_vec()
+                .into(),
+            exit_code,
+            captured_env,
+            ..Default::default()
+        })
+    }
+}
+
+/// The files in the state directory that belong to a single test case
+struct SessionPaths {
+    script: PathBuf,
+    stdout: PathBuf,
+    stderr: PathBuf,
+    env: PathBuf,
+}
+
+/// A shell process that executes test cases one after another
+struct ShellSession {
+    process: Child,
+    control: Option<ChildStdin>,
+    markers: Receiver<String>,
+    state_directory: PathBuf,
+    stderr: PathBuf,
+}
+
+impl ShellSession {
+    fn start(
+        shell: &Path,
+        sandbox: Option<&Sandbox>,
+        state_directory: &Path,
+        testcase: &TestCase,
+        context: &ExecutionContext,
+    ) -> Result<Self> {
+        // the STDERR of the shell is kept to explain why it ended
+        fs::create_dir_all(state_directory).context("create state directory")?;
+        let stderr = state_directory.join("session.err");
+        let stderr_file = fs::File::create(&stderr).context("create STDERR of shell session")?;
+
+        // hermetic executions start from an empty environment
+        let mut command = Command::new(shell);
+        if let Some(passthrough) = hermetic_environment(&context.config) {
+            command.env_clear().envs(&passthrough);
+        }
+        command
+            .envs(&testcase.config.environment)
+            .env("SHELL", shell)
+            .current_dir(&context.work_directory)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(stderr_file);
+
+        // a process group of its own allows to end the shell together with
+        // all processes it started
+        if let Some(sandbox) = sandbox {
+            sandbox
+                .join(&mut command, &context.work_directory)
+                .context("join sandbox")?;
+        } else {
+            command.process_group(0);
+        }
+        let mut process = command.spawn().context("start shell session")?;
+        debug!(pid = process.id(), shell = %shell.display(), "started shell session");
+
+        let stdout = process.stdout.take().context("take STDOUT")?;
+        let (sender, markers) = channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut session = Self {
+            control: process.stdin.take(),
+            process,
+            markers,
+            state_directory: state_directory.to_owned(),
+            stderr,
+        };
+        session.send(ShellDialect::from_shell(shell).session_preamble())?;
+        Ok(session)
+    }
+
+    /// Runs the test case in the shell and returns its exit status, as well
+    /// as the process ID of detached test cases
+    fn execute(&mut self, name: &str, testcase: &TestCase) -> Result<(ExitStatus, Option<u32>)> {
+        let paths = self.paths(name);
+        for path in [&paths.stdout, &paths.stderr, &paths.env] {
+            if path.exists() {
+                fs::remove_file(path).context("remove output of previous attempt")?;
+            }
+        }
+
+        // environment variables may differ per test case
+        let mut script = String::new();
+        for (key, value) in &testcase.config.environment {
+            if is_valid_name(key) {
+                script.push_str(&format!("export {key}={}\n", quote(value)));
+            }
+        }
+        script.push_str(&testcase.shell_expression);
+        script.push('\n');
+        fs::write(&paths.script, script).context("write test case script")?;
+
+        let script = quote(&paths.script.to_string_lossy());
+        let command = if testcase.config.detached.unwrap_or(false) {
+            format!(
+                "( . {script} ) </dev/null >/dev/null 2>&1 &\necho \"{DONE_MARKER} {name} 0 $!\"\n"
+            )
+        } else {
+            let stdout = quote(&paths.stdout.to_string_lossy());
+            let stderr = if testcase.config.output_stream == Some(OutputStreamControl::Combined) {
+                "2>&1".to_string()
+            } else {
+                format!("2>{}", quote(&paths.stderr.to_string_lossy()))
+            };
+            format!(
+                ". {script} </dev/null >{stdout} {stderr}\n__SCRUT_EXIT_CODE=$?\nenv -0 >{}\necho \"{DONE_MARKER} {name} $__SCRUT_EXIT_CODE\"\n",
+                quote(&paths.env.to_string_lossy()),
+            )
+        };
+        trace!(testcase = %&testcase, "running testcase in shell session");
+        if let Err(err) = self.send(&command) {
+            // the shell ended after the previous test case, e.g. in a trap
+            debug!("failed to send test case to shell session: {err}");
+            return Ok((self.wait_for_exit()?, None));
+        }
+
+        match testcase.config.timeout {
+            Some(timeout) => debug!(
+                "waiting for output (max {})",
+                humantime::format_duration(timeout)
+            ),
+            None => debug!("waiting for output (no timeout)"),
+        }
+        let timeout_at = testcase
+            .config
+            .timeout
+            .map(|timeout| Instant::now() + timeout);
+        let prefix = format!("{DONE_MARKER} {name} ");
+        loop {
+            let line = match timeout_at {
+                Some(at) => self
+                    .markers
+                    .recv_timeout(at.saturating_duration_since(Instant::now())),
+                None => self
+                    .markers
+                    .recv()
+                    .map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            match line {
+                Ok(line) => {
+                    let Some(status) = line.strip_prefix(&prefix) else {
+                        trace!("ignoring unexpected output of shell session: {line}");
+                        continue;
+                    };
+                    let mut parts = status.split(' ');
+                    let code = parts.next().and_then(|code| code.parse().ok());
+                    let pid = parts.next().and_then(|pid| pid.parse().ok());
+                    return Ok(match (code, pid) {
+                        (Some(_), Some(pid)) => (ExitStatus::Detached, Some(pid)),
+                        (Some(code), None) => (ExitStatus::Code(code), None),
+                        (None, _) => (ExitStatus::Unknown, None),
+                    });
+                }
+
+                // the test case ended the shell
+                Err(RecvTimeoutError::Disconnected) => return Ok((self.wait_for_exit()?, None)),
+
+                // the test case is still running
+                Err(RecvTimeoutError::Timeout) => {
+                    self.kill();
+                    return Ok((
+                        ExitStatus::Timeout(testcase.config.timeout.unwrap_or_default()),
+                        None,
+                    ));
+                }
+            }
+        }
+    }
+
+    fn paths(&self, name: &str) -> SessionPaths {
+        let path = |extension: &str| self.state_directory.join(format!("{name}.{extension}"));
+        SessionPaths {
+            script: path("sh"),
+            stdout: path("out"),
+            stderr: path("err"),
+            env: path("env"),
+        }
+    }
+
+    /// Whether the shell process ended
+    fn has_ended(&mut self) -> bool {
+        !matches!(self.process.try_wait(), Ok(None))
+    }
+
+    fn send(&mut self, command: &str) -> Result<()> {
+        let control = self.control.as_mut().context("shell session is closed")?;
+        control
+            .write_all(command.as_bytes())
+            .and_then(|_| control.flush())
+            .context("write into shell session")
+    }
+
+    /// Waits for the shell, that closed the control channel, to end and
+    /// reports what it wrote into its STDERR
+    fn wait_for_exit(&mut self) -> Result<ExitStatus> {
+        let status = self.process.wait();
+        let stderr = fs::read(&self.stderr).unwrap_or_default();
+        let stderr = String::from_utf8_lossy(&stderr);
+        let status =
+            status.with_context(|| format!("wait for shell session, which wrote: {stderr}"))?;
+        if !stderr.trim().is_empty() {
+            warn!(
+                pid = self.process.id(),
+                "shell session ended with {status}: {}",
+                stderr.trim_end()
+            );
+        }
+        Ok(status.code().map_or(ExitStatus::Unknown, ExitStatus::Code))
+    }
+
+    /// Ends the shell and all processes it started
+    fn kill(&mut self) {
+        // SAFETY: the process leads a process group of its own
+        unsafe { libc::killpg(self.process.id() as libc::pid_t, libc::SIGKILL) };
+        let _ = self.process.wait();
+    }
+}
+
+impl Drop for ShellSession {
+    fn drop(&mut self) {
+        // closing the control channel ends the shell, but keeps background
+        // processes running, as they would without a session
+        self.control.take();
+        let end = Instant::now() + SHUTDOWN_DURATION;
+        while Instant::now() < end {
+            if self.has_ended() {
+                return;
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+        debug!(pid = self.process.id(), "killing shell session");
+        self.kill();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::time::Duration;
+
+    use super::SessionRunner;
+    use super::ShellSession;
+    use crate::config::TestCaseConfig;
+    use crate::executors::DEFAULT_SHELL;
+    use crate::executors::context::Context as ExecutionContext;
+    use crate::executors::error::ExecutionError;
+    use crate::executors::error::ExecutionTimeout;
+    use crate::executors::executor::tests::combined_output_test_suite;
+    use crate::executors::executor::tests::run_executor_tests;
+    use crate::executors::executor::tests::standard_output_test_suite;
+    use crate::executors::stateful_executor::StatefulExecutor;
+    use crate::output::ExitStatus;
+    use crate::output::Output;
+    use crate::testcase::TestCase;
+
+    fn executor() -> StatefulExecutor {
+        StatefulExecutor::new(SessionRunner::stateful_generator(*DEFAULT_SHELL, None))
+    }
+
+    #[test]
+    fn test_standard_test_suite() {
+        standard_output_test_suite(executor());
+    }
+
+    #[test]
+    fn test_combined_output_test_suite() {
+        combined_output_test_suite(executor());
+    }
+
+    #[test]
+    fn test_executor_keeps_state_that_cannot_be_serialized() {
+        run_executor_tests(
+            executor(),
+            vec![
+                (
+                    "shell process is shared",
+                    vec![
+                        TestCase::from_expression("echo $$ > pid"),
+                        TestCase::from_expression("[ \"$(cat pid)\" = $$ ] && echo same"),
+                    ],
+                    None,
+                    Ok(vec![("", "").into(), ("same\n", "").into()]),
+                ),
+                (
+                    "traps, file descriptors and background jobs are shared",
+                    vec![
+                        TestCase::from_expression(
+                            "trap 'echo trapped' USR1\nexec 3>fd.txt\nsleep 10 &",
+                        ),
+                        TestCase::from_expression(
+                            "kill -USR1 $$\necho written >&3\ncat fd.txt\njobs | wc -l | tr -d ' '\nkill %1",
+                        ),
+                    ],
+                    None,
+                    Ok(vec![("", "").into(), ("trapped\nwritten\n1\n", "").into()]),
+                ),
+                (
+                    "exit ends the session and the next test case starts a new one",
+                    vec![
+                        TestCase::from_expression("FOO=bar\nexit 3"),
+                        TestCase::from_expression("echo \"foo=$FOO\""),
+                    ],
+                    None,
+                    Ok(vec![("", "", Some(3)).into(), ("foo=\n", "").into()]),
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_supports_timeout_per_execution() {
+        let timeout = Duration::from_millis(100);
+        run_executor_tests(
+            executor(),
+            vec![(
+                "timeout ends the execution",
+                vec![
+                    TestCase::from_expression("echo OK"),
+                    TestCase::from_expression_timed(
+                        "echo ONE && sleep 1 && echo TWO",
+                        Some(timeout),
+                    ),
+                ],
+                None,
+                Err(ExecutionError::Timeout(
+                    ExecutionTimeout::Index(1),
+                    vec![
+                        ("OK\n", "").into(),
+                        Output {
+                            exit_code: ExitStatus::Timeout(timeout),
+                            stdout: "ONE\n".into(),
+                            ..Default::default()
+                        },
+                    ],
+                )),
+            )],
+        );
+    }
+
+    #[test]
+    fn test_unsupported_configuration_fails() {
+        run_executor_tests(
+            executor(),
+            vec![
+                (
+                    "limits are not supported",
+                    vec![TestCase {
+                        shell_expression: "echo OK".into(),
+                        config: TestCaseConfig {
+                            limits: Some(Default::default()),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    }],
+                    None,
+                    Err(ExecutionError::failed(
+                        0,
+                        anyhow::anyhow!("limits are not supported in a persistent shell session"),
+                    )),
+                ),
+                (
+                    "retries are not supported",
+                    vec![TestCase {
+                        shell_expression: "echo OK".into(),
+                        config: TestCaseConfig {
+                            retries: Some(1),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    }],
+                    None,
+                    Err(ExecutionError::failed(
+                        0,
+                        anyhow::anyhow!("retries are not supported in a persistent shell session"),
+                    )),
+                ),
+                (
+                    "max_rss is not supported",
+                    vec![TestCase {
+                        shell_expression: "echo OK".into(),
+                        config: TestCaseConfig {
+                            max_rss: Some(1024),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    }],
+                    None,
+                    Err(ExecutionError::failed(
+                        0,
+                        anyhow::anyhow!("max_rss is not supported in a persistent shell session"),
+                    )),
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_stderr_of_shell_is_kept() {
+        let context = ExecutionContext::new_for_test();
+        let state_directory = tempfile::tempdir().expect("create state directory");
+        let mut session = ShellSession::start(
+            &DEFAULT_SHELL,
+            None,
+            state_directory.path(),
+            &TestCase::default(),
+            &context,
+        )
+        .expect("start shell session");
+
+        session
+            .send("echo 'shell broke' >&2\nexit 4\n")
+            .expect("send into shell session");
+        assert_eq!(
+            ExitStatus::Code(4),
+            session.wait_for_exit().expect("shell session ends")
+        );
+        assert_eq!(
+            "shell broke\n",
+            fs::read_to_string(state_directory.path().join("session.err"))
+                .expect("read STDERR of shell session")
+        );
+    }
+}
@@ -0,0 +1,271 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::fmt::Display;
+use std::path::Path;
+
+#[doc = include_str!("./shell_dialect.bash_excluded_variables.md")]
+pub const BASH_EXCLUDED_VARIABLES: &[&str] = &[
+    // variables from Scrut internals
+    "__SCRUT_DECLARE_VARS_CMD",
+    "__SCRUT_TEMP_STATE_PATH",
+    // variables set by scrut in every execution
+    "SCRUT_TEST",
+    // variables from `man bash`
+    "BASHOPTS",
+    "BASH_ALIASES",
+    "BASH_ARGC",
+    "BASH_ARGV",
+    "BASH_ARGV0",
+    "BASH_CMDS",
+    "BASH_COMMAND",
+    "BASH_EXECUTION_STRING",
+    "BASH_LINENO",
+    "BASH_REMATCH",
+    "BASH_SOURCE",
+    "BASH_SUBSHELL",
+    "BASH_VERSINFO",
+    "COPROC",
+    "DIRSTACK",
+    "EUID",
+    "FUNCNAME",
+    "LINENO",
+    "PPID",
+    "SHELLOPTS",
+    "UID",
+];
+
+/// Variables that are not persisted in between executions in `zsh`. Variables
+/// that zsh marks as read-only or special (except for a few user-facing ones,
+/// like `PATH`) are excluded in addition.
+pub const ZSH_EXCLUDED_VARIABLES: &[&str] = &[
+    // variables from Scrut internals
+    "__SCRUT_TEMP_STATE_PATH",
+    // variables set by scrut in every execution
+    "SCRUT_TEST",
+];
+
+/// Variables that are not persisted in between executions in POSIX shells.
+/// Variables that are marked read-only are excluded in addition.
+pub const POSIX_EXCLUDED_VARIABLES: &[&str] = &[
+    // variables from Scrut internals
+    "__SCRUT_EXIT_CODE",
+    "__SCRUT_READONLY_VARS",
+    "__SCRUT_TEMP_STATE_PATH",
+    // variables set by scrut in every execution
+    "SCRUT_TEST",
+    // variables maintained by the shell
+    "LINENO",
+    "PPID",
+    // variables maintained by bash, which is `sh` on some systems (e.g. macOS)
+    "BASHOPTS",
+    "BASH_ALIASES",
+    "BASH_ARGC",
+    "BASH_ARGV",
+    "BASH_ARGV0",
+    "BASH_CMDS",
+    "BASH_COMMAND",
+    "BASH_EXECUTION_STRING",
+    "BASH_LINENO",
+    "BASH_REMATCH",
+    "BASH_SOURCE",
+    "BASH_SUBSHELL",
+    "BASH_VERSINFO",
+    "COPROC",
+    "DIRSTACK",
+    "EUID",
+    "FUNCNAME",
+    "GROUPS",
+    "SHELLOPTS",
+    "UID",
+];
+
+/// Names of shell executables that are considered POSIX shells
+const POSIX_SHELLS: &[&str] = &[
+    "ash", "busybox", "dash", "ksh", "mksh", "posh", "sh", "yash",
+];
+
+const BASH_TEMPLATE: &str = include_str!("shell_dialect.bash.template");
+const ZSH_TEMPLATE: &str = include_str!("shell_dialect.zsh.template");
+const POSIX_TEMPLATE: &str = include_str!("shell_dialect.posix.template");
+
+/// The dialect of a shell determines how the state of an execution (variables,
+/// settings, aliases, functions and the current directory) is dumped after
+/// and restored before each execution of a
+/// [`super::bash_runner::BashRunner`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShellDialect {
+    /// `bash`: persists variables, `set` and `shopt` settings, aliases,
+    /// functions and the directory stack
+    Bash,
+
+    /// `zsh`: persists variables, options, aliases, functions and the
+    /// directory stack
+    Zsh,
+
+    /// POSIX shells, like `sh`, `dash` or `ash`: persists variables, `set`
+    /// settings, aliases and the current directory. Functions cannot be
+    /// persisted, because POSIX shells provide no means to print them.
+    Posix,
+}
+
+impl ShellDialect {
+    /// Returns the dialect of the given shell, derived from the name of its
+    /// executable. Shells with unknown names, like custom wrapper scripts,
+    /// are considered `bash` compatible.
+    pub fn from_shell(shell: &Path) -> Self {
+        let name = shell
+            .file_stem()
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if name.contains("zsh") {
+            Self::Zsh
+        } else if POSIX_SHELLS.contains(&name.as_str()) {
+            Self::Posix
+        } else {
+            Self::Bash
+        }
+    }
+
+    /// Returns the variables that are not persisted in between executions
+    pub fn excluded_variables(&self) -> &'static [&'static str] {
+        match self {
+            Self::Bash => BASH_EXCLUDED_VARIABLES,
+            Self::Zsh => ZSH_EXCLUDED_VARIABLES,
+            Self::Posix => POSIX_EXCLUDED_VARIABLES,
+        }
+    }
+
+    /// Returns the commands that prepare a long-lived shell session, in which
+    /// test cases are sourced one after another
+    pub fn session_preamble(&self) -> &'static str {
+        match self {
+            // aliases are not expanded in non-interactive bash per default
+            Self::Bash => "shopt -s expand_aliases\n",
+            Self::Zsh | Self::Posix => "",
+        }
+    }
+
+    /// Returns a script that restores the state from the state directory,
+    /// then executes the shell expression and, if `persist_state` is set,
+    /// dumps the resulting state into the state directory
+    pub fn render_script(
+        &self,
+        state_directory: &Path,
+        shell_expression: &str,
+        persist_state: bool,
+    ) -> String {
+        let template = match self {
+            Self::Bash => BASH_TEMPLATE,
+            Self::Zsh => ZSH_TEMPLATE,
+            Self::Posix => POSIX_TEMPLATE,
+        };
+        template
+            .replace("{state_directory}", &state_directory.to_string_lossy())
+            .replace("{excluded_variables}", &self.excluded_variables().join("|"))
+            .replace("{persist_state}", if persist_state { "1" } else { "0" })
+            .replace("{shell_expression}", shell_expression)
+    }
+}
+
+impl Display for ShellDialect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Bash => "bash",
+                Self::Zsh => "zsh",
+                Self::Posix => "posix",
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::path::Path;
+    use std::process::Command;
+    use std::process::Stdio;
+
+    use super::ShellDialect;
+
+    #[test]
+    fn test_from_shell() {
+        let tests = vec![
+            ("/bin/bash", ShellDialect::Bash),
+            ("bash", ShellDialect::Bash),
+            ("C:\\Program Files\\Git\\bin\\bash.exe", ShellDialect::Bash),
+            ("/bin/my-bash", ShellDialect::Bash),
+            ("/usr/local/bin/zsh", ShellDialect::Zsh),
+            ("/bin/sh", ShellDialect::Posix),
+            ("/usr/bin/dash", ShellDialect::Posix),
+            ("/bin/busybox", ShellDialect::Posix),
+            ("/path/to/shell.sh", ShellDialect::Bash),
+        ];
+        for (shell, expected) in tests {
+            assert_eq!(
+                expected,
+                ShellDialect::from_shell(Path::new(shell)),
+                "dialect of {shell}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_render_script() {
+        for dialect in [ShellDialect::Bash, ShellDialect::Zsh, ShellDialect::Posix] {
+            let script =
+                dialect.render_script(Path::new("/tmp/state"), "echo {persist_state}", true);
+            assert!(
+                script.contains("__SCRUT_TEMP_STATE_PATH=\"/tmp/state\""),
+                "{dialect}: state directory is set"
+            );
+            assert!(
+                script.ends_with("echo {persist_state}\n"),
+                "{dialect}: expression is not rendered"
+            );
+            assert!(
+                !script.contains("{excluded_variables}"),
+                "{dialect}: excluded variables are rendered"
+            );
+        }
+    }
+
+    /// Some shells print aliases with an `alias` prefix, which is simulated
+    /// by wrapping the builtin in a function of the same name
+    #[cfg(unix)]
+    #[test]
+    fn test_posix_restores_prefixed_aliases() {
+        let state_directory = tempfile::tempdir().expect("create state directory");
+        let run = |expression: &str| {
+            let script =
+                ShellDialect::Posix.render_script(state_directory.path(), expression, true);
+            let mut child = Command::new("sh")
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()
+                .expect("start sh");
+            child
+                .stdin
+                .take()
+                .expect("stdin of sh")
+                .write_all(script.as_bytes())
+                .expect("write script");
+            child.wait_with_output().expect("run sh")
+        };
+
+        let output =
+            run("alias greet='echo hello'\nalias() { command alias \"$@\" | sed 's/^/alias /'; }");
+        assert!(output.status.success(), "define alias: {output:?}");
+        let output = run("greet");
+        assert_eq!("", String::from_utf8_lossy(&output.stderr));
+        assert_eq!("hello\n", String::from_utf8_lossy(&output.stdout));
+    }
+}
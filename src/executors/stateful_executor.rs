@@ -5,6 +5,8 @@
  * LICENSE file in the root directory of this source tree.
  */
 
+use std::collections::BTreeMap;
+use std::fs;
 use std::ops::Add;
 use std::path::Path;
 use std::thread::sleep;
@@ -16,13 +18,18 @@ use tempfile::TempDir;
 use tracing::debug;
 use tracing::trace;
 use tracing::trace_span;
+use tracing::warn;
 
 use super::context::Context as ExecutionContext;
 use super::error::ExecutionError;
 use super::executor::DEFAULT_TOTAL_TIMEOUT;
+use super::executor::ExecutionObserver;
 use super::executor::Executor;
+use super::executor::NoopObserver;
 use super::executor::Result;
 use super::runner::Runner;
+use super::util::is_valid_name;
+use super::util::quote;
 use crate::executors::error::ExecutionTimeout;
 use crate::output::ExitStatus;
 use crate::output::Output;
@@ -62,12 +69,22 @@ impl Executor for StatefulExecutor {
         &self,
         testcases: &[&TestCase],
         context: &ExecutionContext,
+    ) -> Result<Vec<Output>> {
+        self.execute_all_observed(testcases, context, &NoopObserver)
+    }
+
+    /// Notifies the observer about each execution as it happens.
+    fn execute_all_observed(
+        &self,
+        testcases: &[&TestCase],
+        context: &ExecutionContext,
+        observer: &dyn ExecutionObserver,
     ) -> Result<Vec<Output>> {
         let state_directory = TempDir::with_prefix_in(".state.", &context.temp_directory)
             .context("generate temporary output directory")
             .map_err(|err| ExecutionError::aborted(err, None))?;
 
-        let mut session = ExecutionSession::new(&self.0, state_directory.path(), context);
+        let mut session = ExecutionSession::new(&self.0, state_directory.path(), context, observer);
         session.run_all(testcases)
     }
 }
@@ -78,9 +95,11 @@ struct ExecutionSession<'a> {
     runner_gen: &'a StatefulExecutorRunnerGenerator,
     state_dir: &'a Path,
     context: &'a ExecutionContext,
+    observer: &'a dyn ExecutionObserver,
     timeout_at: Option<Instant>,
     timeout_duration: Duration,
     outputs: Vec<Output>,
+    captures: BTreeMap<String, String>,
     done: bool,
 }
 
@@ -89,6 +108,7 @@ impl<'a> ExecutionSession<'a> {
         runner_gen: &'a StatefulExecutorRunnerGenerator,
         state_dir: &'a Path,
         context: &'a ExecutionContext,
+        observer: &'a dyn ExecutionObserver,
     ) -> Self {
         let timeout_duration = context
             .config
@@ -104,25 +124,107 @@ impl<'a> ExecutionSession<'a> {
             runner_gen,
             state_dir,
             context,
+            observer,
             timeout_at,
             timeout_duration,
             outputs: vec![],
+            captures: BTreeMap::new(),
             done: false,
         }
     }
 
-    /// Main loop: prepare, run, check done.
+    /// Main loop: prepare, run, check done. If the execution ends early, the
+    /// remaining testcases that are configured to always run are still run.
     fn run_all(&mut self, testcases: &[&TestCase]) -> Result<Vec<Output>> {
         for (index, testcase) in testcases.iter().enumerate() {
-            let (testcase, is_global_timeout) = self.prepare(testcase, index);
-            self.run_output(&testcase, index, is_global_timeout, testcases.len())?;
+            let (prepared, is_global_timeout) = self.prepare(testcase, index);
+            self.observer.testcase_started(index, testcase);
+            let result = self.run_output(&prepared, index, is_global_timeout, testcases.len());
+
+            // outputs are handed over in the error, if execution ends early
+            let output = match result {
+                Ok(_) => self.outputs.get(index),
+                Err(ExecutionError::Timeout(_, ref outputs))
+                | Err(ExecutionError::Failed(_, ref outputs)) => outputs.get(index),
+                Err(_) => None,
+            };
+            if let Some(output) = output {
+                self.observer.testcase_finished(index, testcase, output);
+                self.captures.extend(captures(&prepared, index, output));
+            }
+            if let Err(mut err) = result {
+                let always_run = self.run_always(testcases, index);
+                match err {
+                    ExecutionError::Timeout(_, ref mut outputs)
+                    | ExecutionError::Failed(_, ref mut outputs)
+                    | ExecutionError::Skipped(_, ref mut outputs) => {
+                        merge_outputs(outputs, always_run)
+                    }
+
+                    // outputs cannot be reported with other errors, but the
+                    // testcases still had to run
+                    _ => {}
+                }
+                return Err(err);
+            }
             if self.done {
+                let always_run = self.run_always(testcases, index);
+                merge_outputs(&mut self.outputs, always_run);
                 break;
             }
         }
         Ok(std::mem::take(&mut self.outputs))
     }
 
+    /// Runs the testcases after the given index that are configured to always
+    /// run, after the execution ended early, and returns their outputs with
+    /// their index. Each of them is constrained by its own timeout and by a
+    /// new total timeout, because the one of the document may have run out.
+    fn run_always(&mut self, testcases: &[&TestCase], index: usize) -> Vec<(usize, Output)> {
+        let mut outputs = vec![];
+        for (index, testcase) in testcases.iter().enumerate().skip(index + 1) {
+            if !testcase
+                .config
+                .with_defaults_from(&self.context.config.defaults)
+                .get_always_run()
+            {
+                continue;
+            }
+            self.timeout_at = if self.timeout_duration.is_zero() {
+                None
+            } else {
+                Some(Instant::now().add(self.timeout_duration))
+            };
+            let (prepared, is_global_timeout) = self.prepare(testcase, index);
+            self.observer.testcase_started(index, testcase);
+            let name = format!("exec{}", index + 1);
+            let mut output =
+                match self.run_attempts(&name, &prepared, self.context, is_global_timeout) {
+                    Ok((mut output, is_global_timeout)) => {
+                        if is_global_timeout && matches!(output.exit_code, ExitStatus::Timeout(_)) {
+                            output.exit_code = ExitStatus::Timeout(self.timeout_duration);
+                        }
+                        output
+                    }
+                    Err(err) => {
+                        warn!(
+                            "failed to run testcase #{} that must always run: {err}",
+                            index + 1
+                        );
+                        Output {
+                            exit_code: ExitStatus::Unknown,
+                            ..Default::default()
+                        }
+                    }
+                };
+            output.always_run = true;
+            self.observer.testcase_finished(index, testcase, &output);
+            self.captures.extend(captures(&prepared, index, &output));
+            outputs.push((index, output));
+        }
+        outputs
+    }
+
     /// Shared pre-execution: apply defaults, compute timeout, handle wait, set env.
     /// Returns the prepared testcase and whether the effective timeout is global.
     fn prepare(&self, testcase: &TestCase, index: usize) -> (TestCase, bool) {
@@ -177,6 +279,28 @@ impl<'a> ExecutionSession<'a> {
             ),
         );
 
+        // captured values are set in the environment, unless the testcase sets
+        // them explicitly
+        let mut exports = String::new();
+        for (name, value) in &self.captures {
+            if is_valid_name(name) {
+                let value = testcase
+                    .config
+                    .environment
+                    .entry(name.to_owned())
+                    .or_insert_with(|| value.to_owned());
+                exports.push_str(&format!("export {name}={}\n", quote(value)));
+            }
+        }
+
+        // the state that previous executions persisted would override them,
+        // so they are restored after the state, too
+        if !exports.is_empty() {
+            if let Err(err) = fs::write(self.state_dir.join("captures"), exports) {
+                warn!("failed to write captured values: {err}");
+            }
+        }
+
         (testcase, is_global_timeout)
     }
 
@@ -192,10 +316,9 @@ impl<'a> ExecutionSession<'a> {
         let context = self.context.to_owned();
 
         trace!("effective testcase configuration: {}", &testcase.config);
-        let mut output = (self.runner_gen)(self.state_dir)
-            .run(&name, testcase, context)
+        let (mut output, is_global_timeout) = self
+            .run_attempts(&name, testcase, context, is_global_timeout)
             .map_err(|err| ExecutionError::failed(index, err))?;
-        trace!("{output:?}");
 
         // handle exit code
         let skip_document_code = testcase.config.get_skip_document_code();
@@ -204,7 +327,7 @@ impl<'a> ExecutionSession<'a> {
             ExitStatus::Code(code) => {
                 // .. ends collecting if user signals to skip
                 if code == skip_document_code {
-                    return Err(ExecutionError::Skipped(index));
+                    return Err(ExecutionError::Skipped(index, vec![]));
                 }
 
                 // .. otherwise keep collecting output
@@ -245,7 +368,7 @@ impl<'a> ExecutionSession<'a> {
 
             // user triggered skip ends all execution
             ExitStatus::Skipped => {
-                return Err(ExecutionError::Skipped(index));
+                return Err(ExecutionError::Skipped(index, vec![]));
             }
 
             // user says the process is running detached and we should ignore it
@@ -269,6 +392,134 @@ impl<'a> ExecutionSession<'a> {
 
         Ok(())
     }
+
+    /// Runs the testcase and, while it fails and retries are left, runs it
+    /// again from the same state the first attempt started from. Returns the
+    /// output of the last attempt and whether its timeout is global.
+    fn run_attempts(
+        &self,
+        name: &str,
+        testcase: &TestCase,
+        context: &ExecutionContext,
+        mut is_global_timeout: bool,
+    ) -> anyhow::Result<(Output, bool)> {
+        let retries = testcase.config.get_retries();
+        let snapshot = if retries > 0 {
+            Some(StateSnapshot::take(
+                self.state_dir,
+                &context.temp_directory,
+            )?)
+        } else {
+            None
+        };
+        let skip_document_code = testcase.config.get_skip_document_code();
+
+        let mut testcase = testcase.clone();
+        let mut previous_attempts = vec![];
+        loop {
+            let started = Instant::now();
+            let mut output = (self.runner_gen)(self.state_dir).run(name, &testcase, context)?;
+            output.duration = Some(started.elapsed());
+            trace!("{output:?}");
+
+            // only completed executions that fail validation are retried
+            let retry = previous_attempts.len() < retries
+                && matches!(output.exit_code, ExitStatus::Code(code) if code != skip_document_code)
+                && testcase.validate(&output).is_err();
+            let Some(snapshot) = snapshot.as_ref().filter(|_| retry) else {
+                output.previous_attempts = previous_attempts;
+                return Ok((output, is_global_timeout));
+            };
+            debug!(
+                attempt = previous_attempts.len() + 1,
+                "retrying failed testcase"
+            );
+            previous_attempts.push(output);
+            snapshot.restore(self.state_dir)?;
+            if let Some(delay) = testcase.config.retry_delay {
+                sleep(delay);
+            }
+
+            // retries count against the timeout over all executions
+            if let Some(timeout_at) = self.timeout_at {
+                let timeout_left = timeout_at.saturating_duration_since(Instant::now());
+                if testcase
+                    .config
+                    .timeout
+                    .is_none_or(|timeout| timeout_left < timeout)
+                {
+                    testcase.config.timeout = Some(timeout_left);
+                    is_global_timeout = true;
+                }
+            }
+        }
+    }
+}
+
+/// Returns the values the expectations of the testcase captured from its
+/// output, so that they are set in the environment of all following testcases
+fn captures(testcase: &TestCase, index: usize, output: &Output) -> BTreeMap<String, String> {
+    if output.exit_code == ExitStatus::Detached {
+        return BTreeMap::new();
+    }
+    testcase.captures(output).unwrap_or_else(|err| {
+        warn!(
+            "failed to capture values from output of testcase #{}: {err:?}",
+            index + 1
+        );
+        BTreeMap::new()
+    })
+}
+
+/// Sets the outputs of testcases that always run at their index. Testcases in
+/// between, that were not executed, are marked as skipped. Outputs of testcases
+/// that always run are marked as such, so they are never mistaken as skipped.
+fn merge_outputs(outputs: &mut Vec<Output>, always_run: Vec<(usize, Output)>) {
+    for (index, output) in always_run {
+        if index < outputs.len() {
+            outputs[index] = output;
+        } else {
+            outputs.resize_with(index, || Output {
+                exit_code: ExitStatus::Skipped,
+                ..Default::default()
+            });
+            outputs.push(output);
+        }
+    }
+}
+
+/// A copy of the files in the state directory, so that retries of a testcase
+/// start from the same state as the first attempt
+struct StateSnapshot(TempDir);
+
+impl StateSnapshot {
+    fn take(state_directory: &Path, temp_directory: &Path) -> anyhow::Result<Self> {
+        let snapshot = TempDir::with_prefix_in(".snapshot.", temp_directory)
+            .context("create state snapshot directory")?;
+        copy_files(state_directory, snapshot.path()).context("copy state into snapshot")?;
+        Ok(Self(snapshot))
+    }
+
+    fn restore(&self, state_directory: &Path) -> anyhow::Result<()> {
+        for entry in fs::read_dir(state_directory).context("read state directory")? {
+            let path = entry?.path();
+            if path.is_file() {
+                fs::remove_file(&path).context("remove state file")?;
+            }
+        }
+        copy_files(self.0.path(), state_directory).context("restore state from snapshot")
+    }
+}
+
+/// Copy all files (not directories) from one directory into another
+fn copy_files(from: &Path, to: &Path) -> std::io::Result<()> {
+    for entry in fs::read_dir(from)? {
+        let path = entry?.path();
+        if let Some(name) = path.file_name().filter(|_| path.is_file()) {
+            fs::copy(&path, to.join(name))?;
+        }
+    }
+    Ok(())
 }
 
 fn wait_until_path_or_time(path: &Path, timeout: Duration) {
@@ -283,21 +534,29 @@ fn wait_until_path_or_time(path: &Path, timeout: Duration) {
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Mutex;
     use std::time::Duration;
 
     use regex::Regex;
 
     use super::StatefulExecutor;
+    use crate::config::TestCaseConfig;
     use crate::executors::DEFAULT_SHELL;
     use crate::executors::bash_runner::BashRunner;
+    use crate::executors::context::Context;
     use crate::executors::error::ExecutionError;
     use crate::executors::error::ExecutionTimeout;
+    use crate::executors::executor::ExecutionObserver;
+    use crate::executors::executor::Executor;
     use crate::executors::executor::tests::combined_output_test_suite;
     use crate::executors::executor::tests::run_executor_tests;
     use crate::executors::executor::tests::standard_output_test_suite;
     use crate::output::ExitStatus;
     use crate::output::Output;
+    use crate::test_expectation;
     use crate::testcase::TestCase;
+    use crate::validation::OutputBody;
+    use crate::validation::ValidationBody;
 
     #[test]
     fn test_standard_test_suite() {
@@ -487,7 +746,7 @@ mod tests {
                 TestCase::from_expression("echo OK2"),
             ],
             None,
-            Err(ExecutionError::Skipped(1)),
+            Err(ExecutionError::Skipped(1, vec![])),
         )];
 
         run_executor_tests(
@@ -496,6 +755,157 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_always_run_testcases_run_after_execution_ended_early() {
+        let always_run = |expression: &str| TestCase {
+            title: "Test".into(),
+            shell_expression: expression.into(),
+            config: TestCaseConfig {
+                always_run: Some(true),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let skipped = || Output {
+            exit_code: ExitStatus::Skipped,
+            ..Default::default()
+        };
+        let tests = vec![
+            (
+                "Skip runs remaining testcases that always run",
+                vec![
+                    TestCase::from_expression("export FOO=bar"),
+                    TestCase::from_expression("exit 80"),
+                    TestCase::from_expression("echo OK1"),
+                    always_run("echo $FOO"),
+                ],
+                None,
+                Err(ExecutionError::Skipped(
+                    1,
+                    vec![skipped(), skipped(), skipped(), ("bar\n", "").into()],
+                )),
+            ),
+            (
+                "Timeout runs remaining testcases that always run",
+                vec![
+                    TestCase::from_expression_timed("sleep 1", Some(Duration::from_millis(50))),
+                    TestCase::from_expression("echo OK1"),
+                    always_run("echo OK2"),
+                ],
+                None,
+                Err(ExecutionError::Timeout(
+                    ExecutionTimeout::Index(0),
+                    vec![
+                        Output {
+                            exit_code: ExitStatus::Timeout(Duration::from_millis(50)),
+                            ..Default::default()
+                        },
+                        skipped(),
+                        ("OK2\n", "").into(),
+                    ],
+                )),
+            ),
+            (
+                "Fail fast runs remaining testcases that always run",
+                vec![
+                    TestCase {
+                        title: "Test".into(),
+                        shell_expression: "echo OK1".into(),
+                        body: ValidationBody::Output(OutputBody {
+                            expectations: vec![test_expectation!("equal", "OTHER")],
+                        }),
+                        config: TestCaseConfig {
+                            fail_fast: Some(true),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    always_run("echo OK2"),
+                ],
+                None,
+                Err(ExecutionError::Failed(
+                    0,
+                    vec![("OK1\n", "").into(), ("OK2\n", "").into()],
+                )),
+            ),
+            (
+                "Testcases that always run are constrained by a new total timeout",
+                vec![
+                    TestCase::from_expression("sleep 2"),
+                    TestCase::from_expression("echo OK1"),
+                    always_run("sleep 2 && echo OK2"),
+                    always_run("echo OK3"),
+                ],
+                Some(Duration::from_millis(300)),
+                Err(ExecutionError::Timeout(
+                    ExecutionTimeout::Total,
+                    vec![
+                        Output {
+                            exit_code: ExitStatus::Timeout(Duration::from_millis(300)),
+                            ..Default::default()
+                        },
+                        skipped(),
+                        Output {
+                            exit_code: ExitStatus::Timeout(Duration::from_millis(300)),
+                            ..Default::default()
+                        },
+                        ("OK3\n", "").into(),
+                    ],
+                )),
+            ),
+            (
+                "Testcases that always run also run without early end",
+                vec![
+                    TestCase::from_expression("echo OK1"),
+                    always_run("echo OK2"),
+                ],
+                None,
+                Ok(vec![("OK1\n", "").into(), ("OK2\n", "").into()]),
+            ),
+        ];
+
+        run_executor_tests(
+            StatefulExecutor(BashRunner::stateful_generator(*DEFAULT_SHELL)),
+            tests,
+        );
+    }
+
+    #[test]
+    fn test_outputs_of_testcases_that_always_run_are_marked() {
+        let executor = StatefulExecutor(BashRunner::stateful_generator(*DEFAULT_SHELL));
+        let testcases = [
+            TestCase::from_expression("exit 80"),
+            TestCase::from_expression("echo OK1"),
+            TestCase {
+                shell_expression: "exit 80".into(),
+                config: TestCaseConfig {
+                    always_run: Some(true),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        ];
+        let result = executor.execute_all(
+            &testcases.iter().collect::<Vec<_>>(),
+            &Context::new_for_test(),
+        );
+        let Err(ExecutionError::Skipped(0, outputs)) = result else {
+            panic!("execution is skipped: {result:?}");
+        };
+        assert_eq!(
+            vec![
+                (ExitStatus::Skipped, false),
+                (ExitStatus::Skipped, false),
+                (ExitStatus::Code(80), true),
+            ],
+            outputs
+                .iter()
+                .map(|output| (output.exit_code.clone(), output.always_run))
+                .collect::<Vec<_>>(),
+            "testcase that always runs is not skipped, even if it returns the skip code"
+        );
+    }
+
     #[test]
     fn test_executor_keeps_state() {
         let tests = vec![
@@ -563,6 +973,132 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_executor_exports_captured_values() {
+        let capturing = |expression: &str, regex: &str| TestCase {
+            title: "Test".into(),
+            shell_expression: expression.into(),
+            body: ValidationBody::Output(OutputBody {
+                expectations: vec![test_expectation!("regex", regex)],
+            }),
+            ..Default::default()
+        };
+        let tests = vec![
+            (
+                "Named groups are exported to following testcases",
+                vec![
+                    TestCase::from_expression("echo JOB_ID=${JOB_ID:-undefined}"),
+                    capturing("echo \"created job 'abc'\"", "created job (?P<JOB_ID>.+)"),
+                    TestCase::from_expression("echo JOB_ID=${JOB_ID:-undefined}"),
+                    capturing("echo 'created job def'", "created job (?P<JOB_ID>.+)"),
+                    TestCase::from_expression("echo JOB_ID=${JOB_ID:-undefined}"),
+                ],
+                None,
+                Ok(vec![
+                    ("JOB_ID=undefined\n", "").into(),
+                    ("created job 'abc'\n", "").into(),
+                    ("JOB_ID='abc'\n", "").into(),
+                    ("created job def\n", "").into(),
+                    ("JOB_ID=def\n", "").into(),
+                ]),
+            ),
+            (
+                "Environment of the testcase takes precedence",
+                vec![
+                    capturing("echo 'created job abc'", "created job (?P<JOB_ID>.+)"),
+                    TestCase {
+                        title: "Test".into(),
+                        shell_expression: "echo JOB_ID=$JOB_ID".into(),
+                        config: TestCaseConfig {
+                            environment: [("JOB_ID".into(), "explicit".into())].into(),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                ],
+                None,
+                Ok(vec![
+                    ("created job abc\n", "").into(),
+                    ("JOB_ID=explicit\n", "").into(),
+                ]),
+            ),
+            (
+                "Captured values do not change line numbers",
+                vec![
+                    TestCase::from_expression("export FIRST_LINE=$LINENO"),
+                    capturing("echo 'created job abc'", "created job (?P<JOB_ID>.+)"),
+                    TestCase::from_expression("echo $((LINENO - FIRST_LINE))"),
+                ],
+                None,
+                Ok(vec![
+                    ("", "").into(),
+                    ("created job abc\n", "").into(),
+                    ("0\n", "").into(),
+                ]),
+            ),
+            (
+                "Testcases that fail and always run capture values",
+                vec![
+                    TestCase {
+                        title: "Test".into(),
+                        shell_expression: "echo 'created job abc'".into(),
+                        body: ValidationBody::Output(OutputBody {
+                            expectations: vec![
+                                test_expectation!("regex", "created job (?P<JOB_ID>.+)"),
+                                test_expectation!("equal", "done"),
+                            ],
+                        }),
+                        config: TestCaseConfig {
+                            fail_fast: Some(true),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    TestCase {
+                        config: TestCaseConfig {
+                            always_run: Some(true),
+                            ..Default::default()
+                        },
+                        ..capturing("echo \"deleted job $JOB_ID\"", "deleted job (?P<OLD_ID>.+)")
+                    },
+                    TestCase {
+                        config: TestCaseConfig {
+                            always_run: Some(true),
+                            ..Default::default()
+                        },
+                        ..TestCase::from_expression("echo OLD_ID=${OLD_ID:-undefined}")
+                    },
+                ],
+                None,
+                Err(ExecutionError::Failed(
+                    0,
+                    vec![
+                        ("created job abc\n", "").into(),
+                        ("deleted job abc\n", "").into(),
+                        ("OLD_ID=abc\n", "").into(),
+                    ],
+                )),
+            ),
+            (
+                "Nothing is exported from output that does not match",
+                vec![
+                    capturing("echo 'deleted job abc'", "created job (?P<JOB_ID>.+)"),
+                    TestCase::from_expression("echo JOB_ID=${JOB_ID:-undefined}"),
+                ],
+                None,
+                Ok(vec![
+                    ("deleted job abc\n", "").into(),
+                    ("JOB_ID=undefined\n", "").into(),
+                ]),
+            ),
+        ];
+
+        run_executor_tests(
+            StatefulExecutor(BashRunner::stateful_generator(*DEFAULT_SHELL)),
+            tests,
+        );
+    }
+
     #[test]
     fn test_non_printable_ascii_in_output() {
         let tests = vec![(
@@ -585,4 +1121,120 @@ mod tests {
             tests,
         );
     }
+
+    #[test]
+    fn test_executor_measures_duration() {
+        let executor = StatefulExecutor(BashRunner::stateful_generator(*DEFAULT_SHELL));
+        let testcases = [
+            TestCase::from_expression("true"),
+            TestCase::from_expression("sleep 0.2"),
+        ];
+        let outputs = executor
+            .execute_all(
+                &testcases.iter().collect::<Vec<_>>(),
+                &Context::new_for_test(),
+            )
+            .expect("execution succeeds");
+        let durations = outputs
+            .iter()
+            .map(|output| output.duration.expect("duration is measured"))
+            .collect::<Vec<_>>();
+        assert!(
+            durations[1] >= Duration::from_millis(200),
+            "sleep is measured: {durations:?}"
+        );
+    }
+
+    #[test]
+    fn test_executor_retries_failing_testcase_from_same_state() {
+        let executor = StatefulExecutor(BashRunner::stateful_generator(*DEFAULT_SHELL));
+        let mut flaky = TestCase::from_expression(
+            "ATTEMPT=$(cat attempt 2>/dev/null || echo 0)\n\
+             echo $((ATTEMPT + 1)) > attempt\n\
+             COUNT=$((COUNT + 1))\n\
+             echo \"COUNT=$COUNT\"\n\
+             [ \"$ATTEMPT\" -ge 2 ]",
+        );
+        flaky.body = ValidationBody::Output(OutputBody {
+            expectations: vec![test_expectation!("equal", "COUNT=2")],
+        });
+        flaky.config.retries = Some(3);
+        let testcases = [
+            TestCase::from_expression("COUNT=1"),
+            flaky,
+            TestCase::from_expression("echo \"COUNT=$COUNT\""),
+        ];
+        let outputs = executor
+            .execute_all(
+                &testcases.iter().collect::<Vec<_>>(),
+                &Context::new_for_test(),
+            )
+            .expect("execution succeeds");
+
+        let expect: Vec<Output> = vec![
+            ("", "").into(),
+            ("COUNT=2\n", "").into(),
+            ("COUNT=2\n", "").into(),
+        ];
+        assert_eq!(expect, outputs);
+        let previous: Vec<Output> = vec![
+            ("COUNT=2\n", "", Some(1)).into(),
+            ("COUNT=2\n", "", Some(1)).into(),
+        ];
+        assert_eq!(previous, outputs[1].previous_attempts);
+        assert!(
+            outputs[0].previous_attempts.is_empty() && outputs[2].previous_attempts.is_empty(),
+            "succeeding testcases are not retried"
+        );
+    }
+
+    #[derive(Default)]
+    struct RecordingObserver(Mutex<Vec<String>>);
+
+    impl ExecutionObserver for RecordingObserver {
+        fn testcase_started(&self, index: usize, testcase: &TestCase) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("started {index}: {}", testcase.shell_expression));
+        }
+
+        fn testcase_finished(&self, index: usize, _testcase: &TestCase, output: &Output) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("finished {index}: {}", output.exit_code));
+        }
+    }
+
+    #[test]
+    fn test_observer_is_notified_per_testcase() {
+        let executor = StatefulExecutor(BashRunner::stateful_generator(*DEFAULT_SHELL));
+        let testcases = [
+            TestCase::from_expression("echo OK1"),
+            TestCase::from_expression("exit 3"),
+            TestCase::from_expression("exit 80"),
+            TestCase::from_expression("echo OK2"),
+        ];
+        let observer = RecordingObserver::default();
+        let result = executor.execute_all_observed(
+            &testcases.iter().collect::<Vec<_>>(),
+            &Context::new_for_test(),
+            &observer,
+        );
+        assert!(
+            matches!(result, Err(ExecutionError::Skipped(2, _))),
+            "expected skip, got {result:?}"
+        );
+        assert_eq!(
+            vec![
+                "started 0: echo OK1",
+                "finished 0: 0",
+                "started 1: exit 3",
+                "finished 1: 3",
+                "started 2: exit 80",
+            ],
+            *observer.0.lock().unwrap()
+        );
+    }
 }
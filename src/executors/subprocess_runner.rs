@@ -9,6 +9,7 @@ use std::collections::BTreeMap;
 use std::io::ErrorKind;
 use std::io::Seek;
 use std::io::Write;
+use std::path::Path;
 use std::path::PathBuf;
 use std::time::Duration;
 
@@ -17,6 +18,7 @@ use anyhow::Result;
 use subprocess::Exec;
 use subprocess::ExitStatus;
 use subprocess::NullFile;
+use subprocess::Popen;
 use subprocess::Redirection;
 use tempfile::tempfile_in;
 use tracing::debug;
@@ -26,16 +28,22 @@ use tracing::trace;
 use super::DEFAULT_SHELL;
 use super::context::Context as ExecutionContext;
 use super::runner::Runner;
+use super::util::hermetic_environment;
+use crate::config::TestCaseLimits;
 use crate::output::DetachedProcess;
 use crate::output::ExitStatus as OutputExitStatus;
 use crate::output::Output;
+use crate::output::ResourceUsage;
 use crate::testcase::TestCase;
 
 /// A runner that starts an interpreter (usually `bash`) in a sub-process and
 /// writes the shell expression of a given [`crate::testcase::TestCase`] into
 /// STDIN.
 ///
-/// Constraining the max execution time is supported.
+/// Constraining the max execution time is supported. Resource limits are
+/// applied in the started process before it executes the shell, so test cases
+/// with limits are run like in the
+/// [`crate::executors::sandbox_runner::SandboxRunner`], without a sandbox.
 #[derive(Clone)]
 pub struct SubprocessRunner(pub(super) PathBuf);
 
@@ -49,11 +57,24 @@ impl Runner for SubprocessRunner {
     fn run(&self, _name: &str, testcase: &TestCase, context: &ExecutionContext) -> Result<Output> {
         let shell = &self.0;
 
+        // limits must be applied in the started process, before it executes
+        // the shell, which is not supported by the subprocess crate
+        if let Some(ref limits) = testcase.config.limits {
+            return run_limited(shell, limits, testcase, context);
+        }
+
         // apply environment variables (ensure SHELL is set)
         let mut envs = testcase.config.environment.clone();
         envs.insert("SHELL".into(), shell.to_string_lossy().to_string());
 
-        let mut exec = Exec::cmd(shell)
+        // hermetic executions start from an empty environment
+        let mut exec = Exec::cmd(shell);
+        if let Some(passthrough) = hermetic_environment(&context.config) {
+            exec = exec
+                .env_clear()
+                .env_extend(&Vec::from_iter(passthrough.iter()));
+        }
+        let mut exec = exec
             .env_extend(&Vec::from_iter(envs.iter()))
             .cwd(&context.work_directory);
 
@@ -122,13 +143,19 @@ impl Runner for SubprocessRunner {
         }
 
         // wait for the process to finish and handle the result
+        let mut resource_usage = None;
+        let mut signal = None;
         let (stdout, stderr, exit_code) = match comm.read() {
             // successs! we are happy!
-            Ok((stdout, stderr)) => (
-                stdout,
-                stderr,
-                process.wait().context("capture process exit")?.into(),
-            ),
+            Ok((stdout, stderr)) => {
+                let (status, usage) =
+                    wait_with_usage(&mut process).context("capture process exit")?;
+                resource_usage = usage;
+                if let ExitStatus::Signaled(number) = status {
+                    signal = Some(i32::from(number));
+                }
+                (stdout, stderr, status.into())
+            }
 
             // bummer, a sad thing happened
             Err(err) => {
@@ -167,10 +194,72 @@ impl Runner for SubprocessRunner {
             exit_code,
             detached_process: None,
             captured_env: BTreeMap::new(),
+            interactive: None,
+            signal,
+            duration: None,
+            resource_usage,
+            previous_attempts: vec![],
+            always_run: false,
         })
     }
 }
 
+/// Runs the test case in a shell process, that has the resource limits applied
+/// before it executes the shell
+#[cfg(target_os = "linux")]
+fn run_limited(
+    shell: &Path,
+    limits: &TestCaseLimits,
+    testcase: &TestCase,
+    context: &ExecutionContext,
+) -> Result<Output> {
+    use super::limits::limit_command;
+    use super::sandbox_runner::run_command;
+    use super::sandbox_runner::shell_command;
+
+    let mut command = shell_command(shell, testcase, context);
+    limit_command(&mut command, limits)?;
+    run_command(command, testcase, context)
+}
+
+/// Resource limits are only supported on Linux
+#[cfg(not(target_os = "linux"))]
+fn run_limited(
+    _shell: &Path,
+    _limits: &TestCaseLimits,
+    _testcase: &TestCase,
+    _context: &ExecutionContext,
+) -> Result<Output> {
+    anyhow::bail!("resource limits are only supported on Linux")
+}
+
+/// Waits for the process to end and returns its exit status, together with
+/// the resources it and all the processes it waited for used
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn wait_with_usage(process: &mut Popen) -> Result<(ExitStatus, Option<ResourceUsage>)> {
+    use super::usage::ProcessExit;
+
+    let Some(pid) = process.pid() else {
+        return Ok((process.wait()?, None));
+    };
+    let (exit, usage) = super::usage::wait_with_usage(pid, true)
+        .context("wait for process")?
+        .context("process ended")?;
+    let status = match exit {
+        ProcessExit::Exited(code) => ExitStatus::Exited(code as u32),
+        ProcessExit::Signaled(signal) => ExitStatus::Signaled(signal as u8),
+        ProcessExit::Other(status) => ExitStatus::Other(status),
+    };
+    Ok((status, Some(usage)))
+}
+
+/// Waits for the process to end and returns its exit status. Resource usage
+/// is not measured on this platform.
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn wait_with_usage(process: &mut Popen) -> Result<(ExitStatus, Option<ResourceUsage>)> {
+    Ok((process.wait()?, None))
+}
+
 impl Default for SubprocessRunner {
     fn default() -> Self {
         Self(DEFAULT_SHELL.to_owned())
@@ -196,10 +285,14 @@ mod tests {
     use super::SubprocessRunner;
     use crate::config::OutputStreamControl;
     use crate::config::TestCaseConfig;
+    #[cfg(target_os = "linux")]
+    use crate::config::TestCaseLimits;
     use crate::executors::context::Context as ExecutionContext;
     use crate::output::ExitStatus;
     use crate::output::Output;
     use crate::testcase::TestCase;
+    #[cfg(target_os = "linux")]
+    use crate::testcase::TestCaseError;
 
     #[cfg(not(target_os = "windows"))]
     #[cfg(feature = "volatile_tests")]
@@ -250,6 +343,116 @@ mod tests {
         assert_eq!(expect, output);
     }
 
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    #[test]
+    fn test_execute_measures_resource_usage() {
+        let output = SubprocessRunner::default()
+            .run(
+                "name",
+                &TestCase::from_expression("head -c 20000000 /dev/zero | tail -c 1 | wc -c"),
+                &ExecutionContext::new_for_test(),
+            )
+            .expect("execute without error");
+        let usage = output.resource_usage.expect("resource usage is measured");
+        assert!(
+            usage.max_rss >= 1024 * 1024,
+            "peak memory is measured: {usage:?}"
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_execute_with_limits() {
+        let output = SubprocessRunner::default()
+            .run(
+                "name",
+                &TestCase {
+                    title: "Test".into(),
+                    shell_expression: "ulimit -n && ulimit -v && ulimit -t".into(),
+                    config: TestCaseConfig {
+                        limits: Some(TestCaseLimits {
+                            memory: Some(512 * 1024 * 1024),
+                            cpu_time: Some(Duration::from_millis(1500)),
+                            open_files: Some(32),
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                &ExecutionContext::new_for_test(),
+            )
+            .expect("execute without error");
+        let expect: Output = ("32\n524288\n2\n", "").into();
+        assert_eq!(expect, output);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_execute_with_exceeded_memory_limit() {
+        let testcase = TestCase {
+            title: "Test".into(),
+            shell_expression: "dd if=/dev/zero of=/dev/null bs=200M count=1".into(),
+            config: TestCaseConfig {
+                limits: Some(TestCaseLimits {
+                    memory: Some(50 * 1024 * 1024),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let output = SubprocessRunner::default()
+            .run("name", &testcase, &ExecutionContext::new_for_test())
+            .expect("execute without error");
+        assert_eq!(ExitStatus::Code(1), output.exit_code, "allocation failed");
+
+        // the failed allocation cannot be told apart from other failures
+        assert_eq!(
+            Err(TestCaseError::InvalidExitCode {
+                actual: 1,
+                expected: 0,
+            }),
+            testcase.validate(&output),
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_execute_reports_terminating_signal() {
+        let testcase = |limits| TestCase {
+            title: "Test".into(),
+            shell_expression: "echo OK && kill -XCPU $$".into(),
+            config: TestCaseConfig {
+                limits,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        for limits in [
+            None,
+            Some(TestCaseLimits {
+                cpu_time: Some(Duration::from_secs(10)),
+                ..Default::default()
+            }),
+        ] {
+            let output = SubprocessRunner::default()
+                .run(
+                    "name",
+                    &testcase(limits.clone()),
+                    &ExecutionContext::new_for_test(),
+                )
+                .expect("execute without error");
+            assert_eq!(ExitStatus::Unknown, output.exit_code, "limits: {limits:?}");
+            assert_eq!(Some(24), output.signal, "limits: {limits:?}");
+            assert_eq!(
+                "OK\n",
+                String::from_utf8_lossy((&output.stdout).into()),
+                "limits: {limits:?}"
+            );
+        }
+    }
+
     #[cfg(not(target_os = "windows"))]
     #[test]
     fn test_execute_captures_non_printable_characters() {
@@ -0,0 +1,141 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::io;
+use std::io::ErrorKind;
+use std::time::Duration;
+
+use nix::libc;
+
+use crate::output::ResourceUsage;
+
+/// How a process ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProcessExit {
+    /// The process exited with the exit code
+    Exited(i32),
+
+    /// The process was terminated by the signal
+    Signaled(i32),
+
+    /// The process ended with the raw wait status, e.g. because it stopped
+    Other(i32),
+}
+
+impl ProcessExit {
+    /// Returns the exit code, if the process exited
+    pub fn code(&self) -> Option<i32> {
+        match self {
+            Self::Exited(code) => Some(*code),
+            _ => None,
+        }
+    }
+
+    /// Returns the signal, if the process was terminated by a signal
+    pub fn signal(&self) -> Option<i32> {
+        match self {
+            Self::Signaled(signal) => Some(*signal),
+            _ => None,
+        }
+    }
+}
+
+/// Waits for the child process with the given id to end and returns how it
+/// ended, together with the resources that it and all the processes it waited
+/// for used. Returns `None` without waiting, if `block` is `false` and the
+/// process did not end yet.
+///
+/// The process is reaped, so it must not be waited for again.
+pub fn wait_with_usage(pid: u32, block: bool) -> io::Result<Option<(ProcessExit, ResourceUsage)>> {
+    let mut status = 0;
+    // SAFETY: rusage is a plain structure, that is valid when zeroed
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    let options = if block { 0 } else { libc::WNOHANG };
+    loop {
+        // SAFETY: the pointers reference valid, initialized structures
+        let result = unsafe { libc::wait4(pid as libc::pid_t, &mut status, options, &mut usage) };
+        match result {
+            0 => return Ok(None),
+            -1 => {
+                let err = io::Error::last_os_error();
+                if err.kind() != ErrorKind::Interrupted {
+                    return Err(err);
+                }
+            }
+            _ => break,
+        }
+    }
+
+    let exit = if libc::WIFEXITED(status) {
+        ProcessExit::Exited(libc::WEXITSTATUS(status))
+    } else if libc::WIFSIGNALED(status) {
+        ProcessExit::Signaled(libc::WTERMSIG(status))
+    } else {
+        ProcessExit::Other(status)
+    };
+
+    // the peak resident set size is measured in kilobytes on Linux
+    let max_rss = usage.ru_maxrss.max(0) as u64;
+    let max_rss = if cfg!(target_os = "linux") {
+        max_rss * 1024
+    } else {
+        max_rss
+    };
+    let time = |time: libc::timeval| {
+        Duration::from_secs(time.tv_sec.max(0) as u64)
+            + Duration::from_micros(time.tv_usec.max(0) as u64)
+    };
+    Ok(Some((
+        exit,
+        ResourceUsage {
+            max_rss,
+            user_time: time(usage.ru_utime),
+            system_time: time(usage.ru_stime),
+        },
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use super::ProcessExit;
+    use super::wait_with_usage;
+
+    // the processes are reaped by waiting with usage
+    #[allow(clippy::zombie_processes)]
+    #[test]
+    fn test_wait_with_usage() {
+        let child = Command::new("sh")
+            .args(["-c", "exit 3"])
+            .spawn()
+            .expect("start process");
+        let (exit, usage) = wait_with_usage(child.id(), true)
+            .expect("wait succeeds")
+            .expect("process ended");
+        assert_eq!(ProcessExit::Exited(3), exit);
+        assert!(usage.max_rss > 0, "max rss is measured: {usage:?}");
+    }
+
+    #[allow(clippy::zombie_processes)]
+    #[test]
+    fn test_wait_with_usage_does_not_block() {
+        let mut child = Command::new("sleep")
+            .arg("10")
+            .spawn()
+            .expect("start process");
+        assert_eq!(
+            None,
+            wait_with_usage(child.id(), false).expect("wait succeeds")
+        );
+        child.kill().expect("kill process");
+        let (exit, _) = wait_with_usage(child.id(), true)
+            .expect("wait succeeds")
+            .expect("process ended");
+        assert_eq!(ProcessExit::Signaled(9), exit);
+    }
+}
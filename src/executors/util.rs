@@ -5,9 +5,12 @@
  * LICENSE file in the root directory of this source tree.
  */
 
+use std::collections::BTreeMap;
 use std::num::NonZeroUsize;
 use std::thread::{self};
 
+use crate::config::DocumentConfig;
+
 /// Default amount of parallel executions. This number often corresponds to the
 /// amount of CPUs or computer has, but it may diverge in various cases.
 pub fn default_parallel_count() -> usize {
@@ -15,3 +18,31 @@ pub fn default_parallel_count() -> usize {
         .unwrap_or(NonZeroUsize::new(1).expect("1 > 0"))
         .get()
 }
+
+/// Returns the variables of the host environment that are passed through into
+/// the executions of a hermetic document, or [`None`] if executions inherit
+/// the whole environment of the host.
+pub fn hermetic_environment(config: &DocumentConfig) -> Option<BTreeMap<String, String>> {
+    if !config.get_hermetic() {
+        return None;
+    }
+    Some(
+        config
+            .env_passthrough
+            .iter()
+            .filter_map(|name| std::env::var(name).ok().map(|value| (name.clone(), value)))
+            .collect(),
+    )
+}
+
+/// Quote the value, so that it is read verbatim by the shell
+pub fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Whether the name can be used as the name of a shell variable
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
@@ -8,6 +8,7 @@
 use std::fmt::Display;
 
 use anyhow::Result;
+use anyhow::bail;
 use serde::Serialize;
 
 use crate::escaping::Escaper;
@@ -25,6 +26,13 @@ pub struct Expectation {
     /// Multiline Expectations (can) match multiple sequential lines of output
     pub multiline: bool,
 
+    /// Unordered Expectations form a group with directly adjacent unordered
+    /// Expectations, that matches a block of lines in any order
+    pub unordered: bool,
+
+    /// Negated Expectations must not match any line of the output
+    pub negated: bool,
+
     /// The actual algorithm that implements the Expectation
     pub rule: Box<dyn Rule>,
 
@@ -44,10 +52,38 @@ impl Expectation {
         self.rule.matches(line)
     }
 
+    /// The named values the Expectation captures from the given line
+    pub fn captures(&self, line: &[u8]) -> Vec<(String, String)> {
+        self.rule.captures(line)
+    }
+
+    /// Explains why the given line does not match the Expectation, if its
+    /// rule can
+    pub fn explain_mismatch(&self, line: &[u8]) -> Option<String> {
+        self.rule.explain_mismatch(line)
+    }
+
     /// Renders the Expectation into an expression from which it can be parsed
     pub fn to_expression_string(&self, escaper: &Escaper) -> String {
-        self.rule
-            .to_expression_string(self.optional, self.multiline, escaper)
+        let mut rendered = self
+            .rule
+            .to_expression_string(self.optional, self.multiline, escaper);
+        if self.negated {
+            // negation is noted in front of the kind, which plain equal
+            // expressions do not render
+            let (kind, expression) = self.rule.unmake();
+            match rendered.rfind(" (") {
+                Some(position) if kind != "equal" || escaper.has_unprintable(&expression) => {
+                    rendered.insert(position + 2, '!')
+                }
+                _ => rendered.push_str(" (!)"),
+            }
+        }
+        if self.unordered {
+            format!("{rendered} {UNORDERED_MODIFIER}")
+        } else {
+            rendered
+        }
     }
 
     /// The original string as it was written in the test file
@@ -66,6 +102,8 @@ impl PartialEq for Expectation {
     fn eq(&self, other: &Self) -> bool {
         self.optional == other.optional
             && self.multiline == other.multiline
+            && self.unordered == other.unordered
+            && self.negated == other.negated
             && self.rule.to_string() == other.rule.to_string()
     }
 }
@@ -79,6 +117,26 @@ impl Serialize for Expectation {
     }
 }
 
+/// Suffix of an expectation that marks it as unordered
+pub const UNORDERED_MODIFIER: &str = "(unordered)";
+
+/// Whether the literal text ends in something that would be parsed as a
+/// modifier of the expectation, so that it must be written with an explicit
+/// kind, like `text (unordered) (equal)` or `text (!) (equal)`
+pub(crate) fn ends_in_modifier(text: &str) -> bool {
+    let unordered = text
+        .strip_suffix(UNORDERED_MODIFIER)
+        .is_some_and(|text| text.ends_with(' '));
+    let negated = text
+        .strip_suffix(')')
+        .and_then(|text| text.rsplit_once(" (!"))
+        .is_some_and(|(_, kind)| {
+            kind.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || "?*+".contains(c))
+        });
+    unordered || negated
+}
+
 /// Facade for [`Expectation`] creation from either line encoded representation
 /// or from components
 pub struct ExpectationMaker(RuleRegistry);
@@ -92,7 +150,8 @@ impl ExpectationMaker {
     /// Create an [`Expectation`] that from it's text encoding, with the BNF form:
     ///
     /// ```bnf
-    ///  <expectation> ::= <expression> | <expression> (<kind>) | <expression> (<quantifier>) | <expression> (<kind><quantifier>)
+    ///  <expectation> ::= <matcher> | <matcher> (unordered) | <expression> (!<kind>) | <expression> (!)
+    ///      <matcher> ::= <expression> | <expression> (<kind>) | <expression> (<quantifier>) | <expression> (<kind><quantifier>)
     ///   <expression> ::= "arbitrary text"
     ///         <kind> ::= <equal-kind> | <no-eol-kind> | <escaped-kind> | <glob-kind> | <regex-kind>
     ///   <equal-kind> ::= "equal" | "eq"
@@ -114,16 +173,32 @@ impl ExpectationMaker {
     ///     .expect("parses expectation");
     /// ```
     pub fn parse(&self, line: &str) -> Result<Expectation> {
-        let (expression, kind, quantifier) = self.extract(line)?;
+        let trimmed = (&line).trim_newlines();
+        let (matcher, unordered) = match trimmed
+            .strip_suffix(UNORDERED_MODIFIER)
+            .and_then(|matcher| matcher.strip_suffix(' '))
+        {
+            Some(matcher) => (matcher, true),
+            None => (line, false),
+        };
+        let (expression, kind, quantifier) = self.extract(matcher)?;
+        let (kind, negated) = match kind.strip_prefix('!') {
+            Some("") => ("equal", true),
+            Some(kind) => (kind, true),
+            None => (kind.as_str(), false),
+        };
+        if negated && !quantifier.is_empty() {
+            bail!("negated expectation `{trimmed}` must not have a quantifier");
+        }
+        if negated && unordered {
+            bail!("negated expectation `{trimmed}` cannot be unordered");
+        }
         let multiline = quantifier == "*" || quantifier == "+";
         let optional = quantifier == "*" || quantifier == "?";
-        self.make(
-            &kind,
-            &expression,
-            optional,
-            multiline,
-            &(&line).trim_newlines(),
-        )
+        let mut expectation = self.make(kind, &expression, optional, multiline, &trimmed)?;
+        expectation.unordered = unordered;
+        expectation.negated = negated;
+        Ok(expectation)
     }
 
     /// Create an [`Expectation`] from the components that make it up
@@ -138,6 +213,8 @@ impl ExpectationMaker {
         Ok(Expectation {
             optional,
             multiline,
+            unordered: false,
+            negated: false,
             rule: self.0.make(kind, expression)?,
             original: original.into(),
         })
@@ -181,6 +258,7 @@ impl ExpectationMaker {
 #[cfg(test)]
 pub(crate) mod tests {
     use super::ExpectationMaker;
+    use super::ends_in_modifier;
     use crate::escaping::Escaper;
     use crate::rules::registry::RuleRegistry;
 
@@ -210,6 +288,9 @@ pub(crate) mod tests {
             ("foo (glob*)", ("foo", "glob", "*")),
             ("foo (glob+)", ("foo", "glob", "+")),
             ("foo (glob+) (glob+)", ("foo (glob+)", "glob", "+")),
+            ("foo (!)", ("foo", "!", "")),
+            ("foo (!glob)", ("foo", "!glob", "")),
+            ("foo (!regex)", ("foo", "!regex", "")),
         ];
 
         tests.iter().for_each(
@@ -232,6 +313,58 @@ pub(crate) mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_negated() {
+        let expectation = expectation_maker()
+            .parse("*panicked* (!glob)")
+            .expect("parse negated expectation");
+        assert!(expectation.negated);
+        assert!(expectation.matches(b"thread panicked at\n"));
+
+        for line in ["foo (!glob+)", "foo (!?)", "foo (!) (unordered)"] {
+            assert!(
+                expectation_maker().parse(line).is_err(),
+                "`{line}` is not a valid negated expectation"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_literal_modifier() {
+        let expectation = expectation_maker()
+            .parse("foo (unordered) (equal)")
+            .expect("parse expectation with explicit kind");
+        assert!(!expectation.unordered);
+        assert!(expectation.matches(b"foo (unordered)\n"));
+
+        for line in ["foo (!)", "foo (!glob)"] {
+            let expectation = expectation_maker()
+                .parse(&format!("{line} (equal)"))
+                .expect("parse expectation with explicit kind");
+            assert!(!expectation.negated, "`{line} (equal)` is not negated");
+            assert!(expectation.matches(format!("{line}\n").as_bytes()));
+        }
+    }
+
+    #[test]
+    fn test_ends_in_modifier() {
+        let tests = vec![
+            (true, "foo (unordered)"),
+            (true, "foo (!)"),
+            (true, "foo (!glob)"),
+            (true, "foo (!no-eol)"),
+            (true, "foo (!glob+)"),
+            (false, "foo(unordered)"),
+            (false, "foo (!not a kind)"),
+            (false, "foo (!"),
+            (false, "foo (glob)"),
+            (false, "foo"),
+        ];
+        for (expect, text) in tests {
+            assert_eq!(expect, ends_in_modifier(text), "{text}");
+        }
+    }
+
     #[test]
     fn test_parse_to_expression_string() {
         let tests = vec![
@@ -250,6 +383,17 @@ pub(crate) mod tests {
             ("foo (regex)", "foo (regex)"),
             ("foo (re)", "foo (regex)"),
             ("foo (regex*)", "foo (regex*)"),
+            ("foo (unordered)", "foo (unordered)"),
+            ("foo (?) (unordered)", "foo (?) (unordered)"),
+            ("foo (re+) (unordered)", "foo (regex+) (unordered)"),
+            ("foo(unordered)", "foo(unordered)"),
+            ("foo (unordered) (equal)", "foo (unordered) (equal)"),
+            ("foo (unordered) (equal+)", "foo (unordered) (equal+)"),
+            ("foo (!)", "foo (!)"),
+            ("foo (!eq)", "foo (!)"),
+            ("foo (!glob)", "foo (!glob)"),
+            ("foo (!re)", "foo (!regex)"),
+            ("foo\\tbar (!esc)", "foo\\tbar (!escaped)"),
         ];
         for (from, to) in tests {
             let expectation = expectation_maker()
@@ -156,12 +156,12 @@ mod tests {
                             exit_code: None,
                             line_number: 234,
                             config: Default::default(),
-                            ..Default::default()
                         },
                         output: ("an expectation\n", "").into(),
                         result: Ok(()),
                         escaping: Escaper::default(),
                         format: ParserType::Cram,
+                        shell: None,
                     }],
                 },
             ),
@@ -191,6 +191,7 @@ mod tests {
                         location: None,
                         escaping: Escaper::default(),
                         format: ParserType::Cram,
+                        shell: None,
                     }],
                 },
             ),
@@ -224,6 +225,7 @@ mod tests {
                         location: None,
                         escaping: Escaper::default(),
                         format: ParserType::Cram,
+                        shell: None,
                     }],
                 },
             ),
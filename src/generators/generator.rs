@@ -87,6 +87,7 @@ pub(super) mod tests {
                     )),
                     escaping: Escaper::default(),
                     format: ParserType::Markdown,
+                    shell: None,
                 },
             ),
             (
@@ -110,6 +111,7 @@ pub(super) mod tests {
                     )),
                     escaping: Escaper::default(),
                     format: ParserType::Markdown,
+                    shell: None,
                 },
             ),
             (
@@ -137,6 +139,7 @@ pub(super) mod tests {
                     )),
                     escaping: Escaper::default(),
                     format: ParserType::Markdown,
+                    shell: None,
                 },
             ),
             (
@@ -160,6 +163,7 @@ pub(super) mod tests {
                     )),
                     escaping: Escaper::default(),
                     format: ParserType::Markdown,
+                    shell: None,
                 },
             ),
             (
@@ -183,6 +187,7 @@ pub(super) mod tests {
                     )),
                     escaping: Escaper::default(),
                     format: ParserType::Markdown,
+                    shell: None,
                 },
             ),
         ];
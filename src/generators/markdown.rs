@@ -182,6 +182,7 @@ mod tests {
 
     use super::MarkdownTestCaseGenerator;
     use super::MarkdownUpdateGenerator;
+    use crate::config::OutputStreamControl;
     use crate::config::TestCaseConfig;
     use crate::config::TestCaseWait;
     use crate::diff::Diff;
@@ -196,6 +197,10 @@ mod tests {
     use crate::test_expectation;
     use crate::testcase::TestCase;
     use crate::testcase::TestCaseError;
+    use crate::validation::MarkedDiff;
+    use crate::validation::MarkedOutputBody;
+    use crate::validation::MarkedSection;
+    use crate::validation::MarkedStream;
     use crate::validation::OutputBody;
     use crate::validation::ValidationBody;
     use crate::validation::ValidationFailure;
@@ -238,6 +243,7 @@ mod tests {
                         result: Ok(()),
                         escaping: Escaper::default(),
                         format: ParserType::Markdown,
+                        shell: None,
                     }],
                 },
             ),
@@ -276,6 +282,7 @@ mod tests {
                         result: Ok(()),
                         escaping: Escaper::default(),
                         format: ParserType::Markdown,
+                        shell: None,
                     }],
                 },
             ),
@@ -319,6 +326,7 @@ mod tests {
                         )),
                         escaping: Escaper::default(),
                         format: ParserType::Markdown,
+                        shell: None,
                     }],
                 },
             ),
@@ -355,6 +363,7 @@ mod tests {
                         }),
                         escaping: Escaper::default(),
                         format: ParserType::Markdown,
+                        shell: None,
                     }],
                 },
             ),
@@ -387,6 +396,7 @@ mod tests {
                         }),
                         escaping: Escaper::default(),
                         format: ParserType::Markdown,
+                        shell: None,
                     }],
                 },
             ),
@@ -424,6 +434,7 @@ mod tests {
                         }),
                         escaping: Escaper::default(),
                         format: ParserType::Markdown,
+                        shell: None,
                     }],
                 },
             ),
@@ -467,6 +478,7 @@ mod tests {
                         )),
                         escaping: Escaper::default(),
                         format: ParserType::Markdown,
+                        shell: None,
                     }],
                 },
             ),
@@ -514,6 +526,7 @@ mod tests {
                         )),
                         escaping: Escaper::default(),
                         format: ParserType::Markdown,
+                        shell: None,
                     }],
                 },
             ),
@@ -556,7 +569,6 @@ mod tests {
                                 }),
                                 ..Default::default()
                             },
-                            ..Default::default()
                         },
                         result: Err(TestCaseError::ValidationFailed(
                             ValidationFailure::MalformedOutput(Diff::new(vec![
@@ -571,6 +583,7 @@ mod tests {
                         )),
                         escaping: Escaper::default(),
                         format: ParserType::Markdown,
+                        shell: None,
                     }],
                 },
             ),
@@ -607,7 +620,6 @@ mod tests {
                                 }),
                                 ..Default::default()
                             },
-                            ..Default::default()
                         },
                         result: Err(TestCaseError::ValidationFailed(
                             ValidationFailure::MalformedOutput(Diff::new(vec![
@@ -622,6 +634,7 @@ mod tests {
                         )),
                         escaping: Escaper::default(),
                         format: ParserType::Markdown,
+                        shell: None,
                     }],
                 },
             ),
@@ -666,6 +679,7 @@ mod tests {
                         )),
                         escaping: Escaper::default(),
                         format: ParserType::Markdown,
+                        shell: None,
                     }],
                 },
             ),
@@ -733,6 +747,7 @@ mod tests {
                             )),
                             escaping: Escaper::default(),
                             format: ParserType::Markdown,
+                            shell: None,
                         },
                         Outcome {
                             location: None,
@@ -763,10 +778,221 @@ mod tests {
                             )),
                             escaping: Escaper::default(),
                             format: ParserType::Markdown,
+                            shell: None,
                         },
                     ],
                 },
             ),
+            (
+                "updated_marked_output",
+                UpdateGeneratorTest {
+                    original_document: &([
+                        "This is a test",
+                        "",
+                        "```scrut {output_stream: marked}",
+                        "$ the command",
+                        "@STDERR",
+                        "old error",
+                        "@STDOUT",
+                        "same output",
+                        "```",
+                    ]
+                    .join("\n")
+                        + "\n"),
+
+                    outcomes: vec![Outcome {
+                        location: None,
+                        output: ("same output\n", "new error\n").into(),
+                        testcase: TestCase {
+                            title: "This is a test".to_string(),
+                            shell_expression: "the command".to_string(),
+                            body: ValidationBody::MarkedOutput(MarkedOutputBody {
+                                sections: vec![
+                                    MarkedSection {
+                                        stream: MarkedStream::Stderr,
+                                        expectations: vec![test_expectation!("equal", "old error")],
+                                    },
+                                    MarkedSection {
+                                        stream: MarkedStream::Stdout,
+                                        expectations: vec![test_expectation!(
+                                            "equal",
+                                            "same output",
+                                            false,
+                                            false,
+                                            "same output"
+                                        )],
+                                    },
+                                ],
+                            }),
+                            exit_code: None,
+                            line_number: 234,
+                            config: TestCaseConfig {
+                                output_stream: Some(OutputStreamControl::Marked),
+                                ..Default::default()
+                            },
+                        },
+                        result: Err(TestCaseError::ValidationFailed(
+                            ValidationFailure::MalformedMarkedOutput(vec![
+                                MarkedDiff {
+                                    stream: MarkedStream::Stderr,
+                                    diff: Diff::new(vec![
+                                        DiffLine::UnmatchedExpectation {
+                                            index: 0,
+                                            expectation: test_expectation!("equal", "old error"),
+                                        },
+                                        DiffLine::UnexpectedLines {
+                                            lines: vec![(
+                                                0,
+                                                formatln!("new error").as_bytes().to_vec(),
+                                            )],
+                                        },
+                                    ]),
+                                },
+                                MarkedDiff {
+                                    stream: MarkedStream::Stdout,
+                                    diff: Diff::new(vec![DiffLine::MatchedExpectation {
+                                        index: 0,
+                                        expectation: test_expectation!(
+                                            "equal",
+                                            "same output",
+                                            false,
+                                            false,
+                                            "same output"
+                                        ),
+                                        lines: vec![(
+                                            0,
+                                            formatln!("same output").as_bytes().to_vec(),
+                                        )],
+                                    }]),
+                                },
+                            ]),
+                        )),
+                        escaping: Escaper::default(),
+                        format: ParserType::Markdown,
+                        shell: None,
+                    }],
+                },
+            ),
+            (
+                "updated_marked_output_new_section",
+                UpdateGeneratorTest {
+                    original_document: &([
+                        "This is a test",
+                        "",
+                        "```scrut {output_stream: marked}",
+                        "$ the command",
+                        "@STDOUT",
+                        "same output",
+                        "```",
+                    ]
+                    .join("\n")
+                        + "\n"),
+
+                    outcomes: vec![Outcome {
+                        location: None,
+                        output: ("same output\n", "new error\n").into(),
+                        testcase: TestCase {
+                            title: "This is a test".to_string(),
+                            shell_expression: "the command".to_string(),
+                            body: ValidationBody::MarkedOutput(MarkedOutputBody {
+                                sections: vec![MarkedSection {
+                                    stream: MarkedStream::Stdout,
+                                    expectations: vec![test_expectation!(
+                                        "equal",
+                                        "same output",
+                                        false,
+                                        false,
+                                        "same output"
+                                    )],
+                                }],
+                            }),
+                            exit_code: None,
+                            line_number: 234,
+                            config: TestCaseConfig {
+                                output_stream: Some(OutputStreamControl::Marked),
+                                ..Default::default()
+                            },
+                        },
+                        result: Err(TestCaseError::ValidationFailed(
+                            ValidationFailure::MalformedMarkedOutput(vec![
+                                MarkedDiff {
+                                    stream: MarkedStream::Stdout,
+                                    diff: Diff::new(vec![DiffLine::MatchedExpectation {
+                                        index: 0,
+                                        expectation: test_expectation!(
+                                            "equal",
+                                            "same output",
+                                            false,
+                                            false,
+                                            "same output"
+                                        ),
+                                        lines: vec![(
+                                            0,
+                                            formatln!("same output").as_bytes().to_vec(),
+                                        )],
+                                    }]),
+                                },
+                                MarkedDiff {
+                                    stream: MarkedStream::Stderr,
+                                    diff: Diff::new(vec![DiffLine::UnexpectedLines {
+                                        lines: vec![(
+                                            0,
+                                            formatln!("new error").as_bytes().to_vec(),
+                                        )],
+                                    }]),
+                                },
+                            ]),
+                        )),
+                        escaping: Escaper::default(),
+                        format: ParserType::Markdown,
+                        shell: None,
+                    }],
+                },
+            ),
+            (
+                "updated_marked_output_changed_exit_code",
+                UpdateGeneratorTest {
+                    original_document: &([
+                        "This is a test",
+                        "",
+                        "```scrut {output_stream: marked}",
+                        "$ the command",
+                        "@STDOUT",
+                        "same output",
+                        "```",
+                    ]
+                    .join("\n")
+                        + "\n"),
+
+                    outcomes: vec![Outcome {
+                        location: None,
+                        output: ("same output\n", "an error", Some(10)).into(),
+                        testcase: TestCase {
+                            title: "This is a test".to_string(),
+                            shell_expression: "the command".to_string(),
+                            body: ValidationBody::MarkedOutput(MarkedOutputBody {
+                                sections: vec![MarkedSection {
+                                    stream: MarkedStream::Stdout,
+                                    expectations: vec![test_expectation!("equal", "same output")],
+                                }],
+                            }),
+                            exit_code: None,
+                            line_number: 234,
+                            config: TestCaseConfig {
+                                output_stream: Some(OutputStreamControl::Marked),
+                                ..Default::default()
+                            },
+                        },
+                        result: Err(TestCaseError::InvalidExitCode {
+                            actual: 10,
+                            expected: 0,
+                        }),
+                        escaping: Escaper::default(),
+                        format: ParserType::Markdown,
+                        shell: None,
+                    }],
+                },
+            ),
         ];
 
         let generator = MarkdownUpdateGenerator::default();
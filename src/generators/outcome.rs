@@ -5,10 +5,14 @@
  * LICENSE file in the root directory of this source tree.
  */
 
+use std::borrow::Cow;
+
 use anyhow::Result;
 use anyhow::bail;
 
+use crate::diff::Diff;
 use crate::diff::DiffLine;
+use crate::expectation::UNORDERED_MODIFIER;
 use crate::formatln;
 use crate::lossy_string;
 use crate::newline::BytesNewline;
@@ -16,7 +20,11 @@ use crate::newline::SplitLinesByNewline;
 use crate::newline::StringNewline;
 use crate::outcome::Outcome;
 use crate::output::ExitStatus;
+use crate::output::OutputStream;
 use crate::testcase::TestCaseError;
+use crate::validation::MarkedOutputBody;
+use crate::validation::MarkedStream;
+use crate::validation::ValidationBody;
 use crate::validation::ValidationFailure;
 
 pub(super) trait OutcomeTestGenerator {
@@ -38,60 +46,142 @@ impl Outcome {
         generated
     }
 
+    /// Generate the expectations from a diff, that is all matching
+    /// expectations and the actual recorded output lines in their place
+    fn generate_testcase_diff(&self, diff: &Diff) -> String {
+        let mut generated = String::new();
+        for (diff_line, unordered) in diff.lines_in_expectation_order() {
+            match diff_line {
+                DiffLine::MatchedExpectation {
+                    index: _,
+                    expectation,
+                    lines: _,
+                } => generated.push_str(&expectation.original_string().assure_newline()),
+                // negated expectations are kept, unless the output violates them
+                DiffLine::NegatedExpectation {
+                    index: _,
+                    expectation,
+                    lines,
+                } if lines.is_empty() => {
+                    generated.push_str(&expectation.original_string().assure_newline())
+                }
+                DiffLine::UnexpectedLines { lines } => {
+                    // lines in between the matches of an unordered group
+                    // become part of that group
+                    let unordered = if unordered {
+                        format!(" {UNORDERED_MODIFIER}")
+                    } else {
+                        String::new()
+                    };
+                    for (_, line) in lines {
+                        let suffix = if line.ends_with(b"\n") {
+                            ""
+                        } else {
+                            " (no-eol)"
+                        };
+                        let line = formatln!(
+                            "{}{}{}",
+                            self.escaping
+                                .escaped_expectation((&line[..]).trim_newlines()),
+                            suffix,
+                            unordered
+                        );
+                        generated.push_str(&line)
+                    }
+                }
+                _ => continue,
+            }
+        }
+        generated
+    }
+
+    /// Generate the `@STDOUT` and `@STDERR` sections of a test case, given the
+    /// content of each stream. Sections are written in the order they have in
+    /// the test case, followed by streams that have content but no section.
+    fn generate_testcase_marked<F>(&self, mut generate_stream: F) -> String
+    where
+        F: FnMut(MarkedStream) -> String,
+    {
+        let body = match self.testcase.body {
+            ValidationBody::MarkedOutput(ref body) => Cow::Borrowed(body),
+            _ => Cow::Owned(MarkedOutputBody::default()),
+        };
+        let streams = body.sections.iter().map(|section| section.stream).chain(
+            MarkedStream::ALL
+                .into_iter()
+                .filter(|stream| body.section(*stream).is_none()),
+        );
+        let mut generated = String::new();
+        for stream in streams {
+            let content = generate_stream(stream);
+            if body.section(stream).is_some() || !content.is_empty() {
+                generated.push_str(&formatln!("{}", stream.marker()));
+                generated.push_str(&content);
+            }
+        }
+        generated
+    }
+
     fn generate_testcase_exit_code(&self) -> Option<String> {
         match &self.output.exit_code {
             ExitStatus::Code(code) if *code != 0 => Some(formatln!("[{}]", code)),
             _ => None,
         }
     }
+
+    /// Generate the test case as it is, with its original expectations
+    fn generate_testcase_unchanged(&self) -> String {
+        let mut generated = self.generate_testcase_expression();
+        self.testcase.expectations().iter().for_each(|expectation| {
+            generated.push_str(&expectation.original_string().assure_newline())
+        });
+        match self.testcase.body {
+            ValidationBody::MarkedOutput(ref body) => {
+                generated.push_str(&self.generate_testcase_marked(|stream| {
+                    body.expectations(stream)
+                        .iter()
+                        .map(|expectation| {
+                            expectation.original_string().assure_newline().to_string()
+                        })
+                        .collect()
+                }))
+            }
+            ValidationBody::Interactive(ref body) => body
+                .directives
+                .iter()
+                .for_each(|directive| generated.push_str(&directive.to_string().assure_newline())),
+            _ => {}
+        }
+        if let Some(exit_code) = self.generate_testcase_exit_code() {
+            generated.push_str(&exit_code)
+        }
+        generated
+    }
 }
 
 impl OutcomeTestGenerator for Outcome {
     fn generate_testcase(&self) -> Result<String> {
         match &self.result {
-            Ok(_) => {
-                let mut generated = self.generate_testcase_expression();
-                self.testcase.expectations().iter().for_each(|expectation| {
-                    generated.push_str(&expectation.original_string().assure_newline())
-                });
-                if let Some(exit_code) = self.generate_testcase_exit_code() {
-                    generated.push_str(&exit_code)
-                }
-                Ok(generated)
-            }
+            Ok(_) => Ok(self.generate_testcase_unchanged()),
             Err(err) => match err {
                 TestCaseError::ValidationFailed(failure) => match failure {
                     ValidationFailure::MalformedOutput(diff) => {
                         let mut generated = self.generate_testcase_expression();
-
-                        // output the actual recorded output lines
-                        for diff_line in diff.lines.iter() {
-                            match diff_line {
-                                DiffLine::MatchedExpectation {
-                                    index: _,
-                                    expectation,
-                                    lines: _,
-                                } => generated
-                                    .push_str(&expectation.original_string().assure_newline()),
-                                DiffLine::UnexpectedLines { lines } => {
-                                    for (_, line) in lines {
-                                        let suffix = if line.ends_with(b"\n") {
-                                            ""
-                                        } else {
-                                            " (no-eol)"
-                                        };
-                                        let line = formatln!(
-                                            "{}{}",
-                                            self.escaping
-                                                .escaped_expectation((&line[..]).trim_newlines()),
-                                            suffix
-                                        );
-                                        generated.push_str(&line)
-                                    }
-                                }
-                                _ => continue,
-                            }
+                        generated.push_str(&self.generate_testcase_diff(diff));
+                        if let Some(exit_code) = self.generate_testcase_exit_code() {
+                            generated.push_str(&exit_code)
                         }
+                        Ok(generated)
+                    }
+                    ValidationFailure::MalformedMarkedOutput(diffs) => {
+                        let mut generated = self.generate_testcase_expression();
+                        generated.push_str(&self.generate_testcase_marked(|stream| {
+                            diffs
+                                .iter()
+                                .find(|marked| marked.stream == stream)
+                                .map(|marked| self.generate_testcase_diff(&marked.diff))
+                                .unwrap_or_default()
+                        }));
                         if let Some(exit_code) = self.generate_testcase_exit_code() {
                             generated.push_str(&exit_code)
                         }
@@ -100,17 +190,30 @@ impl OutcomeTestGenerator for Outcome {
                     ValidationFailure::JsonSchemaFailed(_) => {
                         bail!("cannot generate json_schema testcase")
                     }
+                    ValidationFailure::InteractiveFailed(_) => {
+                        bail!("cannot generate interactive testcase")
+                    }
                 },
                 TestCaseError::InvalidExitCode {
                     actual,
                     expected: _,
                 } => {
                     let mut generated = self.generate_testcase_expression();
-                    let mut output = self.output.stdout.to_output_string(None, &self.escaping);
-                    if !output.is_empty() && !output.ends_with('\n') {
-                        output.push_str(" (no-eol)\n")
+                    let generate_stream = |stream: &OutputStream| {
+                        let mut output = stream.to_output_string(None, &self.escaping);
+                        if !output.is_empty() && !output.ends_with('\n') {
+                            output.push_str(" (no-eol)\n")
+                        }
+                        output
+                    };
+                    if self.testcase.config.is_marked() {
+                        generated.push_str(&self.generate_testcase_marked(|stream| match stream {
+                            MarkedStream::Stdout => generate_stream(&self.output.stdout),
+                            MarkedStream::Stderr => generate_stream(&self.output.stderr),
+                        }));
+                    } else {
+                        generated.push_str(&generate_stream(&self.output.stdout));
                     }
-                    generated.push_str(&output);
                     generated.push_str(&formatln!("[{}]", *actual));
                     Ok(generated)
                 }
@@ -120,6 +223,12 @@ impl OutcomeTestGenerator for Outcome {
                 TestCaseError::Timeout => {
                     bail!("cannot generate timed out testcase")
                 }
+                // the output of a test case that ran too long is as expected
+                TestCaseError::MaxDurationExceeded { .. }
+                | TestCaseError::MaxRssExceeded { .. } => Ok(self.generate_testcase_unchanged()),
+                TestCaseError::LimitExceeded { limit, .. } => {
+                    bail!("cannot generate testcase that exceeded resource limit {limit}")
+                }
                 TestCaseError::Skipped => {
                     bail!("cannot generate skipped testcase")
                 }
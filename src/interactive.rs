@@ -0,0 +1,264 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Building blocks of the interactive validation mode, in which the shell
+//! expression of a [`crate::testcase::TestCase`] is executed in a pseudo
+//! terminal and driven by a script of [`InteractiveDirective`]s.
+
+use std::fmt::Display;
+
+use anyhow::Result;
+use anyhow::bail;
+use serde::Serialize;
+
+use crate::expectation::Expectation;
+use crate::expectation::ExpectationMaker;
+
+/// A single step of the script that drives an interactive test case. Each
+/// directive is written in one line of the test case body:
+///
+/// ```bnf
+/// <directive> ::= "WAIT " <expectation>
+///               | "SEND_KEYS " <keys>
+///               | "ASSERT " <expectation>
+///               | "ASSERT_TRANSCRIPT " <expectation>
+/// ```
+///
+/// Expectations are written exactly like output expectations (e.g.
+/// `Continue? (glob)`) and are matched against single lines of the screen or
+/// the transcript.
+#[derive(Clone, Debug, PartialEq)]
+pub enum InteractiveDirective {
+    /// Wait until any line of the screen matches the expectation
+    Wait(Expectation),
+
+    /// Send the keys to the terminal, see [`encode_keys`]
+    SendKeys(String),
+
+    /// Any line of the screen must match the expectation
+    Assert(Expectation),
+
+    /// Any line of the transcript (all output so far) must match the expectation
+    AssertTranscript(Expectation),
+}
+
+impl InteractiveDirective {
+    /// Parse a directive from a line of a test case body
+    pub fn parse(line: &str, expectation_maker: &ExpectationMaker) -> Result<Self> {
+        let (name, argument) = line.split_once(' ').unwrap_or((line, ""));
+        Ok(match name {
+            "WAIT" => Self::Wait(expectation_maker.parse(argument)?),
+            "SEND_KEYS" => Self::SendKeys(argument.to_string()),
+            "ASSERT" => Self::Assert(expectation_maker.parse(argument)?),
+            "ASSERT_TRANSCRIPT" => Self::AssertTranscript(expectation_maker.parse(argument)?),
+            _ => bail!(
+                "unknown interactive directive `{name}`, expected one of WAIT, SEND_KEYS, ASSERT or ASSERT_TRANSCRIPT"
+            ),
+        })
+    }
+}
+
+impl Display for InteractiveDirective {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Wait(expectation) => write!(f, "WAIT {}", expectation.original_string()),
+            Self::SendKeys(keys) => write!(f, "SEND_KEYS {keys}"),
+            Self::Assert(expectation) => write!(f, "ASSERT {}", expectation.original_string()),
+            Self::AssertTranscript(expectation) => {
+                write!(f, "ASSERT_TRANSCRIPT {}", expectation.original_string())
+            }
+        }
+    }
+}
+
+impl Serialize for InteractiveDirective {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+/// Translate the argument of a `SEND_KEYS` directive into the bytes that are
+/// written to the terminal. Text is sent as-is, while named keys are written
+/// in angle brackets, e.g. `y<Enter>`, `<Down><Down><Enter>` or `<Ctrl-C>`.
+/// Unknown names are sent verbatim, including the brackets.
+pub fn encode_keys(keys: &str) -> Vec<u8> {
+    let mut encoded = vec![];
+    let mut remaining = keys;
+    while let Some(start) = remaining.find('<') {
+        encoded.extend(&remaining.as_bytes()[..start]);
+        remaining = &remaining[start..];
+        let Some(end) = remaining.find('>') else {
+            break;
+        };
+        match encode_named_key(&remaining[1..end]) {
+            Some(key) => encoded.extend(key),
+            None => encoded.extend(&remaining.as_bytes()[..=end]),
+        }
+        remaining = &remaining[end + 1..];
+    }
+    encoded.extend(remaining.as_bytes());
+    encoded
+}
+
+fn encode_named_key(name: &str) -> Option<Vec<u8>> {
+    let key: &[u8] = match name {
+        "Enter" => b"\r",
+        "Tab" => b"\t",
+        "Space" => b" ",
+        "Esc" | "Escape" => b"\x1b",
+        "Backspace" => b"\x7f",
+        "Up" => b"\x1b[A",
+        "Down" => b"\x1b[B",
+        "Right" => b"\x1b[C",
+        "Left" => b"\x1b[D",
+        "Home" => b"\x1b[H",
+        "End" => b"\x1b[F",
+        "Delete" => b"\x1b[3~",
+        "PageUp" => b"\x1b[5~",
+        "PageDown" => b"\x1b[6~",
+        "Lt" => b"<",
+        _ => {
+            let letter = name.strip_prefix("Ctrl-")?;
+            let [letter] = letter.as_bytes() else {
+                return None;
+            };
+            if !letter.is_ascii_alphabetic() {
+                return None;
+            }
+            return Some(vec![letter.to_ascii_lowercase() - b'a' + 1]);
+        }
+    };
+    Some(key.to_vec())
+}
+
+/// Whether any of the given lines (without trailing newline) matches the
+/// expectation, or none does, if the expectation is negated
+pub fn matches_any_line<S: AsRef<str>>(expectation: &Expectation, lines: &[S]) -> bool {
+    let matched = lines.iter().any(|line| {
+        let mut line = line.as_ref().as_bytes().to_vec();
+        line.push(b'\n');
+        expectation.matches(&line)
+    });
+    matched != expectation.negated
+}
+
+/// The record of the execution of an interactive test case, that is later
+/// used to validate the directive script.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct InteractiveSession {
+    /// One step per directive that was processed, in order. If a `WAIT`
+    /// directive timed out, then no further directives were processed.
+    pub steps: Vec<InteractiveStep>,
+
+    /// The lines of the screen at the end of the execution
+    pub screen: Vec<String>,
+}
+
+/// The processing of a single directive in an [`InteractiveSession`]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct InteractiveStep {
+    /// The lines of the screen after the directive was processed
+    pub screen: Vec<String>,
+
+    /// Whether the directive was a `WAIT` that did not see the expected
+    /// content before the configured timeout
+    pub timed_out: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InteractiveDirective;
+    use super::encode_keys;
+    use super::matches_any_line;
+    use crate::expectation::tests::expectation_maker;
+    use crate::test_expectation;
+
+    #[test]
+    fn test_parse_directives() {
+        let maker = expectation_maker();
+        let tests = [
+            (
+                "WAIT Continue? (glob)",
+                InteractiveDirective::Wait(test_expectation!("glob", "Continue?")),
+            ),
+            (
+                "SEND_KEYS y<Enter>",
+                InteractiveDirective::SendKeys("y<Enter>".into()),
+            ),
+            (
+                "ASSERT Done",
+                InteractiveDirective::Assert(test_expectation!("equal", "Done")),
+            ),
+            (
+                "ASSERT_TRANSCRIPT ^ok$ (regex)",
+                InteractiveDirective::AssertTranscript(test_expectation!("regex", "^ok$")),
+            ),
+        ];
+        for (line, expected) in tests {
+            let directive = InteractiveDirective::parse(line, &maker)
+                .unwrap_or_else(|err| panic!("parse {line:?}: {err}"));
+            assert_eq!(expected, directive, "for {line:?}");
+            assert_eq!(line, directive.to_string(), "render {line:?}");
+        }
+    }
+
+    #[test]
+    fn test_parse_unknown_directive_fails() {
+        let maker = expectation_maker();
+        let err = InteractiveDirective::parse("TYPE foo", &maker).expect_err("must fail");
+        assert!(
+            err.to_string()
+                .contains("unknown interactive directive `TYPE`"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_encode_keys() {
+        let tests: &[(&str, &[u8])] = &[
+            ("abc", b"abc"),
+            ("y<Enter>", b"y\r"),
+            ("<Down><Down><Enter>", b"\x1b[B\x1b[B\r"),
+            ("<Ctrl-C>", b"\x03"),
+            ("<Ctrl-d>", b"\x04"),
+            ("a <Lt>b>", b"a <b>"),
+            ("<Unknown> <", b"<Unknown> <"),
+        ];
+        for (keys, expected) in tests {
+            assert_eq!(expected.to_vec(), encode_keys(keys), "for {keys:?}");
+        }
+    }
+
+    #[test]
+    fn test_matches_any_line() {
+        let lines = ["Some header", "Continue? [y/N]", ""];
+        assert!(matches_any_line(
+            &test_expectation!("glob", "Continue?*"),
+            &lines
+        ));
+        assert!(matches_any_line(
+            &test_expectation!("equal", "Some header"),
+            &lines
+        ));
+        assert!(!matches_any_line(
+            &test_expectation!("equal", "Continue?"),
+            &lines
+        ));
+        let maker = expectation_maker();
+        assert!(matches_any_line(
+            &maker.parse("Error* (!glob)").expect("parse"),
+            &lines
+        ));
+        assert!(!matches_any_line(
+            &maker.parse("Some header (!)").expect("parse"),
+            &lines
+        ));
+    }
+}
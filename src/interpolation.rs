@@ -64,13 +64,16 @@ pub fn interpolate_expectation(
         return Ok(expectation.clone());
     }
     let maker = ExpectationMaker::new(RuleRegistry::default());
-    maker.make(
+    let mut interpolated = maker.make(
         &kind,
         &interpolated_expr,
         optional,
         multiline,
         &expectation.original_string(),
-    )
+    )?;
+    interpolated.unordered = expectation.unordered;
+    interpolated.negated = expectation.negated;
+    Ok(interpolated)
 }
 
 #[cfg(test)]
@@ -16,6 +16,7 @@ pub mod escaping;
 pub mod executors;
 pub mod expectation;
 pub mod generators;
+pub mod interactive;
 pub mod interpolation;
 pub mod newline;
 pub mod outcome;
@@ -12,6 +12,7 @@ use serde::ser::SerializeMap;
 
 use crate::escaping::Escaper;
 use crate::output::Output;
+use crate::output::duration_ms;
 use crate::parsers::parser::ParserType;
 use crate::testcase::Result as TestCaseResult;
 use crate::testcase::TestCase;
@@ -22,6 +23,10 @@ pub struct Outcome {
     /// The path / URL of the test
     pub location: Option<String>,
 
+    /// The shell the testcase was executed in, if the document was executed
+    /// in multiple shells
+    pub shell: Option<String>,
+
     /// The output that this outcome describes
     pub output: Output,
 
@@ -47,13 +52,31 @@ impl Serialize for Outcome {
         if self.location.is_some() {
             count += 1;
         }
+        if self.shell.is_some() {
+            count += 1;
+        }
         if self.result.is_err() {
             count += 1;
         }
+        if self.output.duration.is_some() {
+            count += 1;
+        }
+        if self.output.resource_usage.is_some() {
+            count += 1;
+        }
+        if !self.output.previous_attempts.is_empty() {
+            count += 2;
+        }
+        if self.output.always_run {
+            count += 1;
+        }
         let mut outcome = serializer.serialize_map(Some(count))?;
         if let Some(ref location) = self.location {
             outcome.serialize_entry("location", location)?;
         }
+        if let Some(ref shell) = self.shell {
+            outcome.serialize_entry("shell", shell)?;
+        }
         match &self.result {
             Err(err) => {
                 outcome.serialize_entry("output", &self.output)?;
@@ -67,14 +90,31 @@ impl Serialize for Outcome {
                 outcome.serialize_entry("result", &map)?;
             }
         }
+        if let Some(duration) = self.output.duration {
+            outcome.serialize_entry("duration_ms", &duration_ms(duration))?;
+        }
+        if let Some(ref resource_usage) = self.output.resource_usage {
+            outcome.serialize_entry("resource_usage", resource_usage)?;
+        }
+        if !self.output.previous_attempts.is_empty() {
+            outcome.serialize_entry("attempts", &(self.output.previous_attempts.len() + 1))?;
+            outcome.serialize_entry("previous_attempts", &self.output.previous_attempts)?;
+        }
+        if self.output.always_run {
+            outcome.serialize_entry("always_run", &true)?;
+        }
         outcome.end()
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
     use super::Outcome;
     use crate::escaping::Escaper;
+    use crate::output::Output;
+    use crate::output::ResourceUsage;
     use crate::parsers::parser::ParserType;
     use crate::testcase::TestCaseError;
     use crate::validation::OutputBody;
@@ -107,6 +147,7 @@ mod tests {
                     }),
                     escaping: Escaper::default(),
                     format: ParserType::Markdown,
+                    shell: None,
                 },
             ),
             (
@@ -127,6 +168,124 @@ mod tests {
                     result: Ok(()),
                     escaping: Escaper::default(),
                     format: ParserType::Markdown,
+                    shell: None,
+                },
+            ),
+            (
+                "success_with_duration",
+                Outcome {
+                    location: Some("path/file.md".to_string()),
+                    output: Output {
+                        duration: Some(Duration::from_millis(1234)),
+                        ..("stdout", "stderr", Some(123)).into()
+                    },
+                    testcase: TestCase {
+                        title: "the title".to_string(),
+                        shell_expression: "the command".to_string(),
+                        body: ValidationBody::Output(OutputBody {
+                            expectations: vec![test_expectation!("equal", "foo")],
+                        }),
+                        exit_code: Some(123),
+                        line_number: 234,
+                        ..Default::default()
+                    },
+                    result: Ok(()),
+                    escaping: Escaper::default(),
+                    format: ParserType::Markdown,
+                    shell: None,
+                },
+            ),
+            (
+                "success_with_resource_usage",
+                Outcome {
+                    location: Some("path/file.md".to_string()),
+                    output: Output {
+                        duration: Some(Duration::from_millis(1234)),
+                        resource_usage: Some(ResourceUsage {
+                            max_rss: 12 * 1024 * 1024,
+                            user_time: Duration::from_millis(345),
+                            system_time: Duration::from_millis(67),
+                        }),
+                        ..("stdout", "stderr", Some(123)).into()
+                    },
+                    testcase: TestCase {
+                        title: "the title".to_string(),
+                        shell_expression: "the command".to_string(),
+                        body: ValidationBody::Output(OutputBody {
+                            expectations: vec![test_expectation!("equal", "foo")],
+                        }),
+                        exit_code: Some(123),
+                        line_number: 234,
+                        ..Default::default()
+                    },
+                    result: Ok(()),
+                    escaping: Escaper::default(),
+                    format: ParserType::Markdown,
+                    shell: None,
+                },
+            ),
+            (
+                "success_after_retry",
+                Outcome {
+                    location: Some("path/file.md".to_string()),
+                    output: Output {
+                        previous_attempts: vec![("stdout", "stderr", Some(1)).into()],
+                        ..("stdout", "stderr", Some(0)).into()
+                    },
+                    testcase: TestCase {
+                        title: "the title".to_string(),
+                        shell_expression: "the command".to_string(),
+                        body: ValidationBody::Output(OutputBody {
+                            expectations: vec![test_expectation!("equal", "foo")],
+                        }),
+                        line_number: 234,
+                        ..Default::default()
+                    },
+                    result: Ok(()),
+                    escaping: Escaper::default(),
+                    format: ParserType::Markdown,
+                    shell: None,
+                },
+            ),
+            (
+                "success_always_run",
+                Outcome {
+                    location: Some("path/file.md".to_string()),
+                    output: Output {
+                        always_run: true,
+                        ..("stdout", "stderr", Some(0)).into()
+                    },
+                    testcase: TestCase {
+                        title: "the title".to_string(),
+                        shell_expression: "the command".to_string(),
+                        line_number: 234,
+                        ..Default::default()
+                    },
+                    result: Ok(()),
+                    escaping: Escaper::default(),
+                    format: ParserType::Markdown,
+                    shell: None,
+                },
+            ),
+            (
+                "error_in_shell",
+                Outcome {
+                    location: Some("path/file.md".to_string()),
+                    output: ("stdout", "stderr", Some(123)).into(),
+                    testcase: TestCase {
+                        title: "the title".to_string(),
+                        shell_expression: "the command".to_string(),
+                        exit_code: Some(234),
+                        line_number: 234,
+                        ..Default::default()
+                    },
+                    result: Err(TestCaseError::InvalidExitCode {
+                        actual: 123,
+                        expected: 234,
+                    }),
+                    escaping: Escaper::default(),
+                    format: ParserType::Markdown,
+                    shell: Some("/bin/dash".to_string()),
                 },
             ),
         ];
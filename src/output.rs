@@ -16,6 +16,7 @@ use serde::ser::SerializeMap;
 use crate::config::DEFAULT_SKIP_DOCUMENT_CODE;
 use crate::escaping::Escaper;
 use crate::formatln;
+use crate::interactive::InteractiveSession;
 use crate::lossy_string;
 use crate::newline::SplitLinesByNewline;
 use crate::signal::KillSignal;
@@ -29,6 +30,36 @@ pub struct DetachedProcess {
     pub signal: KillSignal,
 }
 
+/// Resources that were used by an execution, including all processes it
+/// started and waited for. The number of started processes is not included,
+/// because `wait4` does not report it and counting it would require a `pids`
+/// cgroup, that is not available to unprivileged users, or tracing the
+/// execution.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ResourceUsage {
+    /// Peak resident set size of the largest process, in bytes
+    pub max_rss: u64,
+
+    /// CPU time spent in user mode
+    pub user_time: Duration,
+
+    /// CPU time spent in kernel mode
+    pub system_time: Duration,
+}
+
+impl Serialize for ResourceUsage {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(3))?;
+        map.serialize_entry("max_rss", &self.max_rss)?;
+        map.serialize_entry("user_time_ms", &duration_ms(self.user_time))?;
+        map.serialize_entry("system_time_ms", &duration_ms(self.system_time))?;
+        map.end()
+    }
+}
+
 /// Product of a single execution that captures output and status
 #[derive(Clone)]
 pub struct Output {
@@ -49,6 +80,32 @@ pub struct Output {
     /// Environment variables captured from the shell after execution.
     /// Used for interpolation of expectations.
     pub captured_env: BTreeMap<String, String>,
+
+    /// The record of the terminal session, if the execution was run in
+    /// interactive mode.
+    pub interactive: Option<Box<InteractiveSession>>,
+
+    /// The signal that terminated the process of the execution, if it was
+    /// terminated by a signal. Commands that the shell of the execution runs
+    /// and that are terminated by a signal make the shell exit with the exit
+    /// code 128 plus the signal number instead.
+    pub signal: Option<i32>,
+
+    /// The wall-clock time the execution took, if known. Executors that run
+    /// all test cases at once may only provide an estimate.
+    pub duration: Option<Duration>,
+
+    /// The resources the execution used, if known. Only executions that run
+    /// each test case in their own process can measure them.
+    pub resource_usage: Option<ResourceUsage>,
+
+    /// The outputs of previous, failed attempts, if the execution was retried.
+    /// This output is from the last attempt.
+    pub previous_attempts: Vec<Output>,
+
+    /// Whether the test case was executed after the execution of the document
+    /// ended early, because it is configured to always run
+    pub always_run: bool,
 }
 
 impl PartialEq for Output {
@@ -57,6 +114,7 @@ impl PartialEq for Output {
             && self.stdout == other.stdout
             && self.exit_code == other.exit_code
             && self.detached_process == other.detached_process
+            && self.interactive == other.interactive
     }
 }
 
@@ -101,6 +159,12 @@ impl Default for Output {
             exit_code: ExitStatus::Unknown,
             detached_process: None,
             captured_env: BTreeMap::new(),
+            interactive: None,
+            signal: None,
+            duration: None,
+            resource_usage: None,
+            previous_attempts: vec![],
+            always_run: false,
         }
     }
 }
@@ -114,7 +178,7 @@ impl Serialize for Output {
             5
         } else {
             3
-        };
+        } + usize::from(self.interactive.is_some());
         let mut map = serializer.serialize_map(Some(count))?;
         map.serialize_entry("exit_code", &self.exit_code.to_string())?;
         map.serialize_entry("stdout", &lossy_string!((&self.stdout).into()))?;
@@ -123,6 +187,9 @@ impl Serialize for Output {
             map.serialize_entry("detached_process_pid", &detached_process.pid)?;
             map.serialize_entry("detached_process_signal", &detached_process.signal)?;
         }
+        if let Some(ref interactive) = self.interactive {
+            map.serialize_entry("screen", &interactive.screen)?;
+        }
         map.end()
     }
 }
@@ -138,6 +205,12 @@ impl<T: ToString, U: ToString> From<(T, U, Option<i32>)> for Output {
             },
             detached_process: None,
             captured_env: BTreeMap::new(),
+            interactive: None,
+            signal: None,
+            duration: None,
+            resource_usage: None,
+            previous_attempts: vec![],
+            always_run: false,
         }
     }
 }
@@ -156,6 +229,12 @@ impl From<Duration> for Output {
             exit_code: ExitStatus::Timeout(timeout),
             detached_process: None,
             captured_env: BTreeMap::new(),
+            interactive: None,
+            signal: None,
+            duration: None,
+            resource_usage: None,
+            previous_attempts: vec![],
+            always_run: false,
         }
     }
 }
@@ -168,6 +247,12 @@ impl From<ExitStatus> for Output {
             exit_code: status,
             detached_process: None,
             captured_env: BTreeMap::new(),
+            interactive: None,
+            signal: None,
+            duration: None,
+            resource_usage: None,
+            previous_attempts: vec![],
+            always_run: false,
         }
     }
 }
@@ -220,6 +305,11 @@ impl Display for ExitStatus {
     }
 }
 
+/// Returns the duration in whole milliseconds, as used in structured output
+pub fn duration_ms(duration: Duration) -> u64 {
+    duration.as_millis().try_into().unwrap_or(u64::MAX)
+}
+
 impl From<i32> for ExitStatus {
     fn from(value: i32) -> Self {
         ExitStatus::Code(value)
@@ -135,7 +135,6 @@ mod tests {
                 exit_code: None,
                 line_number: 2,
                 config: TestCaseConfig::default_cram(),
-                ..Default::default()
             },
             testcases[0]
         );
@@ -167,7 +166,6 @@ This is a title
                 exit_code: None,
                 line_number: 7,
                 config: TestCaseConfig::default_cram(),
-                ..Default::default()
             },
             testcases[0]
         );
@@ -200,7 +198,6 @@ Title 2
                 exit_code: None,
                 line_number: 8,
                 config: TestCaseConfig::default_cram(),
-                ..Default::default()
             },
             testcases[0]
         );
@@ -235,7 +232,6 @@ This is the yet more title
                 exit_code: None,
                 line_number: 3,
                 config: TestCaseConfig::default_cram(),
-                ..Default::default()
             },
             testcases[0],
             "testcase 1",
@@ -250,7 +246,6 @@ This is the yet more title
                 exit_code: None,
                 line_number: 9,
                 config: TestCaseConfig::default_cram(),
-                ..Default::default()
             },
             testcases[1],
             "testcase 2",
@@ -265,7 +260,6 @@ This is the yet more title
                 exit_code: None,
                 line_number: 12,
                 config: TestCaseConfig::default_cram(),
-                ..Default::default()
             },
             testcases[2],
             "testcase 3",
@@ -300,7 +294,6 @@ The title
                 exit_code: None,
                 line_number: 3,
                 config: TestCaseConfig::default_cram(),
-                ..Default::default()
             },
             testcases[0]
         );
@@ -337,7 +330,6 @@ This has an exit code 3
                 exit_code: Some(4),
                 line_number: 3,
                 config: TestCaseConfig::default_cram(),
-                ..Default::default()
             },
             testcases[0]
         );
@@ -365,7 +357,6 @@ This has an exit code 3
                 exit_code: Some(106),
                 line_number: 12,
                 config: TestCaseConfig::default_cram(),
-                ..Default::default()
             },
             testcases[2]
         );
@@ -423,7 +414,6 @@ This is a title
                 exit_code: None,
                 line_number: 6,
                 config: TestCaseConfig::default_cram(),
-                ..Default::default()
             },
             testcases[0]
         );
@@ -13,11 +13,18 @@ use anyhow::Result;
 use anyhow::bail;
 use regex::Regex;
 
+use crate::config::OutputStreamControl;
 use crate::config::TestCaseConfig;
+use crate::config::TestMode;
 use crate::expectation::Expectation;
 use crate::expectation::ExpectationMaker;
+use crate::interactive::InteractiveDirective;
 use crate::testcase::TestCase;
+use crate::validation::InteractiveBody;
 use crate::validation::JsonSchemaBody;
+use crate::validation::MarkedOutputBody;
+use crate::validation::MarkedSection;
+use crate::validation::MarkedStream;
 use crate::validation::OutputBody;
 use crate::validation::ValidationBody;
 
@@ -70,11 +77,18 @@ pub(super) struct LineParser {
     output_start_index: Option<usize>,
     default_config: TestCaseConfig,
     config: Option<TestCaseConfig>,
-    /// Whether the current testcase is in JSON Schema mode, determined when
-    /// the `$` command line is encountered (from fence config or `%` config).
-    json_schema_mode: bool,
+    /// The validation mode of the current testcase, determined when the `$`
+    /// command line is encountered (from fence config or `%` config).
+    mode: TestMode,
     /// Accumulated body lines for JSON Schema mode.
     json_schema_lines: Vec<String>,
+    /// Accumulated directives for interactive mode.
+    interactive_directives: Vec<InteractiveDirective>,
+    /// Whether output expectations are split into `@STDOUT` and `@STDERR`
+    /// sections, determined alongside the mode.
+    marked: bool,
+    /// Accumulated sections for output mode with `output_stream: marked`.
+    marked_sections: Vec<MarkedSection>,
 }
 
 impl LineParser {
@@ -98,8 +112,11 @@ impl LineParser {
             output_start_index: None,
             default_config,
             config: None,
-            json_schema_mode: false,
+            mode: TestMode::Output,
             json_schema_lines: vec![],
+            interactive_directives: vec![],
+            marked: false,
+            marked_sections: vec![],
         }
     }
 
@@ -131,9 +148,9 @@ impl LineParser {
                 // ensure command can be continued on multiple liens
                 self.position = LineParserPosition::Command;
 
-                // detect json schema mode from accumulated config lines and/or
+                // detect validation mode from accumulated config lines and/or
                 // fence config set via set_testcase_config()
-                self.detect_json_schema_mode();
+                self.detect_mode();
                 // mark the starting index and store the command line
                 self.output_start_index = Some(index);
                 self.command_lines.push(line.into());
@@ -166,10 +183,53 @@ impl LineParser {
             return Ok(CodeType::ExitCode);
         }
 
-        // JSON Schema mode: collect all body lines verbatim
-        if self.json_schema_mode {
-            self.json_schema_lines.push(line.to_string());
-            return Ok(CodeType::Expectation);
+        match self.mode {
+            // JSON Schema mode: collect all body lines verbatim
+            TestMode::JsonSchema => {
+                self.json_schema_lines.push(line.to_string());
+                return Ok(CodeType::Expectation);
+            }
+
+            // interactive mode: each non-empty line is a directive
+            TestMode::Interactive => {
+                if !line.trim().is_empty() {
+                    self.interactive_directives.push(
+                        InteractiveDirective::parse(line, &self.expectation_maker)
+                            .with_context(|| format!("parsing line {}", index + 1))?,
+                    );
+                }
+                return Ok(CodeType::Expectation);
+            }
+            // marked output: expectations follow a `@STDOUT` or `@STDERR` marker
+            TestMode::Output if self.marked => {
+                if let Some(stream) = MarkedStream::from_marker(line) {
+                    if self
+                        .marked_sections
+                        .iter()
+                        .any(|section| section.stream == stream)
+                    {
+                        bail!("line {}: {} section given multiple times", index + 1, line)
+                    }
+                    self.marked_sections.push(MarkedSection {
+                        stream,
+                        expectations: vec![],
+                    });
+                    return Ok(CodeType::Expectation);
+                }
+                let Some(section) = self.marked_sections.last_mut() else {
+                    bail!(
+                        "line {}: output expectation given before `@STDOUT` or `@STDERR` marker",
+                        index + 1
+                    )
+                };
+                section.expectations.push(
+                    self.expectation_maker
+                        .parse(line)
+                        .with_context(|| format!("parsing line {}", index + 1))?,
+                );
+                return Ok(CodeType::Expectation);
+            }
+            TestMode::Output => {}
         }
 
         // anything else: output expectation
@@ -219,14 +279,19 @@ impl LineParser {
             title: self.title.to_owned().unwrap_or_default(),
             shell_expression: self.command_lines.join("\n"),
             exit_code: self.exit_code,
-            body: if self.json_schema_mode {
-                ValidationBody::JsonSchema(JsonSchemaBody {
+            body: match self.mode {
+                TestMode::JsonSchema => ValidationBody::JsonSchema(JsonSchemaBody {
                     schema_source: self.json_schema_lines.join("\n"),
-                })
-            } else {
-                ValidationBody::Output(OutputBody {
+                }),
+                TestMode::Interactive => ValidationBody::Interactive(InteractiveBody {
+                    directives: self.interactive_directives.clone(),
+                }),
+                TestMode::Output if self.marked => ValidationBody::MarkedOutput(MarkedOutputBody {
+                    sections: self.marked_sections.clone(),
+                }),
+                TestMode::Output => ValidationBody::Output(OutputBody {
                     expectations: self.expectations.clone(),
-                })
+                }),
             },
             line_number: self.output_start_index.unwrap_or(line_index) + 1,
             config: self
@@ -243,36 +308,35 @@ impl LineParser {
         !self.command_lines.is_empty() || !self.expectations.is_empty()
     }
 
-    /// Detect whether the current testcase is in JSON Schema validation mode.
+    /// Detect the validation mode of the current testcase and whether its
+    /// output expectations are marked.
     ///
     /// Checks both the fence config (set via `set_testcase_config`) and any
     /// accumulated `%` multiline config lines. Called when the `$` command line
     /// is encountered, before body lines are processed.
-    fn detect_json_schema_mode(&mut self) {
-        // Check fence config first
-        if let Some(ref config) = self.config {
-            if config.is_json_schema() {
-                self.json_schema_mode = true;
-                return;
-            }
-        }
-
-        // Check accumulated % config lines for mode: jsonschema
-        if !self.config_lines.is_empty() {
-            if let Ok(config) =
-                serde_yaml::from_str::<TestCaseConfig>(&self.config_lines.join("\n"))
-            {
-                if config.is_json_schema() {
-                    self.json_schema_mode = true;
-                    return;
-                }
-            }
-        }
-
-        // Check default config
-        if self.default_config.is_json_schema() {
-            self.json_schema_mode = true;
-        }
+    fn detect_mode(&mut self) {
+        // accumulated % config lines override fence config, which overrides
+        // default config
+        let multiline_config = if self.config_lines.is_empty() {
+            None
+        } else {
+            serde_yaml::from_str::<TestCaseConfig>(&self.config_lines.join("\n")).ok()
+        };
+        let configs = [
+            multiline_config.as_ref(),
+            self.config.as_ref(),
+            Some(&self.default_config),
+        ];
+        self.mode = configs
+            .iter()
+            .flatten()
+            .find_map(|config| config.mode.clone())
+            .unwrap_or(TestMode::Output);
+        self.marked = configs
+            .iter()
+            .flatten()
+            .find_map(|config| config.output_stream.clone())
+            == Some(OutputStreamControl::Marked);
     }
 
     fn flush(&mut self) {
@@ -284,8 +348,11 @@ impl LineParser {
         self.exit_code = None;
         self.output_start_index = None;
         self.config = None;
-        self.json_schema_mode = false;
+        self.mode = TestMode::Output;
         self.json_schema_lines = vec![];
+        self.interactive_directives = vec![];
+        self.marked = false;
+        self.marked_sections = vec![];
     }
 }
 
@@ -316,12 +383,18 @@ mod tests {
 
     use super::LineParser;
     use super::extract_exit_code;
+    use crate::config::OutputStreamControl;
     use crate::config::TestCaseConfig;
     use crate::config::TestMode;
     use crate::expectation::tests::expectation_maker;
+    use crate::interactive::InteractiveDirective;
     use crate::test_expectation;
     use crate::testcase::TestCase;
+    use crate::validation::InteractiveBody;
     use crate::validation::JsonSchemaBody;
+    use crate::validation::MarkedOutputBody;
+    use crate::validation::MarkedSection;
+    use crate::validation::MarkedStream;
     use crate::validation::OutputBody;
     use crate::validation::ValidationBody;
 
@@ -748,4 +821,153 @@ mod tests {
             "exit code line should not be in schema body"
         );
     }
+
+    #[test]
+    fn test_interactive_mode_parses_directives() {
+        let mut engine = engine(false, true, None);
+        engine
+            .add_testcase_body("% mode: interactive", 1)
+            .expect("add config");
+        engine.add_testcase_body("$ cmd", 2).expect("add command");
+        engine
+            .add_testcase_body("WAIT Continue? (glob)", 3)
+            .expect("add directive");
+        engine
+            .add_testcase_body("SEND_KEYS y<Enter>", 4)
+            .expect("add directive");
+        engine.add_testcase_body("", 5).expect("add empty line");
+        engine
+            .add_testcase_body("ASSERT Done", 6)
+            .expect("add directive");
+        engine.add_testcase_body("[0]", 7).expect("add exit code");
+        engine.end_testcase(8).expect("testcase ending");
+
+        assert_eq!(1, engine.testcases.len(), "should have one testcase");
+        let tc = &engine.testcases[0];
+        assert_eq!(tc.exit_code, Some(0), "exit code should be captured");
+        assert_eq!(
+            tc.body,
+            ValidationBody::Interactive(InteractiveBody {
+                directives: vec![
+                    InteractiveDirective::Wait(test_expectation!("glob", "Continue?")),
+                    InteractiveDirective::SendKeys("y<Enter>".to_string()),
+                    InteractiveDirective::Assert(test_expectation!("equal", "Done")),
+                ],
+            }),
+            "body should contain the directives, without empty lines"
+        );
+    }
+
+    #[test]
+    fn test_interactive_mode_rejects_unknown_directive() {
+        let mut engine = engine(false, true, None);
+        engine
+            .add_testcase_body("% mode: interactive", 1)
+            .expect("add config");
+        engine.add_testcase_body("$ cmd", 2).expect("add command");
+        let Err(err) = engine.add_testcase_body("TYPE foo", 3) else {
+            panic!("unknown directive must fail");
+        };
+        assert!(
+            format!("{err:#}").contains("unknown interactive directive `TYPE`"),
+            "{err:#}"
+        );
+    }
+
+    #[test]
+    fn test_marked_output_stream_collects_sections() {
+        let mut engine = engine(false, true, None);
+        engine
+            .add_testcase_body("% output_stream: marked", 1)
+            .expect("add config");
+        engine.add_testcase_body("$ cmd", 2).expect("add command");
+        engine.add_testcase_body("@STDERR", 3).expect("add marker");
+        engine
+            .add_testcase_body("an error", 4)
+            .expect("add expectation");
+        engine.add_testcase_body("@STDOUT", 5).expect("add marker");
+        engine
+            .add_testcase_body("some output", 6)
+            .expect("add expectation");
+        engine
+            .add_testcase_body("more output", 7)
+            .expect("add expectation");
+        engine.add_testcase_body("[1]", 8).expect("add exit code");
+        engine.end_testcase(9).expect("testcase ending");
+
+        assert_eq!(1, engine.testcases.len(), "should have one testcase");
+        let tc = &engine.testcases[0];
+        assert_eq!(tc.exit_code, Some(1), "exit code should be captured");
+        assert_eq!(
+            tc.body,
+            ValidationBody::MarkedOutput(MarkedOutputBody {
+                sections: vec![
+                    MarkedSection {
+                        stream: MarkedStream::Stderr,
+                        expectations: vec![test_expectation!("equal", "an error")],
+                    },
+                    MarkedSection {
+                        stream: MarkedStream::Stdout,
+                        expectations: vec![
+                            test_expectation!("equal", "some output"),
+                            test_expectation!("equal", "more output"),
+                        ],
+                    },
+                ],
+            }),
+            "body should contain the sections in order"
+        );
+        assert_eq!(5, tc.expectations_lines(), "markers are body lines");
+    }
+
+    #[test]
+    fn test_marked_output_stream_from_fence_config() {
+        let mut engine = engine(false, true, None);
+        engine.set_testcase_config(TestCaseConfig {
+            output_stream: Some(OutputStreamControl::Marked),
+            ..Default::default()
+        });
+        engine.add_testcase_body("$ cmd", 1).expect("add command");
+        engine.add_testcase_body("@STDOUT", 2).expect("add marker");
+        engine.end_testcase(3).expect("testcase ending");
+
+        assert_eq!(
+            engine.testcases[0].body,
+            ValidationBody::MarkedOutput(MarkedOutputBody {
+                sections: vec![MarkedSection {
+                    stream: MarkedStream::Stdout,
+                    expectations: vec![],
+                }],
+            }),
+        );
+    }
+
+    #[test]
+    fn test_marked_output_stream_errors() {
+        let tests: &[(&str, &[&str], &str)] = &[
+            (
+                "expectation before marker",
+                &["some output"],
+                "line 4: output expectation given before `@STDOUT` or `@STDERR` marker",
+            ),
+            (
+                "repeated marker",
+                &["@STDOUT", "some output", "@STDOUT"],
+                "line 6: @STDOUT section given multiple times",
+            ),
+        ];
+        for (name, lines, expected) in tests {
+            let mut engine = engine(false, true, None);
+            engine
+                .add_testcase_body("% output_stream: marked", 1)
+                .expect("add config");
+            engine.add_testcase_body("$ cmd", 2).expect("add command");
+            let err = lines
+                .iter()
+                .enumerate()
+                .find_map(|(index, line)| engine.add_testcase_body(line, index + 3).err())
+                .unwrap_or_else(|| panic!("{name}: must fail"));
+            assert_eq!(*expected, format!("{err:#}"), "{name}");
+        }
+    }
 }
@@ -131,14 +131,16 @@ impl Parser for MarkdownParser {
                         serde_yaml::from_str(&format!("{{{}}}", config_lines.join_newline()))
                             .context("parse testcase config")?
                     };
-                    for (index, line) in &code_lines {
-                        line_parser.add_testcase_body(line, *index)?;
-                    }
+                    // config must be known before the body is added, as it
+                    // determines how the body is parsed (e.g. `mode`)
                     line_parser.set_testcase_config(
                         parsed_config
                             .with_defaults_from(&config.defaults)
                             .with_defaults_from(&self.base_testcase_config),
                     );
+                    for (index, line) in &code_lines {
+                        line_parser.add_testcase_body(line, *index)?;
+                    }
                     line_parser.end_testcase(code_lines[code_lines.len() - 1].0)?;
                     title_paragraph.clear();
                 }
@@ -443,7 +445,6 @@ hello
                 exit_code: None,
                 line_number: 5,
                 config: TestCaseConfig::default_markdown(),
-                ..Default::default()
             },
             testcases[0]
         );
@@ -486,7 +487,6 @@ hello
                 exit_code: None,
                 line_number: 10,
                 config: TestCaseConfig::default_markdown(),
-                ..Default::default()
             },
             testcases[0]
         );
@@ -527,7 +527,6 @@ hello
                     }),
                     ..TestCaseConfig::default_markdown()
                 }),
-                ..Default::default()
             },
             testcases[0]
         );
@@ -560,7 +559,6 @@ hello
                 exit_code: None,
                 line_number: 9,
                 config: TestCaseConfig::default_markdown(),
-                ..Default::default()
             },
             testcases[0]
         );
@@ -594,7 +592,6 @@ hello
                 exit_code: None,
                 line_number: 9,
                 config: TestCaseConfig::default_markdown(),
-                ..Default::default()
             },
             testcases[0]
         );
@@ -625,7 +622,6 @@ hello
                 exit_code: None,
                 line_number: 7,
                 config: TestCaseConfig::default_markdown(),
-                ..Default::default()
             },
             testcases[0]
         );
@@ -656,7 +652,6 @@ hello
                 exit_code: None,
                 line_number: 7,
                 config: TestCaseConfig::default_markdown(),
-                ..Default::default()
             },
             testcases[0]
         );
@@ -706,7 +701,6 @@ world
                 exit_code: None,
                 line_number: 12,
                 config: TestCaseConfig::default_markdown(),
-                ..Default::default()
             },
             testcases[0]
         );
@@ -720,7 +714,6 @@ world
                 exit_code: None,
                 line_number: 26,
                 config: TestCaseConfig::default_markdown(),
-                ..Default::default()
             },
             testcases[1]
         );
@@ -758,7 +751,6 @@ i am output 3
                 exit_code: None,
                 line_number: 7,
                 config: TestCaseConfig::default_markdown(),
-                ..Default::default()
             },
             testcases[0]
         );
@@ -809,7 +801,6 @@ Hello World
                     exit_code: None,
                     line_number: 5,
                     config: TestCaseConfig::default_markdown(),
-                    ..Default::default()
                 },
                 TestCase {
                     shell_expression: "cat test.md".to_string(),
@@ -827,7 +818,6 @@ Hello World
                     exit_code: None,
                     line_number: 15,
                     config: TestCaseConfig::default_markdown(),
-                    ..Default::default()
                 },
             ],
             testcases
@@ -861,7 +851,6 @@ world
                 exit_code: None,
                 line_number: 5,
                 config: TestCaseConfig::default_markdown(),
-                ..Default::default()
             },],
             testcases
         );
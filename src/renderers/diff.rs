@@ -5,22 +5,32 @@
  * LICENSE file in the root directory of this source tree.
  */
 
+use std::borrow::Cow;
 use std::cmp::Ordering;
 use std::fmt::Display;
+use std::time::Duration;
 
 use anyhow::Result;
 use anyhow::bail;
 
 use super::renderer::ErrorRenderer;
 use super::renderer::Renderer;
+use crate::config::ResourceLimit;
 use crate::diff::Diff;
 use crate::diff::DiffLine;
+use crate::expectation::UNORDERED_MODIFIER;
 use crate::formatln;
 use crate::newline::BytesNewline;
 use crate::outcome::Outcome;
 use crate::parsers::parser::ParserType;
+use crate::testcase::TestCaseError;
+use crate::validation::InteractiveFailure;
+use crate::validation::InteractiveFailureKind;
 use crate::validation::JsonSchemaFailure;
 use crate::validation::JsonSchemaFailureKind;
+use crate::validation::MarkedDiff;
+use crate::validation::MarkedOutputBody;
+use crate::validation::ValidationBody;
 
 /// Renderer that uses the traditional Diff render format
 /// See: <https://en.wikipedia.org/wiki/Diff>
@@ -141,13 +151,97 @@ impl ErrorRenderer for DiffRenderer {
     }
 
     fn render_malformed_output(&self, outcome: &Outcome, diff: &Diff) -> Result<String> {
-        UnifiedDiff::default().render(outcome, diff)
+        let line_number = outcome.testcase.line_number + outcome.testcase.shell_expression_lines();
+        UnifiedDiff::default().render(outcome, diff, line_number)
+    }
+
+    /// Renders a hunk per stream that has differences. Output of streams
+    /// without a section is rendered as a new section at the end of the body.
+    fn render_malformed_marked_output(
+        &self,
+        outcome: &Outcome,
+        diffs: &[MarkedDiff],
+    ) -> Result<String> {
+        let body = match outcome.testcase.body {
+            ValidationBody::MarkedOutput(ref body) => Cow::Borrowed(body),
+            _ => Cow::Owned(MarkedOutputBody::default()),
+        };
+        let line_number = outcome.testcase.line_number + outcome.testcase.shell_expression_lines();
+        let mut output = String::new();
+        for marked in diffs {
+            if !marked.diff.has_differences() {
+                continue;
+            }
+            if body.section(marked.stream).is_some() {
+                output.push_str(&UnifiedDiff::default().render(
+                    outcome,
+                    &marked.diff,
+                    line_number + body.offset(marked.stream),
+                )?);
+                continue;
+            }
+
+            let prefix = line_prefix(outcome);
+            let mut lines = vec![marked.stream.marker().to_string()];
+            for line in &marked.diff.lines {
+                if let DiffLine::UnexpectedLines { lines: unexpected } = line {
+                    for (_, line) in unexpected {
+                        lines.push(String::from_utf8((line as &[u8]).trim_newlines().to_vec())?);
+                    }
+                }
+            }
+            output.push_str(
+                &DiffHeader {
+                    old_start: line_number + body.lines(),
+                    old_length: 0,
+                    new_start: line_number + body.lines(),
+                    new_length: lines.len(),
+                    kind: DiffHeaderKind::MalformedOutput,
+                    title: &join_multiline(&outcome.testcase.title, " * "),
+                }
+                .to_string(),
+            );
+            lines
+                .iter()
+                .for_each(|line| output.push_str(&format!("+{prefix}{line}\n")));
+        }
+        Ok(output)
     }
 
     fn render_timeout(&self, _outcome: &Outcome) -> Result<String> {
         Ok("".into())
     }
 
+    /// The output is as expected, so there is nothing to change
+    fn render_max_duration_exceeded(
+        &self,
+        _outcome: &Outcome,
+        _duration: Duration,
+        _max_duration: Duration,
+    ) -> Result<String> {
+        Ok("".into())
+    }
+
+    /// The output is as expected, so there is nothing to change
+    fn render_max_rss_exceeded(
+        &self,
+        _outcome: &Outcome,
+        _rss: u64,
+        _max_rss: u64,
+    ) -> Result<String> {
+        Ok("".into())
+    }
+
+    /// The output is incomplete, so there is nothing to change
+    fn render_limit_exceeded(
+        &self,
+        _outcome: &Outcome,
+        _limit: &ResourceLimit,
+        _err: &TestCaseError,
+    ) -> Result<String> {
+        Ok("".into())
+    }
+
     fn render_skipped(&self, _outcome: &Outcome) -> Result<String> {
         Ok("".into())
     }
@@ -182,6 +276,39 @@ impl ErrorRenderer for DiffRenderer {
         output.push_str("# ---- JSON SCHEMA VALIDATION FAILED ----\n");
         Ok(output)
     }
+
+    fn render_interactive_failed(
+        &self,
+        outcome: &Outcome,
+        failure: &InteractiveFailure,
+    ) -> Result<String> {
+        let title = join_multiline(&outcome.testcase.title, " * ");
+        let kind_str = match failure.kind {
+            InteractiveFailureKind::WaitTimeout => "WaitTimeout",
+            InteractiveFailureKind::ScreenMismatch => "ScreenMismatch",
+            InteractiveFailureKind::TranscriptMismatch => "TranscriptMismatch",
+        };
+
+        let mut output = String::new();
+        output.push_str("# ---- INTERACTIVE VALIDATION FAILED ----\n");
+        if let Some(ref location) = outcome.location {
+            output.push_str(&format!("# PATH:      {location}\n"));
+        }
+        output.push_str(&format!("# TITLE:     {title}\n"));
+        output.push_str(&format!("# KIND:      {kind_str}\n"));
+        output.push_str(&format!("# DIRECTIVE: {}\n", failure.directive));
+        output.push_str("# SCREEN:\n");
+        let visible = failure
+            .screen
+            .iter()
+            .rposition(|line| !line.is_empty())
+            .map_or(0, |last| last + 1);
+        for line in &failure.screen[..visible] {
+            output.push_str(&format!("# {line}\n"));
+        }
+        output.push_str("# ---- INTERACTIVE VALIDATION FAILED ----\n");
+        Ok(output)
+    }
 }
 
 #[derive(Default)]
@@ -200,8 +327,9 @@ impl UnifiedDiff {
         self.unexpected_lines = vec![];
     }
 
-    fn render(&mut self, outcome: &Outcome, diff: &Diff) -> Result<String> {
-        let line_number = outcome.testcase.line_number + outcome.testcase.shell_expression_lines();
+    /// Render the hunks of the diff, with `line_number` being the line of the
+    /// first expectation the diff was created from
+    fn render(&mut self, outcome: &Outcome, diff: &Diff, line_number: usize) -> Result<String> {
         let title = join_multiline(&outcome.testcase.title, " * ");
         let prefix = line_prefix(outcome);
         let mut output = String::new();
@@ -242,7 +370,7 @@ impl UnifiedDiff {
         }
 
         let mut expectation_index = 0;
-        for line in &diff.lines {
+        for (line, unordered) in diff.lines_in_expectation_order() {
             match line {
                 DiffLine::MatchedExpectation {
                     index,
@@ -259,6 +387,22 @@ impl UnifiedDiff {
                     }
                     self.unmatched_lines.push(expectation.original_string())
                 }
+                // violated negated expectations are removed, as updates do
+                DiffLine::NegatedExpectation {
+                    index,
+                    expectation,
+                    lines,
+                } => {
+                    expectation_index = *index;
+                    if lines.is_empty() {
+                        add_diff_hunk!();
+                    } else {
+                        if self.unmatched_start.is_none() {
+                            self.unmatched_start = Some(*index);
+                        }
+                        self.unmatched_lines.push(expectation.original_string())
+                    }
+                }
                 DiffLine::UnexpectedLines { lines } => {
                     if self.unexpected_start.is_none() {
                         self.unexpected_start = Some(expectation_index)
@@ -267,10 +411,13 @@ impl UnifiedDiff {
                         lines
                             .iter()
                             .map(|(i, l)| {
-                                Ok((
-                                    *i,
-                                    String::from_utf8((l as &[u8]).trim_newlines().to_vec())?,
-                                ))
+                                let mut line =
+                                    String::from_utf8((l as &[u8]).trim_newlines().to_vec())?;
+                                if unordered {
+                                    line.push(' ');
+                                    line.push_str(UNORDERED_MODIFIER);
+                                }
+                                Ok((*i, line))
                             })
                             .collect::<Result<Vec<_>>>()?,
                     );
@@ -352,15 +499,23 @@ mod tests {
     use super::DiffRenderer;
     use crate::diff::Diff;
     use crate::diff::DiffLine;
+    use crate::diff::DiffTool;
     use crate::escaping::Escaper;
+    use crate::expectation::tests::expectation_maker;
     use crate::outcome::Outcome;
     use crate::parsers::parser::ParserType;
     use crate::renderers::renderer::Renderer;
     use crate::test_expectation;
     use crate::testcase::TestCase;
     use crate::testcase::TestCaseError;
+    use crate::validation::InteractiveFailure;
+    use crate::validation::InteractiveFailureKind;
     use crate::validation::JsonSchemaFailure;
     use crate::validation::JsonSchemaFailureKind;
+    use crate::validation::MarkedDiff;
+    use crate::validation::MarkedOutputBody;
+    use crate::validation::MarkedSection;
+    use crate::validation::MarkedStream;
     use crate::validation::OutputBody;
     use crate::validation::ValidationBody;
     use crate::validation::ValidationFailure;
@@ -385,6 +540,7 @@ mod tests {
                 result: Ok(()),
                 escaping: Escaper::default(),
                 format: ParserType::Markdown,
+                shell: None,
             }])
             .expect("render succeeds");
         assert_eq!("", &rendered, "success results are not rendered");
@@ -412,6 +568,7 @@ mod tests {
                 ))),
                 escaping: Escaper::default(),
                 format: ParserType::Markdown,
+                shell: None,
             }])
             .expect("render succeeds");
         insta::assert_snapshot!(rendered);
@@ -444,6 +601,7 @@ mod tests {
                         }),
                         escaping: Escaper::default(),
                         format: *parser_type,
+                        shell: None,
                     }])
                     .expect("render succeeds");
                 insta::assert_snapshot!(format!("invalid_exit_code_{parser_type}"), rendered);
@@ -521,6 +679,7 @@ mod tests {
                             )),
                             escaping: Escaper::default(),
                             format: *parser_type,
+                            shell: None,
                         }])
                         .expect("render succeeds");
                     insta::assert_snapshot!(
@@ -531,6 +690,43 @@ mod tests {
             })
     }
 
+    #[test]
+    fn test_malformed_output_unordered_group() {
+        let maker = expectation_maker();
+        let expectations = [
+            "start",
+            "foo (unordered)",
+            "bar (unordered)",
+            "baz (unordered)",
+        ]
+        .iter()
+        .map(|line| maker.parse(line).expect("parse expectation"))
+        .collect::<Vec<_>>();
+        let diff = DiffTool::new(expectations.clone())
+            .diff(b"start\nbaz\nzoing\nfoo\n")
+            .expect("diff created");
+        let rendered = DiffRenderer::new()
+            .render(&[&Outcome {
+                output: ("start\nbaz\nzoing\nfoo\n", "").into(),
+                testcase: TestCase {
+                    title: "the title".into(),
+                    shell_expression: "the command".into(),
+                    body: ValidationBody::Output(OutputBody { expectations }),
+                    line_number: 234,
+                    ..Default::default()
+                },
+                location: Some("the location".into()),
+                result: Err(TestCaseError::ValidationFailed(
+                    ValidationFailure::MalformedOutput(diff),
+                )),
+                escaping: Escaper::default(),
+                format: ParserType::Markdown,
+                shell: None,
+            }])
+            .expect("render succeeds");
+        insta::assert_snapshot!(rendered);
+    }
+
     #[test]
     fn test_render() {
         let renderer = DiffRenderer::new();
@@ -605,6 +801,7 @@ mod tests {
                                 ValidationFailure::MalformedOutput(diff.to_owned()),
                             )),
                             format: *parser_type,
+                            shell: None,
                             escaping: Escaper::default(),
                         },
                         Outcome {
@@ -619,6 +816,7 @@ mod tests {
                                 ValidationFailure::MalformedOutput(diff.to_owned()),
                             )),
                             format: *parser_type,
+                            shell: None,
                             escaping: Escaper::default(),
                         },
                     ];
@@ -679,9 +877,142 @@ mod tests {
                     )),
                     escaping: Escaper::default(),
                     format: ParserType::Markdown,
+                    shell: None,
                 }])
                 .expect("render succeeds");
             insta::assert_snapshot!(format!("json_schema_failed_{name}"), rendered);
         }
     }
+
+    #[test]
+    fn test_interactive_failed() {
+        let renderer = DiffRenderer::new();
+        let kinds = [
+            (
+                "wait_timeout",
+                InteractiveFailureKind::WaitTimeout,
+                "WAIT Continue? (glob)",
+            ),
+            (
+                "screen_mismatch",
+                InteractiveFailureKind::ScreenMismatch,
+                "ASSERT Done",
+            ),
+            (
+                "transcript_mismatch",
+                InteractiveFailureKind::TranscriptMismatch,
+                "ASSERT_TRANSCRIPT ^ok$ (regex)",
+            ),
+        ];
+
+        for (name, kind, directive) in &kinds {
+            let rendered = renderer
+                .render(&[&Outcome {
+                    output: ("Proceed? [y/N]\n", "").into(),
+                    testcase: TestCase {
+                        title: "the title".into(),
+                        shell_expression: "the command".into(),
+                        body: ValidationBody::Output(OutputBody {
+                            expectations: vec![],
+                        }),
+                        exit_code: None,
+                        line_number: 10,
+                        ..Default::default()
+                    },
+                    location: Some("the location".into()),
+                    result: Err(TestCaseError::ValidationFailed(
+                        ValidationFailure::InteractiveFailed(InteractiveFailure {
+                            kind: kind.clone(),
+                            index: 1,
+                            directive: directive.to_string(),
+                            screen: vec!["Proceed? [y/N]".to_string(), "".to_string()],
+                        }),
+                    )),
+                    escaping: Escaper::default(),
+                    format: ParserType::Markdown,
+                    shell: None,
+                }])
+                .expect("render succeeds");
+            insta::assert_snapshot!(format!("interactive_failed_{name}"), rendered);
+        }
+    }
+
+    #[test]
+    fn test_malformed_marked_output() {
+        let renderer = DiffRenderer::new();
+        let stdout_section = MarkedSection {
+            stream: MarkedStream::Stdout,
+            expectations: vec![test_expectation!("the output")],
+        };
+        let stderr_section = MarkedSection {
+            stream: MarkedStream::Stderr,
+            expectations: vec![test_expectation!("the error")],
+        };
+        let stdout_diff = MarkedDiff {
+            stream: MarkedStream::Stdout,
+            diff: Diff::new(vec![DiffLine::MatchedExpectation {
+                index: 0,
+                expectation: test_expectation!("the output"),
+                lines: vec![(0, "the output\n".as_bytes().to_vec())],
+            }]),
+        };
+        let tests = [
+            (
+                "mismatch",
+                vec![stdout_section.clone(), stderr_section.clone()],
+                vec![
+                    stdout_diff.clone(),
+                    MarkedDiff {
+                        stream: MarkedStream::Stderr,
+                        diff: Diff::new(vec![
+                            DiffLine::UnmatchedExpectation {
+                                index: 0,
+                                expectation: test_expectation!("the error"),
+                            },
+                            DiffLine::UnexpectedLines {
+                                lines: vec![(0, "another error\n".as_bytes().to_vec())],
+                            },
+                        ]),
+                    },
+                ],
+            ),
+            (
+                "new_section",
+                vec![stdout_section.clone()],
+                vec![
+                    stdout_diff.clone(),
+                    MarkedDiff {
+                        stream: MarkedStream::Stderr,
+                        diff: Diff::new(vec![DiffLine::UnexpectedLines {
+                            lines: vec![(0, "another error\n".as_bytes().to_vec())],
+                        }]),
+                    },
+                ],
+            ),
+        ];
+
+        for (name, sections, diffs) in tests {
+            let rendered = renderer
+                .render(&[&Outcome {
+                    output: ("the output\n", "another error\n").into(),
+                    testcase: TestCase {
+                        title: "the title".into(),
+                        shell_expression: "the command".into(),
+                        body: ValidationBody::MarkedOutput(MarkedOutputBody { sections }),
+                        exit_code: None,
+                        line_number: 10,
+                        ..Default::default()
+                    },
+                    location: Some("the location".into()),
+                    result: Err(TestCaseError::ValidationFailed(
+                        ValidationFailure::MalformedMarkedOutput(diffs),
+                    )),
+                    escaping: Escaper::default(),
+                    format: ParserType::Markdown,
+                    shell: None,
+                }])
+                .expect("render succeeds");
+            insta::assert_snapshot!(format!("malformed_marked_output_{name}"), rendered);
+        }
+    }
 }
@@ -0,0 +1,407 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::borrow::Cow;
+use std::time::Duration;
+
+use anyhow::Result;
+
+use super::outcome::error_summary;
+use super::outcome::outcome_name;
+use super::pretty::PrettyColorRenderer;
+use super::renderer::ErrorRenderer;
+use super::renderer::Renderer;
+use crate::config::ResourceLimit;
+use crate::diff::Diff;
+use crate::diff::DiffLine;
+use crate::escaping::strip_colors;
+use crate::newline::BytesNewline;
+use crate::outcome::Outcome;
+use crate::testcase::TestCaseError;
+use crate::validation::InteractiveFailure;
+use crate::validation::JsonSchemaFailure;
+use crate::validation::MarkedDiff;
+use crate::validation::MarkedOutputBody;
+use crate::validation::ValidationBody;
+
+/// Renders failed outcomes as GitHub Actions workflow commands, so that they
+/// show up as annotations on the lines of the test document.
+/// See: <https://docs.github.com/en/actions/using-workflows/workflow-commands-for-github-actions>
+///
+/// Differences in output are annotated per hunk, at the absolute line of the
+/// expectation within the test document. All other errors are annotated at
+/// the line of the test case and rendered with the wrapped
+/// [`PrettyColorRenderer`], stripped from colors.
+#[derive(Default)]
+pub struct GithubRenderer(PrettyColorRenderer);
+
+impl GithubRenderer {
+    pub fn new(pretty_renderer: PrettyColorRenderer) -> Self {
+        Self(pretty_renderer)
+    }
+
+    /// Render a single `::error` workflow command
+    fn annotation(&self, outcome: &Outcome, line: usize, message: &str) -> String {
+        let mut properties = vec![];
+        if let Some(ref location) = outcome.location {
+            properties.push(format!("file={}", escape_property(location)));
+        }
+        properties.push(format!("line={line}"));
+        properties.push(format!("title={}", escape_property(&outcome_name(outcome))));
+        format!(
+            "::error {}::{}\n",
+            properties.join(","),
+            escape_data(message)
+        )
+    }
+
+    /// Annotate each hunk of differences, with `line_base` being the absolute
+    /// line of the first of `count_expectations` expectations the diff was
+    /// created from
+    fn annotate_diff(
+        &self,
+        outcome: &Outcome,
+        diff: &Diff,
+        line_base: usize,
+        count_expectations: usize,
+    ) -> String {
+        let mut output = String::new();
+        let mut hunk: Option<(usize, Vec<String>)> = None;
+        let mut last_index = None;
+        let mut flush = |hunk: &mut Option<(usize, Vec<String>)>| {
+            if let Some((line, lines)) = hunk.take() {
+                output.push_str(&self.annotation(
+                    outcome,
+                    line,
+                    &format!("output does not match expectations\n{}", lines.join("\n")),
+                ));
+            }
+        };
+
+        for (position, line) in diff.lines.iter().enumerate() {
+            match line {
+                DiffLine::MatchedExpectation { index, .. } => {
+                    flush(&mut hunk);
+                    last_index = Some(*index);
+                }
+                DiffLine::UnmatchedExpectation { index, expectation } => {
+                    let (_, hunk_lines) = hunk.get_or_insert_with(|| (line_base + index, vec![]));
+                    hunk_lines.push(format!("- {}", expectation.original_string()));
+                    if let Some(explanation) = diff.explain_unmatched(position) {
+                        hunk_lines.extend(
+                            explanation
+                                .lines()
+                                .map(|explanation| format!("  // {explanation}")),
+                        );
+                    }
+                    last_index = Some(*index);
+                }
+                DiffLine::NegatedExpectation {
+                    index,
+                    expectation,
+                    lines,
+                } => {
+                    flush(&mut hunk);
+                    if !lines.is_empty() {
+                        let mut hunk_lines = vec![format!("! {}", expectation.original_string())];
+                        hunk_lines.extend(lines.iter().map(|(_, line)| {
+                            format!(
+                                "+ {}",
+                                outcome
+                                    .escaping
+                                    .escaped_printable((line as &[u8]).trim_newlines())
+                            )
+                        }));
+                        hunk = Some((line_base + index, hunk_lines));
+                        flush(&mut hunk);
+                    }
+                    last_index = Some(*index);
+                }
+                DiffLine::UnexpectedLines { lines } => {
+                    // annotate where the lines would be expected, which is the
+                    // next expectation or the last one, if there is none
+                    let position = last_index
+                        .map_or(0, |index| index + 1)
+                        .min(count_expectations.saturating_sub(1));
+                    let (_, hunk_lines) =
+                        hunk.get_or_insert_with(|| (line_base + position, vec![]));
+                    for (_, line) in lines {
+                        hunk_lines.push(format!(
+                            "+ {}",
+                            outcome
+                                .escaping
+                                .escaped_printable((line as &[u8]).trim_newlines())
+                        ));
+                    }
+                }
+            }
+        }
+        flush(&mut hunk);
+
+        output
+    }
+}
+
+impl Renderer for GithubRenderer {
+    fn render(&self, outcomes: &[&Outcome]) -> Result<String> {
+        let mut output = String::new();
+        for outcome in outcomes {
+            match outcome.result {
+                Ok(_) | Err(TestCaseError::Skipped) => {}
+                Err(ref err) => output.push_str(&self.render_error(err, outcome)?),
+            }
+        }
+        Ok(output)
+    }
+}
+
+impl ErrorRenderer for GithubRenderer {
+    fn render_invalid_exit_code(
+        &self,
+        outcome: &Outcome,
+        actual: i32,
+        expected: i32,
+    ) -> Result<String> {
+        let line = outcome.testcase.line_number
+            + outcome.testcase.shell_expression_lines()
+            + outcome.testcase.expectations_lines();
+        Ok(self.annotation(
+            outcome,
+            line,
+            &error_summary(&TestCaseError::InvalidExitCode { actual, expected }),
+        ))
+    }
+
+    fn render_delegated_error(&self, outcome: &Outcome, err: &anyhow::Error) -> Result<String> {
+        Ok(self.annotation(
+            outcome,
+            outcome.testcase.line_number,
+            &format!("error: {err}"),
+        ))
+    }
+
+    fn render_malformed_output(&self, outcome: &Outcome, diff: &Diff) -> Result<String> {
+        Ok(self.annotate_diff(
+            outcome,
+            diff,
+            outcome.testcase.line_number + outcome.testcase.shell_expression_lines(),
+            outcome.testcase.expectations().len(),
+        ))
+    }
+
+    /// Annotates each stream with differences. Output of streams without a
+    /// section is annotated at the last line of the body.
+    fn render_malformed_marked_output(
+        &self,
+        outcome: &Outcome,
+        diffs: &[MarkedDiff],
+    ) -> Result<String> {
+        let body = match outcome.testcase.body {
+            ValidationBody::MarkedOutput(ref body) => Cow::Borrowed(body),
+            _ => Cow::Owned(MarkedOutputBody::default()),
+        };
+        let line_base = outcome.testcase.line_number + outcome.testcase.shell_expression_lines();
+        let mut output = String::new();
+        for marked in diffs {
+            if !marked.diff.has_differences() {
+                continue;
+            }
+            if body.section(marked.stream).is_some() {
+                output.push_str(&self.annotate_diff(
+                    outcome,
+                    &marked.diff,
+                    line_base + body.offset(marked.stream),
+                    body.expectations(marked.stream).len(),
+                ));
+            } else {
+                output.push_str(&self.annotate_diff(
+                    outcome,
+                    &marked.diff,
+                    line_base + body.lines().saturating_sub(1),
+                    0,
+                ));
+            }
+        }
+        Ok(output)
+    }
+
+    fn render_timeout(&self, outcome: &Outcome) -> Result<String> {
+        let rendered = strip_colors(&self.0.render_timeout(outcome)?)?;
+        Ok(self.annotation(outcome, outcome.testcase.line_number, rendered.trim_end()))
+    }
+
+    fn render_max_duration_exceeded(
+        &self,
+        outcome: &Outcome,
+        duration: Duration,
+        max_duration: Duration,
+    ) -> Result<String> {
+        Ok(self.annotation(
+            outcome,
+            outcome.testcase.line_number,
+            &error_summary(&TestCaseError::MaxDurationExceeded {
+                duration,
+                max_duration,
+            }),
+        ))
+    }
+
+    fn render_max_rss_exceeded(&self, outcome: &Outcome, rss: u64, max_rss: u64) -> Result<String> {
+        Ok(self.annotation(
+            outcome,
+            outcome.testcase.line_number,
+            &error_summary(&TestCaseError::MaxRssExceeded { rss, max_rss }),
+        ))
+    }
+
+    /// Annotates the exceeded limit, followed by the annotations of the error
+    fn render_limit_exceeded(
+        &self,
+        outcome: &Outcome,
+        limit: &ResourceLimit,
+        err: &TestCaseError,
+    ) -> Result<String> {
+        let mut output = self.annotation(
+            outcome,
+            outcome.testcase.line_number,
+            &format!("resource limit exceeded: {limit}"),
+        );
+        output.push_str(&self.render_error(err, outcome)?);
+        Ok(output)
+    }
+
+    fn render_skipped(&self, _outcome: &Outcome) -> Result<String> {
+        Ok("".into())
+    }
+
+    fn render_json_schema_failed(
+        &self,
+        outcome: &Outcome,
+        failure: &JsonSchemaFailure,
+    ) -> Result<String> {
+        let rendered = strip_colors(&self.0.render_json_schema_failed(outcome, failure)?)?;
+        Ok(self.annotation(
+            outcome,
+            outcome.testcase.line_number,
+            &format!("output does not match JSON schema\n{}", rendered.trim_end()),
+        ))
+    }
+
+    fn render_interactive_failed(
+        &self,
+        outcome: &Outcome,
+        failure: &InteractiveFailure,
+    ) -> Result<String> {
+        let rendered = strip_colors(&self.0.render_interactive_failed(outcome, failure)?)?;
+        Ok(self.annotation(
+            outcome,
+            outcome.testcase.line_number,
+            &format!("interactive session failed\n{}", rendered.trim_end()),
+        ))
+    }
+}
+
+/// Escape the message of a workflow command
+fn escape_data(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escape a property value of a workflow command
+fn escape_property(text: &str) -> String {
+    escape_data(text).replace(':', "%3A").replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GithubRenderer;
+    use super::escape_property;
+    use crate::diff::Diff;
+    use crate::diff::DiffLine;
+    use crate::escaping::Escaper;
+    use crate::outcome::Outcome;
+    use crate::parsers::parser::ParserType;
+    use crate::renderers::renderer::Renderer;
+    use crate::test_expectation;
+    use crate::testcase::TestCase;
+    use crate::testcase::TestCaseError;
+    use crate::validation::OutputBody;
+    use crate::validation::ValidationBody;
+    use crate::validation::ValidationFailure;
+
+    #[test]
+    fn test_escape_property() {
+        assert_eq!("a%3A b%2C c%25d%0Ae", escape_property("a: b, c%d\ne"));
+    }
+
+    #[test]
+    fn test_render() {
+        let renderer = GithubRenderer::default();
+        let expectations = vec![
+            test_expectation!("equal", "foo"),
+            test_expectation!("equal", "bar"),
+            test_expectation!("equal", "baz"),
+        ];
+        let outcome = |title: &str, result| Outcome {
+            location: Some("docs/foo.md".to_string()),
+            output: ("foo\nbaz\nzoing\n", "").into(),
+            testcase: TestCase {
+                title: title.to_string(),
+                shell_expression: "the command".to_string(),
+                body: ValidationBody::Output(OutputBody {
+                    expectations: expectations.clone(),
+                }),
+                line_number: 42,
+                ..Default::default()
+            },
+            result,
+            escaping: Escaper::default(),
+            format: ParserType::Markdown,
+            shell: None,
+        };
+        let rendered = renderer
+            .render(&[
+                &outcome("success", Ok(())),
+                &outcome("skipped", Err(TestCaseError::Skipped)),
+                &outcome(
+                    "malformed, output",
+                    Err(TestCaseError::ValidationFailed(
+                        ValidationFailure::MalformedOutput(Diff::new(vec![
+                            DiffLine::MatchedExpectation {
+                                index: 0,
+                                expectation: expectations[0].clone(),
+                                lines: vec![(0, b"foo\n".to_vec())],
+                            },
+                            DiffLine::UnmatchedExpectation {
+                                index: 1,
+                                expectation: expectations[1].clone(),
+                            },
+                            DiffLine::MatchedExpectation {
+                                index: 2,
+                                expectation: expectations[2].clone(),
+                                lines: vec![(1, b"baz\n".to_vec())],
+                            },
+                            DiffLine::UnexpectedLines {
+                                lines: vec![(2, b"zoing\n".to_vec())],
+                            },
+                        ])),
+                    )),
+                ),
+                &outcome(
+                    "exit code",
+                    Err(TestCaseError::InvalidExitCode {
+                        actual: 1,
+                        expected: 0,
+                    }),
+                ),
+                &outcome("timeout", Err(TestCaseError::Timeout)),
+            ])
+            .expect("rendering succeeds");
+        insta::assert_snapshot!(rendered);
+    }
+}
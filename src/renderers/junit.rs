@@ -0,0 +1,256 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use anyhow::Result;
+
+use super::outcome::error_summary;
+use super::outcome::outcome_name;
+use super::pretty::PrettyColorRenderer;
+use super::renderer::ErrorRenderer;
+use super::renderer::Renderer;
+use crate::escaping::strip_colors;
+use crate::outcome::Outcome;
+use crate::testcase::TestCaseError;
+
+/// Name of the suite that holds outcomes without location
+const DEFAULT_SUITE_NAME: &str = "scrut";
+
+/// Renders outcomes as JUnit XML report, that can be ingested by CI systems.
+///
+/// Each test document (location) becomes a `<testsuite>` and each outcome a
+/// `<testcase>` within. The body of failures is rendered with the wrapped
+/// [`PrettyColorRenderer`], stripped from colors.
+#[derive(Default)]
+pub struct JunitRenderer(PrettyColorRenderer);
+
+impl JunitRenderer {
+    pub fn new(pretty_renderer: PrettyColorRenderer) -> Self {
+        Self(pretty_renderer)
+    }
+
+    fn render_testcase(&self, outcome: &Outcome, suite_name: &str) -> Result<String> {
+        let mut attributes = format!(
+            "name=\"{}\" classname=\"{}\"",
+            escape_xml(&outcome_name(outcome)),
+            escape_xml(suite_name)
+        );
+        if let Some(ref location) = outcome.location {
+            attributes.push_str(&format!(" file=\"{}\"", escape_xml(location)));
+        }
+        attributes.push_str(&format!(" line=\"{}\"", outcome.testcase.line_number));
+
+        let err = match outcome.result {
+            Ok(_) => return Ok(format!("    <testcase {attributes} />\n")),
+            Err(ref err) => err,
+        };
+        let mut output = format!("    <testcase {attributes}>\n");
+        if matches!(err, TestCaseError::Skipped) {
+            output.push_str("      <skipped />\n");
+        } else {
+            let element = if matches!(err, TestCaseError::InternalError(_)) {
+                "error"
+            } else {
+                "failure"
+            };
+            let body = strip_colors(&self.0.render_error(err, outcome)?)?;
+            output.push_str(&format!(
+                "      <{element} type=\"{}\" message=\"{}\">{}</{element}>\n",
+                err.kind(),
+                escape_xml(&error_summary(err)),
+                escape_xml(&body),
+            ));
+        }
+        output.push_str("    </testcase>\n");
+        Ok(output)
+    }
+}
+
+impl Renderer for JunitRenderer {
+    fn render(&self, outcomes: &[&Outcome]) -> Result<String> {
+        // group outcomes by location, keeping the order of first appearance
+        let mut suites: Vec<(&str, Vec<&Outcome>)> = vec![];
+        for outcome in outcomes {
+            let name = outcome.location.as_deref().unwrap_or(DEFAULT_SUITE_NAME);
+            match suites
+                .iter_mut()
+                .find(|(suite_name, _)| *suite_name == name)
+            {
+                Some((_, suite)) => suite.push(outcome),
+                None => suites.push((name, vec![outcome])),
+            }
+        }
+
+        let mut rendered_suites = String::new();
+        let mut total = SuiteCounts::default();
+        for (name, suite) in suites {
+            let counts = SuiteCounts::from_outcomes(&suite);
+            rendered_suites.push_str(&format!(
+                "  <testsuite name=\"{}\" {}>\n",
+                escape_xml(name),
+                counts.attributes()
+            ));
+            for outcome in suite {
+                rendered_suites.push_str(&self.render_testcase(outcome, name)?);
+            }
+            rendered_suites.push_str("  </testsuite>\n");
+            total.add(&counts);
+        }
+
+        let mut output = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        output.push_str(&format!(
+            "<testsuites name=\"{DEFAULT_SUITE_NAME}\" {}>\n",
+            total.attributes()
+        ));
+        output.push_str(&rendered_suites);
+        output.push_str("</testsuites>\n");
+        Ok(output)
+    }
+}
+
+/// Counts of outcomes within a `<testsuite>` or `<testsuites>`
+#[derive(Default)]
+struct SuiteCounts {
+    tests: usize,
+    failures: usize,
+    errors: usize,
+    skipped: usize,
+}
+
+impl SuiteCounts {
+    fn from_outcomes(outcomes: &[&Outcome]) -> Self {
+        let mut counts = Self {
+            tests: outcomes.len(),
+            ..Default::default()
+        };
+        for outcome in outcomes {
+            match outcome.result {
+                Ok(_) => {}
+                Err(TestCaseError::Skipped) => counts.skipped += 1,
+                Err(TestCaseError::InternalError(_)) => counts.errors += 1,
+                Err(_) => counts.failures += 1,
+            }
+        }
+        counts
+    }
+
+    fn add(&mut self, other: &Self) {
+        self.tests += other.tests;
+        self.failures += other.failures;
+        self.errors += other.errors;
+        self.skipped += other.skipped;
+    }
+
+    fn attributes(&self) -> String {
+        format!(
+            "tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\"",
+            self.tests, self.failures, self.errors, self.skipped
+        )
+    }
+}
+
+/// Escape text for use in XML attributes and text nodes. Control characters
+/// that are not allowed in XML 1.0 are replaced.
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if (c as u32) < 0x20 => escaped.push('\u{FFFD}'),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::JunitRenderer;
+    use super::escape_xml;
+    use crate::diff::Diff;
+    use crate::diff::DiffLine;
+    use crate::escaping::Escaper;
+    use crate::outcome::Outcome;
+    use crate::parsers::parser::ParserType;
+    use crate::renderers::renderer::Renderer;
+    use crate::test_expectation;
+    use crate::testcase::TestCase;
+    use crate::testcase::TestCaseError;
+    use crate::validation::OutputBody;
+    use crate::validation::ValidationBody;
+    use crate::validation::ValidationFailure;
+
+    #[test]
+    fn test_escape_xml() {
+        assert_eq!(
+            "&lt;a href=&quot;x&quot;&gt;&amp;&apos;\u{FFFD}\n",
+            escape_xml("<a href=\"x\">&'\x1b\n")
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let renderer = JunitRenderer::default();
+        let outcome = |location: &str, title: &str, result| Outcome {
+            location: Some(location.to_string()),
+            output: ("the stdout\n", "the stderr\n").into(),
+            testcase: TestCase {
+                title: title.to_string(),
+                shell_expression: "the command".to_string(),
+                body: ValidationBody::Output(OutputBody {
+                    expectations: vec![test_expectation!("equal", "expected")],
+                }),
+                line_number: 123,
+                ..Default::default()
+            },
+            result,
+            escaping: Escaper::default(),
+            format: ParserType::Markdown,
+            shell: None,
+        };
+        let rendered = renderer
+            .render(&[
+                &outcome("first.md", "success", Ok(())),
+                &outcome(
+                    "first.md",
+                    "malformed",
+                    Err(TestCaseError::ValidationFailed(
+                        ValidationFailure::MalformedOutput(Diff::new(vec![
+                            DiffLine::UnmatchedExpectation {
+                                index: 0,
+                                expectation: test_expectation!("equal", "expected"),
+                            },
+                            DiffLine::UnexpectedLines {
+                                lines: vec![(0, b"the <stdout>\n".to_vec())],
+                            },
+                        ])),
+                    )),
+                ),
+                &outcome("second.md", "", Err(TestCaseError::Skipped)),
+                &outcome("second.md", "timeout", Err(TestCaseError::Timeout)),
+                &outcome(
+                    "second.md",
+                    "exit code",
+                    Err(TestCaseError::InvalidExitCode {
+                        actual: 1,
+                        expected: 0,
+                    }),
+                ),
+                &outcome(
+                    "second.md",
+                    "internal",
+                    Err(TestCaseError::InternalError(anyhow::anyhow!("broken"))),
+                ),
+            ])
+            .expect("rendering succeeds");
+        insta::assert_snapshot!(rendered);
+    }
+}
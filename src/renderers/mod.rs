@@ -9,6 +9,8 @@
 //! executions.
 
 pub mod diff;
+pub mod github;
+pub mod junit;
 pub mod outcome;
 pub mod pretty;
 pub mod renderer;
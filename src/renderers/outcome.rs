@@ -5,12 +5,18 @@
  * LICENSE file in the root directory of this source tree.
  */
 
+use std::time::Duration;
+
 use anyhow::Result;
 use console::StyledObject;
 use console::style;
 
+use crate::config::format_byte_size;
 use crate::formatln;
 use crate::outcome::Outcome;
+use crate::output::duration_ms;
+use crate::testcase::TestCaseError;
+use crate::validation::ValidationFailure;
 
 const MAX_LINE_LENGTH: usize = 80;
 
@@ -21,19 +27,20 @@ pub(super) trait OutcomeHeader {
 impl OutcomeHeader for Outcome {
     fn render_header(&self) -> Result<String> {
         let mut headers = vec![];
-        if let Some(ref location) = self.location {
-            headers.push(header_to_title(
-                "@",
-                &format!("{}:{}", location, self.testcase.line_number),
-                |s| style(s).bright().blue(),
-            ));
+        let mut location = if let Some(ref location) = self.location {
+            format!("{}:{}", location, self.testcase.line_number)
         } else {
-            headers.push(header_to_title(
-                "@",
-                &format!("Line {}", self.testcase.line_number),
-                |s| style(s).bright().blue(),
-            ));
+            format!("Line {}", self.testcase.line_number)
+        };
+        if let Some(ref shell) = self.shell {
+            location.push_str(&format!(" [{shell}]"));
+        }
+        if self.output.always_run {
+            location.push_str(" (always run)");
         }
+        headers.push(header_to_title("@", &location, |s| {
+            style(s).bright().blue()
+        }));
         if !self.testcase.title.is_empty() {
             headers.push(header_to_title("#", &self.testcase.title, |s| {
                 style(s).bright().cyan()
@@ -79,3 +86,69 @@ fn header_to_title(
     }
     title
 }
+
+/// A short name of the outcome, which is the title of the test case or the
+/// first line of its shell expression, if the test case has no title. Test
+/// cases that ran after the execution ended early are marked as always run.
+pub(super) fn outcome_name(outcome: &Outcome) -> String {
+    let name = if outcome.testcase.title.is_empty() {
+        outcome
+            .testcase
+            .shell_expression
+            .lines()
+            .next()
+            .unwrap_or_default()
+    } else {
+        &outcome.testcase.title
+    };
+    let mut name = match outcome.shell {
+        Some(ref shell) => format!("{name} [{shell}]"),
+        None => name.to_string(),
+    };
+    if outcome.output.always_run {
+        name.push_str(" (always run)");
+    }
+    name
+}
+
+/// A single line summary of the error
+pub(super) fn error_summary(err: &TestCaseError) -> String {
+    match err {
+        TestCaseError::ValidationFailed(failure) => match failure {
+            ValidationFailure::MalformedOutput(_) | ValidationFailure::MalformedMarkedOutput(_) => {
+                "output does not match expectations".to_string()
+            }
+            ValidationFailure::JsonSchemaFailed(_) => {
+                "output does not match JSON schema".to_string()
+            }
+            ValidationFailure::InteractiveFailed(_) => "interactive session failed".to_string(),
+        },
+        TestCaseError::InvalidExitCode { actual, expected } => {
+            format!("unexpected exit code: expected {expected}, actual {actual}")
+        }
+        TestCaseError::InternalError(err) => format!("error: {err}"),
+        TestCaseError::Timeout => "timeout in execution".to_string(),
+        TestCaseError::MaxDurationExceeded {
+            duration,
+            max_duration,
+        } => format!(
+            "max duration exceeded: expected at most {}, actual {}",
+            format_duration_ms(*max_duration),
+            format_duration_ms(*duration)
+        ),
+        TestCaseError::MaxRssExceeded { rss, max_rss } => format!(
+            "max rss exceeded: expected at most {}, actual {}",
+            format_byte_size(*max_rss),
+            format_byte_size(*rss)
+        ),
+        TestCaseError::LimitExceeded { limit, error } => {
+            format!("resource limit exceeded: {limit}: {}", error_summary(error))
+        }
+        TestCaseError::Skipped => "skipped".to_string(),
+    }
+}
+
+/// Format a duration for humans, rounded down to milliseconds
+pub(super) fn format_duration_ms(duration: Duration) -> String {
+    humantime::format_duration(Duration::from_millis(duration_ms(duration))).to_string()
+}
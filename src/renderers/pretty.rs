@@ -5,14 +5,20 @@
  * LICENSE file in the root directory of this source tree.
  */
 
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::time::Duration;
 
 use anyhow::Result;
 use console::style;
 
 use super::outcome::OutcomeHeader;
+use super::outcome::format_duration_ms;
+use super::outcome::outcome_name;
 use super::renderer::ErrorRenderer;
 use super::renderer::Renderer;
+use crate::config::ResourceLimit;
+use crate::config::format_byte_size;
 use crate::diff::Diff;
 use crate::diff::DiffLine;
 use crate::escaping::strip_colors;
@@ -21,13 +27,19 @@ use crate::newline::BytesNewline;
 use crate::newline::StringNewline;
 use crate::outcome::Outcome;
 use crate::testcase::TestCaseError;
+use crate::validation::InteractiveFailure;
+use crate::validation::InteractiveFailureKind;
 use crate::validation::JsonSchemaFailure;
 use crate::validation::JsonSchemaFailureKind;
+use crate::validation::MarkedDiff;
+use crate::validation::MarkedOutputBody;
+use crate::validation::ValidationBody;
 
 pub const DEFAULT_SURROUNDING_LINES: usize = 5;
 pub const DEFAULT_ABSOLUTE_LINE_NUMBERS: bool = false;
 pub const DEFAULT_SUMMARIZE: bool = true;
 pub const DEFAULT_MULTILINE_MATCHED_LINES: usize = 100;
+pub const DEFAULT_SLOWEST: usize = 0;
 
 /// Renders errors in a human readable way, that higlights the differences eper
 /// test case.
@@ -54,10 +66,21 @@ pub struct PrettyColorRenderer {
     pub absolute_line_numbers: bool,
     pub summarize: bool,
     pub max_multiline_matched_lines: usize,
+    /// Amount of the slowest test cases that are listed in the summary
+    pub slowest: usize,
 }
 
 impl PrettyColorRenderer {
-    fn render_summary(&self, files: usize, ok: usize, errors: usize, ignored: usize) -> String {
+    fn render_summary(
+        &self,
+        outcomes: &[&Outcome],
+        files: usize,
+        ok: usize,
+        errors: usize,
+        ignored: usize,
+    ) -> String {
+        let mut output = self.render_slowest(outcomes);
+        output.push_str(&self.render_always_run(outcomes));
         let summary = style("Result").underlined();
         let total = ok + errors + ignored;
         let tests = style(format!("{} testcase(s)", total)).bold();
@@ -73,113 +96,145 @@ impl PrettyColorRenderer {
         if ignored > 0 {
             skipped = skipped.bold();
         }
-        format!(
+        output.push_str(&format!(
             "{}: {} document(s) with {}: {}, {} and {}\n",
             summary, files, tests, succeeded, failed, skipped,
-        )
+        ));
+        output
     }
-}
 
-impl Default for PrettyColorRenderer {
-    fn default() -> Self {
-        PrettyColorRenderer {
-            max_surrounding_lines: DEFAULT_SURROUNDING_LINES,
-            absolute_line_numbers: DEFAULT_ABSOLUTE_LINE_NUMBERS,
-            summarize: DEFAULT_SUMMARIZE,
-            max_multiline_matched_lines: DEFAULT_MULTILINE_MATCHED_LINES,
+    /// Render the list of the slowest executed test cases, if enabled
+    fn render_slowest(&self, outcomes: &[&Outcome]) -> String {
+        if self.slowest == 0 {
+            return String::new();
         }
-    }
-}
+        let mut timed = outcomes
+            .iter()
+            .filter(|outcome| !matches!(outcome.result, Err(TestCaseError::Skipped)))
+            .filter_map(|outcome| outcome.output.duration.map(|duration| (duration, outcome)))
+            .collect::<Vec<_>>();
+        if timed.is_empty() {
+            return String::new();
+        }
+        timed.sort_by(|(a, _), (b, _)| b.cmp(a));
+        timed.truncate(self.slowest);
 
-impl Renderer for PrettyColorRenderer {
-    fn render(&self, outcomes: &[&Outcome]) -> Result<String> {
-        let mut output = String::new();
-        let mut count_errors = 0;
-        let mut count_ok = 0;
-        let mut count_skipped = 0;
-        let mut locations = HashMap::new();
+        let durations = timed
+            .iter()
+            .map(|(duration, _)| format_duration_ms(*duration))
+            .collect::<Vec<_>>();
+        let width = durations.iter().map(|d| d.len()).max().unwrap_or_default();
+        let mut output = formatln!(
+            "{}: {} testcase(s)",
+            style("Slowest").underlined(),
+            timed.len()
+        );
+        for ((_, outcome), duration) in timed.iter().zip(durations) {
+            let location = match outcome.location {
+                Some(ref location) => format!("{}:{}", location, outcome.testcase.line_number),
+                None => format!("Line {}", outcome.testcase.line_number),
+            };
+            output.push_str(&formatln!(
+                "  {}  {} {}",
+                style(format!("{duration:>width$}")).bold(),
+                style(location).blue(),
+                outcome_name(outcome)
+            ));
+        }
+        output.push('\n');
+        output
+    }
 
-        for outcome in outcomes {
-            if let Some(ref location) = outcome.location {
-                locations.insert(location, true);
-            }
-            if let Err(ref err) = outcome.result {
-                if matches!(err, TestCaseError::Skipped) {
-                    count_skipped += 1;
-                    continue;
-                }
-                count_errors += 1;
-                output.push_str(&outcome.render_header()?);
-                output.push_str(&self.render_error(err, outcome)?);
-                output.push_str("\n\n");
+    /// Render the list of test cases that ran after the execution of their
+    /// document ended early, because they are configured to always run
+    fn render_always_run(&self, outcomes: &[&Outcome]) -> String {
+        let always_run = outcomes
+            .iter()
+            .filter(|outcome| outcome.output.always_run)
+            .collect::<Vec<_>>();
+        if always_run.is_empty() {
+            return String::new();
+        }
+        let mut output = formatln!(
+            "{}: {} testcase(s) ran after the execution ended early",
+            style("Always run").underlined(),
+            always_run.len()
+        );
+        for outcome in always_run {
+            let location = match outcome.location {
+                Some(ref location) => format!("{}:{}", location, outcome.testcase.line_number),
+                None => format!("Line {}", outcome.testcase.line_number),
+            };
+            let result = if outcome.result.is_ok() {
+                style("succeeded").green()
             } else {
-                count_ok += 1;
-            }
+                style("failed").red()
+            };
+            output.push_str(&formatln!(
+                "  {}  {} {}",
+                result.bold(),
+                style(location).blue(),
+                outcome_name(outcome)
+            ));
         }
+        output.push('\n');
+        output
+    }
 
-        if self.summarize {
-            output.push_str(&self.render_summary(
-                locations.len(),
-                count_ok,
-                count_errors,
-                count_skipped,
-            ));
+    /// The number that is added to the (one-based) line numbers of
+    /// expectations and output lines
+    fn line_base(&self, outcome: &Outcome) -> usize {
+        if self.absolute_line_numbers {
+            outcome.testcase.line_number + outcome.testcase.shell_expression_lines() - 1
+        } else {
+            0
         }
-        Ok(output)
     }
-}
 
-impl ErrorRenderer for PrettyColorRenderer {
-    fn render_invalid_exit_code(
+    /// Render the lines of a diff, with `count_expectations` being the number
+    /// of expectations it was created from
+    fn render_diff(
         &self,
         outcome: &Outcome,
-        actual: i32,
-        expected: i32,
+        diff: &Diff,
+        line_base: usize,
+        count_expectations: usize,
     ) -> Result<String> {
-        let mut out = String::new();
-        out.push_str(&formatln!("unexpected exit code"));
-        out.push_str(&formatln!("  expected: {}", expected));
-        out.push_str(&formatln!("  actual:   {}", actual));
-        out.push_str(&formatln!(""));
-        out.push_str(&outcome.output.to_error_string(&outcome.escaping));
-        Ok(out)
-    }
-
-    fn render_delegated_error(&self, _outcome: &Outcome, err: &anyhow::Error) -> Result<String> {
-        Ok(formatln!("error: {}", err))
-    }
-
-    fn render_malformed_output(&self, outcome: &Outcome, diff: &Diff) -> Result<String> {
         let mut output = String::new();
-        let line_base = if self.absolute_line_numbers {
-            outcome.testcase.line_number + outcome.testcase.shell_expression_lines() - 1
-        } else {
-            0
-        };
-        let decorator = Decorator::new(
-            line_base
-                + diff
-                    .count_output_lines
-                    .max(outcome.testcase.expectations().len()),
-        );
+        let decorator = Decorator::new(line_base + diff.count_output_lines.max(count_expectations));
         let mut last_error_index = None;
         let next_error_index = |index: usize| {
             diff.lines
                 .iter()
                 .skip(index)
-                .position(|line| {
-                    !matches!(
-                        line,
-                        DiffLine::MatchedExpectation {
-                            index: _,
-                            expectation: _,
-                            lines: _,
-                        }
-                    )
-                })
+                .position(DiffLine::is_difference)
                 .map(|v| v + index)
         };
 
+        // whether a line without difference is too far from any difference to
+        // be shown and whether it is the first such line after a difference
+        let is_skipped = |diff_index: usize, last_error_index: Option<usize>| {
+            let mut skip = true;
+            let mut first_skip = false;
+            if self.max_surrounding_lines > 0 {
+                if let Some(last_error_index) = last_error_index {
+                    if last_error_index + self.max_surrounding_lines >= diff_index {
+                        skip = false;
+                    } else if last_error_index + self.max_surrounding_lines + 1 == diff_index {
+                        first_skip = true;
+                    }
+                }
+                if let Some(next_error_index) = next_error_index(diff_index + 1) {
+                    if diff_index + self.max_surrounding_lines >= next_error_index {
+                        skip = false;
+                    }
+                }
+            } else {
+                skip = false;
+            }
+            (skip, first_skip)
+        };
+
         for (diff_index, line) in diff.lines.iter().enumerate() {
             match line {
                 DiffLine::MatchedExpectation {
@@ -187,26 +242,7 @@ impl ErrorRenderer for PrettyColorRenderer {
                     expectation,
                     lines,
                 } => {
-                    let mut skip = true;
-                    let mut first_skip = false;
-                    if self.max_surrounding_lines > 0 {
-                        if let Some(last_error_index) = last_error_index {
-                            if last_error_index + self.max_surrounding_lines >= diff_index {
-                                skip = false;
-                            } else if last_error_index + self.max_surrounding_lines + 1
-                                == diff_index
-                            {
-                                first_skip = true;
-                            }
-                        }
-                        if let Some(next_error_index) = next_error_index(diff_index + 1) {
-                            if diff_index + self.max_surrounding_lines >= next_error_index {
-                                skip = false;
-                            }
-                        }
-                    } else {
-                        skip = false;
-                    }
+                    let (skip, first_skip) = is_skipped(diff_index, last_error_index);
                     if !skip {
                         if !expectation.multiline || self.max_multiline_matched_lines <= 1 {
                             output.push_str(
@@ -321,7 +357,66 @@ impl ErrorRenderer for PrettyColorRenderer {
                                 &content,
                             )
                             .assure_newline(),
-                    )
+                    );
+
+                    // explain what in the output violates the expectation
+                    if let Some(explanation) = diff.explain_unmatched(diff_index) {
+                        explanation.lines().for_each(|explanation| {
+                            output.push_str(
+                                &decorator
+                                    .line(
+                                        None,
+                                        None,
+                                        false,
+                                        " ",
+                                        &format!(
+                                            "{} {}",
+                                            style("//").magenta(),
+                                            style(explanation).magenta().bold()
+                                        ),
+                                    )
+                                    .assure_newline(),
+                            )
+                        });
+                    }
+                }
+                DiffLine::NegatedExpectation {
+                    index,
+                    expectation,
+                    lines,
+                } => {
+                    let content = expectation.to_expression_string(&outcome.escaping);
+                    if lines.is_empty() {
+                        let (skip, first_skip) = is_skipped(diff_index, last_error_index);
+                        if !skip {
+                            output.push_str(
+                                &decorator
+                                    .line(None, Some(line_base + index + 1), false, " ", &content)
+                                    .assure_newline(),
+                            );
+                        } else if first_skip {
+                            output.push_str(&"...".assure_newline());
+                        }
+                        continue;
+                    }
+
+                    // show the expectation, followed by the lines that violate it
+                    last_error_index = Some(diff_index);
+                    output.push_str(
+                        &decorator
+                            .line(None, Some(line_base + index + 1), false, "!", &content)
+                            .assure_newline(),
+                    );
+                    lines.iter().for_each(|(line_index, line)| {
+                        let line = outcome
+                            .escaping
+                            .escaped_printable((line as &[u8]).trim_newlines());
+                        output.push_str(
+                            &decorator
+                                .line(Some(line_base + line_index + 1), None, false, "!", &line)
+                                .assure_newline(),
+                        )
+                    })
                 }
                 DiffLine::UnexpectedLines { lines } => {
                     lines.iter().for_each(|(line_index, line)| {
@@ -350,6 +445,120 @@ impl ErrorRenderer for PrettyColorRenderer {
 
         Ok(output)
     }
+}
+
+impl Default for PrettyColorRenderer {
+    fn default() -> Self {
+        PrettyColorRenderer {
+            max_surrounding_lines: DEFAULT_SURROUNDING_LINES,
+            absolute_line_numbers: DEFAULT_ABSOLUTE_LINE_NUMBERS,
+            summarize: DEFAULT_SUMMARIZE,
+            max_multiline_matched_lines: DEFAULT_MULTILINE_MATCHED_LINES,
+            slowest: DEFAULT_SLOWEST,
+        }
+    }
+}
+
+impl Renderer for PrettyColorRenderer {
+    fn render(&self, outcomes: &[&Outcome]) -> Result<String> {
+        let mut output = String::new();
+        let mut count_errors = 0;
+        let mut count_ok = 0;
+        let mut count_skipped = 0;
+        let mut locations = HashMap::new();
+
+        for outcome in outcomes {
+            if let Some(ref location) = outcome.location {
+                locations.insert(location, true);
+            }
+            if let Err(ref err) = outcome.result {
+                if matches!(err, TestCaseError::Skipped) {
+                    count_skipped += 1;
+                    continue;
+                }
+                count_errors += 1;
+                output.push_str(&outcome.render_header()?);
+                output.push_str(&self.render_error(err, outcome)?);
+                output.push_str("\n\n");
+            } else {
+                count_ok += 1;
+            }
+        }
+
+        if self.summarize {
+            output.push_str(&self.render_summary(
+                outcomes,
+                locations.len(),
+                count_ok,
+                count_errors,
+                count_skipped,
+            ));
+        }
+        Ok(output)
+    }
+}
+
+impl ErrorRenderer for PrettyColorRenderer {
+    fn render_invalid_exit_code(
+        &self,
+        outcome: &Outcome,
+        actual: i32,
+        expected: i32,
+    ) -> Result<String> {
+        let mut out = String::new();
+        out.push_str(&formatln!("unexpected exit code"));
+        out.push_str(&formatln!("  expected: {}", expected));
+        out.push_str(&formatln!("  actual:   {}", actual));
+        out.push_str(&formatln!(""));
+        out.push_str(&outcome.output.to_error_string(&outcome.escaping));
+        Ok(out)
+    }
+
+    fn render_delegated_error(&self, _outcome: &Outcome, err: &anyhow::Error) -> Result<String> {
+        Ok(formatln!("error: {}", err))
+    }
+
+    fn render_malformed_output(&self, outcome: &Outcome, diff: &Diff) -> Result<String> {
+        self.render_diff(
+            outcome,
+            diff,
+            self.line_base(outcome),
+            outcome.testcase.expectations().len(),
+        )
+    }
+
+    fn render_malformed_marked_output(
+        &self,
+        outcome: &Outcome,
+        diffs: &[MarkedDiff],
+    ) -> Result<String> {
+        let body = match outcome.testcase.body {
+            ValidationBody::MarkedOutput(ref body) => Cow::Borrowed(body),
+            _ => Cow::Owned(MarkedOutputBody::default()),
+        };
+        let mut output = String::new();
+        for marked in diffs {
+            if !marked.diff.has_differences() {
+                continue;
+            }
+            let line_base = if self.absolute_line_numbers {
+                self.line_base(outcome) + body.offset(marked.stream)
+            } else {
+                0
+            };
+            if !output.is_empty() {
+                output.push('\n');
+            }
+            output.push_str(&formatln!("{}", style(marked.stream.marker()).bold()));
+            output.push_str(&self.render_diff(
+                outcome,
+                &marked.diff,
+                line_base,
+                body.expectations(marked.stream).len(),
+            )?);
+        }
+        Ok(output)
+    }
 
     fn render_timeout(&self, outcome: &Outcome) -> Result<String> {
         let mut out = String::new();
@@ -359,6 +568,52 @@ impl ErrorRenderer for PrettyColorRenderer {
         Ok(out)
     }
 
+    fn render_max_duration_exceeded(
+        &self,
+        _outcome: &Outcome,
+        duration: Duration,
+        max_duration: Duration,
+    ) -> Result<String> {
+        let mut out = String::new();
+        out.push_str(&formatln!("max duration exceeded"));
+        out.push_str(&formatln!(
+            "  expected: at most {}",
+            format_duration_ms(max_duration)
+        ));
+        out.push_str(&formatln!("  actual:   {}", format_duration_ms(duration)));
+        Ok(out)
+    }
+
+    fn render_max_rss_exceeded(
+        &self,
+        _outcome: &Outcome,
+        rss: u64,
+        max_rss: u64,
+    ) -> Result<String> {
+        let mut out = String::new();
+        out.push_str(&formatln!("max rss exceeded"));
+        out.push_str(&formatln!(
+            "  expected: at most {}",
+            format_byte_size(max_rss)
+        ));
+        out.push_str(&formatln!("  actual:   {}", format_byte_size(rss)));
+        Ok(out)
+    }
+
+    fn render_limit_exceeded(
+        &self,
+        outcome: &Outcome,
+        limit: &ResourceLimit,
+        err: &TestCaseError,
+    ) -> Result<String> {
+        let mut out = String::new();
+        out.push_str(&formatln!("resource limit exceeded"));
+        out.push_str(&formatln!("  {}", limit));
+        out.push_str(&formatln!(""));
+        out.push_str(&self.render_error(err, outcome)?);
+        Ok(out)
+    }
+
     fn render_skipped(&self, _outcome: &Outcome) -> Result<String> {
         Ok("".into())
     }
@@ -369,35 +624,94 @@ impl ErrorRenderer for PrettyColorRenderer {
         failure: &JsonSchemaFailure,
     ) -> Result<String> {
         let kind_str = match failure.kind {
-            JsonSchemaFailureKind::InvalidSchema => "invalid schema",
-            JsonSchemaFailureKind::InvalidJson => "invalid JSON output",
-            JsonSchemaFailureKind::ValidationErrors => "schema validation errors",
+            JsonSchemaFailureKind::InvalidSchema => "invalid schema",
+            JsonSchemaFailureKind::InvalidJson => "invalid JSON output",
+            JsonSchemaFailureKind::ValidationErrors => "schema validation errors",
+        };
+
+        let mut out = String::new();
+        out.push_str(&formatln!(
+            "{}",
+            style(format!("JSON Schema validation failed: {}", kind_str))
+                .red()
+                .bold()
+        ));
+        out.push_str(&formatln!(""));
+
+        for error in &failure.errors {
+            out.push_str(&formatln!(
+                "  {} {}",
+                style("-").red().bold(),
+                style(error).red()
+            ));
+        }
+
+        if !failure.output.is_empty() {
+            out.push_str(&formatln!(""));
+            out.push_str(&formatln!("{}", style("actual output:").underlined()));
+            for line in failure.output.lines() {
+                out.push_str(&formatln!("  {}", line));
+            }
+        }
+
+        Ok(out)
+    }
+
+    fn render_interactive_failed(
+        &self,
+        outcome: &Outcome,
+        failure: &InteractiveFailure,
+    ) -> Result<String> {
+        let kind_str = match failure.kind {
+            InteractiveFailureKind::WaitTimeout => "timed out waiting for screen content",
+            InteractiveFailureKind::ScreenMismatch => "expected content not on screen",
+            InteractiveFailureKind::TranscriptMismatch => "expected content not in transcript",
+        };
+        let line_number = if self.absolute_line_numbers {
+            outcome.testcase.line_number + outcome.testcase.shell_expression_lines() + failure.index
+        } else {
+            failure.index + 1
         };
 
         let mut out = String::new();
         out.push_str(&formatln!(
             "{}",
-            style(format!("JSON Schema validation failed: {}", kind_str))
+            style(format!("interactive directive failed: {}", kind_str))
                 .red()
                 .bold()
         ));
         out.push_str(&formatln!(""));
+        out.push_str(&formatln!(
+            "  {} {}",
+            style(format!("{line_number}:")).bold(),
+            style(&failure.directive).red()
+        ));
 
-        for error in &failure.errors {
+        // render the screen within a frame, without trailing empty lines
+        let screen = failure
+            .screen
+            .iter()
+            .rposition(|line| !line.is_empty())
+            .map_or(&[] as &[String], |last| &failure.screen[..=last])
+            .iter()
+            .map(|line| outcome.escaping.escaped_printable(line.as_bytes()))
+            .collect::<Vec<_>>();
+        let width = screen
+            .iter()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or_default();
+        out.push_str(&formatln!(""));
+        out.push_str(&formatln!("{}", style("screen:").underlined()));
+        out.push_str(&formatln!("  ┌{}┐", "─".repeat(width)));
+        for line in &screen {
             out.push_str(&formatln!(
-                "  {} {}",
-                style("-").red().bold(),
-                style(error).red()
+                "  │{}{}│",
+                line,
+                " ".repeat(width - line.chars().count())
             ));
         }
-
-        if !failure.output.is_empty() {
-            out.push_str(&formatln!(""));
-            out.push_str(&formatln!("{}", style("actual output:").underlined()));
-            for line in failure.output.lines() {
-                out.push_str(&formatln!("  {}", line));
-            }
-        }
+        out.push_str(&formatln!("  └{}┘", "─".repeat(width)));
 
         Ok(out)
     }
@@ -479,12 +793,12 @@ impl Decorator {
     ) -> String {
         let color = match symbol {
             "+" => |s: &str| style(s).green().bold().to_string(),
-            "-" => |s: &str| style(s).red().bold().to_string(),
+            "-" | "!" => |s: &str| style(s).red().bold().to_string(),
             _ => |s: &str| style(s).white().to_string(),
         };
         let line_color = match symbol {
             "+" => |s: &str| style(s).green().to_string(),
-            "-" => |s: &str| style(s).red().to_string(),
+            "-" | "!" => |s: &str| style(s).red().to_string(),
             _ => |s: &str| s.to_string(),
         };
         style(format!(
@@ -502,23 +816,36 @@ impl Decorator {
 
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
     use anyhow::anyhow;
 
     use super::PrettyColorRenderer;
     use super::PrettyMonochromeRenderer;
     use crate::bformatln;
+    use crate::config::ResourceLimit;
     use crate::diff::Diff;
     use crate::diff::DiffLine;
+    use crate::diff::DiffTool;
     use crate::escaping::Escaper;
+    use crate::expectation::tests::expectation_maker;
     use crate::formatln;
     use crate::outcome::Outcome;
+    use crate::output::Output;
+    use crate::output::ResourceUsage;
     use crate::parsers::parser::ParserType;
     use crate::renderers::renderer::Renderer;
     use crate::test_expectation;
     use crate::testcase::TestCase;
     use crate::testcase::TestCaseError;
+    use crate::validation::InteractiveFailure;
+    use crate::validation::InteractiveFailureKind;
     use crate::validation::JsonSchemaFailure;
     use crate::validation::JsonSchemaFailureKind;
+    use crate::validation::MarkedDiff;
+    use crate::validation::MarkedOutputBody;
+    use crate::validation::MarkedSection;
+    use crate::validation::MarkedStream;
     use crate::validation::OutputBody;
     use crate::validation::ValidationBody;
     use crate::validation::ValidationFailure;
@@ -552,6 +879,7 @@ mod tests {
                 result: Ok(()),
                 escaping: Escaper::default(),
                 format: ParserType::Markdown,
+                shell: None,
             }])
             .expect("render succeeds");
         assert_eq!(
@@ -584,6 +912,7 @@ mod tests {
                 }),
                 escaping: Escaper::default(),
                 format: ParserType::Markdown,
+                shell: None,
             }])
             .expect("render succeeds");
         insta::assert_snapshot!(rendered);
@@ -612,6 +941,7 @@ mod tests {
                 }),
                 escaping: Escaper::default(),
                 format: ParserType::Markdown,
+                shell: None,
             }])
             .expect("render succeeds");
         insta::assert_snapshot!(rendered);
@@ -668,6 +998,7 @@ mod tests {
                     }),
                     escaping: Escaper::default(),
                     format: ParserType::Markdown,
+                    shell: None,
                 }])
                 .expect("render succeeds");
             insta::assert_snapshot!(
@@ -681,6 +1012,218 @@ mod tests {
         })
     }
 
+    #[test]
+    fn test_render_max_duration_exceeded() {
+        let renderer = new_test_renderer();
+        let rendered = renderer
+            .render(&[&Outcome {
+                location: Some("the location".to_string()),
+                output: Output {
+                    duration: Some(Duration::from_millis(1234)),
+                    ..("the stdout", "the stderr").into()
+                },
+                testcase: TestCase {
+                    title: "the title".to_string(),
+                    shell_expression: "the command".to_string(),
+                    line_number: 234,
+                    ..Default::default()
+                },
+                result: Err(TestCaseError::MaxDurationExceeded {
+                    duration: Duration::from_millis(1234),
+                    max_duration: Duration::from_secs(1),
+                }),
+                escaping: Escaper::default(),
+                format: ParserType::Markdown,
+                shell: None,
+            }])
+            .expect("render succeeds");
+        insta::assert_snapshot!(rendered);
+    }
+
+    #[test]
+    fn test_render_max_rss_exceeded() {
+        let renderer = new_test_renderer();
+        let rendered = renderer
+            .render(&[&Outcome {
+                location: Some("the location".to_string()),
+                output: Output {
+                    resource_usage: Some(ResourceUsage {
+                        max_rss: 3 * 1024 * 1024,
+                        ..Default::default()
+                    }),
+                    ..("the stdout", "the stderr").into()
+                },
+                testcase: TestCase {
+                    title: "the title".to_string(),
+                    shell_expression: "the command".to_string(),
+                    line_number: 234,
+                    ..Default::default()
+                },
+                result: Err(TestCaseError::MaxRssExceeded {
+                    rss: 3 * 1024 * 1024,
+                    max_rss: 1024 * 1024,
+                }),
+                escaping: Escaper::default(),
+                format: ParserType::Markdown,
+                shell: None,
+            }])
+            .expect("render succeeds");
+        insta::assert_snapshot!(rendered);
+    }
+
+    #[test]
+    fn test_render_limit_exceeded() {
+        let renderer = new_test_renderer();
+        let rendered = renderer
+            .render(&[&Outcome {
+                location: Some("the location".to_string()),
+                output: ("the stdout", "", Some(152)).into(),
+                testcase: TestCase {
+                    title: "the title".to_string(),
+                    shell_expression: "the command".to_string(),
+                    line_number: 234,
+                    ..Default::default()
+                },
+                result: Err(TestCaseError::LimitExceeded {
+                    limit: ResourceLimit::CpuTime(Duration::from_secs(1)),
+                    error: Box::new(TestCaseError::InvalidExitCode {
+                        actual: 152,
+                        expected: 0,
+                    }),
+                }),
+                escaping: Escaper::default(),
+                format: ParserType::Markdown,
+                shell: None,
+            }])
+            .expect("render succeeds");
+        insta::assert_snapshot!(rendered);
+    }
+
+    #[test]
+    fn test_render_invalid_exit_code_in_shell() {
+        let renderer = new_test_renderer();
+        let rendered = renderer
+            .render(&[&Outcome {
+                location: Some("the location".to_string()),
+                output: ("", "", Some(1)).into(),
+                testcase: TestCase {
+                    title: "the title".to_string(),
+                    shell_expression: "the command".to_string(),
+                    line_number: 234,
+                    ..Default::default()
+                },
+                result: Err(TestCaseError::InvalidExitCode {
+                    actual: 1,
+                    expected: 0,
+                }),
+                escaping: Escaper::default(),
+                format: ParserType::Markdown,
+                shell: Some("/bin/dash".to_string()),
+            }])
+            .expect("render succeeds");
+        insta::assert_snapshot!(rendered);
+    }
+
+    #[test]
+    fn test_render_always_run() {
+        let renderer = PrettyMonochromeRenderer::new(PrettyColorRenderer::default());
+        let outcome = |title: &str, line_number: usize, always_run: bool, result| Outcome {
+            location: Some("the location".to_string()),
+            output: Output {
+                always_run,
+                ..("", "").into()
+            },
+            testcase: TestCase {
+                title: title.to_string(),
+                shell_expression: "the command".to_string(),
+                line_number,
+                ..Default::default()
+            },
+            result,
+            escaping: Escaper::default(),
+            format: ParserType::Markdown,
+            shell: None,
+        };
+        let rendered = renderer
+            .render(&[
+                &outcome("timeout", 1, false, Err(TestCaseError::Timeout)),
+                &outcome("skipped", 5, false, Err(TestCaseError::Skipped)),
+                &outcome("teardown", 10, true, Ok(())),
+            ])
+            .expect("render succeeds");
+        assert_eq!(
+            [
+                "// =============================================================================",
+                "// @ the location:1",
+                "// -----------------------------------------------------------------------------",
+                "// # timeout",
+                "// -----------------------------------------------------------------------------",
+                "// $ the command",
+                "// =============================================================================",
+                "",
+                "timeout in execution",
+                "",
+                "## STDOUT",
+                "## STDERR",
+                "",
+                "",
+                "Always run: 1 testcase(s) ran after the execution ended early",
+                "  succeeded  the location:10 teardown (always run)",
+                "",
+                "Result: 1 document(s) with 3 testcase(s): 1 succeeded, 1 failed and 1 skipped",
+                "",
+            ]
+            .join("\n"),
+            rendered,
+        );
+    }
+
+    #[test]
+    fn test_render_slowest() {
+        let renderer = PrettyMonochromeRenderer::new(PrettyColorRenderer {
+            slowest: 2,
+            ..Default::default()
+        });
+        let outcome = |title: &str, line_number: usize, duration: Option<u64>, result| Outcome {
+            location: Some("the location".to_string()),
+            output: Output {
+                duration: duration.map(Duration::from_millis),
+                ..("the stdout", "the stderr").into()
+            },
+            testcase: TestCase {
+                title: title.to_string(),
+                shell_expression: "the command".to_string(),
+                line_number,
+                ..Default::default()
+            },
+            result,
+            escaping: Escaper::default(),
+            format: ParserType::Markdown,
+            shell: None,
+        };
+        let rendered = renderer
+            .render(&[
+                &outcome("fast", 1, Some(12), Ok(())),
+                &outcome("slowest", 5, Some(2345), Ok(())),
+                &outcome("unknown", 10, None, Ok(())),
+                &outcome("skipped", 15, Some(9999), Err(TestCaseError::Skipped)),
+                &outcome("slow", 20, Some(678), Ok(())),
+            ])
+            .expect("render succeeds");
+        assert_eq!(
+            [
+                "Slowest: 2 testcase(s)",
+                "  2s 345ms  the location:5 slowest",
+                "     678ms  the location:20 slow",
+                "",
+                "Result: 1 document(s) with 5 testcase(s): 4 succeeded, 0 failed and 1 skipped",
+                "",
+            ]
+            .join("\n"),
+            rendered,
+        );
+    }
+
     #[test]
     fn test_render_internal_error() {
         let renderer = new_test_renderer();
@@ -701,6 +1244,7 @@ mod tests {
                 result: Err(TestCaseError::InternalError(anyhow!("something failed"))),
                 escaping: Escaper::default(),
                 format: ParserType::Markdown,
+                shell: None,
             }])
             .expect("render does not fail");
         insta::assert_snapshot!(rendered);
@@ -750,11 +1294,97 @@ mod tests {
                 )),
                 escaping: Escaper::default(),
                 format: ParserType::Markdown,
+                shell: None,
+            }])
+            .expect("render does not fail");
+        insta::assert_snapshot!(rendered);
+    }
+
+    #[test]
+    fn test_render_malformed_output_negated_expectation() {
+        let renderer = new_test_renderer();
+        let maker = expectation_maker();
+        let expectations = ["start", "*panicked at* (!glob)", "WARNING (!)", "end"]
+            .iter()
+            .map(|line| maker.parse(line).expect("parse expectation"))
+            .collect::<Vec<_>>();
+        let output = "start\nthread panicked at here\nend\n";
+        let diff = DiffTool::new(expectations.clone())
+            .diff(output.as_bytes())
+            .expect("diff created");
+        let rendered = renderer
+            .render(&[&Outcome {
+                location: None,
+                output: (output, "", Some(0)).into(),
+                testcase: TestCase {
+                    title: "the title".to_string(),
+                    shell_expression: "the command".to_string(),
+                    body: ValidationBody::Output(OutputBody { expectations }),
+                    line_number: 234,
+                    ..Default::default()
+                },
+                result: Err(TestCaseError::ValidationFailed(
+                    ValidationFailure::MalformedOutput(diff),
+                )),
+                escaping: Escaper::default(),
+                format: ParserType::Markdown,
+                shell: None,
             }])
             .expect("render does not fail");
         insta::assert_snapshot!(rendered);
     }
 
+    #[test]
+    fn test_render_malformed_output_explains_mismatch() {
+        let rendered = render_malformed_output(
+            &["start", "elapsed: {0.5±0.1}s (approx)", "end"],
+            "start\nelapsed: 0.9s\nend\n",
+        );
+        insta::assert_snapshot!(rendered);
+    }
+
+    #[test]
+    fn test_render_malformed_output_explains_json_mismatch() {
+        let rendered = render_malformed_output(
+            &[
+                r#"{"level": "info", "msg": "<string>"} (json)"#,
+                r#"{"level": "info", "took": "<number>", "tags": ["a"]} (json)"#,
+            ],
+            "{\"msg\":\"started\",\"level\":\"info\"}\n{\"level\":\"warn\",\"took\":\"1s\",\"tags\":[\"a\",\"b\"]}\n",
+        );
+        insta::assert_snapshot!(rendered);
+    }
+
+    fn render_malformed_output(expectations: &[&str], output: &str) -> String {
+        let maker = expectation_maker();
+        let expectations = expectations
+            .iter()
+            .map(|line| maker.parse(line).expect("parse expectation"))
+            .collect::<Vec<_>>();
+        let diff = DiffTool::new(expectations.clone())
+            .diff(output.as_bytes())
+            .expect("diff created");
+        new_test_renderer()
+            .render(&[&Outcome {
+                location: None,
+                output: (output, "", Some(0)).into(),
+                testcase: TestCase {
+                    title: "the title".to_string(),
+                    shell_expression: "the command".to_string(),
+                    body: ValidationBody::Output(OutputBody { expectations }),
+                    line_number: 234,
+                    ..Default::default()
+                },
+                result: Err(TestCaseError::ValidationFailed(
+                    ValidationFailure::MalformedOutput(diff),
+                )),
+                escaping: Escaper::default(),
+                format: ParserType::Markdown,
+                shell: None,
+            }])
+            .expect("render does not fail")
+    }
+
     #[test]
     fn test_render_malformed_output_multiple_lines() {
         let renderer = PrettyMonochromeRenderer::new(PrettyColorRenderer {
@@ -806,6 +1436,7 @@ mod tests {
                 )),
                 escaping: Escaper::default(),
                 format: ParserType::Markdown,
+                shell: None,
             }])
             .expect("render does not fail");
         insta::assert_snapshot!(rendered);
@@ -903,6 +1534,7 @@ mod tests {
                     )),
                     escaping: Escaper::default(),
                     format: ParserType::Markdown,
+                    shell: None,
                 }])
                 .expect("render does not fail");
             insta::assert_snapshot!(
@@ -949,6 +1581,7 @@ mod tests {
                 )),
                 escaping: Escaper::default(),
                 format: ParserType::Markdown,
+                shell: None,
             }])
             .expect("render does not fail");
         insta::assert_snapshot!(rendered);
@@ -1015,6 +1648,7 @@ mod tests {
                     )),
                     escaping: Escaper::default(),
                     format: ParserType::Markdown,
+                    shell: None,
                 }])
                 .expect("render does not fail");
             insta::assert_snapshot!(
@@ -1061,6 +1695,7 @@ mod tests {
                     absolute_line_numbers: false,
                     summarize: false,
                     max_multiline_matched_lines: max_multiline_matched_lines as usize,
+                    ..Default::default()
                 });
                 let rendered = renderer
                     .render(&[&Outcome {
@@ -1072,6 +1707,7 @@ mod tests {
                         )),
                         escaping: Escaper::default(),
                         format: ParserType::Markdown,
+                        shell: None,
                     }])
                     .expect("render does not fail");
                 insta::assert_snapshot!(
@@ -1133,9 +1769,142 @@ mod tests {
                     )),
                     escaping: Escaper::default(),
                     format: ParserType::Markdown,
+                    shell: None,
                 }])
                 .expect("render succeeds");
             insta::assert_snapshot!(format!("json_schema_failed_{name}"), rendered);
         }
     }
+
+    #[test]
+    fn test_render_interactive_failed() {
+        let renderer = new_test_renderer();
+        let kinds = [
+            (
+                "wait_timeout",
+                InteractiveFailureKind::WaitTimeout,
+                "WAIT Continue? (glob)",
+            ),
+            (
+                "screen_mismatch",
+                InteractiveFailureKind::ScreenMismatch,
+                "ASSERT Done",
+            ),
+            (
+                "transcript_mismatch",
+                InteractiveFailureKind::TranscriptMismatch,
+                "ASSERT_TRANSCRIPT ^ok$ (regex)",
+            ),
+        ];
+
+        for (name, kind, directive) in &kinds {
+            let rendered = renderer
+                .render(&[&Outcome {
+                    output: ("Proceed? [y/N]\n", "").into(),
+                    testcase: TestCase {
+                        title: "the title".to_string(),
+                        shell_expression: "the command".into(),
+                        body: ValidationBody::Output(OutputBody {
+                            expectations: vec![],
+                        }),
+                        exit_code: None,
+                        line_number: 10,
+                        ..Default::default()
+                    },
+                    location: Some("the location".into()),
+                    result: Err(TestCaseError::ValidationFailed(
+                        ValidationFailure::InteractiveFailed(InteractiveFailure {
+                            kind: kind.clone(),
+                            index: 1,
+                            directive: directive.to_string(),
+                            screen: vec!["Proceed? [y/N]".to_string(), "".to_string()],
+                        }),
+                    )),
+                    escaping: Escaper::default(),
+                    format: ParserType::Markdown,
+                    shell: None,
+                }])
+                .expect("render succeeds");
+            insta::assert_snapshot!(format!("interactive_failed_{name}"), rendered);
+        }
+    }
+
+    #[test]
+    fn test_render_malformed_marked_output() {
+        let renderer = new_test_renderer();
+        let stdout_section = MarkedSection {
+            stream: MarkedStream::Stdout,
+            expectations: vec![test_expectation!("the output")],
+        };
+        let stderr_section = MarkedSection {
+            stream: MarkedStream::Stderr,
+            expectations: vec![test_expectation!("the error")],
+        };
+        let stdout_diff = MarkedDiff {
+            stream: MarkedStream::Stdout,
+            diff: Diff::new(vec![DiffLine::MatchedExpectation {
+                index: 0,
+                expectation: test_expectation!("the output"),
+                lines: vec![(0, "the output\n".as_bytes().to_vec())],
+            }]),
+        };
+        let tests = [
+            (
+                "mismatch",
+                vec![stdout_section.clone(), stderr_section.clone()],
+                vec![
+                    stdout_diff.clone(),
+                    MarkedDiff {
+                        stream: MarkedStream::Stderr,
+                        diff: Diff::new(vec![
+                            DiffLine::UnmatchedExpectation {
+                                index: 0,
+                                expectation: test_expectation!("the error"),
+                            },
+                            DiffLine::UnexpectedLines {
+                                lines: vec![(0, "another error\n".as_bytes().to_vec())],
+                            },
+                        ]),
+                    },
+                ],
+            ),
+            (
+                "new_section",
+                vec![stdout_section.clone()],
+                vec![
+                    stdout_diff.clone(),
+                    MarkedDiff {
+                        stream: MarkedStream::Stderr,
+                        diff: Diff::new(vec![DiffLine::UnexpectedLines {
+                            lines: vec![(0, "another error\n".as_bytes().to_vec())],
+                        }]),
+                    },
+                ],
+            ),
+        ];
+
+        for (name, sections, diffs) in tests {
+            let rendered = renderer
+                .render(&[&Outcome {
+                    output: ("the output\n", "another error\n").into(),
+                    testcase: TestCase {
+                        title: "the title".to_string(),
+                        shell_expression: "the command".into(),
+                        body: ValidationBody::MarkedOutput(MarkedOutputBody { sections }),
+                        exit_code: None,
+                        line_number: 10,
+                        ..Default::default()
+                    },
+                    location: Some("the location".into()),
+                    result: Err(TestCaseError::ValidationFailed(
+                        ValidationFailure::MalformedMarkedOutput(diffs),
+                    )),
+                    escaping: Escaper::default(),
+                    format: ParserType::Markdown,
+                    shell: None,
+                }])
+                .expect("render succeeds");
+            insta::assert_snapshot!(format!("malformed_marked_output_{name}"), rendered);
+        }
+    }
 }
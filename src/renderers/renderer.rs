@@ -5,12 +5,17 @@
  * LICENSE file in the root directory of this source tree.
  */
 
+use std::time::Duration;
+
 use anyhow::Result;
 
+use crate::config::ResourceLimit;
 use crate::diff::Diff;
 use crate::outcome::Outcome;
 use crate::testcase::TestCaseError;
+use crate::validation::InteractiveFailure;
 use crate::validation::JsonSchemaFailure;
+use crate::validation::MarkedDiff;
 use crate::validation::ValidationFailure;
 
 /// Renderer translate errors from validating [`crate::testcase::TestCase`]s into
@@ -32,6 +37,16 @@ pub(super) trait ErrorRenderer: Renderer {
             }
             TestCaseError::InternalError(err) => self.render_delegated_error(outcome, err),
             TestCaseError::Timeout => self.render_timeout(outcome),
+            TestCaseError::MaxDurationExceeded {
+                duration,
+                max_duration,
+            } => self.render_max_duration_exceeded(outcome, *duration, *max_duration),
+            TestCaseError::MaxRssExceeded { rss, max_rss } => {
+                self.render_max_rss_exceeded(outcome, *rss, *max_rss)
+            }
+            TestCaseError::LimitExceeded { limit, error } => {
+                self.render_limit_exceeded(outcome, limit, error)
+            }
             TestCaseError::Skipped => self.render_skipped(outcome),
         }
     }
@@ -43,9 +58,15 @@ pub(super) trait ErrorRenderer: Renderer {
     ) -> Result<String> {
         match failure {
             ValidationFailure::MalformedOutput(diff) => self.render_malformed_output(outcome, diff),
+            ValidationFailure::MalformedMarkedOutput(diffs) => {
+                self.render_malformed_marked_output(outcome, diffs)
+            }
             ValidationFailure::JsonSchemaFailed(failure) => {
                 self.render_json_schema_failed(outcome, failure)
             }
+            ValidationFailure::InteractiveFailed(failure) => {
+                self.render_interactive_failed(outcome, failure)
+            }
         }
     }
 
@@ -60,8 +81,30 @@ pub(super) trait ErrorRenderer: Renderer {
 
     fn render_malformed_output(&self, outcome: &Outcome, diff: &Diff) -> Result<String>;
 
+    fn render_malformed_marked_output(
+        &self,
+        outcome: &Outcome,
+        diffs: &[MarkedDiff],
+    ) -> Result<String>;
+
     fn render_timeout(&self, outcome: &Outcome) -> Result<String>;
 
+    fn render_max_duration_exceeded(
+        &self,
+        outcome: &Outcome,
+        duration: Duration,
+        max_duration: Duration,
+    ) -> Result<String>;
+
+    fn render_max_rss_exceeded(&self, outcome: &Outcome, rss: u64, max_rss: u64) -> Result<String>;
+
+    fn render_limit_exceeded(
+        &self,
+        outcome: &Outcome,
+        limit: &ResourceLimit,
+        err: &TestCaseError,
+    ) -> Result<String>;
+
     fn render_skipped(&self, outcome: &Outcome) -> Result<String>;
 
     fn render_json_schema_failed(
@@ -69,4 +112,9 @@ pub(super) trait ErrorRenderer: Renderer {
         outcome: &Outcome,
         failure: &JsonSchemaFailure,
     ) -> Result<String>;
+    fn render_interactive_failed(
+        &self,
+        outcome: &Outcome,
+        failure: &InteractiveFailure,
+    ) -> Result<String>;
 }
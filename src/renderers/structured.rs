@@ -5,8 +5,17 @@
  * LICENSE file in the root directory of this source tree.
  */
 
+use serde_yaml::Mapping;
+use serde_yaml::Value;
+
+use super::outcome::error_summary;
+use super::outcome::outcome_name;
+use super::pretty::PrettyColorRenderer;
+use super::renderer::ErrorRenderer;
 use super::renderer::Renderer;
+use crate::escaping::strip_colors;
 use crate::outcome::Outcome;
+use crate::testcase::TestCaseError;
 
 pub struct JsonRenderer(bool);
 
@@ -53,18 +62,106 @@ impl Renderer for YamlRenderer {
     }
 }
 
+/// Renders outcomes in the [Test Anything Protocol](https://testanything.org/)
+/// version 13, with YAML diagnostics for failed outcomes. Diffs in the
+/// diagnostics are rendered with the wrapped [`PrettyColorRenderer`], stripped
+/// from colors.
+#[derive(Default)]
+pub struct TapRenderer(PrettyColorRenderer);
+
+impl TapRenderer {
+    pub fn new(pretty_renderer: PrettyColorRenderer) -> Self {
+        Self(pretty_renderer)
+    }
+
+    fn render_diagnostics(&self, outcome: &Outcome, err: &TestCaseError) -> anyhow::Result<String> {
+        let mut diagnostics = Mapping::new();
+        diagnostics.insert("message".into(), error_summary(err).into());
+        diagnostics.insert("severity".into(), "fail".into());
+        diagnostics.insert("kind".into(), err.kind().into());
+        let mut at = Mapping::new();
+        if let Some(ref location) = outcome.location {
+            at.insert("file".into(), location.as_str().into());
+        }
+        at.insert("line".into(), outcome.testcase.line_number.into());
+        diagnostics.insert("at".into(), at.into());
+        match err {
+            TestCaseError::InvalidExitCode { actual, expected } => {
+                diagnostics.insert("expected".into(), (*expected).into());
+                diagnostics.insert("actual".into(), (*actual).into());
+            }
+            TestCaseError::ValidationFailed(_) => {
+                let diff = strip_colors(&self.0.render_error(err, outcome)?)?;
+                diagnostics.insert("diff".into(), diff.into());
+            }
+            _ => {}
+        }
+
+        let rendered = serde_yaml::to_string(&Value::Mapping(diagnostics))?;
+        let mut output = String::from("  ---\n");
+        for line in rendered.lines() {
+            output.push_str(&format!("  {line}\n"));
+        }
+        output.push_str("  ...\n");
+        Ok(output)
+    }
+}
+
+impl Renderer for TapRenderer {
+    fn render(&self, outcomes: &[&Outcome]) -> anyhow::Result<String> {
+        let mut output = String::from("TAP version 13\n");
+        output.push_str(&format!("1..{}\n", outcomes.len()));
+        for (index, outcome) in outcomes.iter().enumerate() {
+            let mut description = escape_tap(&outcome_name(outcome));
+            if let Some(ref location) = outcome.location {
+                description.push_str(&format!(
+                    " ({}:{})",
+                    escape_tap(location),
+                    outcome.testcase.line_number
+                ));
+            }
+            match outcome.result {
+                Ok(_) => output.push_str(&format!("ok {} - {description}\n", index + 1)),
+                Err(TestCaseError::Skipped) => {
+                    output.push_str(&format!("ok {} - {description} # SKIP\n", index + 1))
+                }
+                Err(ref err) => {
+                    output.push_str(&format!("not ok {} - {description}\n", index + 1));
+                    output.push_str(&self.render_diagnostics(outcome, err)?);
+                }
+            }
+        }
+        Ok(output)
+    }
+}
+
+/// Escape text for use in a TAP test line description, which must not contain
+/// newlines or unescaped `#`, that would start a directive
+fn escape_tap(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('#', "\\#")
+        .replace(['\n', '\r'], " ")
+}
+
 #[cfg(test)]
 mod tests {
     use anyhow::Result;
 
     use super::JsonRenderer;
+    use super::TapRenderer;
     use super::YamlRenderer;
+    use crate::diff::Diff;
+    use crate::diff::DiffLine;
     use crate::escaping::Escaper;
     use crate::outcome::Outcome;
     use crate::parsers::parser::ParserType;
     use crate::renderers::renderer::Renderer;
+    use crate::test_expectation;
     use crate::testcase::TestCase;
     use crate::testcase::TestCaseError;
+    use crate::validation::OutputBody;
+    use crate::validation::ValidationBody;
+    use crate::validation::ValidationFailure;
 
     #[test]
     fn test_json_render() {
@@ -87,6 +184,57 @@ mod tests {
         insta::assert_snapshot!(rendered);
     }
 
+    #[test]
+    fn test_tap_render() {
+        let renderer = TapRenderer::default();
+        let rendered = render(renderer).expect("rendering succeeds");
+        insta::assert_snapshot!(rendered);
+    }
+
+    #[test]
+    fn test_tap_render_diagnostics() {
+        let renderer = TapRenderer::default();
+        let outcome = |title: &str, result| Outcome {
+            output: ("the stdout\n", "the stderr\n").into(),
+            testcase: TestCase {
+                title: title.to_string(),
+                shell_expression: "the command".to_string(),
+                body: ValidationBody::Output(OutputBody {
+                    expectations: vec![test_expectation!("equal", "expected")],
+                }),
+                exit_code: None,
+                line_number: 234,
+                ..Default::default()
+            },
+            location: Some("the/location.md".to_string()),
+            result,
+            escaping: Escaper::default(),
+            format: ParserType::Markdown,
+            shell: None,
+        };
+        let rendered = renderer
+            .render(&[
+                &outcome("skipped #1", Err(TestCaseError::Skipped)),
+                &outcome(
+                    "malformed",
+                    Err(TestCaseError::ValidationFailed(
+                        ValidationFailure::MalformedOutput(Diff::new(vec![
+                            DiffLine::UnmatchedExpectation {
+                                index: 0,
+                                expectation: test_expectation!("equal", "expected"),
+                            },
+                            DiffLine::UnexpectedLines {
+                                lines: vec![(0, b"the stdout\n".to_vec())],
+                            },
+                        ])),
+                    )),
+                ),
+                &outcome("timeout", Err(TestCaseError::Timeout)),
+            ])
+            .expect("rendering succeeds");
+        insta::assert_snapshot!(rendered);
+    }
+
     fn render<T: Renderer>(renderer: T) -> Result<String> {
         renderer.render(&[
             &Outcome {
@@ -102,6 +250,7 @@ mod tests {
                 result: Ok(()),
                 escaping: Escaper::default(),
                 format: ParserType::Markdown,
+                shell: None,
             },
             &Outcome {
                 output: ("stdout 1", "stderr 1").into(),
@@ -116,6 +265,7 @@ mod tests {
                 result: Ok(()),
                 escaping: Escaper::default(),
                 format: ParserType::Markdown,
+                shell: None,
             },
             &Outcome {
                 output: ("stdout 2", "stderr 2").into(),
@@ -133,6 +283,7 @@ mod tests {
                 }),
                 escaping: Escaper::default(),
                 format: ParserType::Markdown,
+                shell: None,
             },
         ])
     }
@@ -0,0 +1,277 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! The approx rule matches lines that are literal, except for numeric
+//! placeholders in curly brackets, which accept any number within a tolerance:
+//! - `{0.5±0.1}` (or `{0.5+-0.1}`): a number within an absolute tolerance
+//! - `{100±5%}`: a number within a tolerance relative to the value
+//! - `{1..100}`: a number within the inclusive range, either end can be omitted
+//!
+//! Curly brackets that do not contain a placeholder are matched literally.
+use std::fmt::Display;
+use std::sync::LazyLock;
+
+use anyhow::Result;
+use anyhow::bail;
+use regex::Regex;
+use regex::bytes::Regex as ByteRegex;
+
+use super::rule::Rule;
+use super::rule::RuleMaker;
+use crate::lossy_string;
+use crate::newline::BytesNewline;
+
+/// A number, as it is written in the expression and looked for in the output
+const NUMBER: &str = r"[-+]?(?:[0-9]+(?:\.[0-9]*)?|\.[0-9]+)(?:[eE][-+]?[0-9]+)?";
+
+static PLACEHOLDER: LazyLock<Regex> = LazyLock::new(|| {
+    let unsigned = NUMBER.trim_start_matches("[-+]?");
+    Regex::new(&format!(
+        r"\{{\s*(?:(?P<value>{NUMBER})\s*(?:±|\+/?-)\s*(?P<tolerance>{unsigned})\s*(?P<percent>%)?|(?P<min>{NUMBER})?\s*\.\.\s*(?P<max>{NUMBER})?)\s*\}}"
+    ))
+    .expect("approx placeholder regex must compile")
+});
+
+/// Match lines with numbers that must be within a tolerance
+#[derive(Clone, Debug)]
+pub struct ApproxRule {
+    expression: String,
+    regex: ByteRegex,
+    placeholders: Vec<Placeholder>,
+}
+
+/// The inclusive bounds a number in the output must be within
+#[derive(Clone, Debug)]
+struct Placeholder {
+    text: String,
+    min: f64,
+    max: f64,
+}
+
+impl Placeholder {
+    fn contains(&self, value: f64) -> bool {
+        self.min <= value && value <= self.max
+    }
+}
+
+impl ApproxRule {
+    /// Returns each number of the line with the placeholder it was matched
+    /// by, or `None` if the line does not look like the expression at all
+    fn numbers<'a>(&'a self, line: &'a [u8]) -> Option<Vec<(String, &'a Placeholder)>> {
+        let captures = self.regex.captures(line.trim_newlines())?;
+        Some(
+            captures
+                .iter()
+                .skip(1)
+                .zip(&self.placeholders)
+                .filter_map(|(number, placeholder)| {
+                    number.map(|number| (lossy_string!(number.as_bytes()), placeholder))
+                })
+                .collect(),
+        )
+    }
+}
+
+impl Display for ApproxRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.expression)
+    }
+}
+
+impl PartialEq for ApproxRule {
+    fn eq(&self, other: &Self) -> bool {
+        self.expression == other.expression
+    }
+}
+
+impl Rule for ApproxRule {
+    fn kind(&self) -> &'static str {
+        "approx"
+    }
+
+    fn matches(&self, line: &[u8]) -> bool {
+        self.numbers(line).is_some_and(|numbers| {
+            numbers.iter().all(|(number, placeholder)| {
+                number
+                    .parse()
+                    .is_ok_and(|value| placeholder.contains(value))
+            })
+        })
+    }
+
+    fn unmake(&self) -> (String, Vec<u8>) {
+        (self.kind().to_string(), self.expression.as_bytes().to_vec())
+    }
+
+    fn explain_mismatch(&self, line: &[u8]) -> Option<String> {
+        self.numbers(line)?
+            .into_iter()
+            .find(|(number, placeholder)| {
+                !number
+                    .parse()
+                    .is_ok_and(|value| placeholder.contains(value))
+            })
+            .map(|(number, placeholder)| format!("{number} is not within {}", placeholder.text))
+    }
+}
+
+impl RuleMaker for ApproxRule {
+    fn make(expression: &str) -> Result<Box<dyn Rule>> {
+        let mut pattern = String::from("^");
+        let mut placeholders = vec![];
+        let mut literal_start = 0;
+        for captures in PLACEHOLDER.captures_iter(expression) {
+            let matched = captures.get(0).expect("placeholder match");
+            let number = |name: &str| -> Result<Option<f64>> {
+                captures
+                    .name(name)
+                    .map(|number| number.as_str().parse::<f64>())
+                    .transpose()
+                    .map_err(anyhow::Error::new)
+            };
+            let (min, max) = if let Some(value) = number("value")? {
+                let mut tolerance = number("tolerance")?.unwrap_or_default();
+                if captures.name("percent").is_some() {
+                    tolerance *= value.abs() / 100.0;
+                }
+                // compensate for the imprecision of floating point arithmetic
+                let slack = (value.abs() + tolerance) * 1e-12;
+                (value - tolerance - slack, value + tolerance + slack)
+            } else {
+                let min = number("min")?.unwrap_or(f64::NEG_INFINITY);
+                let max = number("max")?.unwrap_or(f64::INFINITY);
+                if min > max {
+                    bail!(
+                        "range placeholder `{}` must not start after it ends",
+                        matched.as_str()
+                    );
+                }
+                (min, max)
+            };
+
+            pattern.push_str(&regex::escape(&expression[literal_start..matched.start()]));
+            pattern.push_str(&format!("({NUMBER})"));
+            literal_start = matched.end();
+            placeholders.push(Placeholder {
+                text: matched.as_str().to_string(),
+                min,
+                max,
+            });
+        }
+        if placeholders.is_empty() {
+            bail!(
+                "approx expression `{expression}` must contain a placeholder, like `{{0.5±0.1}}`, `{{100±5%}}` or `{{1..100}}`"
+            );
+        }
+        pattern.push_str(&regex::escape(&expression[literal_start..]));
+        pattern.push('$');
+
+        Ok(Box::new(Self {
+            expression: expression.to_string(),
+            regex: ByteRegex::new(&pattern)?,
+            placeholders,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ApproxRule;
+    use crate::lossy_string;
+    use crate::newline::StringNewline;
+    use crate::rules::rule::RuleMaker;
+
+    #[test]
+    fn test_make_unmake() {
+        let rule = ApproxRule::make("elapsed: {0.5±0.1}s").expect("rule is created");
+        let (kind, expression) = rule.unmake();
+        assert_eq!("approx", kind);
+        assert_eq!("elapsed: {0.5±0.1}s", lossy_string!(&expression));
+    }
+
+    #[test]
+    fn test_make_fails() {
+        let tests = vec![
+            ("no placeholder", "elapsed: 0.5s"),
+            ("no number placeholder", "elapsed: {foo}s"),
+            ("range ends before it starts", "count: {10..1}"),
+        ];
+        for (name, expression) in tests {
+            assert!(ApproxRule::make(expression).is_err(), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_rule_matches() {
+        let tests = vec![
+            (true, "elapsed: {0.5±0.1}s", "elapsed: 0.532s"),
+            (true, "elapsed: {0.5±0.1}s", "elapsed: 0.4s"),
+            (true, "elapsed: {0.5±0.1}s", "elapsed: 0.6s"),
+            (true, "elapsed: {1.2±0.1}s", "elapsed: 1.3s"),
+            (false, "elapsed: {0.5±0.1}s", "elapsed: 0.61s"),
+            (false, "elapsed: {0.5±0.1}s", "elapsed: 0.5"),
+            (false, "elapsed: {0.5±0.1}s", "took: 0.5s"),
+            (true, "elapsed: {0.5+-0.1}s", "elapsed: 0.45s"),
+            (true, "elapsed: {0.5+/-0.1}s", "elapsed: 0.45s"),
+            (true, "elapsed: { 0.5 ± 0.1 }s", "elapsed: 0.45s"),
+            (true, "size: {10±5%} MiB", "size: 10.2 MiB"),
+            (true, "size: {-10±5%} MiB", "size: -9.5 MiB"),
+            (false, "size: {10±5%} MiB", "size: 10.6 MiB"),
+            (true, "score={1e3±1}", "score=1000.5"),
+            (true, "score={1000±1}", "score=1.0005e3"),
+            (true, "{1..100} files", "1 files"),
+            (true, "{1..100} files", "100 files"),
+            (false, "{1..100} files", "0 files"),
+            (false, "{1..100} files", "101 files"),
+            (true, "{..0} left", "-3 left"),
+            (true, "{5..} done", "7.5 done"),
+            (false, "{5..} done", "4.9 done"),
+            (true, "{1..9}.txt", "3.txt"),
+            (true, "{..} anything", "12345 anything"),
+            (true, "{1..2} of {10±1}", "2 of 9"),
+            (false, "{1..2} of {10±1}", "2 of 12"),
+            (true, "{foo} is {1..2}", "{foo} is 1"),
+            (true, "a.b {1..2}", "a.b 1"),
+            (false, "a.b {1..2}", "axb 1"),
+        ];
+
+        for (expect, expression, line) in tests {
+            let rule = ApproxRule::make(expression)
+                .unwrap_or_else(|err| panic!("create rule from {expression}: {err}"));
+            assert_eq!(
+                expect,
+                rule.matches(line.assure_newline().as_bytes()),
+                "{expression} vs {line}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_explain_mismatch() {
+        let rule = ApproxRule::make("took {0.5±0.1}s for {1..10} files").expect("rule is created");
+        assert_eq!(None, rule.explain_mismatch(b"took 0.5s for 5 files\n"));
+        assert_eq!(None, rule.explain_mismatch(b"something else\n"));
+        assert_eq!(
+            Some("0.9 is not within {0.5±0.1}".to_string()),
+            rule.explain_mismatch(b"took 0.9s for 11 files\n")
+        );
+        assert_eq!(
+            Some("11 is not within {1..10}".to_string()),
+            rule.explain_mismatch(b"took 0.5s for 11 files\n")
+        );
+    }
+
+    #[test]
+    fn test_rule_serialize() {
+        let rule = ApproxRule::make("{1..2}").unwrap();
+        let serialized = serde_json::to_string(&rule).expect("serialize");
+        assert_eq!(
+            "{\"kind\":\"approx\",\"expression\":\"{1..2}\"}",
+            serialized
+        );
+    }
+}
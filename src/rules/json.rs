@@ -0,0 +1,248 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! The json rule parses both the expression and the output line as JSON and
+//! compares them structurally: the order of object keys and the formatting of
+//! numbers are ignored. String values in the expression can be placeholders:
+//! - `"<any>"`: any value
+//! - `"<string>"`: any string
+//! - `"<number>"`: any number
+//! - `"<bool>"`: `true` or `false`
+use std::fmt::Display;
+
+use anyhow::Context;
+use anyhow::Result;
+use serde_json::Number;
+use serde_json::Value;
+
+use super::rule::Rule;
+use super::rule::RuleMaker;
+use crate::newline::BytesNewline;
+
+/// Match lines that contain a JSON value, that is structurally equal to the
+/// expression
+#[derive(Clone, Debug)]
+pub struct JsonRule(String, Value);
+
+impl Display for JsonRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl PartialEq for JsonRule {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl JsonRule {
+    /// Parses the line as JSON, if it is JSON
+    fn parse_line(line: &[u8]) -> Option<Value> {
+        serde_json::from_slice(line.trim_newlines()).ok()
+    }
+}
+
+impl Rule for JsonRule {
+    fn kind(&self) -> &'static str {
+        "json"
+    }
+
+    fn matches(&self, line: &[u8]) -> bool {
+        Self::parse_line(line).is_some_and(|actual| {
+            let mut differences = vec![];
+            compare("$", &self.1, &actual, &mut differences);
+            differences.is_empty()
+        })
+    }
+
+    fn unmake(&self) -> (String, Vec<u8>) {
+        (self.kind().to_string(), self.0.as_bytes().to_vec())
+    }
+
+    /// Lists the differences, one per line, with the JSON path to each
+    fn explain_mismatch(&self, line: &[u8]) -> Option<String> {
+        let actual = Self::parse_line(line)?;
+        let mut differences = vec![];
+        compare("$", &self.1, &actual, &mut differences);
+        if differences.is_empty() {
+            None
+        } else {
+            Some(differences.join("\n"))
+        }
+    }
+}
+
+impl RuleMaker for JsonRule {
+    fn make(expression: &str) -> Result<Box<dyn Rule>> {
+        let value = serde_json::from_str(expression)
+            .with_context(|| format!("parse json expression `{expression}`"))?;
+        Ok(Box::new(Self(expression.to_string(), value)))
+    }
+}
+
+/// Collects the differences between the expected and the actual value at the
+/// given JSON path
+fn compare(path: &str, expected: &Value, actual: &Value, differences: &mut Vec<String>) {
+    let matches = match (expected, actual) {
+        (Value::String(placeholder), _) if placeholder == "<any>" => true,
+        (Value::String(placeholder), Value::String(_)) if placeholder == "<string>" => true,
+        (Value::String(placeholder), Value::Number(_)) if placeholder == "<number>" => true,
+        (Value::String(placeholder), Value::Bool(_)) if placeholder == "<bool>" => true,
+        (Value::Number(expected), Value::Number(actual)) => numbers_equal(expected, actual),
+        (Value::Array(expected), Value::Array(actual)) => {
+            for (index, (expected, actual)) in expected.iter().zip(actual).enumerate() {
+                compare(&format!("{path}[{index}]"), expected, actual, differences);
+            }
+            if expected.len() != actual.len() {
+                differences.push(format!(
+                    "{path}: expected length {}, got {}",
+                    expected.len(),
+                    actual.len()
+                ));
+            }
+            true
+        }
+        (Value::Object(expected), Value::Object(actual)) => {
+            for (key, expected) in expected {
+                let key_path = key_path(path, key);
+                match actual.get(key) {
+                    Some(actual) => compare(&key_path, expected, actual, differences),
+                    None => differences.push(format!("{key_path}: missing key")),
+                }
+            }
+            for key in actual.keys().filter(|key| !expected.contains_key(*key)) {
+                differences.push(format!("{}: unexpected key", key_path(path, key)));
+            }
+            true
+        }
+        (expected, actual) => expected == actual,
+    };
+    if !matches {
+        differences.push(format!("{path}: expected {expected}, got {actual}"));
+    }
+}
+
+/// Numbers are equal if they have the same value, regardless of their format
+fn numbers_equal(expected: &Number, actual: &Number) -> bool {
+    if let (Some(expected), Some(actual)) = (expected.as_i64(), actual.as_i64()) {
+        expected == actual
+    } else if let (Some(expected), Some(actual)) = (expected.as_u64(), actual.as_u64()) {
+        expected == actual
+    } else {
+        expected.as_f64() == actual.as_f64()
+    }
+}
+
+/// The JSON path of the key within the object at the given path
+fn key_path(path: &str, key: &str) -> String {
+    if !key.is_empty()
+        && !key.starts_with(|c: char| c.is_ascii_digit())
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        format!("{path}.{key}")
+    } else {
+        format!("{path}[{}]", Value::String(key.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::JsonRule;
+    use crate::lossy_string;
+    use crate::newline::StringNewline;
+    use crate::rules::rule::RuleMaker;
+
+    #[test]
+    fn test_make_unmake() {
+        let rule = JsonRule::make(r#"{"b": 1, "a": [true]}"#).expect("rule is created");
+        let (kind, expression) = rule.unmake();
+        assert_eq!("json", kind);
+        assert_eq!(r#"{"b": 1, "a": [true]}"#, lossy_string!(&expression));
+    }
+
+    #[test]
+    fn test_make_fails_for_invalid_json() {
+        assert!(JsonRule::make(r#"{"a": "#).is_err());
+        assert!(JsonRule::make("foo").is_err());
+    }
+
+    #[test]
+    fn test_rule_matches() {
+        let tests = vec![
+            (true, r#"{"a": 1, "b": "x"}"#, r#"{"b":"x","a":1}"#),
+            (true, r#"{"a": 1.0}"#, r#"{"a":1}"#),
+            (true, r#"{"a": 100}"#, r#"{"a":1e2}"#),
+            (false, r#"{"a": 1}"#, r#"{"a":2}"#),
+            (false, r#"{"a": 1}"#, r#"{"a":"1"}"#),
+            (false, r#"{"a": 1}"#, r#"{"a":1,"b":2}"#),
+            (false, r#"{"a": 1, "b": 2}"#, r#"{"a":1}"#),
+            (true, r#"[1, 2]"#, r#"[1,2]"#),
+            (false, r#"[1, 2]"#, r#"[2,1]"#),
+            (false, r#"[1, 2]"#, r#"[1,2,3]"#),
+            (true, r#"{"a": "<any>"}"#, r#"{"a":{"b":[null]}}"#),
+            (true, r#"{"a": "<string>"}"#, r#"{"a":"foo"}"#),
+            (false, r#"{"a": "<string>"}"#, r#"{"a":1}"#),
+            (true, r#"{"a": "<number>"}"#, r#"{"a":0.5}"#),
+            (false, r#"{"a": "<number>"}"#, r#"{"a":"0.5"}"#),
+            (true, r#"{"a": "<bool>"}"#, r#"{"a":false}"#),
+            (false, r#"{"a": "<bool>"}"#, r#"{"a":null}"#),
+            (true, r#"{"a": "<string>"}"#, r#"{"a":"<string>"}"#),
+            (true, r#""text""#, r#""text""#),
+            (false, r#"{"a": 1}"#, r#"not json"#),
+            (false, r#"{"a": 1}"#, r#"{"a": 1} trailing"#),
+        ];
+
+        for (expect, expression, line) in tests {
+            let rule = JsonRule::make(expression)
+                .unwrap_or_else(|err| panic!("create rule from {expression}: {err}"));
+            assert_eq!(
+                expect,
+                rule.matches(line.assure_newline().as_bytes()),
+                "{expression} vs {line}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_explain_mismatch() {
+        let rule = JsonRule::make(
+            r#"{"id": "<number>", "user": {"name": "jane", "tags": ["a", "b"]}, "the key": 1}"#,
+        )
+        .expect("rule is created");
+        assert_eq!(
+            None,
+            rule.explain_mismatch(
+                br#"{"the key":1,"user":{"tags":["a","b"],"name":"jane"},"id":3}"#
+            )
+        );
+        assert_eq!(None, rule.explain_mismatch(b"not json\n"));
+        assert_eq!(
+            Some(
+                [
+                    r#"$.id: expected "<number>", got "3""#,
+                    r#"$["the key"]: missing key"#,
+                    r#"$.user.name: expected "jane", got "john""#,
+                    r#"$.user.tags[1]: expected "b", got "c""#,
+                    r#"$.user.tags: expected length 2, got 3"#,
+                    r#"$.extra: unexpected key"#,
+                ]
+                .join("\n")
+            ),
+            rule.explain_mismatch(
+                br#"{"id":"3","user":{"name":"john","tags":["a","c","d"]},"extra":true}"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_rule_serialize() {
+        let rule = JsonRule::make(r#"{"a": 1}"#).unwrap();
+        let serialized = serde_json::to_string(&rule).expect("serialize");
+        assert_eq!(r#"{"kind":"json","expression":"{\"a\": 1}"}"#, serialized);
+    }
+}
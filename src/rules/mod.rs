@@ -5,11 +5,13 @@
  * LICENSE file in the root directory of this source tree.
  */
 
+pub mod approx;
 pub mod equal;
 pub mod escaped;
 pub mod escaped_filter;
 pub mod glob;
 pub mod glob_cram;
+pub mod json;
 pub mod no_eol;
 pub mod prelude;
 pub mod regex;
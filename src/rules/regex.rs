@@ -15,6 +15,7 @@ use regex::bytes::Regex as ByteRegex;
 
 use super::rule::Rule;
 use super::rule::RuleMaker;
+use crate::lossy_string;
 use crate::newline::BytesNewline;
 
 /// Simple equality match for lines that end in a new-line character
@@ -45,6 +46,25 @@ impl Rule for RegexRule {
     fn unmake(&self) -> (String, Vec<u8>) {
         (self.kind().to_string(), self.0.as_bytes().to_vec())
     }
+
+    fn has_captures(&self) -> bool {
+        self.1.capture_names().flatten().next().is_some()
+    }
+
+    fn captures(&self, line: &[u8]) -> Vec<(String, String)> {
+        let Some(captures) = self.1.captures(line.trim_newlines()) else {
+            return vec![];
+        };
+        self.1
+            .capture_names()
+            .flatten()
+            .filter_map(|name| {
+                captures
+                    .name(name)
+                    .map(|value| (name.to_string(), lossy_string!(value.as_bytes())))
+            })
+            .collect()
+    }
 }
 
 impl RuleMaker for RegexRule {
@@ -300,6 +320,25 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_rule_captures() {
+        let rule = RegexRule::make("created (?P<KIND>\\w+) (?P<ID>[0-9a-f]+)( in .+)?")
+            .expect("rule is created");
+        assert!(rule.has_captures());
+        assert_eq!(
+            vec![
+                ("KIND".to_string(), "job".to_string()),
+                ("ID".to_string(), "abc123".to_string()),
+            ],
+            rule.captures("created job abc123".assure_newline().as_bytes())
+        );
+        assert!(rule.captures(b"deleted job abc123").is_empty());
+
+        let rule = RegexRule::make("created (\\w+)").expect("rule is created");
+        assert!(!rule.has_captures());
+        assert!(rule.captures(b"created job").is_empty());
+    }
+
     #[test]
     fn test_rule_serialize() {
         let rule = RegexRule::make("abc").unwrap();
@@ -11,9 +11,11 @@ use anyhow::Result;
 use anyhow::anyhow;
 use regex::Regex;
 
+use super::approx::ApproxRule;
 use super::equal::EqualRule;
 use super::escaped::EscapedRule;
 use super::glob::GlobRule;
+use super::json::JsonRule;
 use super::no_eol::EqualNoEolRule;
 use super::regex::RegexRule;
 use super::rule::MakeRule;
@@ -51,7 +53,7 @@ impl RuleRegistry {
                 \s
                 \(
                     (
-                        {names}|
+                        !?(?:{names}|)
                     )?
                     ([*+?])?
                 \)
@@ -84,10 +86,12 @@ impl Default for RuleRegistry {
     fn default() -> Self {
         let mut registry = Self::new();
         registry
+            .register(ApproxRule::make, &["approx"])
             .register(EqualRule::make, &["equal", "eq"])
             .register(EqualNoEolRule::make, &["no-eol"])
             .register(EscapedRule::make, &["escaped", "esc"])
             .register(GlobRule::make, &["glob", "gl"])
+            .register(JsonRule::make, &["json"])
             .register(RegexRule::make, &["regex", "re"]);
         registry
     }
@@ -102,10 +106,12 @@ mod tests {
     #[test]
     fn test_default() {
         let tests = vec![
+            (vec!["approx"], "foo {1..2}"),
             (vec!["equal", "eq"], "foo"),
             (vec!["no-eol"], "foo"),
             (vec!["escaped", "esc"], "foo"),
             (vec!["glob", "gl"], "foo"),
+            (vec!["json"], "{\"foo\": 1}"),
             (vec!["regex", "re"], "foo"),
         ];
         let registry = RuleRegistry::default();
@@ -13,6 +13,7 @@ use serde::Serialize;
 use serde::ser::SerializeMap;
 
 use crate::escaping::Escaper;
+use crate::expectation::ends_in_modifier;
 
 /// Rule implements the line-level comparisons of [`crate::expectation::Expectation`]s
 pub trait Rule: RuleClone + Debug + Send {
@@ -25,6 +26,23 @@ pub trait Rule: RuleClone + Debug + Send {
     /// Decompose the rule into components from which it can be re-made
     fn unmake(&self) -> (String, Vec<u8>);
 
+    /// Whether the rule captures named values from the lines it matches
+    fn has_captures(&self) -> bool {
+        false
+    }
+
+    /// The named values that the rule captures from the given line, which
+    /// are empty if the line does not match
+    fn captures(&self, _line: &[u8]) -> Vec<(String, String)> {
+        vec![]
+    }
+
+    /// Explains why the given line, that resembles what the rule expects, does
+    /// not match. Returns `None` if the rule cannot explain the mismatch.
+    fn explain_mismatch(&self, _line: &[u8]) -> Option<String> {
+        None
+    }
+
     /// The string representation of the Rule as it would be written in
     /// a test document
     fn to_expression_string(&self, optional: bool, multiline: bool, escaper: &Escaper) -> String {
@@ -44,6 +62,8 @@ pub trait Rule: RuleClone + Debug + Send {
         if kind == "equal" {
             if escaper.has_unprintable(&expression) {
                 format!("{rendered} (escaped{quantifier})")
+            } else if ends_in_modifier(&rendered) {
+                format!("{rendered} (equal{quantifier})")
             } else {
                 format!("{rendered}{equal_quantifier}")
             }
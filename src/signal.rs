@@ -203,8 +203,8 @@ mod nix_signal {
                 ("\"disabled\"", KillSignal::Disabled),
             ];
             for (input, expected) in cases {
-                let value: KillSignal =
-                    serde_json::from_str(input).expect(&format!("deserialize from '{input}'"));
+                let value: KillSignal = serde_json::from_str(input)
+                    .unwrap_or_else(|_| panic!("deserialize from '{input}'"));
                 assert_eq!(value, expected);
             }
         }
@@ -217,7 +217,8 @@ mod nix_signal {
                 (KillSignal::Disabled, "\"disabled\""),
             ];
             for (input, expected) in cases {
-                let value = serde_json::to_string(&input).expect(&format!("serialize '{}'", input));
+                let value =
+                    serde_json::to_string(&input).unwrap_or_else(|_| panic!("serialize '{input}'"));
                 assert_eq!(&value as &str, expected);
             }
         }
@@ -365,8 +366,8 @@ mod win_signal {
                 ("\"disabled\"", KillSignal::Disabled),
             ];
             for (input, expected) in cases {
-                let value: KillSignal =
-                    serde_json::from_str(input).expect(&format!("deserialize from '{input}'"));
+                let value: KillSignal = serde_json::from_str(input)
+                    .unwrap_or_else(|_| panic!("deserialize from '{input}'"));
                 assert_eq!(value, expected);
             }
         }
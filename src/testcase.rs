@@ -6,8 +6,8 @@
  */
 
 use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::fmt::Display;
-#[cfg(test)]
 use std::time::Duration;
 
 use serde::Serialize;
@@ -17,21 +17,39 @@ use serde_json::Value;
 use serde_json::json;
 
 use crate::config::OutputStreamControl;
+use crate::config::ResourceLimit;
 use crate::config::TestCaseConfig;
 use crate::diff::DiffTool;
 use crate::escaping::strip_colors_bytes;
 use crate::expectation::Expectation;
+use crate::interactive::InteractiveDirective;
+use crate::interactive::matches_any_line;
 use crate::newline::replace_crlf;
 use crate::output::ExitStatus;
 use crate::output::Output;
+use crate::output::OutputStream;
+use crate::output::duration_ms;
+use crate::validation::InteractiveBody;
+use crate::validation::InteractiveFailure;
+use crate::validation::InteractiveFailureKind;
 use crate::validation::JsonSchemaBody;
 use crate::validation::JsonSchemaFailure;
 use crate::validation::JsonSchemaFailureKind;
+use crate::validation::MarkedDiff;
+use crate::validation::MarkedOutputBody;
+use crate::validation::MarkedStream;
 use crate::validation::ValidationBody;
 use crate::validation::ValidationFailure;
 
 pub type Result<T> = anyhow::Result<T, TestCaseError>;
 
+/// The signal that terminates processes which exceed their soft CPU time limit
+const SIGXCPU: i32 = 24;
+
+/// The signal that terminates processes which exceed their hard CPU time
+/// limit
+const SIGKILL: i32 = 9;
+
 /// An aggregate that unifies all ingredients for a test: a title
 /// of the expected and intended state of the world; what a specific
 /// command line should output and why
@@ -61,9 +79,78 @@ pub struct TestCase {
 
 impl TestCase {
     /// Validate that the outcome of an execution matches with the assumed
-    /// outcome in regards to exit code and (STDOUT) output, or return an
-    /// [`TestCaseError`]
+    /// outcome in regards to exit code, (STDOUT) output and duration, or
+    /// return an [`TestCaseError`]
     pub fn validate(&self, output: &Output) -> Result<()> {
+        // failures of executions that ran into a resource limit keep the limit
+        // as context, as the output is likely incomplete
+        self.validate_output(output)
+            .map_err(|err| match self.exceeded_limit(output) {
+                Some(limit) => TestCaseError::LimitExceeded {
+                    limit,
+                    error: Box::new(err),
+                },
+                None => err,
+            })?;
+
+        if let (Some(max_duration), Some(duration)) = (self.config.max_duration, output.duration) {
+            if duration > max_duration {
+                return Err(TestCaseError::MaxDurationExceeded {
+                    duration,
+                    max_duration,
+                });
+            }
+        }
+
+        // the max rss is never silently ignored, if the resources of the
+        // execution could not be measured
+        match (self.config.max_rss, &output.resource_usage) {
+            (Some(max_rss), Some(usage)) if usage.max_rss > max_rss => {
+                return Err(TestCaseError::MaxRssExceeded {
+                    rss: usage.max_rss,
+                    max_rss,
+                });
+            }
+            (Some(_), None) if matches!(output.exit_code, ExitStatus::Code(_)) => {
+                return Err(TestCaseError::InternalError(anyhow::anyhow!(
+                    "max_rss is not supported, because the resource usage of the execution was not measured"
+                )));
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Returns the configured resource limit, that the execution ran into.
+    /// Processes that exceed their CPU time receive `SIGXCPU` and are killed
+    /// with `SIGKILL` at the hard limit, which is told by the signal that
+    /// terminated the shell or, as reported by the shell, the last command,
+    /// or by the measured CPU time of the execution. The other limits make
+    /// system calls (e.g. allocations of memory) fail, which the failing
+    /// program handles in its own way, so that they cannot be attributed.
+    fn exceeded_limit(&self, output: &Output) -> Option<ResourceLimit> {
+        let limits = self.config.limits.as_ref()?;
+        let signal = output.signal.or(match output.exit_code {
+            ExitStatus::Code(code) if code > 128 => Some(code - 128),
+            _ => None,
+        });
+        let cpu_time = output
+            .resource_usage
+            .as_ref()
+            .map(|usage| usage.user_time + usage.system_time);
+        limits.to_vec().into_iter().find(|limit| match limit {
+            ResourceLimit::CpuTime(max_cpu_time) => {
+                matches!(signal, Some(SIGXCPU | SIGKILL))
+                    || cpu_time.is_some_and(|cpu_time| cpu_time >= *max_cpu_time)
+            }
+            ResourceLimit::Memory(_)
+            | ResourceLimit::OpenFiles(_)
+            | ResourceLimit::Processes(_) => false,
+        })
+    }
+
+    /// Validate exit code and output of an execution
+    fn validate_output(&self, output: &Output) -> Result<()> {
         if let ExitStatus::Code(exit_code) = output.exit_code {
             let expected = self.exit_code.unwrap_or(0);
             if exit_code != expected {
@@ -76,18 +163,7 @@ impl TestCase {
 
         match &self.body {
             ValidationBody::Output(body) => {
-                let expectations = if self.config.interpolated == Some(true) {
-                    body.expectations
-                        .iter()
-                        .map(|e| {
-                            crate::interpolation::interpolate_expectation(e, &output.captured_env)
-                        })
-                        .collect::<anyhow::Result<Vec<_>>>()
-                        .map_err(TestCaseError::InternalError)?
-                } else {
-                    body.expectations.clone()
-                };
-
+                let expectations = self.resolve_expectations(&body.expectations, output)?;
                 let diff_tool = DiffTool::new(expectations);
                 let stream = if self.config.output_stream == Some(OutputStreamControl::Stderr) {
                     &output.stderr
@@ -105,7 +181,99 @@ impl TestCase {
                     Ok(())
                 }
             }
+            ValidationBody::MarkedOutput(body) => self.validate_marked_output(body, output),
             ValidationBody::JsonSchema(body) => self.validate_json_schema(body, output),
+            ValidationBody::Interactive(body) => self.validate_interactive(body, output),
+        }
+    }
+
+    /// Returns the values of the named capture groups of all expectations that
+    /// matched the output, keyed by group name. Later matches overwrite earlier
+    /// ones with the same name.
+    pub fn captures(&self, output: &Output) -> Result<BTreeMap<String, String>> {
+        let bodies: Vec<(&[Expectation], &OutputStream)> = match &self.body {
+            ValidationBody::Output(body) => {
+                let stream = if self.config.output_stream == Some(OutputStreamControl::Stderr) {
+                    &output.stderr
+                } else {
+                    &output.stdout
+                };
+                vec![(&body.expectations, stream)]
+            }
+            ValidationBody::MarkedOutput(body) => body
+                .sections
+                .iter()
+                .map(|section| {
+                    let stream = match section.stream {
+                        MarkedStream::Stdout => &output.stdout,
+                        MarkedStream::Stderr => &output.stderr,
+                    };
+                    (&section.expectations[..], stream)
+                })
+                .collect(),
+            _ => vec![],
+        };
+
+        let mut captures = BTreeMap::new();
+        for (expectations, stream) in bodies {
+            if !expectations.iter().any(|e| e.rule.has_captures()) {
+                continue;
+            }
+            let expectations = self.resolve_expectations(expectations, output)?;
+            let diff = DiffTool::new(expectations)
+                .diff(stream.into())
+                .map_err(TestCaseError::InternalError)?;
+            captures.extend(diff.captures());
+        }
+        Ok(captures)
+    }
+
+    /// Returns the expectations, interpolated with the captured environment
+    /// variables if configured.
+    fn resolve_expectations(
+        &self,
+        expectations: &[Expectation],
+        output: &Output,
+    ) -> Result<Vec<Expectation>> {
+        if self.config.interpolated == Some(true) {
+            expectations
+                .iter()
+                .map(|e| crate::interpolation::interpolate_expectation(e, &output.captured_env))
+                .collect::<anyhow::Result<Vec<_>>>()
+                .map_err(TestCaseError::InternalError)
+        } else {
+            Ok(expectations.to_vec())
+        }
+    }
+
+    /// Validate STDOUT and STDERR against the expectations of their marked
+    /// sections. A stream without a section is expected to be empty.
+    fn validate_marked_output(&self, body: &MarkedOutputBody, output: &Output) -> Result<()> {
+        let streams = body.sections.iter().map(|section| section.stream).chain(
+            MarkedStream::ALL
+                .into_iter()
+                .filter(|stream| body.section(*stream).is_none()),
+        );
+
+        let mut diffs = vec![];
+        for stream in streams {
+            let expectations = self.resolve_expectations(body.expectations(stream), output)?;
+            let output_stream = match stream {
+                MarkedStream::Stdout => &output.stdout,
+                MarkedStream::Stderr => &output.stderr,
+            };
+            let diff = DiffTool::new(expectations)
+                .diff(output_stream.into())
+                .map_err(TestCaseError::InternalError)?;
+            diffs.push(MarkedDiff { stream, diff });
+        }
+
+        if diffs.iter().any(|marked| marked.diff.has_differences()) {
+            Err(TestCaseError::ValidationFailed(
+                ValidationFailure::MalformedMarkedOutput(diffs),
+            ))
+        } else {
+            Ok(())
         }
     }
 
@@ -177,11 +345,64 @@ impl TestCase {
         }
     }
 
-    /// Returns the output expectations for this test case.
+    /// Validate the recorded terminal session against the directives of an
+    /// interactive test case. The first failing directive is reported.
+    fn validate_interactive(&self, body: &InteractiveBody, output: &Output) -> Result<()> {
+        let session = output.interactive.as_ref().ok_or_else(|| {
+            TestCaseError::InternalError(anyhow::anyhow!(
+                "no terminal session recorded for interactive test case"
+            ))
+        })?;
+        let transcript =
+            strip_colors_bytes((&output.stdout).into()).map_err(TestCaseError::InternalError)?;
+        let transcript = String::from_utf8_lossy(&transcript);
+        let transcript = transcript
+            .lines()
+            .map(|line| line.trim_end_matches('\r'))
+            .collect::<Vec<_>>();
+
+        for (index, directive) in body.directives.iter().enumerate() {
+            let Some(step) = session.steps.get(index) else {
+                break;
+            };
+            let kind = match directive {
+                InteractiveDirective::Wait(_) if step.timed_out => {
+                    Some(InteractiveFailureKind::WaitTimeout)
+                }
+                InteractiveDirective::Assert(expectation)
+                    if !matches_any_line(expectation, &step.screen) =>
+                {
+                    Some(InteractiveFailureKind::ScreenMismatch)
+                }
+                InteractiveDirective::AssertTranscript(expectation)
+                    if !matches_any_line(expectation, &transcript) =>
+                {
+                    Some(InteractiveFailureKind::TranscriptMismatch)
+                }
+                _ => None,
+            };
+            if let Some(kind) = kind {
+                return Err(TestCaseError::ValidationFailed(
+                    ValidationFailure::InteractiveFailed(InteractiveFailure {
+                        kind,
+                        index,
+                        directive: directive.to_string(),
+                        screen: step.screen.clone(),
+                    }),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the output expectations for this test case. Test cases with
+    /// marked `@STDOUT` / `@STDERR` sections have no single list of expectations.
     pub fn expectations(&self) -> &[Expectation] {
         match &self.body {
             ValidationBody::Output(body) => &body.expectations,
-            ValidationBody::JsonSchema(_) => &[],
+            ValidationBody::MarkedOutput(_)
+            | ValidationBody::JsonSchema(_)
+            | ValidationBody::Interactive(_) => &[],
         }
     }
 
@@ -229,7 +450,11 @@ impl TestCase {
     }
 
     pub(crate) fn expectations_lines(&self) -> usize {
-        self.expectations().len()
+        match &self.body {
+            ValidationBody::MarkedOutput(body) => body.lines(),
+            ValidationBody::Interactive(body) => body.directives.len(),
+            _ => self.expectations().len(),
+        }
     }
 }
 
@@ -285,10 +510,42 @@ pub enum TestCaseError {
     /// Test case timed out
     Timeout,
 
+    /// Test case ran longer than its configured max duration
+    MaxDurationExceeded {
+        duration: Duration,
+        max_duration: Duration,
+    },
+
+    /// Test case used more peak resident memory than its configured max
+    MaxRssExceeded { rss: u64, max_rss: u64 },
+
+    /// Test case failed with the error after it ran into a configured
+    /// resource limit
+    LimitExceeded {
+        limit: ResourceLimit,
+        error: Box<TestCaseError>,
+    },
+
     /// Whether this test was skipped intentionally
     Skipped,
 }
 
+impl TestCaseError {
+    /// Machine readable identifier of the error
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::ValidationFailed(failure) => failure.kind(),
+            Self::InvalidExitCode { .. } => "invalid_exit_code",
+            Self::InternalError(_) => "internal_error",
+            Self::Timeout => "timeout",
+            Self::MaxDurationExceeded { .. } => "max_duration_exceeded",
+            Self::MaxRssExceeded { .. } => "max_rss_exceeded",
+            Self::LimitExceeded { .. } => "limit_exceeded",
+            Self::Skipped => "skipped",
+        }
+    }
+}
+
 impl PartialEq for TestCaseError {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
@@ -304,6 +561,36 @@ impl PartialEq for TestCaseError {
                 },
             ) => l_actual == r_actual && l_expected == r_expected,
             (Self::InternalError(l0), Self::InternalError(r0)) => l0.to_string() == r0.to_string(),
+            (
+                Self::MaxDurationExceeded {
+                    duration: l_duration,
+                    max_duration: l_max_duration,
+                },
+                Self::MaxDurationExceeded {
+                    duration: r_duration,
+                    max_duration: r_max_duration,
+                },
+            ) => l_duration == r_duration && l_max_duration == r_max_duration,
+            (
+                Self::MaxRssExceeded {
+                    rss: l_rss,
+                    max_rss: l_max_rss,
+                },
+                Self::MaxRssExceeded {
+                    rss: r_rss,
+                    max_rss: r_max_rss,
+                },
+            ) => l_rss == r_rss && l_max_rss == r_max_rss,
+            (
+                Self::LimitExceeded {
+                    limit: l_limit,
+                    error: l_error,
+                },
+                Self::LimitExceeded {
+                    limit: r_limit,
+                    error: r_error,
+                },
+            ) => l_limit == r_limit && l_error == r_error,
             (_, _) => false,
         }
     }
@@ -318,25 +605,50 @@ impl Serialize for TestCaseError {
             Self::ValidationFailed(failure) => failure.serialize(serializer),
             Self::InvalidExitCode { actual, expected } => {
                 let mut variant = serializer.serialize_map(Some(3))?;
-                variant.serialize_entry("kind", "invalid_exit_code")?;
+                variant.serialize_entry("kind", self.kind())?;
                 variant.serialize_entry("actual", actual)?;
                 variant.serialize_entry("expected", expected)?;
                 variant.end()
             }
             Self::InternalError(err) => {
                 let mut variant = serializer.serialize_map(Some(2))?;
-                variant.serialize_entry("kind", "internal_error")?;
+                variant.serialize_entry("kind", self.kind())?;
                 variant.serialize_entry("error", &format!("{}", err))?;
                 variant.end()
             }
             Self::Timeout => {
                 let mut variant = serializer.serialize_map(Some(1))?;
-                variant.serialize_entry("kind", "timeout")?;
+                variant.serialize_entry("kind", self.kind())?;
+                variant.end()
+            }
+            Self::MaxDurationExceeded {
+                duration,
+                max_duration,
+            } => {
+                let mut variant = serializer.serialize_map(Some(3))?;
+                variant.serialize_entry("kind", self.kind())?;
+                variant.serialize_entry("duration_ms", &duration_ms(*duration))?;
+                variant.serialize_entry("max_duration_ms", &duration_ms(*max_duration))?;
+                variant.end()
+            }
+            Self::MaxRssExceeded { rss, max_rss } => {
+                let mut variant = serializer.serialize_map(Some(3))?;
+                variant.serialize_entry("kind", self.kind())?;
+                variant.serialize_entry("rss", rss)?;
+                variant.serialize_entry("max_rss", max_rss)?;
+                variant.end()
+            }
+            Self::LimitExceeded { limit, error } => {
+                let mut variant = serializer.serialize_map(Some(4))?;
+                variant.serialize_entry("kind", self.kind())?;
+                variant.serialize_entry("limit", limit.name())?;
+                variant.serialize_entry("value", &limit.value())?;
+                variant.serialize_entry("error", error)?;
                 variant.end()
             }
             Self::Skipped => {
                 let mut variant = serializer.serialize_map(Some(1))?;
-                variant.serialize_entry("kind", "skipped")?;
+                variant.serialize_entry("kind", self.kind())?;
                 variant.end()
             }
         }
@@ -345,17 +657,33 @@ impl Serialize for TestCaseError {
 
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
     use super::TestCase;
     use super::TestCaseError;
+    use crate::config::OutputStreamControl;
+    use crate::config::ResourceLimit;
     use crate::config::TestCaseConfig;
+    use crate::config::TestCaseLimits;
     use crate::config::TestMode;
     use crate::diff::Diff;
     use crate::diff::DiffLine;
+    use crate::expectation::tests::expectation_maker;
+    use crate::interactive::InteractiveDirective;
+    use crate::interactive::InteractiveSession;
+    use crate::interactive::InteractiveStep;
     use crate::lossy_string;
+    use crate::output::ExitStatus;
     use crate::output::Output;
+    use crate::output::ResourceUsage;
     use crate::test_expectation;
+    use crate::validation::InteractiveBody;
+    use crate::validation::InteractiveFailureKind;
     use crate::validation::JsonSchemaBody;
     use crate::validation::JsonSchemaFailureKind;
+    use crate::validation::MarkedOutputBody;
+    use crate::validation::MarkedSection;
+    use crate::validation::MarkedStream;
     use crate::validation::OutputBody;
     use crate::validation::ValidationBody;
     use crate::validation::ValidationFailure;
@@ -407,6 +735,173 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_validate_fails_on_exceeded_max_duration() {
+        let testcase = TestCase {
+            title: "an testcase".to_string(),
+            shell_expression: "a command".to_string(),
+            body: ValidationBody::Output(OutputBody {
+                expectations: vec![test_expectation!("no-eol", "the stdout")],
+            }),
+            config: TestCaseConfig {
+                max_duration: Some(Duration::from_millis(100)),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let output = |duration: Option<u64>| Output {
+            duration: duration.map(Duration::from_millis),
+            ..("the stdout", "the stderr").into()
+        };
+        testcase
+            .validate(&output(None))
+            .expect("unknown duration is not validated");
+        testcase
+            .validate(&output(Some(100)))
+            .expect("duration within max duration");
+        assert_eq!(
+            Err(TestCaseError::MaxDurationExceeded {
+                duration: Duration::from_millis(101),
+                max_duration: Duration::from_millis(100),
+            }),
+            testcase.validate(&output(Some(101))),
+        );
+    }
+
+    #[test]
+    fn test_validate_fails_on_exceeded_max_rss() {
+        let testcase = TestCase {
+            title: "an testcase".to_string(),
+            shell_expression: "a command".to_string(),
+            body: ValidationBody::Output(OutputBody {
+                expectations: vec![test_expectation!("no-eol", "the stdout")],
+            }),
+            config: TestCaseConfig {
+                max_rss: Some(1024),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let output = |max_rss: Option<u64>| Output {
+            resource_usage: max_rss.map(|max_rss| ResourceUsage {
+                max_rss,
+                ..Default::default()
+            }),
+            ..("the stdout", "the stderr").into()
+        };
+        assert!(
+            matches!(
+                testcase.validate(&output(None)),
+                Err(TestCaseError::InternalError(_))
+            ),
+            "unknown resource usage fails validation"
+        );
+        testcase
+            .validate(&output(Some(1024)))
+            .expect("rss within max rss");
+        assert_eq!(
+            Err(TestCaseError::MaxRssExceeded {
+                rss: 1025,
+                max_rss: 1024,
+            }),
+            testcase.validate(&output(Some(1025))),
+        );
+    }
+
+    #[test]
+    fn test_validate_fails_on_exceeded_limit() {
+        let testcase = TestCase {
+            title: "an testcase".to_string(),
+            shell_expression: "a command".to_string(),
+            body: ValidationBody::Output(OutputBody {
+                expectations: vec![test_expectation!("no-eol", "the stdout")],
+            }),
+            config: TestCaseConfig {
+                limits: Some(TestCaseLimits {
+                    cpu_time: Some(Duration::from_secs(1)),
+                    open_files: Some(16),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let invalid_exit_code = |actual| TestCaseError::InvalidExitCode {
+            actual,
+            expected: 0,
+        };
+        testcase
+            .validate(&("the stdout", "").into())
+            .expect("output as expected");
+        assert_eq!(
+            Err(TestCaseError::LimitExceeded {
+                limit: ResourceLimit::CpuTime(Duration::from_secs(1)),
+                error: Box::new(invalid_exit_code(152)),
+            }),
+            testcase.validate(&("", "", Some(152)).into()),
+            "command of the shell terminated by SIGXCPU",
+        );
+        assert_eq!(
+            Err(TestCaseError::LimitExceeded {
+                limit: ResourceLimit::CpuTime(Duration::from_secs(1)),
+                error: Box::new(invalid_exit_code(137)),
+            }),
+            testcase.validate(&("", "", Some(137)).into()),
+            "command of the shell terminated by SIGKILL at the hard limit",
+        );
+        let signaled = Output {
+            exit_code: ExitStatus::Unknown,
+            signal: Some(24),
+            ..("the", "").into()
+        };
+        assert!(
+            matches!(
+                testcase.validate(&signaled),
+                Err(TestCaseError::LimitExceeded {
+                    limit: ResourceLimit::CpuTime(_),
+                    error,
+                }) if matches!(*error, TestCaseError::ValidationFailed(_))
+            ),
+            "shell terminated by SIGXCPU keeps the diff",
+        );
+        assert_eq!(
+            Err(TestCaseError::LimitExceeded {
+                limit: ResourceLimit::CpuTime(Duration::from_secs(1)),
+                error: Box::new(invalid_exit_code(1)),
+            }),
+            testcase.validate(&Output {
+                resource_usage: Some(ResourceUsage {
+                    max_rss: 1024,
+                    user_time: Duration::from_millis(800),
+                    system_time: Duration::from_millis(200),
+                }),
+                ..("", "", Some(1)).into()
+            }),
+            "measured CPU time reached the limit",
+        );
+        assert_eq!(
+            Err(invalid_exit_code(1)),
+            testcase.validate(&("", "Too many open files", Some(1)).into()),
+            "limits are not guessed from the output",
+        );
+        assert_eq!(
+            Err(invalid_exit_code(137)),
+            TestCase {
+                config: TestCaseConfig {
+                    limits: Some(TestCaseLimits {
+                        memory: Some(1024),
+                        open_files: Some(16),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                },
+                ..testcase.clone()
+            }
+            .validate(&("", "", Some(137)).into()),
+            "only configured limits are detected",
+        );
+    }
+
     #[test]
     fn test_validate_fails_on_malformed_output() {
         let expectations = vec![test_expectation!(
@@ -472,7 +967,6 @@ mod tests {
                     keep_crlf: Some(*crlf_support),
                     ..Default::default()
                 },
-                ..Default::default()
             };
             let output = tc
                 .render_output(from.as_bytes())
@@ -514,7 +1008,6 @@ mod tests {
                     strip_ansi_escaping: Some(*strip_ansi_escaping),
                     ..Default::default()
                 },
-                ..Default::default()
             };
             let output = tc
                 .render_output(from.as_bytes())
@@ -543,7 +1036,6 @@ mod tests {
                 interpolated: Some(true),
                 ..Default::default()
             },
-            ..Default::default()
         };
         let mut output: Output = ("Hello world\n", "").into();
         output.captured_env =
@@ -567,7 +1059,6 @@ mod tests {
                 interpolated: Some(true),
                 ..Default::default()
             },
-            ..Default::default()
         };
         let mut output: Output = ("Hello other\n", "").into();
         output.captured_env =
@@ -589,7 +1080,6 @@ mod tests {
             exit_code: Some(0),
             line_number: 1,
             config: TestCaseConfig::default(),
-            ..Default::default()
         };
         // With interpolation disabled, `$FOOBAR` is matched literally
         let mut output: Output = ("Hello $FOOBAR\n", "").into();
@@ -600,6 +1090,93 @@ mod tests {
             .expect("literal match should succeed when interpolation is disabled");
     }
 
+    fn marked_testcase(sections: &[(MarkedStream, &[&str])]) -> TestCase {
+        TestCase {
+            title: "marked test".to_string(),
+            shell_expression: "echo out; echo err >&2".to_string(),
+            body: ValidationBody::MarkedOutput(MarkedOutputBody {
+                sections: sections
+                    .iter()
+                    .map(|(stream, expectations)| MarkedSection {
+                        stream: *stream,
+                        expectations: expectations
+                            .iter()
+                            .map(|expectation| test_expectation!("equal", expectation))
+                            .collect(),
+                    })
+                    .collect(),
+            }),
+            exit_code: Some(0),
+            line_number: 1,
+            config: TestCaseConfig {
+                output_stream: Some(OutputStreamControl::Marked),
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_validate_marked_output() {
+        let output: Output = ("out\n", "err\n").into();
+        let valid = [
+            vec![
+                (MarkedStream::Stdout, &["out"][..]),
+                (MarkedStream::Stderr, &["err"][..]),
+            ],
+            vec![
+                (MarkedStream::Stderr, &["err"][..]),
+                (MarkedStream::Stdout, &["out"][..]),
+            ],
+        ];
+        for sections in valid {
+            marked_testcase(&sections)
+                .validate(&output)
+                .unwrap_or_else(|err| panic!("{sections:?} should pass: {err:?}"));
+        }
+    }
+
+    #[test]
+    fn test_validate_marked_output_fails_per_stream() {
+        let output: Output = ("out\n", "err\n").into();
+        let tests = [
+            (
+                "stderr mismatch",
+                vec![
+                    (MarkedStream::Stdout, &["out"][..]),
+                    (MarkedStream::Stderr, &["other"][..]),
+                ],
+                vec![(MarkedStream::Stdout, false), (MarkedStream::Stderr, true)],
+            ),
+            (
+                "missing stderr section",
+                vec![(MarkedStream::Stdout, &["out"][..])],
+                vec![(MarkedStream::Stdout, false), (MarkedStream::Stderr, true)],
+            ),
+            (
+                "section order is kept",
+                vec![
+                    (MarkedStream::Stderr, &["err"][..]),
+                    (MarkedStream::Stdout, &["other"][..]),
+                ],
+                vec![(MarkedStream::Stderr, false), (MarkedStream::Stdout, true)],
+            ),
+        ];
+        for (name, sections, expected) in tests {
+            match marked_testcase(&sections).validate(&output) {
+                Err(TestCaseError::ValidationFailed(ValidationFailure::MalformedMarkedOutput(
+                    diffs,
+                ))) => {
+                    let actual = diffs
+                        .iter()
+                        .map(|marked| (marked.stream, marked.diff.has_differences()))
+                        .collect::<Vec<_>>();
+                    assert_eq!(expected, actual, "{name}");
+                }
+                other => panic!("{name}: expected MalformedMarkedOutput, got {:?}", other),
+            }
+        }
+    }
+
     fn json_schema_testcase(schema_source: &str) -> TestCase {
         TestCase {
             title: "json schema test".to_string(),
@@ -613,7 +1190,6 @@ mod tests {
                 mode: Some(TestMode::JsonSchema),
                 ..Default::default()
             },
-            ..Default::default()
         }
     }
 
@@ -721,4 +1297,116 @@ required:
             .validate(&output)
             .expect("YAML schema with --- prefix should parse correctly");
     }
+
+    fn interactive_testcase(directives: &[&str]) -> TestCase {
+        let maker = expectation_maker();
+        TestCase {
+            title: "interactive test".to_string(),
+            shell_expression: "read -p 'Continue? ' answer".to_string(),
+            body: ValidationBody::Interactive(InteractiveBody {
+                directives: directives
+                    .iter()
+                    .map(|line| InteractiveDirective::parse(line, &maker).expect("parse directive"))
+                    .collect(),
+            }),
+            exit_code: Some(0),
+            line_number: 1,
+            config: TestCaseConfig {
+                mode: Some(TestMode::Interactive),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Screen lines and whether the `WAIT` timed out, per recorded step
+    type Steps<'a> = &'a [(&'a [&'a str], bool)];
+
+    fn interactive_output(transcript: &str, steps: Steps) -> Output {
+        let mut output: Output = (transcript, "").into();
+        output.interactive = Some(Box::new(InteractiveSession {
+            steps: steps
+                .iter()
+                .map(|(screen, timed_out)| InteractiveStep {
+                    screen: screen.iter().map(|line| line.to_string()).collect(),
+                    timed_out: *timed_out,
+                })
+                .collect(),
+            screen: vec![],
+        }));
+        output
+    }
+
+    #[test]
+    fn test_validate_interactive_valid() {
+        let testcase = interactive_testcase(&[
+            "WAIT Continue?* (glob)",
+            "SEND_KEYS y<Enter>",
+            "ASSERT Continue? y",
+            "ASSERT_TRANSCRIPT Continue? y",
+        ]);
+        let output = interactive_output(
+            "Continue? y\r\n",
+            &[
+                (&["Continue? "], false),
+                (&["Continue? y"], false),
+                (&["Continue? y"], false),
+                (&["Continue? y"], false),
+            ],
+        );
+        testcase
+            .validate(&output)
+            .expect("matching session should pass");
+    }
+
+    #[test]
+    fn test_validate_interactive_failures() {
+        let tests: &[(&str, &[&str], Steps, InteractiveFailureKind, usize)] = &[
+            (
+                "wait timed out",
+                &["SEND_KEYS y<Enter>", "WAIT Done"],
+                &[(&["Continue? y"], false), (&["Continue? y"], true)],
+                InteractiveFailureKind::WaitTimeout,
+                1,
+            ),
+            (
+                "screen does not match",
+                &["ASSERT Done"],
+                &[(&["Continue? "], false)],
+                InteractiveFailureKind::ScreenMismatch,
+                0,
+            ),
+            (
+                "transcript does not match",
+                &["ASSERT Continue?* (glob)", "ASSERT_TRANSCRIPT Done"],
+                &[(&["Continue? "], false), (&["Continue? "], false)],
+                InteractiveFailureKind::TranscriptMismatch,
+                1,
+            ),
+        ];
+        for (name, directives, steps, expected_kind, expected_index) in tests {
+            let testcase = interactive_testcase(directives);
+            let output = interactive_output("Continue? \r\n", steps);
+            match testcase.validate(&output) {
+                Err(TestCaseError::ValidationFailed(ValidationFailure::InteractiveFailed(f))) => {
+                    assert_eq!(*expected_kind, f.kind, "kind for {name}");
+                    assert_eq!(*expected_index, f.index, "index for {name}");
+                    assert_eq!(directives[f.index], f.directive, "directive for {name}");
+                }
+                other => panic!("{name}: expected InteractiveFailed, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate_interactive_without_session_fails() {
+        let testcase = interactive_testcase(&["WAIT Continue?* (glob)"]);
+        let output: Output = ("Continue? ", "").into();
+        assert!(
+            matches!(
+                testcase.validate(&output),
+                Err(TestCaseError::InternalError(_))
+            ),
+            "missing session must be an internal error"
+        );
+    }
 }
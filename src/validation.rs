@@ -17,6 +17,7 @@ use serde::ser::SerializeMap;
 
 use crate::diff::Diff;
 use crate::expectation::Expectation;
+use crate::interactive::InteractiveDirective;
 
 /// Mode-specific test body, replacing flat `expectations` + `interactive_directives`
 /// fields on [`crate::testcase::TestCase`].
@@ -24,8 +25,13 @@ use crate::expectation::Expectation;
 pub enum ValidationBody {
     /// Output mode: line-by-line expectations compared against command output.
     Output(OutputBody),
+    /// Output mode with `output_stream: marked`: line-by-line expectations,
+    /// separately for STDOUT and STDERR.
+    MarkedOutput(MarkedOutputBody),
     /// JSON Schema mode: validate command output against a JSON Schema.
     JsonSchema(JsonSchemaBody),
+    /// Interactive mode: drive the command in a pseudo terminal with directives.
+    Interactive(InteractiveBody),
 }
 
 impl Default for ValidationBody {
@@ -38,7 +44,9 @@ impl PartialEq for ValidationBody {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (Self::Output(a), Self::Output(b)) => a == b,
+            (Self::MarkedOutput(a), Self::MarkedOutput(b)) => a == b,
             (Self::JsonSchema(a), Self::JsonSchema(b)) => a == b,
+            (Self::Interactive(a), Self::Interactive(b)) => a == b,
             _ => false,
         }
     }
@@ -51,8 +59,10 @@ impl Serialize for ValidationBody {
     {
         match self {
             Self::Output(body) => body.expectations.serialize(serializer),
+            Self::MarkedOutput(body) => body.sections.serialize(serializer),
             // JSON Schema body is not serialized as expectations
             Self::JsonSchema(_) => serializer.serialize_none(),
+            Self::Interactive(body) => body.directives.serialize(serializer),
         }
     }
 }
@@ -64,6 +74,107 @@ pub struct OutputBody {
     pub expectations: Vec<Expectation>,
 }
 
+/// Body for output-mode test cases with `output_stream: marked`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MarkedOutputBody {
+    /// The `@STDOUT` and `@STDERR` sections in the order they are written.
+    /// Each stream has at most one section.
+    pub sections: Vec<MarkedSection>,
+}
+
+impl MarkedOutputBody {
+    /// Returns the section of the given stream, if there is one.
+    pub fn section(&self, stream: MarkedStream) -> Option<&MarkedSection> {
+        self.sections
+            .iter()
+            .find(|section| section.stream == stream)
+    }
+
+    /// Returns the expectations for the given stream, which are empty if no
+    /// section for the stream is written.
+    pub fn expectations(&self, stream: MarkedStream) -> &[Expectation] {
+        self.section(stream)
+            .map_or(&[], |section| &section.expectations)
+    }
+
+    /// Returns the number of body lines, including the marker lines.
+    pub fn lines(&self) -> usize {
+        self.sections
+            .iter()
+            .map(|section| section.expectations.len() + 1)
+            .sum()
+    }
+
+    /// Returns the number of body lines that precede the first expectation of
+    /// the section of the given stream. For streams that have no section this
+    /// is the offset at which a new section would be appended.
+    pub fn offset(&self, stream: MarkedStream) -> usize {
+        let mut offset = 0;
+        for section in &self.sections {
+            offset += 1;
+            if section.stream == stream {
+                return offset;
+            }
+            offset += section.expectations.len();
+        }
+        offset + 1
+    }
+}
+
+/// A section of output expectations for a single stream, introduced by a
+/// `@STDOUT` or `@STDERR` marker line.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct MarkedSection {
+    /// The stream the expectations apply to.
+    pub stream: MarkedStream,
+    /// The expectations that describe the expected output of the stream.
+    pub expectations: Vec<Expectation>,
+}
+
+/// The output stream of a [`MarkedSection`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MarkedStream {
+    Stdout,
+    Stderr,
+}
+
+impl MarkedStream {
+    /// Both streams in their canonical order.
+    pub const ALL: [MarkedStream; 2] = [MarkedStream::Stdout, MarkedStream::Stderr];
+
+    /// The marker line that introduces a section of this stream.
+    pub fn marker(&self) -> &'static str {
+        match self {
+            Self::Stdout => "@STDOUT",
+            Self::Stderr => "@STDERR",
+        }
+    }
+
+    /// Parse a marker line, if it is one.
+    pub fn from_marker(line: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|stream| stream.marker() == line)
+    }
+}
+
+/// The result of comparing the output of one stream of a test case with
+/// `output_stream: marked` to the expectations of its section.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct MarkedDiff {
+    /// The stream that was compared.
+    pub stream: MarkedStream,
+    /// The result of the comparison.
+    #[serde(serialize_with = "serialize_diff_lines")]
+    pub diff: Diff,
+}
+
+fn serialize_diff_lines<S>(diff: &Diff, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    diff.lines.serialize(serializer)
+}
+
 /// Body for JSON Schema validation mode test cases.
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct JsonSchemaBody {
@@ -71,6 +182,13 @@ pub struct JsonSchemaBody {
     pub schema_source: String,
 }
 
+/// Body for interactive mode test cases.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InteractiveBody {
+    /// The script of directives that drive the terminal session, in order.
+    pub directives: Vec<InteractiveDirective>,
+}
+
 /// The kind of JSON Schema validation failure.
 #[derive(Clone, Debug, PartialEq)]
 pub enum JsonSchemaFailureKind {
@@ -95,26 +213,79 @@ pub struct JsonSchemaFailure {
     pub schema_source: String,
 }
 
+/// The kind of interactive validation failure.
+#[derive(Clone, Debug, PartialEq)]
+pub enum InteractiveFailureKind {
+    /// A `WAIT` directive did not see the expected content in time.
+    WaitTimeout,
+    /// An `ASSERT` directive did not find the expected content on the screen.
+    ScreenMismatch,
+    /// An `ASSERT_TRANSCRIPT` directive did not find the expected content in
+    /// the transcript.
+    TranscriptMismatch,
+}
+
+/// An interactive validation failure with context.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InteractiveFailure {
+    /// The kind of failure.
+    pub kind: InteractiveFailureKind,
+    /// Index of the failed directive within the test case body.
+    pub index: usize,
+    /// The failed directive as it was written in the test case.
+    pub directive: String,
+    /// The lines of the screen when the directive failed.
+    pub screen: Vec<String>,
+}
+
 /// Mode-specific validation failure, replacing separate `MalformedOutput` and
 /// `InteractiveFailed` variants on [`crate::testcase::TestCaseError`].
 #[derive(Clone, Debug)]
 pub enum ValidationFailure {
     /// Output lines did not match expectations.
     MalformedOutput(Diff),
+    /// Output lines of STDOUT and/or STDERR did not match the expectations of
+    /// their marked sections. Contains one diff per stream, in the order of
+    /// the sections, followed by streams that have no section.
+    MalformedMarkedOutput(Vec<MarkedDiff>),
     /// JSON Schema validation failed.
     JsonSchemaFailed(JsonSchemaFailure),
+    /// A directive of an interactive test case failed.
+    InteractiveFailed(InteractiveFailure),
 }
 
 impl PartialEq for ValidationFailure {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (Self::MalformedOutput(a), Self::MalformedOutput(b)) => a == b,
+            (Self::MalformedMarkedOutput(a), Self::MalformedMarkedOutput(b)) => a == b,
             (Self::JsonSchemaFailed(a), Self::JsonSchemaFailed(b)) => a == b,
+            (Self::InteractiveFailed(a), Self::InteractiveFailed(b)) => a == b,
             _ => false,
         }
     }
 }
 
+impl ValidationFailure {
+    /// Machine readable identifier of the failure
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::MalformedOutput(_) => "malformed_output",
+            Self::MalformedMarkedOutput(_) => "malformed_marked_output",
+            Self::JsonSchemaFailed(failure) => match failure.kind {
+                JsonSchemaFailureKind::InvalidSchema => "json_schema_invalid_schema",
+                JsonSchemaFailureKind::InvalidJson => "json_schema_invalid_json",
+                JsonSchemaFailureKind::ValidationErrors => "json_schema_validation_errors",
+            },
+            Self::InteractiveFailed(failure) => match failure.kind {
+                InteractiveFailureKind::WaitTimeout => "interactive_wait_timeout",
+                InteractiveFailureKind::ScreenMismatch => "interactive_screen_mismatch",
+                InteractiveFailureKind::TranscriptMismatch => "interactive_transcript_mismatch",
+            },
+        }
+    }
+}
+
 impl Serialize for ValidationFailure {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -123,22 +294,31 @@ impl Serialize for ValidationFailure {
         match self {
             Self::MalformedOutput(diff) => {
                 let mut map = serializer.serialize_map(Some(2))?;
-                map.serialize_entry("kind", "malformed_output")?;
+                map.serialize_entry("kind", self.kind())?;
                 map.serialize_entry("diff", &diff.lines)?;
                 map.end()
             }
+            Self::MalformedMarkedOutput(diffs) => {
+                let mut map = serializer.serialize_map(Some(2))?;
+                map.serialize_entry("kind", self.kind())?;
+                map.serialize_entry("diffs", diffs)?;
+                map.end()
+            }
             Self::JsonSchemaFailed(failure) => {
-                let kind = match failure.kind {
-                    JsonSchemaFailureKind::InvalidSchema => "json_schema_invalid_schema",
-                    JsonSchemaFailureKind::InvalidJson => "json_schema_invalid_json",
-                    JsonSchemaFailureKind::ValidationErrors => "json_schema_validation_errors",
-                };
                 let mut map = serializer.serialize_map(Some(3))?;
-                map.serialize_entry("kind", kind)?;
+                map.serialize_entry("kind", self.kind())?;
                 map.serialize_entry("errors", &failure.errors)?;
                 map.serialize_entry("output", &failure.output)?;
                 map.end()
             }
+            Self::InteractiveFailed(failure) => {
+                let mut map = serializer.serialize_map(Some(4))?;
+                map.serialize_entry("kind", self.kind())?;
+                map.serialize_entry("index", &failure.index)?;
+                map.serialize_entry("directive", &failure.directive)?;
+                map.serialize_entry("screen", &failure.screen)?;
+                map.end()
+            }
         }
     }
 }